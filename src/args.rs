@@ -1,7 +1,17 @@
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
 use argh::FromArgs;
+use serde::Deserialize;
+
+use crate::generator::{
+    BoardShape, CluePlacement, GeneratorConfig, LoopLengthBounds, ShapeWeights, SolverBackend,
+    Style, Symmetry, TechniqueSet,
+};
+use crate::io::{ManifestFormat, MapEncoding, MapVersion, load_mask};
+use crate::model::{RegionMask, RimEdges, check_radius};
+use crate::pdf::{MAX_PER_PAGE, PaperSize};
 
 /// Standard grid sizes
 ///
@@ -71,12 +81,27 @@ pub enum Difficulty {
     #[default]
     /// As many hints as possible removed
     Hard,
+    /// Given fraction (0.0-1.0) of hints kept visible
+    Custom(f32),
+    /// Numeric difficulty from 1 (easiest) to 10 (hardest); Easy, Medium and Hard are 2, 5 and 10
+    Level(u8),
 }
 
 impl Difficulty {
     pub fn all() -> &'static [Difficulty] {
         &[Difficulty::Easy, Difficulty::Medium, Difficulty::Hard]
     }
+
+    /// Position on the 1-10 scale, or `None` for a custom clue density
+    pub fn level(self) -> Option<u8> {
+        match self {
+            Difficulty::Easy => Some(2),
+            Difficulty::Medium => Some(5),
+            Difficulty::Hard => Some(10),
+            Difficulty::Custom(_) => None,
+            Difficulty::Level(level) => Some(level),
+        }
+    }
 }
 
 impl std::fmt::Display for Difficulty {
@@ -85,6 +110,8 @@ impl std::fmt::Display for Difficulty {
             Difficulty::Easy => f.write_str("easy"),
             Difficulty::Medium => f.write_str("medium"),
             Difficulty::Hard => f.write_str("hard"),
+            Difficulty::Custom(density) => write!(f, "density_{}", density),
+            Difficulty::Level(level) => write!(f, "level_{}", level),
         }
     }
 }
@@ -97,39 +124,121 @@ impl FromStr for Difficulty {
             "easy" => Ok(Difficulty::Easy),
             "medium" => Ok(Difficulty::Medium),
             "hard" => Ok(Difficulty::Hard),
-            _ => Err(format!("Invalid difficulty: {}", s)),
+            _ => {
+                if let Some(Ok(density)) = s.strip_prefix("density_").map(str::parse) {
+                    return Ok(Difficulty::Custom(validate_clue_density(density)?));
+                }
+                match s.strip_prefix("level_").unwrap_or(s).parse() {
+                    Ok(level @ 1..=10) => Ok(Difficulty::Level(level)),
+                    Ok(level) => Err(format!(
+                        "Invalid difficulty level: {} (must be 1-10)",
+                        level
+                    )),
+                    Err(_) => Err(format!("Invalid difficulty: {}", s)),
+                }
+            }
         }
     }
 }
 
-/// Generation strategy
+fn validate_clue_density(density: f32) -> Result<f32, String> {
+    if (0.0..=1.0).contains(&density) {
+        Ok(density)
+    } else {
+        Err(format!(
+            "Invalid clue density: {} (must be in [0.0, 1.0])",
+            density
+        ))
+    }
+}
+
+/// Generation algorithm
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
-pub enum Strategy {
-    #[default]
+pub enum Algorithm {
+    /// Placeholder map with every cell Inside
     Dummy,
+    #[default]
+    /// Random hole-free Inside region bounded by a single loop
+    Loop,
+    /// Loop traced directly by a self-avoiding random walk over hex corners
+    RandomWalk,
+    /// Inside region grown from the center hex to a target fill ratio
+    RegionGrowth,
+    /// Simulated annealing toward a target loop length
+    Annealing,
+}
+
+impl std::fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Algorithm::Dummy => f.write_str("dummy"),
+            Algorithm::Loop => f.write_str("loop"),
+            Algorithm::RandomWalk => f.write_str("random-walk"),
+            Algorithm::RegionGrowth => f.write_str("region-growth"),
+            Algorithm::Annealing => f.write_str("annealing"),
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dummy" => Ok(Algorithm::Dummy),
+            "loop" => Ok(Algorithm::Loop),
+            "random-walk" => Ok(Algorithm::RandomWalk),
+            "region-growth" => Ok(Algorithm::RegionGrowth),
+            "annealing" => Ok(Algorithm::Annealing),
+            _ => Err(format!("Invalid algorithm: {}", s)),
+        }
+    }
+}
+
+/// Puzzle rules variant
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Variant {
+    /// A single loop
+    #[default]
+    Standard,
+    /// Several disjoint loops, none inside another
+    MultiLoop,
+    /// Some clues are sheep (Inside) or wolf (Outside) markers instead of numbers
+    SheepAndWolves,
+    /// Some clues only show whether their number is even or odd
+    Parity,
 }
 
-impl std::fmt::Display for Strategy {
+impl std::fmt::Display for Variant {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Strategy::Dummy => f.write_str("dummy"),
+            Variant::Standard => f.write_str("standard"),
+            Variant::MultiLoop => f.write_str("multi-loop"),
+            Variant::SheepAndWolves => f.write_str("sheep-and-wolves"),
+            Variant::Parity => f.write_str("parity"),
         }
     }
 }
 
-impl FromStr for Strategy {
+impl FromStr for Variant {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "dummy" => Ok(Strategy::Dummy),
-            _ => Err(format!("Invalid strategy: {}", s)),
+            "standard" => Ok(Variant::Standard),
+            "multi-loop" => Ok(Variant::MultiLoop),
+            "sheep-and-wolves" => Ok(Variant::SheepAndWolves),
+            "parity" => Ok(Variant::Parity),
+            _ => Err(format!("Invalid variant: {}", s)),
         }
     }
 }
 
 /// Slitherlink map generator
 #[derive(Debug, FromArgs)]
+#[argh(
+    note = "Use `{command_name} solve <file>` to solve a saved puzzle, `{command_name} verify <dir>` to check a folder of them, or `{command_name} rate <file|dir>` to estimate their difficulty, or `{command_name} bench` to time the solvers."
+)]
 pub struct Args {
     /// hexagonal grid radius - exclusive with and takes precedence over size
     #[argh(option)]
@@ -139,11 +248,17 @@ pub struct Args {
     #[argh(option)]
     pub size: Option<GridSize>,
 
-    /// difficulty of the puzzle to be generated
+    /// difficulty of the puzzle to be generated: easy, medium, hard, or a level from 1 to 10
+    /// (easy, medium and hard are levels 2, 5 and 10)
     #[argh(option)]
     pub difficulty: Option<Difficulty>,
 
-    /// output directory for generated maps. defaults to "./maps"
+    /// fraction of clues left visible, in [0.0, 1.0] - exclusive with and takes precedence over difficulty
+    #[argh(option)]
+    pub clue_density: Option<f32>,
+
+    /// output directory for generated maps. defaults to "./maps". '-' writes every puzzle to
+    /// stdout instead, as one pack (or a JSON array with --format json)
     #[argh(option, default = "String::from(\"./maps\")")]
     pub output: String,
 
@@ -155,11 +270,18 @@ pub struct Args {
     #[argh(option)]
     pub count: Option<u32>,
 
+    /// generate a level progression of N puzzles of one size with strictly increasing measured
+    /// difficulty, picked from a pool of candidates across the 1-10 scale. saved in a
+    /// curriculum folder in order of difficulty
+    #[argh(option)]
+    pub curriculum: Option<u32>,
+
     /// positional count argument. if provided, behaves like --count and --all
     #[argh(positional)]
     pub count_pos: Option<u32>,
 
-    /// input file to load and display (skips generation)
+    /// input file to load and display (skips generation). '-' reads maps from stdin, as written
+    /// by --output -
     #[argh(option)]
     pub load: Option<String>,
 
@@ -167,7 +289,11 @@ pub struct Args {
     #[argh(switch)]
     pub display: bool,
 
-    /// output format (currently only 'binary-full' is supported)
+    /// output format: binary-full (default, <n>.bin), json (<n>.map.json, see the spec for its
+    /// schema), text (<n>.map.txt, rows of clues to edit by hand), svg (<n>.bin with a
+    /// <n>.svg drawing of the puzzle beside it), qr (<n>.bin with a <n>.qr.svg QR code of its
+    /// share code beside it) or html (<n>.bin with a <n>.html page beside it that opens the
+    /// puzzle in the viewer without a server; with --output -, one page of the whole batch)
     #[argh(
         option,
         from_str_fn(output_format_from_str),
@@ -175,49 +301,1043 @@ pub struct Args {
     )]
     pub format: OutputFormat,
 
+    /// with --format svg, also draw each answer with its loop to <n>.solution.svg
+    #[argh(switch)]
+    pub svg_solution: bool,
+
+    /// with --format qr, text put before each share code in its QR code, such as the URL of a
+    /// viewer that reads the code after it
+    #[argh(option)]
+    pub qr_prefix: Option<String>,
+
     /// save maps in legacy format (no flags byte)
     #[argh(switch)]
     pub legacy: bool,
 
-    /// generation strategy (default: dummy)
-    #[argh(option, default = "Strategy::default()")]
-    pub strategy: Strategy,
+    /// save maps at half a byte per hexagon, leaving out the counts, which loading works out
+    /// from the answer
+    #[argh(switch)]
+    pub compress: bool,
+
+    /// generation algorithm: loop, random-walk, region-growth, annealing or dummy (default: loop)
+    #[argh(option, default = "Algorithm::default()")]
+    pub algorithm: Algorithm,
+
+    /// rules variant: standard (default), multi-loop (several disjoint loops, see --loops; loop
+    /// algorithm only), sheep-and-wolves (markers for Inside and Outside cells replace some
+    /// clues) or parity (some clues only show whether they are even or odd)
+    #[argh(option, default = "Variant::default()")]
+    pub variant: Variant,
+
+    /// whether clues count the sides of rim cells facing off the board: counted (default, as
+    /// if beyond the board were Outside) or excluded, to match apps that leave them out
+    #[argh(option, default = "RimEdges::default()")]
+    pub rim_edges: RimEdges,
+
+    /// number of disjoint loops in each answer for the multi-loop variant, from 1 to 15.
+    /// defaults to 2
+    #[argh(option)]
+    pub loops: Option<u8>,
+
+    /// outline of the Inside region for the loop algorithm: blob, crescent or snake, a weighted
+    /// mix to pick from per puzzle such as blob:2,snake:1, or random for the default mix
+    #[argh(option)]
+    pub shape: Option<ShapeWeights>,
+
+    /// JSON file listing generation tasks as [{ radius, difficulty, count, min_loop_length,
+    /// max_loop_length, max_attempts }]. replaces the tasks selected by
+    /// --radius/--size/--difficulty/--all; --count, the loop length flags and --max-attempts
+    /// are used for entries without their own values
+    #[argh(option)]
+    pub tasks_file: Option<String>,
+
+    /// ASCII or JSON region mask to use as the answer key instead of generating one.
+    /// the mask sets the radius, so it can't be combined with --radius, --size or --tasks-file
+    #[argh(option)]
+    pub mask: Option<String>,
+
+    /// ASCII or JSON mask of the board's cells, for irregularly shaped puzzles: hexes outside it
+    /// are cut out as holes. like --mask it sets the radius. loop algorithm only
+    #[argh(option)]
+    pub board: Option<String>,
+
+    /// predefined board outline with its side length in cells: hex, rhombus or triangle, e.g.
+    /// rhombus:5. like --board it sets the radius. loop algorithm only
+    #[argh(option)]
+    pub board_shape: Option<BoardShape>,
+
+    /// fraction of the board inside the loop for the region-growth algorithm, in (0.0, 1.0]. defaults to 0.5
+    #[argh(option)]
+    pub fill_ratio: Option<f64>,
+
+    /// comma-separated fill ratios for the region-growth algorithm, e.g. 0.3,0.4,0.5. every task
+    /// is repeated once per ratio, and each ratio's puzzles go in their own fill_<ratio> folder
+    #[argh(option, from_str_fn(fill_ratios_from_str))]
+    pub fill_ratios: Option<Vec<f64>>,
+
+    /// number of edges in the solution loop for the annealing algorithm. defaults to the number of cells
+    #[argh(option)]
+    pub target_loop_length: Option<u32>,
+
+    /// symmetry of the region and visible clues: none, rotational (2-fold), rotational3 or mirror.
+    /// only supported by the region-growth algorithm
+    #[argh(option)]
+    pub symmetry: Option<Symmetry>,
+
+    /// minimum number of edges in the solution loop, e.g. to give Hard puzzles long, winding loops
+    #[argh(option)]
+    pub min_loop_length: Option<u32>,
+
+    /// maximum number of edges in the solution loop, e.g. to give Easy puzzles short, simple loops
+    #[argh(option)]
+    pub max_loop_length: Option<u32>,
+
+    /// visual character of the loop: relaxed (default) accepts any loop, strict rejects long
+    /// straight stretches and large blobs of 0 clues
+    #[argh(option, default = "Style::default()")]
+    pub style: Style,
+
+    /// where visible clues end up: random (default), border (outer rings, with interior clues
+    /// only where uniqueness needs them), spread (evenly distributed) or clustered
+    #[argh(option, default = "CluePlacement::default()")]
+    pub clue_placement: CluePlacement,
+
+    /// rate difficulty by the deduction techniques a solver needs (counting for easy, chains for
+    /// medium, trial and error for hard) instead of the fraction of clues hidden
+    #[argh(switch)]
+    pub rate_difficulty: bool,
+
+    /// solver checking that puzzles stay unique while clues are hidden: backtracking (default)
+    /// or sat, which copes better with huge boards (needs slithergen built with --features sat)
+    #[argh(option, default = "SolverBackend::default()")]
+    pub solver: SolverBackend,
+
+    /// only accept puzzles solvable with these techniques, e.g. for tutorials. comma-separated
+    /// list of zero-six (0 and 6 clues), counting, chain, and trial or trial-N (trial and error
+    /// nested at most N deep)
+    #[argh(option)]
+    pub techniques: Option<TechniqueSet>,
+
+    /// number of candidate answers to try per puzzle before giving up on the generation constraints.
+    /// defaults to 100
+    #[argh(option)]
+    pub max_attempts: Option<u32>,
+
+    /// keep puzzles that repeat an earlier puzzle of the run up to rotation or reflection,
+    /// instead of regenerating them
+    #[argh(switch)]
+    pub allow_duplicates: bool,
+
+    /// also save each puzzle's solving trace, the ordered deductions of the logical solver,
+    /// as <n>.trace.json next to <n>.bin
+    #[argh(switch)]
+    pub trace: bool,
+
+    /// also save each puzzle without its answer as <n>.puzzle.json, for shipping to players;
+    /// <n>.bin (or <n>.map.json) keeps the answer key
+    #[argh(switch)]
+    pub player_files: bool,
+
+    /// save each puzzle without its answer, for publishing: regions are left out except behind
+    /// markers, hidden clues lose their counts, and the metadata drops the seed and loop length.
+    /// binary maps set a flag and zero the region bits
+    #[argh(switch)]
+    pub strip_answers: bool,
+
+    /// also save every generated puzzle, with its answer, to this one pack file (.slpack)
+    #[argh(option)]
+    pub pack: Option<String>,
+
+    /// write tuning_report.json to the output folder: for every task, how many candidate
+    /// answers were tried and how many were rejected by each check (loop too short, difficulty,
+    /// duplicate, ...)
+    #[argh(switch)]
+    pub tuning_report: bool,
+
+    /// write a manifest of every saved puzzle to the output folder, as manifest.csv (csv) or
+    /// manifest.tsv (tsv): its file, radius, difficulty, seed, loop length, clue count and
+    /// measured difficulty
+    #[argh(option, from_str_fn(manifest_format_from_str))]
+    pub manifest: Option<ManifestFormat>,
+
+    /// how to name saved puzzles (default: {index}.{ext}), such as
+    /// {size}-{difficulty}-{seed}-{index}.{ext}. placeholders: {size}, {radius}, {difficulty},
+    /// {seed} and {index}, the position in its folder's run. must end in .{ext} and hold
+    /// {index} or {seed}; files saved beside a puzzle take its name in place of the number
+    #[argh(option, from_str_fn(name_template_from_str))]
+    pub name_template: Option<NameTemplate>,
+
+    /// what to do when a puzzle's file already exists: error (default; stop before generating
+    /// anything), skip (keep the old puzzle and don't generate this one; not with --curriculum),
+    /// overwrite, or suffix (save as <n>-1, <n>-2, ...)
+    #[argh(option, from_str_fn(on_conflict_from_str))]
+    pub on_conflict: Option<OnConflict>,
+
+    /// time limit in seconds for each puzzle. when it runs out, the puzzle is saved with the clues
+    /// hidden so far (still uniquely solvable) and marked as timed out
+    #[argh(option)]
+    pub timeout_secs: Option<u64>,
+
+    /// number of worker threads for batch generation. threads left without a puzzle help check
+    /// clues for uniqueness. defaults to the number of CPU cores
+    #[argh(option)]
+    pub threads: Option<usize>,
+
+    /// show generation progress (attempts, clues hidden, puzzles finished) on stderr
+    #[argh(switch)]
+    pub progress: bool,
+
+    /// seed for reproducible generation. puzzle n of the run (counting from 0) uses seed + n,
+    /// so `--seed <recorded seed> --count 1` regenerates a single puzzle. random if omitted
+    #[argh(option)]
+    pub seed: Option<u64>,
+}
+
+/// Solve a saved puzzle from its visible clues and report whether it has a unique solution.
+/// Exits with status 1 if it can't be loaded, 2 if it has no solution and 3 if it has several
+#[derive(Debug, FromArgs)]
+pub struct SolveArgs {
+    /// puzzle file to solve. the answer stored in it is ignored
+    #[argh(positional)]
+    pub file: String,
+
+    /// write the solved map to this file, as JSON if it ends in .json or text if it ends in
+    /// .txt
+    #[argh(option)]
+    pub output: Option<String>,
+
+    /// display the solved puzzle, or every solution found side by side
+    #[argh(switch)]
+    pub display: bool,
+
+    /// debugging: how many solutions to look for, at least 2 (default 2)
+    #[argh(option, default = "2")]
+    pub solutions: usize,
+
+    /// debugging: save every solution found to this folder, as solution_0.bin, solution_1.bin,
+    /// ... and their loops as edge lists in solutions.json
+    #[argh(option)]
+    pub trace_dir: Option<String>,
+}
+
+/// Check every saved puzzle in a folder: the file loads, its clues match its answer, the
+/// answer is a valid loop and the visible clues have exactly one solution
+#[derive(Debug, FromArgs)]
+pub struct VerifyArgs {
+    /// folder to check, searched recursively for .bin, .map.json, .map.txt and .slpack files
+    #[argh(positional)]
+    pub dir: String,
+}
+
+/// Rate saved puzzles by the hardest technique needed to solve them, and estimate their
+/// difficulty from it
+#[derive(Debug, FromArgs)]
+pub struct RateArgs {
+    /// puzzle file or pack, or folder searched recursively for .bin, .map.json, .map.txt and
+    /// .slpack files
+    #[argh(positional)]
+    pub path: String,
+}
+
+/// List the cells where two saved maps differ in region, clue count or clue shown
+#[derive(Debug, FromArgs)]
+pub struct CompareArgs {
+    /// first map file
+    #[argh(positional)]
+    pub first: String,
+
+    /// second map file
+    #[argh(positional)]
+    pub second: String,
+}
+
+/// Import a puzzle from another program into a map file, to solve, rate or re-export. Reads
+/// game IDs of Loopy's honeycomb grid, such as 5x4t2dh:2a3b...
+#[derive(Debug, FromArgs)]
+pub struct ImportArgs {
+    /// game ID, or a file holding one
+    #[argh(positional)]
+    pub source: String,
+
+    /// map file to write: binary, or JSON or text if it ends in .json or .txt. Imported puzzles
+    /// have no answer, so they load as puzzles but can't be verified
+    #[argh(positional)]
+    pub output: String,
+}
+
+/// Print a short share code for a saved map, to paste in chat instead of attaching the file
+#[derive(Debug, FromArgs)]
+pub struct EncodeArgs {
+    /// map file to encode, with its answer
+    #[argh(positional)]
+    pub file: String,
+}
+
+/// Open a puzzle in the viewer to solve, resuming from a save file made by its Save Progress
+/// button
+#[derive(Debug, FromArgs)]
+pub struct PlayArgs {
+    /// save file to resume, or any map file to start afresh
+    #[argh(positional)]
+    pub file: String,
+}
+
+/// Print the byte layout of the binary map format this build writes, for writing parsers
+#[derive(Debug, FromArgs)]
+pub struct FormatSpecArgs {
+    /// print the layout of puzzle packs (.slpack) instead
+    #[argh(switch)]
+    pub pack: bool,
+}
+
+/// Rewrite binary maps in another layout, such as old answer files in the current one
+#[derive(Debug, FromArgs)]
+pub struct ConvertArgs {
+    /// map file, or folder to convert every .bin file under
+    #[argh(positional)]
+    pub path: String,
+
+    /// only convert files in this layout: legacy, v1, v2 or v3 (default: all)
+    #[argh(option, from_str_fn(map_version_from_str))]
+    pub from: Option<MapVersion>,
+
+    /// layout to write: v3 (default, the current one), compressed (v3 without counts) or legacy
+    #[argh(
+        option,
+        from_str_fn(map_encoding_from_str),
+        default = "MapEncoding::Standard"
+    )]
+    pub to: MapEncoding,
+
+    /// folder to write the converted files to, keeping their paths under <path>, instead of
+    /// replacing them
+    #[argh(option)]
+    pub output: Option<String>,
+}
+
+/// Turn a share code back into a map
+#[derive(Debug, FromArgs)]
+pub struct DecodeArgs {
+    /// share code printed by encode
+    #[argh(positional)]
+    pub code: String,
+
+    /// write the map to this file, as JSON if it ends in .json or text if it ends in .txt.
+    /// prints it as text if omitted
+    #[argh(option)]
+    pub output: Option<String>,
+
+    /// display the decoded puzzle
+    #[argh(switch)]
+    pub display: bool,
+}
+
+/// Lay saved puzzles out several to a page in one PDF, for printing, with their answers at the
+/// back
+#[derive(Debug, FromArgs)]
+pub struct PrintArgs {
+    /// puzzle file or pack, or folder searched recursively for .bin, .map.json, .map.txt and
+    /// .slpack files
+    #[argh(positional)]
+    pub path: String,
+
+    /// PDF file to write (default: puzzles.pdf)
+    #[argh(option, default = "String::from(\"puzzles.pdf\")")]
+    pub output: String,
+
+    /// paper size: a4 (default) or letter
+    #[argh(option, default = "PaperSize::default()")]
+    pub paper: PaperSize,
+
+    /// puzzles on each page, from 1 to 12 (default 4)
+    #[argh(option, from_str_fn(per_page_from_str), default = "4")]
+    pub per_page: usize,
+
+    /// heading of every page (default: Slitherlink)
+    #[argh(option, default = "String::from(\"Slitherlink\")")]
+    pub title: String,
+}
+
+/// Time each solver backend on a reproducible corpus of generated puzzles per board size
+#[derive(Debug, FromArgs)]
+pub struct BenchArgs {
+    /// board size to benchmark: small, medium, large or huge. repeat for several (default:
+    /// small, medium and large)
+    #[argh(option)]
+    pub size: Vec<GridSize>,
+
+    /// puzzles per size (default 5)
+    #[argh(option, default = "5")]
+    pub count: usize,
+
+    /// seed of the first puzzle of each corpus, the others counting up from it (default 0)
+    #[argh(option, default = "0")]
+    pub seed: u64,
+
+    /// solver backend to time: backtracking or sat. repeat for several (default: every
+    /// backend in this build)
+    #[argh(option)]
+    pub solver: Vec<SolverBackend>,
+}
+
+/// What to run, picked by the first argument
+#[derive(Debug)]
+pub enum Command {
+    Generate(Box<Args>),
+    Solve(SolveArgs),
+    Verify(VerifyArgs),
+    Rate(RateArgs),
+    Bench(BenchArgs),
+    Compare(CompareArgs),
+    Print(PrintArgs),
+    Import(ImportArgs),
+    Encode(EncodeArgs),
+    Decode(DecodeArgs),
+    FormatSpec(FormatSpecArgs),
+    Convert(ConvertArgs),
+    Play(PlayArgs),
+}
+
+impl Command {
+    /// Parse the arguments after the program name. A leading `solve`, `verify`, `rate`,
+    /// `bench`, `compare`, `print`, `import`, `encode`, `decode`, `format-spec`, `convert` or
+    /// `play` picks that subcommand, which is split off here because argh can't mix
+    /// subcommands with the positional count.
+    pub fn from_args(program: &str, args: &[&str]) -> Result<Self, argh::EarlyExit> {
+        match args.split_first() {
+            Some((&"solve", rest)) => {
+                SolveArgs::from_args(&[program, "solve"], rest).map(Command::Solve)
+            }
+            Some((&"verify", rest)) => {
+                VerifyArgs::from_args(&[program, "verify"], rest).map(Command::Verify)
+            }
+            Some((&"rate", rest)) => {
+                RateArgs::from_args(&[program, "rate"], rest).map(Command::Rate)
+            }
+            Some((&"bench", rest)) => {
+                BenchArgs::from_args(&[program, "bench"], rest).map(Command::Bench)
+            }
+            Some((&"compare", rest)) => {
+                CompareArgs::from_args(&[program, "compare"], rest).map(Command::Compare)
+            }
+            Some((&"print", rest)) => {
+                PrintArgs::from_args(&[program, "print"], rest).map(Command::Print)
+            }
+            Some((&"import", rest)) => {
+                ImportArgs::from_args(&[program, "import"], rest).map(Command::Import)
+            }
+            Some((&"encode", rest)) => {
+                EncodeArgs::from_args(&[program, "encode"], rest).map(Command::Encode)
+            }
+            Some((&"decode", rest)) => {
+                DecodeArgs::from_args(&[program, "decode"], rest).map(Command::Decode)
+            }
+            Some((&"convert", rest)) => {
+                ConvertArgs::from_args(&[program, "convert"], rest).map(Command::Convert)
+            }
+            Some((&"play", rest)) => {
+                PlayArgs::from_args(&[program, "play"], rest).map(Command::Play)
+            }
+            Some((&"format-spec", rest)) => {
+                FormatSpecArgs::from_args(&[program, "format-spec"], rest).map(Command::FormatSpec)
+            }
+            _ => Args::from_args(&[program], args).map(|args| Command::Generate(Box::new(args))),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum OutputFormat {
     BinaryFull,
+    Json,
+    /// Rows of clues and of the answer, for editing by hand and diffing
+    Text,
+    /// Binary maps, each with an SVG drawing of the puzzle
+    Svg,
+    /// Binary maps, each with an SVG QR code of its share code
+    Qr,
+    /// Binary maps, each with a standalone viewer page
+    Html,
+}
+
+impl OutputFormat {
+    /// File extension of saved maps; `io::save_map` picks the format from it
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::BinaryFull
+            | OutputFormat::Svg
+            | OutputFormat::Qr
+            | OutputFormat::Html => "bin",
+            OutputFormat::Json => "map.json",
+            OutputFormat::Text => "map.txt",
+        }
+    }
+}
+
+fn check_fill_ratio(fill_ratio: f64) -> Result<(), String> {
+    if fill_ratio > 0.0 && fill_ratio <= 1.0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid fill ratio: {} (must be in (0.0, 1.0])",
+            fill_ratio
+        ))
+    }
+}
+
+fn fill_ratios_from_str(s: &str) -> Result<Vec<f64>, String> {
+    s.split(',')
+        .map(|ratio| {
+            let ratio = ratio
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid fill ratio: {}", ratio))?;
+            check_fill_ratio(ratio)?;
+            Ok(ratio)
+        })
+        .collect()
+}
+
+fn per_page_from_str(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(n) if (1..=MAX_PER_PAGE).contains(&n) => Ok(n),
+        _ => Err(format!(
+            "Invalid puzzles per page: {} (must be from 1 to {})",
+            s, MAX_PER_PAGE
+        )),
+    }
+}
+
+fn map_version_from_str(s: &str) -> Result<MapVersion, String> {
+    match s {
+        "legacy" => Ok(MapVersion::Legacy),
+        "v1" => Ok(MapVersion::V1),
+        "v2" => Ok(MapVersion::V2),
+        "v3" => Ok(MapVersion::V3),
+        _ => Err(format!("Unknown map layout: {}", s)),
+    }
+}
+
+fn map_encoding_from_str(s: &str) -> Result<MapEncoding, String> {
+    match s {
+        "v3" => Ok(MapEncoding::Standard),
+        "compressed" => Ok(MapEncoding::Compressed),
+        "legacy" => Ok(MapEncoding::Legacy),
+        "v2" => {
+            Err("Maps are written as v3, which is v2 with a checksum; pass --to v3".to_string())
+        }
+        _ => Err(format!("Unknown map layout: {}", s)),
+    }
+}
+
+fn manifest_format_from_str(s: &str) -> Result<ManifestFormat, String> {
+    match s {
+        "csv" => Ok(ManifestFormat::Csv),
+        "tsv" => Ok(ManifestFormat::Tsv),
+        _ => Err(format!("Unknown manifest format: {}", s)),
+    }
 }
 
 fn output_format_from_str(s: &str) -> Result<OutputFormat, String> {
     match s {
         "binary-full" => Ok(OutputFormat::BinaryFull),
+        "json" => Ok(OutputFormat::Json),
+        "text" => Ok(OutputFormat::Text),
+        "svg" => Ok(OutputFormat::Svg),
+        "qr" => Ok(OutputFormat::Qr),
+        "html" => Ok(OutputFormat::Html),
         _ => Err(format!("Unknown format: {}", s)),
     }
 }
 
+/// What `generate` does with a puzzle whose file already exists from an earlier run
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OnConflict {
+    /// Stop before generating anything
+    #[default]
+    Error,
+    /// Keep the file there and don't generate its puzzle, so a seeded rerun fills in the
+    /// missing ones
+    Skip,
+    Overwrite,
+    /// Save under the first free name with `-1`, `-2`, ... after the template's
+    Suffix,
+}
+
+fn on_conflict_from_str(s: &str) -> Result<OnConflict, String> {
+    match s {
+        "error" => Ok(OnConflict::Error),
+        "skip" => Ok(OnConflict::Skip),
+        "overwrite" => Ok(OnConflict::Overwrite),
+        "suffix" => Ok(OnConflict::Suffix),
+        _ => Err(format!("Unknown conflict policy: {}", s)),
+    }
+}
+
+/// Placeholders a name template may hold
+const NAME_PLACEHOLDERS: [&str; 5] = ["size", "radius", "difficulty", "seed", "index"];
+
+/// How saved puzzles are named, from `--name-template`: text with placeholders in braces,
+/// ending in `.{ext}`
+#[derive(Clone, Debug, PartialEq)]
+pub struct NameTemplate {
+    /// The template without its `.{ext}` ending, which names the files beside the puzzle too
+    stem: String,
+}
+
+impl Default for NameTemplate {
+    fn default() -> Self {
+        Self {
+            stem: "{index}".to_string(),
+        }
+    }
+}
+
+/// What the placeholders of a name template stand for, for one puzzle
+pub struct NameFields<'a> {
+    /// Output folder name of the board size, such as `small` or `radius_5`
+    pub size: &'a str,
+    pub radius: u8,
+    pub difficulty: &'a str,
+    pub seed: u64,
+    pub index: usize,
+}
+
+impl NameTemplate {
+    /// The name of a puzzle without its extension, which the files saved beside it share
+    pub fn stem(&self, fields: &NameFields) -> String {
+        self.stem
+            .replace("{size}", fields.size)
+            .replace("{radius}", &fields.radius.to_string())
+            .replace("{difficulty}", fields.difficulty)
+            .replace("{seed}", &fields.seed.to_string())
+            .replace("{index}", &fields.index.to_string())
+    }
+}
+
+fn name_template_from_str(s: &str) -> Result<NameTemplate, String> {
+    let stem = s
+        .strip_suffix(".{ext}")
+        .ok_or_else(|| format!("Name template {} must end in .{{ext}}", s))?;
+    if stem.contains(['/', '\\']) {
+        return Err(format!("Name template {} can't hold path separators", s));
+    }
+    let mut rest = stem;
+    let mut placeholders = Vec::new();
+    while let Some(start) = rest.find(['{', '}']) {
+        let close = rest[start..].find('}').map(|end| start + end);
+        let Some(end) = close.filter(|_| rest[start..].starts_with('{')) else {
+            return Err(format!("Unbalanced braces in name template {}", s));
+        };
+        let name = &rest[start + 1..end];
+        if !NAME_PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "Unknown placeholder {{{}}} in name template {}",
+                name, s
+            ));
+        }
+        placeholders.push(name);
+        rest = &rest[end + 1..];
+    }
+    if !placeholders.contains(&"index") && !placeholders.contains(&"seed") {
+        return Err(format!(
+            "Name template {} needs {{index}} or {{seed}} to tell puzzles apart",
+            s
+        ));
+    }
+    Ok(NameTemplate {
+        stem: stem.to_string(),
+    })
+}
+
+/// A single generation job: `count` puzzles of the given radius and difficulty
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Task {
+    pub radius: u8,
+    pub difficulty: Difficulty,
+    pub count: u32,
+    /// Solution loop length bounds, enforced by regenerating answers that fall outside them
+    pub loop_length: LoopLengthBounds,
+    /// Candidate answers to try per puzzle, overriding --max-attempts for this task
+    pub max_attempts: Option<u32>,
+    /// Fraction of the board inside the loop, overriding --fill-ratio for this task
+    pub fill_ratio: Option<f64>,
+}
+
+/// One entry of a `--tasks-file`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TaskFileEntry {
+    radius: u8,
+    difficulty: String,
+    count: Option<u32>,
+    min_loop_length: Option<u32>,
+    max_loop_length: Option<u32>,
+    max_attempts: Option<u32>,
+}
+
 // Rewriting Config to handle custom radius vs GridSize clearer.
 // Actually, I will simplify: we generate a list of (Radius, Difficulty).
 // GridSize is just a helper for Radius.
 #[derive(Debug, PartialEq)]
 pub struct ResolvedConfig {
     pub output_dir: PathBuf,
+    /// Write the puzzles to stdout rather than to files under `output_dir`
+    pub to_stdout: bool,
+    /// Default number of puzzles per task (from --count or the positional count)
     pub count_per_task: u32,
-    pub tasks: Vec<(u8, Difficulty)>, // radius, difficulty
+    pub tasks: Vec<Task>,
     pub load_path: Option<PathBuf>,
     pub display: bool,
     pub format: OutputFormat,
-    pub legacy: bool,
-    pub strategy: Strategy,
+    /// Layout of binary maps, from --legacy or --compress
+    pub encoding: MapEncoding,
+    pub algorithm: Algorithm,
+    pub variant: Variant,
+    /// User-supplied answer key replacing the generation algorithm
+    pub mask: Option<RegionMask>,
+    /// Cells of an irregular board to generate on, in place of the whole hexagon
+    pub board: Option<RegionMask>,
+    pub generator: GeneratorConfig,
+    pub seed: Option<u64>,
+    pub allow_duplicates: bool,
+    /// Number of puzzles in a difficulty ramp replacing the tasks (uses the single task's radius)
+    pub curriculum: Option<u32>,
+    /// Save a solving trace next to every puzzle
+    pub trace: bool,
+    /// Save a copy of every puzzle without its answer next to it
+    pub player_files: bool,
+    /// Save every puzzle without its answer, in place of the full map
+    pub strip_answers: bool,
+    /// With `OutputFormat::Svg`, also draw every answer next to its puzzle
+    pub svg_solution: bool,
+    /// With `OutputFormat::Qr`, text put before each share code in its QR code
+    pub qr_prefix: Option<String>,
+    /// Pack file to save every generated puzzle to as well
+    pub pack: Option<PathBuf>,
+    /// Write per-task rejection statistics to tuning_report.json
+    pub tuning_report: bool,
+    /// List every saved puzzle in a manifest in the output folder
+    pub manifest: Option<ManifestFormat>,
+    /// How saved puzzles and the files beside them are named
+    pub name_template: NameTemplate,
+    /// What to do with puzzles whose file already exists
+    pub on_conflict: OnConflict,
+    /// Worker threads for batch generation
+    pub threads: usize,
+    /// Report generation progress on stderr
+    pub progress: bool,
 }
 
 impl Args {
-    pub fn normalize(&self) -> ResolvedConfig {
+    /// Resolve the raw CLI arguments into a list of generation tasks.
+    ///
+    /// Precedence: if `--tasks-file` is given, its entries are the complete task list and
+    /// `--radius`, `--size`, `--difficulty` and `--all` are ignored. A `count` in a file entry
+    /// wins over `--count` (or the positional count), which only fills in entries without one.
+    /// An error here makes `generate` exit with status 1 before doing anything.
+    pub fn normalize(&self) -> Result<ResolvedConfig, String> {
         let count = self.count_pos.or(self.count).unwrap_or(1);
         let output_dir = PathBuf::from(&self.output);
+        let to_stdout = self.output == "-";
+        if to_stdout {
+            let flags = [
+                ("--curriculum", self.curriculum.is_some()),
+                ("--trace", self.trace),
+                ("--player-files", self.player_files),
+                ("--tuning-report", self.tuning_report),
+                ("--manifest", self.manifest.is_some()),
+                ("--name-template", self.name_template.is_some()),
+                ("--on-conflict", self.on_conflict.is_some()),
+                ("--legacy", self.legacy),
+                ("--compress", self.compress),
+                ("--display", self.display),
+                ("--format text", self.format == OutputFormat::Text),
+                ("--format svg", self.format == OutputFormat::Svg),
+                ("--format qr", self.format == OutputFormat::Qr),
+            ];
+            if let Some((flag, _)) = flags.iter().find(|(_, set)| *set) {
+                return Err(format!("{} can't be combined with --output -", flag));
+            }
+        }
+
+        if let Some(density) = self.clue_density {
+            validate_clue_density(density)?;
+        }
+
+        let threads = match self.threads {
+            Some(0) => return Err("Invalid thread count: must be at least 1".to_string()),
+            Some(threads) => threads,
+            None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        };
+
+        if let Some(radius) = self.radius {
+            check_radius(radius).map_err(|e| e.to_string())?;
+        }
+        let loop_length = LoopLengthBounds {
+            min: self.min_loop_length,
+            max: self.max_loop_length,
+        };
+        loop_length.validate()?;
 
+        let mask = match &self.mask {
+            Some(path) => {
+                if self.radius.is_some() || self.size.is_some() || self.tasks_file.is_some() {
+                    return Err(
+                        "--mask sets the radius and can't be combined with --radius, --size or --tasks-file"
+                            .to_string(),
+                    );
+                }
+                Some(load_mask(path).map_err(|e| format!("Failed to load mask {}: {}", path, e))?)
+            }
+            None => None,
+        };
+        let board = match (&self.board, self.board_shape) {
+            (Some(_), Some(_)) => {
+                return Err("--board-shape can't be combined with --board".to_string());
+            }
+            (None, None) => None,
+            (path, shape) => {
+                let flag = if path.is_some() {
+                    "--board"
+                } else {
+                    "--board-shape"
+                };
+                if self.radius.is_some() || self.size.is_some() || self.tasks_file.is_some() {
+                    return Err(format!(
+                        "{} sets the radius and can't be combined with --radius, --size or --tasks-file",
+                        flag
+                    ));
+                }
+                if mask.is_some() {
+                    return Err(format!("{} can't be combined with --mask", flag));
+                }
+                if self.algorithm != Algorithm::Loop || self.variant == Variant::MultiLoop {
+                    return Err(format!(
+                        "{} is only supported by the loop algorithm with a single loop, not {} ({})",
+                        flag, self.algorithm, self.variant
+                    ));
+                }
+                match (path, shape) {
+                    (Some(path), _) => Some(
+                        load_mask(path)
+                            .map_err(|e| format!("Failed to load board {}: {}", path, e))?,
+                    ),
+                    (None, shape) => shape.map(BoardShape::mask),
+                }
+            }
+        };
+
+        let mut tasks = match (mask.as_ref().or(board.as_ref()), &self.tasks_file) {
+            (Some(mask), _) => self
+                .difficulties_from_flags()
+                .into_iter()
+                .map(|difficulty| Task {
+                    radius: mask.radius,
+                    difficulty,
+                    count,
+                    loop_length,
+                    max_attempts: None,
+                    fill_ratio: None,
+                })
+                .collect(),
+            (None, Some(path)) => load_tasks_file(path, count, loop_length)?,
+            (None, None) => self.tasks_from_flags(count, loop_length),
+        };
+
+        let mut generator = GeneratorConfig::default();
+        if let Some(fill_ratio) = self.fill_ratio {
+            check_fill_ratio(fill_ratio)?;
+            generator.fill_ratio = fill_ratio;
+        }
+        if let Some(fill_ratios) = &self.fill_ratios {
+            if self.fill_ratio.is_some() {
+                return Err("--fill-ratios can't be combined with --fill-ratio".to_string());
+            }
+            if mask.is_some() || self.algorithm != Algorithm::RegionGrowth {
+                return Err(format!(
+                    "--fill-ratios is only supported by the region-growth algorithm without --mask, not {}",
+                    self.algorithm
+                ));
+            }
+            tasks = tasks
+                .into_iter()
+                .flat_map(|task| {
+                    fill_ratios.iter().map(move |&fill_ratio| Task {
+                        fill_ratio: Some(fill_ratio),
+                        ..task
+                    })
+                })
+                .collect();
+        }
+        generator.target_loop_length = self.target_loop_length;
+        if let Some(max_attempts) = self.max_attempts {
+            if max_attempts == 0 {
+                return Err("Invalid max attempts: must be at least 1".to_string());
+            }
+            generator.constraints.max_attempts = max_attempts;
+        }
+        generator.constraints.style = self.style;
+        generator.constraints.clue_placement = self.clue_placement;
+        generator.constraints.rate_difficulty = self.rate_difficulty;
+        generator.constraints.solver = self.solver;
+        generator.constraints.techniques = self.techniques;
+        generator.constraints.timeout = self.timeout_secs.map(Duration::from_secs);
+        if let Some(symmetry) = self.symmetry {
+            if symmetry != Symmetry::None && self.algorithm != Algorithm::RegionGrowth {
+                return Err(format!(
+                    "Symmetry is only supported by the region-growth algorithm, not {}",
+                    self.algorithm
+                ));
+            }
+            generator.symmetry = symmetry;
+        }
+        if self.variant == Variant::MultiLoop {
+            if self.mask.is_some() {
+                return Err("The multi-loop variant can't be combined with --mask".to_string());
+            }
+            if self.algorithm != Algorithm::Loop {
+                return Err(format!(
+                    "The multi-loop variant is only supported by the loop algorithm, not {}",
+                    self.algorithm
+                ));
+            }
+            if let Some(loops) = self.loops {
+                if !(1..=15).contains(&loops) {
+                    return Err(format!(
+                        "Invalid loop count: {} (must be in [1, 15])",
+                        loops
+                    ));
+                }
+                generator.loop_count = loops;
+            }
+        } else if self.loops.is_some() {
+            return Err("--loops is only supported by the multi-loop variant".to_string());
+        }
+        if let Some(shapes) = self.shape {
+            if self.mask.is_some() || board.is_some() {
+                return Err(
+                    "--shape can't be combined with --mask, --board or --board-shape".to_string(),
+                );
+            }
+            if self.algorithm != Algorithm::Loop || self.variant == Variant::MultiLoop {
+                return Err(format!(
+                    "--shape is only supported by the loop algorithm with a single loop, not {} ({})",
+                    self.algorithm, self.variant
+                ));
+            }
+            // A snake is one cell wide throughout, so the pinched-cell limit would reject them all
+            if shapes.snake > 0 {
+                generator.constraints.max_pinched_fraction = 1.0;
+            }
+            generator.shapes = Some(shapes);
+        }
+        if let Some(curriculum) = self.curriculum {
+            if curriculum == 0 {
+                return Err("Invalid curriculum: must have at least 1 puzzle".to_string());
+            }
+            if self.difficulty.is_some() || self.clue_density.is_some() {
+                return Err(
+                    "--curriculum picks its own difficulties, so it can't be combined with --difficulty or --clue-density"
+                        .to_string(),
+                );
+            }
+            if tasks.iter().any(|t| t.radius != tasks[0].radius) {
+                return Err(
+                    "--curriculum needs a single size: pass --radius, --size, --mask, --board or --board-shape"
+                        .to_string(),
+                );
+            }
+            if self.tuning_report {
+                return Err(
+                    "--tuning-report reports on tasks, which --curriculum replaces".to_string(),
+                );
+            }
+            if self.on_conflict == Some(OnConflict::Skip) {
+                return Err(
+                    "--on-conflict skip keeps puzzles by name, which --curriculum only learns once it has picked them"
+                        .to_string(),
+                );
+            }
+        }
+        let encoding = match (self.legacy, self.compress) {
+            (true, true) => {
+                return Err("--legacy can't be combined with --compress".to_string());
+            }
+            (true, false) => MapEncoding::Legacy,
+            (false, true) => MapEncoding::Compressed,
+            (false, false) => MapEncoding::Standard,
+        };
+        if encoding != MapEncoding::Standard && self.format != OutputFormat::BinaryFull {
+            return Err("--legacy and --compress only apply to --format binary-full".to_string());
+        }
+        if self.legacy && self.variant == Variant::MultiLoop {
+            return Err(
+                "--legacy can't store the loop count of the multi-loop variant".to_string(),
+            );
+        }
+        if self.legacy && !self.rim_edges.is_counted() {
+            return Err("--legacy can't store clues that exclude the rim".to_string());
+        }
+        if self.svg_solution && self.format != OutputFormat::Svg {
+            return Err("--svg-solution only applies to --format svg".to_string());
+        }
+        if self.qr_prefix.is_some() && self.format != OutputFormat::Qr {
+            return Err("--qr-prefix only applies to --format qr".to_string());
+        }
+        if self.strip_answers {
+            // Each of these saves or needs the answer
+            let flags = [
+                ("--trace", self.trace),
+                ("--svg-solution", self.svg_solution),
+                ("--player-files", self.player_files),
+                ("--legacy", self.legacy),
+                ("--compress", self.compress),
+                ("--format qr", self.format == OutputFormat::Qr),
+            ];
+            if let Some((flag, _)) = flags.iter().find(|(_, set)| *set) {
+                return Err(format!("{} can't be combined with --strip-answers", flag));
+            }
+        }
+        generator.constraints.markers = self.variant == Variant::SheepAndWolves;
+        generator.constraints.parity = self.variant == Variant::Parity;
+        generator.constraints.rim_edges = self.rim_edges;
+
+        Ok(ResolvedConfig {
+            output_dir,
+            to_stdout,
+            count_per_task: count,
+            tasks,
+            load_path: self.load.as_ref().map(PathBuf::from),
+            display: self.display,
+            format: self.format,
+            encoding,
+            algorithm: self.algorithm,
+            variant: self.variant,
+            mask,
+            board,
+            generator,
+            seed: self.seed,
+            allow_duplicates: self.allow_duplicates,
+            curriculum: self.curriculum,
+            trace: self.trace,
+            player_files: self.player_files,
+            strip_answers: self.strip_answers,
+            svg_solution: self.svg_solution,
+            qr_prefix: self.qr_prefix.clone(),
+            pack: self.pack.as_ref().map(PathBuf::from),
+            tuning_report: self.tuning_report,
+            manifest: self.manifest,
+            name_template: self.name_template.clone().unwrap_or_default(),
+            on_conflict: self.on_conflict.unwrap_or_default(),
+            threads,
+            progress: self.progress,
+        })
+    }
+
+    fn tasks_from_flags(&self, count: u32, loop_length: LoopLengthBounds) -> Vec<Task> {
         let specific_size_or_radius = self.size.is_some() || self.radius.is_some();
-        let specific_difficulty = self.difficulty.is_some();
+        let specific_difficulty = self.difficulty.is_some() || self.clue_density.is_some();
         let force_all = self.all || self.count_pos.is_some();
 
         // Logic for Radii
@@ -245,42 +1365,225 @@ impl Args {
             GridSize::all().iter().map(|s| *s as u8).collect()
         };
 
-        // Logic for Difficulties
-        let final_difficulties: Vec<Difficulty> = if specific_difficulty {
-            vec![self.difficulty.unwrap()]
-        } else {
-            Difficulty::all().to_vec()
-        };
+        let final_difficulties = self.difficulties_from_flags();
 
         let mut tasks = Vec::new();
         for &r in &final_radii {
             for &d in &final_difficulties {
-                tasks.push((r, d));
+                tasks.push(Task {
+                    radius: r,
+                    difficulty: d,
+                    count,
+                    loop_length,
+                    max_attempts: None,
+                    fill_ratio: None,
+                });
             }
         }
+        tasks
+    }
 
-        ResolvedConfig {
-            output_dir,
-            count_per_task: count,
-            tasks,
-            load_path: self.load.as_ref().map(PathBuf::from),
-            display: self.display,
-            format: self.format,
-            legacy: self.legacy,
-            strategy: self.strategy,
+    /// Difficulties selected by --clue-density or --difficulty, or all of them if neither is given
+    fn difficulties_from_flags(&self) -> Vec<Difficulty> {
+        if let Some(density) = self.clue_density {
+            vec![Difficulty::Custom(density)]
+        } else if let Some(difficulty) = self.difficulty {
+            vec![difficulty]
+        } else {
+            Difficulty::all().to_vec()
         }
     }
 }
 
+/// Read a JSON tasks file, using `default_count` and `default_loop_length` for entries that
+/// don't specify their own
+fn load_tasks_file(
+    path: &str,
+    default_count: u32,
+    default_loop_length: LoopLengthBounds,
+) -> Result<Vec<Task>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read tasks file {}: {}", path, e))?;
+    parse_tasks(&contents, default_count, default_loop_length)
+        .map_err(|e| format!("Invalid tasks file {}: {}", path, e))
+}
+
+fn parse_tasks(
+    contents: &str,
+    default_count: u32,
+    default_loop_length: LoopLengthBounds,
+) -> Result<Vec<Task>, String> {
+    let entries: Vec<TaskFileEntry> = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let difficulty = entry
+                .difficulty
+                .parse()
+                .map_err(|e| format!("entry {}: {}", i, e))?;
+            let loop_length = LoopLengthBounds {
+                min: entry.min_loop_length,
+                max: entry.max_loop_length,
+            }
+            .or(default_loop_length);
+            loop_length
+                .validate()
+                .map_err(|e| format!("entry {}: {}", i, e))?;
+            check_radius(entry.radius).map_err(|e| format!("entry {}: {}", i, e))?;
+            if entry.max_attempts == Some(0) {
+                return Err(format!(
+                    "entry {}: invalid max attempts: must be at least 1",
+                    i
+                ));
+            }
+            Ok(Task {
+                radius: entry.radius,
+                difficulty,
+                count: entry.count.unwrap_or(default_count),
+                loop_length,
+                max_attempts: entry.max_attempts,
+                fill_ratio: None,
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use googletest::prelude::*;
 
+    #[googletest::test]
+    fn leading_solve_picks_the_subcommand() {
+        let command = Command::from_args("slithergen", &["solve", "a.bin", "--display"]).unwrap();
+        expect_that!(
+            command,
+            matches_pattern!(Command::Solve(matches_pattern!(SolveArgs {
+                file: eq("a.bin"),
+                output: none(),
+                display: eq(&true),
+                solutions: eq(&2),
+                trace_dir: none(),
+            })))
+        );
+        expect_that!(
+            Command::from_args("slithergen", &["5"]).unwrap(),
+            matches_pattern!(Command::Generate(_))
+        );
+        expect_that!(
+            Command::from_args("slithergen", &["solve"]).is_err(),
+            eq(true)
+        );
+        expect_that!(
+            Command::from_args("slithergen", &["compare", "a.bin", "b.json"]).unwrap(),
+            matches_pattern!(Command::Compare(matches_pattern!(CompareArgs {
+                first: eq("a.bin"),
+                second: eq("b.json"),
+            })))
+        );
+    }
+
+    #[googletest::test]
+    fn print_lays_out_pages() {
+        let command = Command::from_args(
+            "slithergen",
+            &["print", "maps", "--paper", "letter", "--per-page", "6"],
+        )
+        .unwrap();
+        expect_that!(
+            command,
+            matches_pattern!(Command::Print(matches_pattern!(PrintArgs {
+                path: eq("maps"),
+                output: eq("puzzles.pdf"),
+                paper: eq(&PaperSize::Letter),
+                per_page: eq(&6),
+                title: eq("Slitherlink"),
+            })))
+        );
+        for per_page in ["0", "13", "two"] {
+            expect_that!(
+                Command::from_args("slithergen", &["print", "maps", "--per-page", per_page])
+                    .is_err(),
+                eq(true)
+            );
+        }
+    }
+
+    #[googletest::test]
+    fn import_takes_a_source_and_output() {
+        expect_that!(
+            Command::from_args("slithergen", &["import", "4x3t2:l", "a.map.json"]).unwrap(),
+            matches_pattern!(Command::Import(matches_pattern!(ImportArgs {
+                source: eq("4x3t2:l"),
+                output: eq("a.map.json"),
+            })))
+        );
+        expect_that!(
+            Command::from_args("slithergen", &["import", "4x3t2:l"]).is_err(),
+            eq(true)
+        );
+    }
+
+    #[googletest::test]
+    fn share_codes_encode_files_and_decode_codes() {
+        expect_that!(
+            Command::from_args("slithergen", &["encode", "0.bin"]).unwrap(),
+            matches_pattern!(Command::Encode(matches_pattern!(EncodeArgs {
+                file: eq("0.bin"),
+            })))
+        );
+        expect_that!(
+            Command::from_args("slithergen", &["decode", "U0xUSAM", "--output", "a.bin"]).unwrap(),
+            matches_pattern!(Command::Decode(matches_pattern!(DecodeArgs {
+                code: eq("U0xUSAM"),
+                output: some(eq("a.bin")),
+                display: eq(&false),
+            })))
+        );
+    }
+
+    #[googletest::test]
+    fn convert_picks_layouts_to_read_and_write() {
+        expect_that!(
+            Command::from_args("slithergen", &["convert", "--from", "legacy", "old"]).unwrap(),
+            matches_pattern!(Command::Convert(matches_pattern!(ConvertArgs {
+                path: eq("old"),
+                from: some(eq(&MapVersion::Legacy)),
+                to: eq(&MapEncoding::Standard),
+                output: none(),
+            })))
+        );
+        expect_that!(
+            Command::from_args("slithergen", &["convert", "old", "--to", "v2"]).is_err(),
+            eq(true)
+        );
+    }
+
+    #[googletest::test]
+    fn format_spec_prints_maps_or_packs() {
+        expect_that!(
+            Command::from_args("slithergen", &["format-spec", "--pack"]).unwrap(),
+            matches_pattern!(Command::FormatSpec(matches_pattern!(FormatSpecArgs {
+                pack: eq(&true),
+            })))
+        );
+    }
+
+    #[googletest::test]
+    fn play_takes_a_save_or_map_file() {
+        expect_that!(
+            Command::from_args("slithergen", &["play", "0.save.json"]).unwrap(),
+            matches_pattern!(Command::Play(matches_pattern!(PlayArgs {
+                file: eq("0.save.json"),
+            })))
+        );
+    }
+
     #[googletest::test]
     fn no_args_uses_all() -> Result<()> {
         let args = Args::from_args(&[], &[]).unwrap();
-        let config = args.normalize();
+        let config = args.normalize().unwrap();
 
         expect_that!(config.count_per_task, eq(1));
         // Use matches_pattern or eq reference for PathBuf
@@ -293,7 +1596,7 @@ mod tests {
     #[googletest::test]
     fn single_numeric_arg_implies_count_and_all() -> Result<()> {
         let args = Args::from_args(&[], &["5"]).unwrap();
-        let config = args.normalize();
+        let config = args.normalize().unwrap();
 
         expect_that!(config.count_per_task, eq(5));
         expect_that!(config.tasks, len(eq(12))); // All implied
@@ -303,7 +1606,7 @@ mod tests {
     #[googletest::test]
     fn explicit_all_flag_sets_count_one() -> Result<()> {
         let args = Args::from_args(&[], &["--all"]).unwrap();
-        let config = args.normalize();
+        let config = args.normalize().unwrap();
 
         expect_that!(config.count_per_task, eq(1));
         expect_that!(config.tasks, len(eq(12)));
@@ -313,11 +1616,11 @@ mod tests {
     #[googletest::test]
     fn specific_size_filters_tasks() -> Result<()> {
         let args = Args::from_args(&[], &["--size", "small"]).unwrap();
-        let config = args.normalize();
+        let config = args.normalize().unwrap();
 
         // Specific size, default difficulties (all 3)
         expect_that!(config.tasks, len(eq(3)));
-        let radii: Vec<u8> = config.tasks.iter().map(|(r, _)| *r).collect();
+        let radii: Vec<u8> = config.tasks.iter().map(|t| t.radius).collect();
         expect_that!(radii, each(eq(&(GridSize::Small as u8))));
         Ok(())
     }
@@ -325,11 +1628,11 @@ mod tests {
     #[googletest::test]
     fn specific_difficulty_filters_tasks() -> Result<()> {
         let args = Args::from_args(&[], &["--difficulty", "hard"]).unwrap();
-        let config = args.normalize();
+        let config = args.normalize().unwrap();
 
         // Default sizes (all 4), specific difficulty
         expect_that!(config.tasks, len(eq(4)));
-        let difficulties: Vec<Difficulty> = config.tasks.iter().map(|(_, d)| *d).collect();
+        let difficulties: Vec<Difficulty> = config.tasks.iter().map(|t| t.difficulty).collect();
         expect_that!(difficulties, each(eq(&Difficulty::Hard)));
         Ok(())
     }
@@ -337,12 +1640,19 @@ mod tests {
     #[googletest::test]
     fn specific_size_and_diff_singles_task() -> Result<()> {
         let args = Args::from_args(&[], &["--size", "huge", "--difficulty", "easy"]).unwrap();
-        let config = args.normalize();
+        let config = args.normalize().unwrap();
 
         expect_that!(config.tasks, len(eq(1)));
         expect_that!(
             config.tasks,
-            elements_are![eq(&(GridSize::Huge as u8, Difficulty::Easy))]
+            elements_are![eq(&Task {
+                radius: GridSize::Huge as u8,
+                difficulty: Difficulty::Easy,
+                count: 1,
+                loop_length: LoopLengthBounds::default(),
+                max_attempts: None,
+                fill_ratio: None,
+            })]
         );
         Ok(())
     }
@@ -351,7 +1661,7 @@ mod tests {
     fn numeric_arg_with_filter_combines() -> Result<()> {
         // "5 --size small"
         let args = Args::from_args(&[], &["5", "--size", "small"]).unwrap();
-        let config = args.normalize();
+        let config = args.normalize().unwrap();
 
         expect_that!(config.count_per_task, eq(5));
         // Small size, all difficulties (3)
@@ -362,16 +1672,834 @@ mod tests {
     #[googletest::test]
     fn custom_output_dir() -> Result<()> {
         let args = Args::from_args(&[], &["--output", "foo/bar"]).unwrap();
-        let config = args.normalize();
+        let config = args.normalize().unwrap();
         expect_that!(config.output_dir, eq(&PathBuf::from("foo/bar")));
         Ok(())
     }
 
     #[googletest::test]
-    fn default_strategy_is_dummy() -> Result<()> {
+    fn default_algorithm_is_loop() -> Result<()> {
+        let args = Args::from_args(&[], &[]).unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.algorithm, eq(Algorithm::Loop));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn dummy_algorithm_still_selectable() -> Result<()> {
+        let args = Args::from_args(&[], &["--algorithm", "dummy"]).unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.algorithm, eq(Algorithm::Dummy));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn algorithm_names_round_trip() -> Result<()> {
+        for name in ["dummy", "loop", "random-walk", "region-growth", "annealing"] {
+            let args = Args::from_args(&[], &["--algorithm", name]).unwrap();
+            let config = args.normalize().unwrap();
+            expect_that!(config.algorithm.to_string(), eq(name));
+        }
+        expect_that!("spiral".parse::<Algorithm>(), err(anything()));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn tasks_file_replaces_flag_tasks() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.json");
+        std::fs::write(
+            &path,
+            r#"[
+                { "radius": 3, "difficulty": "easy", "count": 7 },
+                { "radius": 20, "difficulty": "hard" }
+            ]"#,
+        )?;
+        let path = path.to_str().unwrap();
+        let args = Args::from_args(
+            &[],
+            &["--tasks-file", path, "--size", "small", "--count", "2"],
+        )
+        .unwrap();
+        let config = args.normalize().unwrap();
+
+        expect_that!(
+            config.tasks,
+            elements_are![
+                eq(&Task {
+                    radius: 3,
+                    difficulty: Difficulty::Easy,
+                    count: 7,
+                    loop_length: LoopLengthBounds::default(),
+                    max_attempts: None,
+                    fill_ratio: None,
+                }),
+                eq(&Task {
+                    radius: 20,
+                    difficulty: Difficulty::Hard,
+                    count: 2,
+                    loop_length: LoopLengthBounds::default(),
+                    max_attempts: None,
+                    fill_ratio: None,
+                }),
+            ]
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn empty_tasks_file_schedules_nothing() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.json");
+        std::fs::write(&path, "[]")?;
+        let args = Args::from_args(&[], &["--tasks-file", path.to_str().unwrap()]).unwrap();
+        let config = args.normalize().unwrap();
+
+        expect_that!(config.tasks, is_empty());
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn malformed_tasks_file_is_reported_as_error() -> Result<()> {
+        expect_that!(
+            parse_tasks(r#"[{ "radius": 2 }]"#, 1, LoopLengthBounds::default()),
+            err(contains_substring("difficulty"))
+        );
+        expect_that!(
+            parse_tasks(
+                r#"[{ "radius": 2, "difficulty": "brutal" }]"#,
+                1,
+                LoopLengthBounds::default()
+            ),
+            err(contains_substring("Invalid difficulty: brutal"))
+        );
+        expect_that!(
+            parse_tasks("not json", 1, LoopLengthBounds::default()),
+            err(anything())
+        );
+        expect_that!(
+            parse_tasks(
+                r#"[{ "radius": 200, "difficulty": "easy" }]"#,
+                1,
+                LoopLengthBounds::default()
+            ),
+            err(contains_substring("entry 0: Radius 200 is too large"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn radius_beyond_the_coordinate_range_is_rejected() {
+        let args = Args::from_args(&[], &["--radius", "127"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("at most 126")));
+    }
+
+    #[googletest::test]
+    fn missing_tasks_file_is_reported_as_error() -> Result<()> {
+        let args = Args::from_args(&[], &["--tasks-file", "/nonexistent/tasks.json"]).unwrap();
+        expect_that!(
+            args.normalize(),
+            err(contains_substring("Failed to read tasks file"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn generator_options_are_passed_to_generator_config() -> Result<()> {
+        let args = Args::from_args(&[], &["--fill-ratio", "0.35"]).unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.generator.fill_ratio, eq(0.35));
+        expect_that!(config.generator.target_loop_length, none());
+
+        let args = Args::from_args(&[], &["--target-loop-length", "42"]).unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.generator.target_loop_length, some(eq(42)));
+
+        let args = Args::from_args(&[], &[]).unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.generator, eq(GeneratorConfig::default()));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn fill_ratios_multiply_tasks() -> Result<()> {
+        let args = Args::from_args(
+            &[],
+            &[
+                "--algorithm",
+                "region-growth",
+                "--radius",
+                "4",
+                "--fill-ratios",
+                "0.3,0.5",
+            ],
+        )
+        .unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.tasks, len(eq(2 * Difficulty::all().len())));
+        expect_that!(config.tasks[0].fill_ratio, some(eq(0.3)));
+        expect_that!(config.tasks[1].fill_ratio, some(eq(0.5)));
+
+        let args = Args::from_args(&[], &["--fill-ratios", "0.3,0.5"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("region-growth")));
+
+        expect_that!(
+            Args::from_args(&[], &["--fill-ratios", "0.3,1.5"]),
+            err(anything())
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn compress_picks_the_compressed_encoding() -> Result<()> {
+        let config = Args::from_args(&[], &[]).unwrap().normalize().unwrap();
+        expect_that!(config.encoding, eq(MapEncoding::Standard));
+        let args = Args::from_args(&[], &["--compress"]).unwrap();
+        expect_that!(
+            args.normalize().unwrap().encoding,
+            eq(MapEncoding::Compressed)
+        );
+
+        let args = Args::from_args(&[], &["--compress", "--legacy"]).unwrap();
+        expect_that!(
+            args.normalize(),
+            err(contains_substring("can't be combined"))
+        );
+
+        let args = Args::from_args(&[], &["--legacy", "--rim-edges", "excluded"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("exclude the rim")));
+        let args = Args::from_args(&[], &["--compress", "--rim-edges", "excluded"]).unwrap();
+        expect_that!(args.normalize(), ok(anything()));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn dash_output_writes_to_stdout() -> Result<()> {
+        let config = Args::from_args(&[], &["--output", "-"])
+            .unwrap()
+            .normalize()
+            .unwrap();
+        expect_that!(config.to_stdout, eq(true));
+
+        let args = Args::from_args(&[], &["--output", "-", "--trace"]).unwrap();
+        expect_that!(
+            args.normalize(),
+            err(eq("--trace can't be combined with --output -"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn json_format_saves_map_json_files() -> Result<()> {
+        let args = Args::from_args(&[], &["--format", "json"]).unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.format.extension(), eq("map.json"));
+
+        let args = Args::from_args(&[], &["--format", "json", "--legacy"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("--legacy")));
+        let args = Args::from_args(&[], &["--format", "json", "--compress"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("--compress")));
+
+        let args = Args::from_args(&[], &["--format", "text"]).unwrap();
+        expect_that!(args.normalize().unwrap().format.extension(), eq("map.txt"));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn svg_format_draws_next_to_binary_maps() -> Result<()> {
+        let args = Args::from_args(&[], &["--format", "svg", "--svg-solution"]).unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.format, eq(OutputFormat::Svg));
+        expect_that!(config.format.extension(), eq("bin"));
+        expect_that!(config.svg_solution, eq(true));
+
+        let args = Args::from_args(&[], &["--svg-solution"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("--format svg")));
+        let args = Args::from_args(&[], &["--format", "svg", "--output", "-"]).unwrap();
+        expect_that!(
+            args.normalize(),
+            err(eq("--format svg can't be combined with --output -"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn stripped_answers_rule_out_files_that_hold_them() -> Result<()> {
+        let args = Args::from_args(&[], &["--strip-answers", "--format", "json"]).unwrap();
+        expect_that!(args.normalize().unwrap().strip_answers, eq(true));
+        for flag in [&["--trace"][..], &["--compress"], &["--format", "qr"]] {
+            let args = Args::from_args(&[], &[&["--strip-answers"], flag].concat()).unwrap();
+            expect_that!(
+                args.normalize(),
+                err(eq(&format!(
+                    "{} can't be combined with --strip-answers",
+                    flag.join(" ")
+                )))
+            );
+        }
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn manifests_are_csv_or_tsv() -> Result<()> {
+        let args = Args::from_args(&[], &["--manifest", "tsv"]).unwrap();
+        expect_that!(
+            args.normalize().unwrap().manifest,
+            some(eq(ManifestFormat::Tsv))
+        );
+        expect_that!(
+            Args::from_args(&[], &["--manifest", "xlsx"]),
+            err(anything())
+        );
+        let args = Args::from_args(&[], &["--manifest", "csv", "--output", "-"]).unwrap();
+        expect_that!(
+            args.normalize(),
+            err(eq("--manifest can't be combined with --output -"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn qr_format_holds_prefixed_share_codes() -> Result<()> {
+        let args =
+            Args::from_args(&[], &["--format", "qr", "--qr-prefix", "https://x.test/#"]).unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.format, eq(OutputFormat::Qr));
+        expect_that!(config.format.extension(), eq("bin"));
+        expect_that!(config.qr_prefix, some(eq("https://x.test/#")));
+
+        let args = Args::from_args(&[], &["--qr-prefix", "x"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("--format qr")));
+        let args = Args::from_args(&[], &["--format", "qr", "--output", "-"]).unwrap();
+        expect_that!(
+            args.normalize(),
+            err(eq("--format qr can't be combined with --output -"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn html_format_pages_each_puzzle_or_the_whole_batch() -> Result<()> {
+        let args = Args::from_args(&[], &["--format", "html"]).unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.format, eq(OutputFormat::Html));
+        expect_that!(config.format.extension(), eq("bin"));
+
+        let args = Args::from_args(&[], &["--format", "html", "--output", "-"]).unwrap();
+        expect_that!(args.normalize().unwrap().to_stdout, eq(true));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn name_templates_fill_in_each_puzzle() -> Result<()> {
+        let fields = NameFields {
+            size: "small",
+            radius: 2,
+            difficulty: "hard",
+            seed: 42,
+            index: 3,
+        };
+        let config = Args::from_args(&[], &[]).unwrap().normalize().unwrap();
+        expect_that!(config.name_template.stem(&fields), eq("3"));
+
+        let args = Args::from_args(
+            &[],
+            &[
+                "--name-template",
+                "{size}-{difficulty}-{seed}-{index}.{ext}",
+            ],
+        )
+        .unwrap();
+        let names = args.normalize().unwrap().name_template;
+        expect_that!(names.stem(&fields), eq("small-hard-42-3"));
+        let names = name_template_from_str("r{radius}_{seed}.{ext}").unwrap();
+        expect_that!(names.stem(&fields), eq("r2_42"));
+
+        for (template, error) in [
+            ("{index}.bin", "must end in .{ext}"),
+            ("{size}/{index}.{ext}", "can't hold path separators"),
+            ("{index}-{level}.{ext}", "Unknown placeholder {level}"),
+            ("{index.{ext}", "Unbalanced braces"),
+            ("index}.{ext}", "Unbalanced braces"),
+            ("{size}-{difficulty}.{ext}", "needs {index} or {seed}"),
+        ] {
+            expect_that!(
+                name_template_from_str(template),
+                err(contains_substring(error))
+            );
+        }
+        let args =
+            Args::from_args(&[], &["--name-template", "{seed}.{ext}", "--output", "-"]).unwrap();
+        expect_that!(
+            args.normalize(),
+            err(eq("--name-template can't be combined with --output -"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn existing_files_are_an_error_unless_told_otherwise() -> Result<()> {
+        let config = Args::from_args(&[], &[]).unwrap().normalize().unwrap();
+        expect_that!(config.on_conflict, eq(OnConflict::Error));
+        let args = Args::from_args(&[], &["--on-conflict", "skip"]).unwrap();
+        expect_that!(args.normalize().unwrap().on_conflict, eq(OnConflict::Skip));
+        expect_that!(
+            Args::from_args(&[], &["--on-conflict", "merge"]),
+            err(anything())
+        );
+        let args = Args::from_args(&[], &["--on-conflict", "suffix", "--output", "-"]).unwrap();
+        expect_that!(
+            args.normalize(),
+            err(eq("--on-conflict can't be combined with --output -"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn out_of_range_fill_ratio_is_rejected() -> Result<()> {
+        for ratio in ["0", "1.5", "-0.2"] {
+            let args = Args::from_args(&[], &["--fill-ratio", ratio]).unwrap();
+            expect_that!(
+                args.normalize(),
+                err(contains_substring("Invalid fill ratio"))
+            );
+        }
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn seed_is_optional() -> Result<()> {
+        let args = Args::from_args(&[], &[]).unwrap();
+        expect_that!(args.normalize().unwrap().seed, none());
+
+        let args = Args::from_args(&[], &["--seed", "1234"]).unwrap();
+        expect_that!(args.normalize().unwrap().seed, some(eq(1234)));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn multi_loop_variant_sets_loop_count() -> Result<()> {
+        let config = Args::from_args(&[], &["--variant", "multi-loop"])
+            .unwrap()
+            .normalize()
+            .unwrap();
+        expect_that!(config.variant, eq(Variant::MultiLoop));
+        expect_that!(config.generator.loop_count, eq(2));
+
+        let args = Args::from_args(&[], &["--variant", "multi-loop", "--loops", "4"]).unwrap();
+        expect_that!(args.normalize().unwrap().generator.loop_count, eq(4));
+
+        let args = Args::from_args(&[], &["--variant", "multi-loop", "--loops", "16"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("loop count")));
+        let args = Args::from_args(&[], &["--loops", "3"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("multi-loop")));
+        let args = Args::from_args(&[], &["--variant", "multi-loop", "--legacy"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("--legacy")));
+        let args = Args::from_args(
+            &[],
+            &["--variant", "multi-loop", "--algorithm", "annealing"],
+        )
+        .unwrap();
+        expect_that!(args.normalize(), err(contains_substring("annealing")));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn sheep_and_wolves_variant_enables_markers() -> Result<()> {
+        let args = Args::from_args(&[], &["--variant", "sheep-and-wolves"]).unwrap();
+        expect_that!(
+            args.normalize().unwrap().generator.constraints.markers,
+            eq(true)
+        );
         let args = Args::from_args(&[], &[]).unwrap();
-        let config = args.normalize();
-        expect_that!(config.strategy, eq(Strategy::Dummy));
+        expect_that!(
+            args.normalize().unwrap().generator.constraints.markers,
+            eq(false)
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn parity_variant_enables_parity_clues() -> Result<()> {
+        let args = Args::from_args(&[], &["--variant", "parity"]).unwrap();
+        let constraints = args.normalize().unwrap().generator.constraints;
+        expect_that!(constraints.parity, eq(true));
+        expect_that!(constraints.markers, eq(false));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn techniques_parse_into_constraints() -> Result<()> {
+        let args = Args::from_args(&[], &["--techniques", "zero-six,counting"]).unwrap();
+        let techniques = args.normalize().unwrap().generator.constraints.techniques;
+        expect_that!(
+            techniques.map(|t| t.to_string()),
+            some(eq("zero-six,counting"))
+        );
+        expect_that!(
+            Args::from_args(&[], &["--techniques", "magic"]),
+            err(anything())
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn shape_requires_single_loop_algorithm() -> Result<()> {
+        let args = Args::from_args(&[], &["--shape", "blob:2,snake:1"]).unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.generator.constraints.max_pinched_fraction, eq(1.0));
+        expect_that!(
+            config.generator.shapes,
+            some(eq(ShapeWeights {
+                blob: 2,
+                crescent: 0,
+                snake: 1
+            }))
+        );
+
+        let args = Args::from_args(&[], &["--shape", "snake", "--algorithm", "annealing"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("--shape")));
+
+        let args = Args::from_args(&[], &["--shape", "random", "--variant", "multi-loop"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("--shape")));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn symmetry_requires_region_growth_algorithm() -> Result<()> {
+        let args = Args::from_args(
+            &[],
+            &["--symmetry", "mirror", "--algorithm", "region-growth"],
+        )
+        .unwrap();
+        expect_that!(
+            args.normalize().unwrap().generator.symmetry,
+            eq(Symmetry::Mirror)
+        );
+
+        let args = Args::from_args(&[], &["--symmetry", "rotational"]).unwrap();
+        expect_that!(
+            args.normalize(),
+            err(contains_substring("only supported by the region-growth"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn clue_density_becomes_custom_difficulty() -> Result<()> {
+        let args =
+            Args::from_args(&[], &["--clue-density", "0.45", "--difficulty", "easy"]).unwrap();
+        let config = args.normalize().unwrap();
+
+        // All sizes, only the custom density
+        expect_that!(config.tasks, len(eq(4)));
+        let difficulties: Vec<Difficulty> = config.tasks.iter().map(|t| t.difficulty).collect();
+        expect_that!(difficulties, each(eq(&Difficulty::Custom(0.45))));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn out_of_range_clue_density_is_rejected() -> Result<()> {
+        let args = Args::from_args(&[], &["--clue-density", "1.2"]).unwrap();
+        expect_that!(
+            args.normalize(),
+            err(contains_substring("Invalid clue density"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn custom_difficulty_round_trips_through_its_name() -> Result<()> {
+        let difficulty = Difficulty::Custom(0.45);
+        expect_that!(difficulty.to_string(), eq("density_0.45"));
+        expect_that!("density_0.45".parse::<Difficulty>(), ok(eq(&difficulty)));
+        expect_that!(
+            "density_2".parse::<Difficulty>(),
+            err(contains_substring("Invalid clue density"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn curriculum_needs_a_single_size() -> Result<()> {
+        let args = Args::from_args(&[], &["--curriculum", "8", "--size", "medium"]).unwrap();
+        expect_that!(args.normalize().unwrap().curriculum, some(eq(8)));
+
+        let args = Args::from_args(&[], &["--curriculum", "8"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("single size")));
+
+        let args = Args::from_args(
+            &[],
+            &["--curriculum", "8", "--radius", "3", "--difficulty", "hard"],
+        )
+        .unwrap();
+        expect_that!(args.normalize(), err(contains_substring("--difficulty")));
+
+        let args = Args::from_args(
+            &[],
+            &["--curriculum", "8", "--radius", "3", "--tuning-report"],
+        )
+        .unwrap();
+        expect_that!(args.normalize(), err(contains_substring("--tuning-report")));
+
+        let args = Args::from_args(
+            &[],
+            &[
+                "--curriculum",
+                "8",
+                "--radius",
+                "3",
+                "--on-conflict",
+                "skip",
+            ],
+        )
+        .unwrap();
+        expect_that!(
+            args.normalize(),
+            err(contains_substring("--on-conflict skip"))
+        );
+        let args = Args::from_args(
+            &[],
+            &[
+                "--curriculum",
+                "8",
+                "--radius",
+                "3",
+                "--on-conflict",
+                "suffix",
+            ],
+        )
+        .unwrap();
+        expect_that!(args.normalize(), ok(anything()));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn numeric_difficulty_parses_as_level() -> Result<()> {
+        expect_that!("7".parse::<Difficulty>(), ok(eq(&Difficulty::Level(7))));
+        expect_that!(
+            "level_10".parse::<Difficulty>(),
+            ok(eq(&Difficulty::Level(10)))
+        );
+        expect_that!(Difficulty::Level(3).to_string(), eq("level_3"));
+        expect_that!(
+            "11".parse::<Difficulty>(),
+            err(contains_substring("must be 1-10"))
+        );
+        expect_that!(Difficulty::Medium.level(), some(eq(5)));
+        expect_that!(Difficulty::Custom(0.5).level(), none());
+
+        let args = Args::from_args(&[], &["--difficulty", "4", "--radius", "3"]).unwrap();
+        let tasks = args.normalize().unwrap().tasks;
+        expect_that!(tasks, len(eq(1)));
+        expect_that!(
+            tasks,
+            each(field!(Task.difficulty, eq(&Difficulty::Level(4))))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn max_attempts_sets_constraint_cap() -> Result<()> {
+        let args = Args::from_args(&[], &["--max-attempts", "7"]).unwrap();
+        expect_that!(
+            args.normalize().unwrap().generator.constraints.max_attempts,
+            eq(7)
+        );
+
+        let args = Args::from_args(&[], &["--max-attempts", "0"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("max attempts")));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn tasks_file_sets_per_task_max_attempts() -> Result<()> {
+        let tasks = parse_tasks(
+            r#"[{ "radius": 2, "difficulty": "hard", "max_attempts": 5 },
+                { "radius": 4, "difficulty": "easy" }]"#,
+            1,
+            LoopLengthBounds::default(),
+        )
+        .unwrap();
+        expect_that!(tasks[0].max_attempts, some(eq(5)));
+        expect_that!(tasks[1].max_attempts, none());
+
+        expect_that!(
+            parse_tasks(
+                r#"[{ "radius": 2, "difficulty": "hard", "max_attempts": 0 }]"#,
+                1,
+                LoopLengthBounds::default(),
+            ),
+            err(contains_substring("entry 0: invalid max attempts"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn mask_sets_task_radius() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mask.txt");
+        std::fs::write(&path, "  # .\n # # #\n  . #\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        let args = Args::from_args(&[], &["--mask", path, "--difficulty", "easy"]).unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.mask, some(field!(RegionMask.radius, eq(&1))));
+        expect_that!(
+            config.tasks,
+            elements_are![eq(&Task {
+                radius: 1,
+                difficulty: Difficulty::Easy,
+                count: 1,
+                loop_length: LoopLengthBounds::default(),
+                max_attempts: None,
+                fill_ratio: None,
+            })]
+        );
+
+        let args = Args::from_args(&[], &["--mask", path, "--radius", "3"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("--mask")));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn board_sets_task_radius() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("board.txt");
+        std::fs::write(
+            &path,
+            "  # #
+ # . #
+  # #
+",
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+
+        let args = Args::from_args(&[], &["--board", path, "--difficulty", "easy"]).unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.board, some(field!(RegionMask.radius, eq(&1))));
+        expect_that!(config.tasks, elements_are![field!(Task.radius, eq(&1))]);
+
+        for conflicting in [
+            &["--mask", path][..],
+            &["--radius", "3"],
+            &["--algorithm", "annealing"],
+        ] {
+            let args = Args::from_args(&[], &[&["--board", path], conflicting].concat()).unwrap();
+            expect_that!(args.normalize(), err(contains_substring("--board")));
+        }
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn board_shape_sets_task_radius() {
+        let args = Args::from_args(&[], &["--board-shape", "triangle:5"]).unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(config.board.map(|board| board.inside.len()), some(eq(15)));
+        expect_that!(config.tasks, each(field!(Task.radius, eq(&3))));
+
+        for conflicting in [&["--board", "board.txt"][..], &["--radius", "3"]] {
+            let args =
+                Args::from_args(&[], &[&["--board-shape", "hex:3"], conflicting].concat()).unwrap();
+            expect_that!(args.normalize(), err(contains_substring("--board-shape")));
+        }
+    }
+
+    #[googletest::test]
+    fn loop_length_bounds_apply_per_task() -> Result<()> {
+        let args = Args::from_args(
+            &[],
+            &[
+                "--radius",
+                "4",
+                "--difficulty",
+                "hard",
+                "--min-loop-length",
+                "40",
+            ],
+        )
+        .unwrap();
+        let config = args.normalize().unwrap();
+        expect_that!(
+            config.tasks,
+            each(field!(
+                Task.loop_length,
+                eq(&LoopLengthBounds {
+                    min: Some(40),
+                    max: None
+                })
+            ))
+        );
+
+        let tasks = parse_tasks(
+            r#"[{ "radius": 4, "difficulty": "easy", "max_loop_length": 30 },
+                { "radius": 4, "difficulty": "hard" }]"#,
+            1,
+            LoopLengthBounds {
+                min: Some(10),
+                max: None,
+            },
+        )
+        .unwrap();
+        expect_that!(
+            tasks[0].loop_length,
+            eq(LoopLengthBounds {
+                min: Some(10),
+                max: Some(30)
+            })
+        );
+        expect_that!(tasks[1].loop_length.max, none());
+
+        let args =
+            Args::from_args(&[], &["--min-loop-length", "50", "--max-loop-length", "20"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("min 50 > max 20")));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn threads_default_to_at_least_one() -> Result<()> {
+        let config = Args::from_args(&[], &[]).unwrap().normalize().unwrap();
+        expect_that!(config.threads, ge(1));
+
+        let args = Args::from_args(&[], &["--threads", "3"]).unwrap();
+        expect_that!(args.normalize().unwrap().threads, eq(3));
+
+        let args = Args::from_args(&[], &["--threads", "0"]).unwrap();
+        expect_that!(args.normalize(), err(contains_substring("thread count")));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn style_sets_constraint_style() -> Result<()> {
+        let config = Args::from_args(&[], &[]).unwrap().normalize().unwrap();
+        expect_that!(config.generator.constraints.style, eq(Style::Relaxed));
+
+        let args = Args::from_args(&[], &["--style", "strict"]).unwrap();
+        expect_that!(
+            args.normalize().unwrap().generator.constraints.style,
+            eq(Style::Strict)
+        );
+        expect_that!(Args::from_args(&[], &["--style", "fancy"]), err(anything()));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn solver_backend_is_passed_to_constraints() -> Result<()> {
+        let args = Args::from_args(&[], &["--solver", "backtracking"]).unwrap();
+        expect_that!(
+            args.normalize().unwrap().generator.constraints.solver,
+            eq(SolverBackend::Backtracking)
+        );
+        expect_that!(
+            "quantum".parse::<SolverBackend>(),
+            err(contains_substring("Invalid solver"))
+        );
+        #[cfg(not(feature = "sat"))]
+        expect_that!(
+            "sat".parse::<SolverBackend>(),
+            err(contains_substring("--features sat"))
+        );
         Ok(())
     }
 }
@@ -0,0 +1,97 @@
+//! Solver benchmarks: a reproducible corpus of puzzles per board size, and how long each
+//! solver backend takes to count their solutions.
+//!
+//! Puzzle `i` of a corpus is a Hard puzzle from the loop generator seeded with `seed + i`, so
+//! the same arguments always give the same puzzles.
+
+use std::time::{Duration, Instant};
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::args::Difficulty;
+use crate::generator::{Generator, LoopGenerator, SolverBackend};
+use crate::model::Map;
+use crate::solver;
+
+/// Timings and search effort of one backend on one corpus
+#[derive(Clone, Debug, PartialEq)]
+pub struct Measurement {
+    pub backend: SolverBackend,
+    pub puzzles: usize,
+    pub total: Duration,
+    /// Slowest single puzzle
+    pub max: Duration,
+    /// Search nodes summed over the corpus
+    pub nodes: usize,
+    /// Puzzles the backend didn't find exactly one solution for
+    pub not_unique: usize,
+}
+
+impl Measurement {
+    pub fn mean(&self) -> Duration {
+        self.total / self.puzzles.max(1) as u32
+    }
+}
+
+/// `count` puzzles of the given radius, the same for the same `seed`
+pub fn corpus(radius: u8, count: usize, seed: u64) -> Vec<Map> {
+    (0..count as u64)
+        .map(|i| {
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(i));
+            LoopGenerator.generate(radius, Difficulty::Hard, &mut rng)
+        })
+        .collect()
+}
+
+/// Time `backend` checking that each puzzle of `corpus` is unique
+pub fn measure(corpus: &[Map], backend: SolverBackend) -> Measurement {
+    let mut measurement = Measurement {
+        backend,
+        puzzles: corpus.len(),
+        total: Duration::ZERO,
+        max: Duration::ZERO,
+        nodes: 0,
+        not_unique: 0,
+    };
+    for map in corpus {
+        let view = map.view();
+        let start = Instant::now();
+        let stats = solver::count_solutions_with(&view, 2, backend);
+        let elapsed = start.elapsed();
+        measurement.total += elapsed;
+        measurement.max = measurement.max.max(elapsed);
+        measurement.nodes += stats.nodes;
+        measurement.not_unique += (stats.solutions != 1) as usize;
+    }
+    measurement
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::prelude::*;
+
+    #[googletest::test]
+    fn corpus_is_reproducible() {
+        let canonical =
+            |maps: Vec<Map>| -> Vec<Vec<u8>> { maps.iter().map(Map::canonical_form).collect() };
+        expect_that!(canonical(corpus(2, 3, 7)), eq(&canonical(corpus(2, 3, 7))));
+        expect_that!(
+            canonical(corpus(2, 3, 7))[1..],
+            eq(&canonical(corpus(2, 2, 8))[..])
+        );
+    }
+
+    #[googletest::test]
+    fn every_backend_measures_the_whole_corpus() {
+        let corpus = corpus(3, 2, 1);
+        for &backend in SolverBackend::all() {
+            let measurement = measure(&corpus, backend);
+            expect_that!(measurement.puzzles, eq(2));
+            expect_that!(measurement.not_unique, eq(0));
+            expect_that!(measurement.nodes, gt(0));
+            expect_that!(measurement.max, le(measurement.total));
+        }
+    }
+}
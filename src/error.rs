@@ -0,0 +1,55 @@
+//! Errors of the model, generator and map files, as one enum callers can match on rather than
+//! a message to print.
+
+use std::io;
+
+use thiserror::Error;
+
+use crate::model::{MAX_RADIUS, MapError};
+
+#[derive(Debug, Error)]
+pub enum SlitherError {
+    /// The radius is beyond [`MAX_RADIUS`]
+    #[error("Radius {0} is too large: at most {MAX_RADIUS} is supported")]
+    InvalidRadius(u8),
+    /// The holes of an irregular board leave no usable board, as found by
+    /// [`check_board`](crate::model::check_board)
+    #[error("{0}")]
+    InvalidBoard(String),
+    /// The clues don't match the regions of the answer
+    #[error("clues don't match the regions of the answer")]
+    InconsistentClues,
+    /// The regions don't make valid loops, as found by
+    /// [`Map::validate_topology`](crate::model::Map::validate_topology)
+    #[error("{0}")]
+    Topology(String),
+    /// No answer met the generation constraints within the allowed attempts
+    #[error("No puzzle met the generation constraints in {attempts} attempts (last: {last})")]
+    Generation { attempts: u32, last: String },
+    /// A map or mask is malformed, or can't be stored in the format asked for
+    #[error("{0}")]
+    InvalidData(String),
+    /// A binary map's checksum doesn't match its contents, as when the file was truncated or
+    /// damaged in transit
+    #[error(
+        "Map file is corrupt: its checksum is {expected:#010x}, but its contents give {found:#010x}"
+    )]
+    Corrupt { expected: u32, found: u32 },
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl From<MapError> for SlitherError {
+    fn from(e: MapError) -> Self {
+        match e {
+            MapError::RadiusTooLarge(radius) => SlitherError::InvalidRadius(radius),
+            MapError::Board(reason) => SlitherError::InvalidBoard(reason),
+            MapError::Topology(reason) => SlitherError::Topology(reason),
+            MapError::OutOfBounds(_) | MapError::MissingCell(_) => {
+                SlitherError::InvalidData(e.to_string())
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use rand::{Rng, RngCore};
+
+use crate::model::{Coord, Map, RegionBitmap};
+
+use super::Generator;
+use super::region_growth::is_simple;
+
+/// Annealing steps per cell on the board
+const STEPS_PER_CELL: usize = 400;
+const START_TEMPERATURE: f64 = 4.0;
+const END_TEMPERATURE: f64 = 0.05;
+
+/// Searches for an Inside region whose loop has a target length, by simulated annealing
+/// over single-cell toggles that keep the region free of holes.
+pub struct AnnealingGenerator {
+    /// Desired number of edges in the solution loop. Defaults to one edge per cell of the board.
+    pub target_loop_length: Option<u32>,
+}
+
+impl Generator for AnnealingGenerator {
+    fn generate_solution(&self, radius: u8, rng: &mut dyn RngCore) -> Map {
+        let coords: Vec<Coord> = Map::new(radius).iter_coords().collect();
+        let target = self
+            .target_loop_length
+            .map_or(coords.len() as i64, i64::from)
+            .max(6);
+
+        let mut inside = RegionBitmap::new(radius);
+        inside.set(Coord::new(0, 0), true);
+        let mut size = 1;
+        let mut length: i64 = 6;
+
+        let steps = STEPS_PER_CELL * coords.len();
+        let cooling = (END_TEMPERATURE / START_TEMPERATURE).powf(1.0 / steps as f64);
+        let mut temperature = START_TEMPERATURE;
+
+        for _ in 0..steps {
+            if length == target {
+                break;
+            }
+            temperature *= cooling;
+
+            let cell = coords[rng.random_range(0..coords.len())];
+            let removing = inside.get(cell);
+            if (removing && size == 1) || !is_simple(&inside, cell) {
+                continue;
+            }
+
+            let delta = toggle_delta(&inside, cell);
+            let change = (length + delta - target).abs() - (length - target).abs();
+            if change <= 0 || rng.random::<f64>() < (-(change as f64) / temperature).exp() {
+                inside.set(cell, !removing);
+                size = if removing { size - 1 } else { size + 1 };
+                length += delta;
+            }
+        }
+
+        inside.to_map(Map::new(radius))
+    }
+}
+
+/// Change in loop length from moving `cell` to the other region.
+///
+/// Every side the cell shares with its own region becomes a loop edge, and every
+/// side it shares with the other region stops being one.
+fn toggle_delta(inside: &RegionBitmap, cell: Coord) -> i64 {
+    let cell_inside = inside.get(cell);
+    cell.neighbors()
+        .iter()
+        .map(|&n| if inside.get(n) == cell_inside { 1 } else { -1 })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Difficulty;
+    use crate::generator::loop_length;
+    use crate::generator::testing::component_count;
+    use crate::model::Region;
+    use googletest::prelude::*;
+
+    #[googletest::test]
+    fn reaches_target_loop_length() {
+        for (radius, target) in [(2, 18), (4, 40), (6, 90)] {
+            let generator = AnnealingGenerator {
+                target_loop_length: Some(target),
+            };
+            let map = generator.generate(radius, Difficulty::Easy, &mut rand::rng());
+            expect_that!(loop_length(&map), eq(target));
+            expect_that!(component_count(&map, Region::Inside), eq(1));
+            expect_that!(component_count(&map, Region::Outside), eq(1));
+        }
+    }
+
+    #[googletest::test]
+    fn toggling_lone_cell_removes_its_loop() {
+        let mut inside = RegionBitmap::new(2);
+        inside.set(Coord::new(0, 0), true);
+        expect_that!(toggle_delta(&inside, Coord::new(0, 0)), eq(-6));
+        // A neighbor adds 4 new edges and removes the shared one
+        expect_that!(toggle_delta(&inside, Coord::new(1, 0)), eq(4));
+    }
+}
@@ -0,0 +1,210 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::args::Difficulty;
+use crate::error::SlitherError;
+use crate::model::Map;
+
+use super::constraints::generate_in_context;
+use super::post::{Pipeline, PostContext};
+use super::progress::{AttemptStats, Tally};
+use super::{GenerationConstraints, Generator, GeneratorProgress};
+
+/// One puzzle to generate, with its own seed so the result doesn't depend on scheduling
+#[derive(Clone)]
+pub struct Job<'a> {
+    pub generator: &'a (dyn Generator + Sync),
+    pub radius: u8,
+    pub difficulty: Difficulty,
+    pub constraints: GenerationConstraints,
+    pub seed: u64,
+}
+
+/// Outcome of a job, along with its RNG so the puzzle can be regenerated deterministically
+/// (e.g. when it turns out to be a duplicate)
+pub struct JobResult {
+    pub map: Result<Map, SlitherError>,
+    pub rng: StdRng,
+    /// Answers tried for the puzzle and why the rejected ones were turned down
+    pub stats: AttemptStats,
+}
+
+/// Threads a batch can spare for uniqueness checks: the ones no worker needs, plus the workers
+/// that have run out of jobs. Clue hiding borrows them to check several clues at once, so the
+/// run never uses more threads than it was given.
+#[derive(Debug, Default)]
+pub struct SolverPool {
+    spare: AtomicUsize,
+}
+
+impl SolverPool {
+    pub fn new(spare: usize) -> Self {
+        Self {
+            spare: AtomicUsize::new(spare),
+        }
+    }
+
+    /// Borrow up to `max` spare threads, given back when the lease is dropped
+    pub fn lease(&self, max: usize) -> Lease<'_> {
+        let taken = self
+            .spare
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |spare| {
+                Some(spare - spare.min(max))
+            })
+            .map_or(0, |spare| spare.min(max));
+        Lease {
+            pool: self,
+            threads: taken,
+        }
+    }
+
+    /// Add threads that have nothing else to do
+    pub fn give(&self, threads: usize) {
+        self.spare.fetch_add(threads, Ordering::AcqRel);
+    }
+}
+
+/// Threads borrowed from a [`SolverPool`]
+pub struct Lease<'a> {
+    pool: &'a SolverPool,
+    pub threads: usize,
+}
+
+impl Drop for Lease<'_> {
+    fn drop(&mut self) {
+        self.pool.give(self.threads);
+    }
+}
+
+/// Run `jobs` on a pool of `threads` workers pulling from a shared queue. Threads without a job
+/// help the remaining workers check clues for uniqueness.
+/// Results come back in job order and match a serial run exactly.
+pub fn run_jobs(jobs: &[Job], threads: usize, progress: &dyn GeneratorProgress) -> Vec<JobResult> {
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let workers = threads.clamp(1, jobs.len().max(1));
+    let pool = SolverPool::new(threads.saturating_sub(workers));
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (next, pool) = (&next, &pool);
+            scope.spawn(move || {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(job) = jobs.get(index) else {
+                        pool.give(1);
+                        break;
+                    };
+                    let mut rng = StdRng::seed_from_u64(job.seed);
+                    let tally = Tally::new(progress);
+                    let mut context =
+                        PostContext::new(job.generator, job.difficulty, &job.constraints, &tally);
+                    context.hide_options.pool = Some(pool);
+                    let map = generate_in_context(
+                        job.radius,
+                        &job.constraints,
+                        &Pipeline::standard(&job.constraints),
+                        &context,
+                        &mut rng,
+                    );
+                    if let Ok(map) = &map {
+                        progress.puzzle_finished(map);
+                    }
+                    let stats = tally.into_stats();
+                    // The receiver outlives the scope, so sending can't fail
+                    let _ = sender.send((index, JobResult { map, rng, stats }));
+                }
+            });
+        }
+    });
+    drop(sender);
+
+    let mut results: Vec<Option<JobResult>> = jobs.iter().map(|_| None).collect();
+    for (index, result) in receiver {
+        results[index] = Some(result);
+    }
+    results
+        .into_iter()
+        .map(|result| result.expect("every job is run exactly once"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::progress::testing::RecordingProgress;
+    use crate::generator::{LoopGenerator, NoProgress};
+    use googletest::prelude::*;
+
+    fn jobs() -> Vec<Job<'static>> {
+        (0..6)
+            .map(|i| Job {
+                generator: &LoopGenerator,
+                radius: 2 + (i % 3) as u8,
+                difficulty: Difficulty::Easy,
+                constraints: GenerationConstraints::default(),
+                seed: 1000 + i,
+            })
+            .collect()
+    }
+
+    fn canonical_forms(results: &[JobResult]) -> Vec<Vec<u8>> {
+        results
+            .iter()
+            .map(|r| r.map.as_ref().unwrap().canonical_form())
+            .collect()
+    }
+
+    #[googletest::test]
+    fn parallel_results_match_serial_results() {
+        let jobs = jobs();
+        let serial = run_jobs(&jobs, 1, &NoProgress);
+        let parallel = run_jobs(&jobs, 4, &NoProgress);
+        expect_that!(parallel.len(), eq(jobs.len()));
+        expect_that!(canonical_forms(&parallel), eq(&canonical_forms(&serial)));
+    }
+
+    #[googletest::test]
+    fn results_follow_job_order() {
+        let jobs = jobs();
+        let progress = RecordingProgress::default();
+        let results = run_jobs(&jobs, 3, &progress);
+        for (job, result) in jobs.iter().zip(&results) {
+            expect_that!(result.map.as_ref().unwrap().radius, eq(job.radius));
+        }
+        expect_that!(
+            progress.finished.load(Ordering::Relaxed),
+            eq(jobs.len() as u32)
+        );
+    }
+
+    #[googletest::test]
+    fn spare_threads_leave_results_unchanged() {
+        let jobs: Vec<Job> = (0..2)
+            .map(|i| Job {
+                generator: &LoopGenerator,
+                radius: 4,
+                difficulty: Difficulty::Hard,
+                constraints: GenerationConstraints::default(),
+                seed: 2000 + i,
+            })
+            .collect();
+        let serial = run_jobs(&jobs, 1, &NoProgress);
+        let helped = run_jobs(&jobs, 6, &NoProgress);
+        expect_that!(canonical_forms(&helped), eq(&canonical_forms(&serial)));
+    }
+
+    #[googletest::test]
+    fn leases_never_take_more_than_spare() {
+        let pool = SolverPool::new(3);
+        let first = pool.lease(2);
+        expect_that!(first.threads, eq(2));
+        expect_that!(pool.lease(5).threads, eq(1));
+        drop(first);
+        expect_that!(pool.lease(5).threads, eq(3));
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::{BTreeSet, HashSet};
+use std::str::FromStr;
+
+use rand::{Rng, RngCore};
+
+use crate::error::SlitherError;
+use crate::model::{Coord, Map, RegionMask, check_board, check_radius};
+
+use super::region_growth::grow_region;
+use super::{Generator, Symmetry};
+
+/// Generates answers like [`LoopGenerator`](super::LoopGenerator) on an irregular board: the
+/// Inside cells of a mask are the board, and the rest of its hexagon is cut out as holes.
+pub struct BoardGenerator {
+    board: Map,
+}
+
+impl BoardGenerator {
+    /// Check that the mask's cells make a connected board (see [`check_board`])
+    pub fn new(mask: RegionMask) -> Result<Self, SlitherError> {
+        let holes: BTreeSet<Coord> = Map::new(mask.radius)
+            .iter_coords()
+            .filter(|c| !mask.inside.contains(c))
+            .collect();
+        check_board(mask.radius, &holes)?;
+        Ok(Self {
+            board: Map::with_holes(mask.radius, holes),
+        })
+    }
+}
+
+/// A predefined board outline, `side` cells along each edge, in the smallest hexagon that
+/// holds it. The rest of the hexagon is cut out as holes, like the outside of a `--board` mask.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoardShape {
+    Hex(u8),
+    Rhombus(u8),
+    Triangle(u8),
+}
+
+impl BoardShape {
+    /// Radius of the smallest hexagon the shape fits in
+    pub fn radius(self) -> u8 {
+        match self {
+            BoardShape::Hex(side) | BoardShape::Rhombus(side) => side - 1,
+            // The three corners are 2 * (side - 1) apart, split as evenly as possible
+            BoardShape::Triangle(side) => (2 * (side as u16 - 1)).div_ceil(3) as u8,
+        }
+    }
+
+    /// The cells of the shape, for [`BoardGenerator::new`]
+    pub fn mask(self) -> RegionMask {
+        let radius = self.radius();
+        RegionMask {
+            radius,
+            inside: Map::new(radius)
+                .iter_coords()
+                .filter(|&c| self.contains(c))
+                .collect::<HashSet<Coord>>(),
+        }
+    }
+
+    /// Whether `coord`, a hex of the shape's hexagon, is one of its cells
+    fn contains(self, coord: Coord) -> bool {
+        let (q, r) = (coord.q as i16, coord.r as i16);
+        match self {
+            BoardShape::Hex(_) => true,
+            BoardShape::Rhombus(side) => {
+                // The long diagonal runs along q + r, so center the shape on it
+                let n = side as i16;
+                let q_min = -(n / 2);
+                let r_min = -(n - 1) - q_min;
+                (q_min..q_min + n).contains(&q) && (r_min..r_min + n).contains(&r)
+            }
+            BoardShape::Triangle(side) => {
+                // The cells with q, r and s = -q - r at least their minimums, which add up to
+                // 1 - side, shared out so each coordinate stays within the radius
+                let (n, radius) = (side as i16, self.radius() as i16);
+                let high = radius - (n - 1);
+                let mut deficit = 3 * high + (n - 1);
+                let mut min = [high; 3];
+                for coordinate in &mut min {
+                    let step = deficit.min(high + radius);
+                    *coordinate -= step;
+                    deficit -= step;
+                }
+                q >= min[0] && r >= min[1] && -q - r >= min[2]
+            }
+        }
+    }
+}
+
+impl FromStr for BoardShape {
+    type Err = String;
+
+    /// A shape and its side length, e.g. "hex:4", "rhombus:5" or "triangle:6"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, side) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid board shape: {} (expected e.g. rhombus:5)", s))?;
+        let side: u8 = side
+            .parse()
+            .map_err(|_| format!("Invalid board side length: {}", side))?;
+        if side < 2 {
+            return Err(format!(
+                "Board side length {} is too small: at least 2",
+                side
+            ));
+        }
+        let shape = match name {
+            "hex" => BoardShape::Hex(side),
+            "rhombus" => BoardShape::Rhombus(side),
+            "triangle" => BoardShape::Triangle(side),
+            _ => {
+                return Err(format!(
+                    "Invalid board shape: {} (expected hex, rhombus or triangle)",
+                    name
+                ));
+            }
+        };
+        check_radius(shape.radius()).map_err(|e| e.to_string())?;
+        Ok(shape)
+    }
+}
+
+impl Generator for BoardGenerator {
+    // The mask fixes the board, so the requested radius is ignored
+    fn generate_solution(&self, _radius: u8, rng: &mut dyn RngCore) -> Map {
+        let coords: Vec<Coord> = self.board.iter_coords().collect();
+        let start = coords[rng.random_range(0..coords.len())];
+        let target = ((coords.len() as f64 * rng.random_range(0.4..0.6)).round() as usize).max(1);
+
+        let inside = grow_region(&self.board, start, target, Symmetry::None, rng);
+        inside.to_map(self.board.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Difficulty;
+    use crate::generator::uniqueness;
+    use crate::model::Region;
+    use googletest::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    /// A radius 3 hexagon with its center and one corner cut out
+    fn ring_board() -> RegionMask {
+        let mut inside: std::collections::HashSet<Coord> = Map::new(3).iter_coords().collect();
+        inside.remove(&Coord::new(0, 0));
+        inside.remove(&Coord::new(3, 0));
+        RegionMask { radius: 3, inside }
+    }
+
+    #[googletest::test]
+    fn puzzles_keep_to_the_board() {
+        let generator = BoardGenerator::new(ring_board()).unwrap();
+        for seed in 0..4 {
+            let map = generator.generate(0, Difficulty::Medium, &mut StdRng::seed_from_u64(seed));
+            expect_that!(map.radius, eq(3));
+            expect_that!(map.holes.len(), eq(2));
+            expect_that!(map.cells.len(), eq(35));
+            expect_that!(map.cells.get(&Coord::new(0, 0)), none());
+            expect_that!(map.validate_topology(), ok(anything()));
+            expect_that!(map.is_consistent(), eq(true));
+            expect_that!(uniqueness::is_unique(&map), eq(true));
+            expect_that!(
+                map.cells.values().any(|c| c.region == Region::Inside),
+                eq(true)
+            );
+        }
+    }
+
+    #[googletest::test]
+    fn board_shapes_fit_their_hexagon() -> Result<()> {
+        for (shape, radius, cells) in [
+            ("hex:3", 2, 19),
+            ("rhombus:4", 3, 16),
+            ("rhombus:5", 4, 25),
+            ("triangle:4", 2, 10),
+            ("triangle:5", 3, 15),
+        ] {
+            let mask = shape.parse::<BoardShape>().unwrap().mask();
+            expect_that!(mask.radius, eq(radius), "{}", shape);
+            expect_that!(mask.inside.len(), eq(cells), "{}", shape);
+            let generator = BoardGenerator::new(mask)?;
+            let map = generator.generate(0, Difficulty::Medium, &mut StdRng::seed_from_u64(1));
+            expect_that!(map.validate_topology(), ok(anything()), "{}", shape);
+        }
+        expect_that!(
+            "square:4".parse::<BoardShape>(),
+            err(contains_substring("square"))
+        );
+        expect_that!(
+            "triangle:1".parse::<BoardShape>(),
+            err(contains_substring("too small"))
+        );
+        expect_that!(
+            "hex:200".parse::<BoardShape>(),
+            err(contains_substring("too large"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn rejects_split_boards() {
+        let inside = [Coord::new(-1, 0), Coord::new(1, 0)].into();
+        expect_that!(
+            BoardGenerator::new(RegionMask { radius: 1, inside }).map(|_| ()),
+            err(matches_pattern!(SlitherError::InvalidBoard(eq(
+                "The board's cells are not connected"
+            ))))
+        );
+    }
+}
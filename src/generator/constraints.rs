@@ -0,0 +1,728 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::time::Duration;
+
+use rand::RngCore;
+
+use crate::args::Difficulty;
+use crate::error::SlitherError;
+use crate::model::{Coord, Map, Region, RimEdges, cell_count};
+
+use super::post::{Pipeline, PostContext};
+use super::{
+    CluePlacement, DifficultyRater, Generator, GeneratorProgress, SolverBackend, TechniqueSet,
+    loop_length,
+};
+
+/// Regions smaller than this are too small to judge for pinching
+const MIN_CELLS_FOR_PINCH_CHECK: usize = 7;
+
+/// Optional bounds on the solution loop length, in edges
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LoopLengthBounds {
+    pub min: Option<u32>,
+    pub max: Option<u32>,
+}
+
+impl LoopLengthBounds {
+    /// Use `other`'s bounds wherever this one doesn't set its own
+    pub fn or(self, other: LoopLengthBounds) -> Self {
+        Self {
+            min: self.min.or(other.min),
+            max: self.max.or(other.max),
+        }
+    }
+
+    /// Reject bounds that no loop can satisfy
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) if min > max => Err(format!(
+                "Invalid loop length bounds: min {} > max {}",
+                min, max
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Visual character of generated loops
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Style {
+    /// Accept any loop that meets the basic constraints
+    #[default]
+    Relaxed,
+    /// Reject long straight stretches of loop and large blobs of 0 clues
+    Strict,
+}
+
+impl Style {
+    /// Longest allowed straight run of loop edges on a board of the given radius.
+    /// Hex edges always turn at a corner, so a straight run is a zigzag of alternating
+    /// left and right turns along a row of cells.
+    pub fn max_straight_run(self, radius: u8) -> Option<u32> {
+        match self {
+            Style::Relaxed => None,
+            Style::Strict => Some(radius as u32 + 4),
+        }
+    }
+
+    /// Largest allowed group of adjacent 0-clue cells on a board of the given radius
+    pub fn max_zero_cluster(self, radius: u8) -> Option<usize> {
+        match self {
+            Style::Relaxed => None,
+            Style::Strict => Some((cell_count(radius) * 3 / 20).max(3)),
+        }
+    }
+}
+
+impl std::fmt::Display for Style {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Style::Relaxed => f.write_str("relaxed"),
+            Style::Strict => f.write_str("strict"),
+        }
+    }
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "relaxed" => Ok(Style::Relaxed),
+            "strict" => Ok(Style::Strict),
+            _ => Err(format!("Invalid style: {}", s)),
+        }
+    }
+}
+
+/// Minimum quality requirements for a generated answer.
+/// Answers that fail are thrown away and generated again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GenerationConstraints {
+    /// Minimum loop length, as a multiple of the radius (radius 0 counts as 1)
+    pub min_loop_length_factor: u32,
+    /// Explicit loop length bounds, e.g. short loops for Easy tasks and long ones for Hard
+    pub loop_length: LoopLengthBounds,
+    /// Minimum number of Inside cells (capped at the size of the board)
+    pub min_inside_cells: usize,
+    /// Largest allowed fraction of pinched Inside cells, i.e. cells where the region is
+    /// only one cell wide because no two adjacent neighbors of the cell are Inside
+    pub max_pinched_fraction: f64,
+    /// Limits on straight runs of loop and blobs of 0 clues
+    pub style: Style,
+    /// Where visible clues should end up once the rest are hidden
+    pub clue_placement: CluePlacement,
+    /// Calibrate difficulty by the deduction techniques a solver needs rather than the fraction
+    /// of clues hidden, regenerating puzzles that don't land in the difficulty's band
+    pub rate_difficulty: bool,
+    /// Solver checking that puzzles stay unique while clues are hidden
+    pub solver: SolverBackend,
+    /// Only accept puzzles solvable with these techniques, e.g. for tutorials
+    pub techniques: Option<TechniqueSet>,
+    /// Show sheep and wolf markers in place of some clues (sheep-and-wolves variant)
+    pub markers: bool,
+    /// Show only the parity of some clues (parity variant)
+    pub parity: bool,
+    /// Whether clues count the sides of rim cells facing off the board
+    pub rim_edges: RimEdges,
+    /// Number of answers to try before giving up
+    pub max_attempts: u32,
+    /// Time limit per puzzle. Clue removal stops when it runs out, keeping the clues hidden so far
+    pub timeout: Option<Duration>,
+}
+
+impl Default for GenerationConstraints {
+    fn default() -> Self {
+        Self {
+            min_loop_length_factor: 6,
+            loop_length: LoopLengthBounds::default(),
+            min_inside_cells: 4,
+            max_pinched_fraction: 0.8,
+            style: Style::Relaxed,
+            clue_placement: CluePlacement::Random,
+            rate_difficulty: false,
+            solver: SolverBackend::default(),
+            techniques: None,
+            markers: false,
+            parity: false,
+            rim_edges: RimEdges::Counted,
+            max_attempts: 100,
+            timeout: None,
+        }
+    }
+}
+
+impl GenerationConstraints {
+    /// Constraints every answer passes, tried once
+    pub fn unconstrained() -> Self {
+        Self {
+            min_loop_length_factor: 0,
+            loop_length: LoopLengthBounds::default(),
+            min_inside_cells: 0,
+            max_pinched_fraction: 1.0,
+            style: Style::Relaxed,
+            clue_placement: CluePlacement::Random,
+            rate_difficulty: false,
+            solver: SolverBackend::default(),
+            techniques: None,
+            markers: false,
+            parity: false,
+            rim_edges: RimEdges::Counted,
+            max_attempts: 1,
+            timeout: None,
+        }
+    }
+
+    /// Check an answer against the constraints, describing the first one it breaks
+    pub fn check(&self, map: &Map) -> Result<(), String> {
+        self.check_kind(map).map_err(|(_, reason)| reason)
+    }
+
+    /// Like [`check`](Self::check), also naming the kind of constraint broken for tuning reports
+    fn check_kind(&self, map: &Map) -> Result<(), (&'static str, String)> {
+        let min_length = (self.min_loop_length_factor * (map.radius as u32).max(1))
+            .max(self.loop_length.min.unwrap_or(0));
+        let length = loop_length(map);
+        if length < min_length {
+            return Err((
+                "loop-too-short",
+                format!("loop length {} < {}", length, min_length),
+            ));
+        }
+        if let Some(max_length) = self.loop_length.max
+            && length > max_length
+        {
+            return Err((
+                "loop-too-long",
+                format!("loop length {} > {}", length, max_length),
+            ));
+        }
+
+        let inside: Vec<Coord> = map
+            .iter_coords()
+            .filter(|c| map.cells[c].region == Region::Inside)
+            .collect();
+        let min_inside = self.min_inside_cells.min(map.cells.len());
+        if inside.len() < min_inside {
+            return Err((
+                "too-few-inside",
+                format!("{} Inside cells < {}", inside.len(), min_inside),
+            ));
+        }
+
+        if inside.len() >= MIN_CELLS_FOR_PINCH_CHECK {
+            let pinched = inside.iter().filter(|c| is_pinched(map, **c)).count();
+            let fraction = pinched as f64 / inside.len() as f64;
+            if fraction > self.max_pinched_fraction {
+                return Err((
+                    "pinched",
+                    format!(
+                        "{:.0}% of Inside cells pinched > {:.0}%",
+                        fraction * 100.0,
+                        self.max_pinched_fraction * 100.0
+                    ),
+                ));
+            }
+        }
+
+        if let Some(max_run) = self.style.max_straight_run(map.radius) {
+            let run = longest_straight_run(map);
+            if run > max_run {
+                return Err((
+                    "straight-run",
+                    format!("straight run of {} edges > {}", run, max_run),
+                ));
+            }
+        }
+
+        if let Some(max_cluster) = self.style.max_zero_cluster(map.radius) {
+            let cluster = largest_zero_cluster(map);
+            if cluster > max_cluster {
+                return Err((
+                    "zero-cluster",
+                    format!("{} adjacent 0 clues > {}", cluster, max_cluster),
+                ));
+            }
+        }
+
+        if let Some(techniques) = self.techniques
+            && DifficultyRater::default()
+                .rate_with(map, &techniques)
+                .is_none()
+        {
+            return Err((
+                "techniques",
+                format!("needs techniques beyond {}", techniques),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Length in edges of the longest straight stretch of the loop.
+///
+/// The loop is traced with the Inside region on one side, noting at each corner whether it
+/// turns around a single Inside cell or around a single Outside cell. A straight stretch is
+/// a run of corners where the two kinds alternate.
+fn longest_straight_run(map: &Map) -> u32 {
+    let is_inside = |c: Coord| {
+        map.cells
+            .get(&c)
+            .is_some_and(|c| c.region == Region::Inside)
+    };
+
+    let mut visited = HashSet::new();
+    let mut longest = 0;
+    for start in map.iter_coords().filter(|&c| is_inside(c)) {
+        for start_dir in 0..6 {
+            if is_inside(start.neighbors()[start_dir]) || visited.contains(&(start, start_dir)) {
+                continue;
+            }
+
+            // Edges are (Inside cell, direction of the Outside neighbor across the edge)
+            let mut corners = Vec::new();
+            let (mut cell, mut dir) = (start, start_dir);
+            while visited.insert((cell, dir)) {
+                let next = cell.neighbors()[(dir + 1) % 6];
+                if is_inside(next) {
+                    corners.push(false);
+                    (cell, dir) = (next, (dir + 5) % 6);
+                } else {
+                    corners.push(true);
+                    dir = (dir + 1) % 6;
+                }
+            }
+
+            // Walk the corners twice so runs that wrap around the start are counted whole
+            let len = corners.len();
+            let mut run = 1;
+            for i in 1..2 * len {
+                if corners[i % len] != corners[(i - 1) % len] {
+                    run += 1;
+                } else {
+                    run = 1;
+                }
+                longest = longest.max((run + 1).min(len) as u32);
+            }
+        }
+    }
+    longest
+}
+
+/// Size of the largest connected group of cells with a clue of 0
+fn largest_zero_cluster(map: &Map) -> usize {
+    let is_zero = |c: &Coord| map.cells.get(c).is_some_and(|c| c.full_neighbor_count == 0);
+    let mut seen = HashSet::new();
+    let mut largest = 0;
+    for start in map.iter_coords().filter(is_zero) {
+        if !seen.insert(start) {
+            continue;
+        }
+        let mut size = 0;
+        let mut stack = vec![start];
+        while let Some(coord) = stack.pop() {
+            size += 1;
+            for n in map.neighbors_in_bounds(coord) {
+                if is_zero(&n) && seen.insert(n) {
+                    stack.push(n);
+                }
+            }
+        }
+        largest = largest.max(size);
+    }
+    largest
+}
+
+/// Whether no two adjacent neighbors of the cell are Inside, so the region is one cell wide there
+fn is_pinched(map: &Map, coord: Coord) -> bool {
+    let around = coord.neighbors().map(|n| {
+        map.cells
+            .get(&n)
+            .is_some_and(|c| c.region == Region::Inside)
+    });
+    (0..6).all(|i| !(around[i] && around[(i + 1) % 6]))
+}
+
+/// Generate a puzzle whose answer meets `constraints`, trying up to `constraints.max_attempts`
+/// answers before giving up. With `rate_difficulty`, puzzles rated outside the difficulty's
+/// band also count as failed attempts. Clues are only hidden once an answer has been accepted, and only
+/// until `constraints.timeout` (counted from the start of this call) runs out.
+pub fn generate_constrained(
+    generator: &dyn Generator,
+    radius: u8,
+    difficulty: Difficulty,
+    constraints: &GenerationConstraints,
+    progress: &dyn GeneratorProgress,
+    rng: &mut dyn RngCore,
+) -> Result<Map, SlitherError> {
+    generate_with_pipeline(
+        generator,
+        radius,
+        difficulty,
+        constraints,
+        &Pipeline::standard(constraints),
+        progress,
+        rng,
+    )
+}
+
+/// Like [`generate_constrained`], but turns accepted answers into puzzles with the steps of
+/// `pipeline`. A step rejecting the puzzle counts as a failed attempt. Every failed attempt is
+/// reported to `progress` along with the kind of check that failed.
+pub fn generate_with_pipeline(
+    generator: &dyn Generator,
+    radius: u8,
+    difficulty: Difficulty,
+    constraints: &GenerationConstraints,
+    pipeline: &Pipeline,
+    progress: &dyn GeneratorProgress,
+    rng: &mut dyn RngCore,
+) -> Result<Map, SlitherError> {
+    let context = PostContext::new(generator, difficulty, constraints, progress);
+    generate_in_context(radius, constraints, pipeline, &context, rng)
+}
+
+/// Like [`generate_with_pipeline`], with the context the pipeline runs in already set up
+pub(super) fn generate_in_context(
+    radius: u8,
+    constraints: &GenerationConstraints,
+    pipeline: &Pipeline,
+    context: &PostContext,
+    rng: &mut dyn RngCore,
+) -> Result<Map, SlitherError> {
+    let (generator, progress) = (context.generator, context.hide_options.progress);
+    let mut last_failure = String::new();
+    for attempt in 1..=constraints.max_attempts {
+        let mut map = generator.generate_solution(radius, rng);
+        if map.rim_edges != constraints.rim_edges {
+            map.rim_edges = constraints.rim_edges;
+            map.recompute_clues();
+        }
+        progress.answer_generated(attempt, loop_length(&map));
+        match map
+            .validate_topology()
+            .map_err(|reason| ("topology", reason.to_string()))
+            .and_then(|()| {
+                map.is_consistent()
+                    .then_some(())
+                    .ok_or(("topology", "clues don't match the regions".to_string()))
+            })
+            .and_then(|()| constraints.check_kind(&map))
+            .and_then(|()| {
+                // Hiding clues only adds answers, so there is no puzzle in an answer whose full
+                // clues fit others too, as happens more often with the rim left out of them
+                super::is_unique(&map, context.hide_options.solver)
+                    .then_some(())
+                    .ok_or((
+                        "not-unique",
+                        "more than one answer fits every clue".to_string(),
+                    ))
+            })
+            .and_then(|()| pipeline.run(&mut map, context, rng))
+        {
+            Ok(()) => return Ok(map),
+            Err((kind, reason)) => {
+                progress.answer_rejected(attempt, kind, &reason);
+                last_failure = reason;
+            }
+        }
+    }
+    Err(SlitherError::Generation {
+        attempts: constraints.max_attempts,
+        last: last_failure,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::progress::Tally;
+    use crate::generator::progress::testing::RecordingProgress;
+    use crate::generator::{LoopGenerator, NoProgress, map_from_inside};
+    use crate::model::RegionBitmap;
+    use googletest::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::collections::BTreeMap;
+
+    #[googletest::test]
+    fn short_loops_are_rejected() {
+        let map = map_from_inside(4, &HashSet::from([Coord::new(0, 0)]));
+        let result = GenerationConstraints::default().check(&map);
+        expect_that!(result, err(contains_substring("loop length 6 < 24")));
+    }
+
+    #[googletest::test]
+    fn small_regions_are_rejected() {
+        let constraints = GenerationConstraints {
+            min_loop_length_factor: 0,
+            min_inside_cells: 3,
+            ..Default::default()
+        };
+        let map = map_from_inside(2, &HashSet::from([Coord::new(0, 0), Coord::new(1, 0)]));
+        let result = constraints.check(&map);
+        expect_that!(result, err(contains_substring("2 Inside cells < 3")));
+    }
+
+    #[googletest::test]
+    fn one_cell_wide_snakes_are_rejected() {
+        let constraints = GenerationConstraints {
+            min_loop_length_factor: 0,
+            ..Default::default()
+        };
+        let snake: HashSet<Coord> = (-3..=3).map(|q| Coord::new(q, 0)).collect();
+        let map = map_from_inside(3, &snake);
+        let result = constraints.check(&map);
+        expect_that!(result, err(contains_substring("pinched")));
+
+        let blob: HashSet<Coord> = Map::new(1).iter_coords().collect();
+        let map = map_from_inside(3, &blob);
+        let result = constraints.check(&map);
+        expect_that!(result, ok(anything()));
+    }
+
+    #[googletest::test]
+    fn loop_length_bounds_are_enforced() {
+        // Three cells in a line: loop length 6 + 4 + 4
+        let map = map_from_inside(
+            3,
+            &HashSet::from([Coord::new(-1, 0), Coord::new(0, 0), Coord::new(1, 0)]),
+        );
+        let bounded = |min, max| GenerationConstraints {
+            min_loop_length_factor: 0,
+            loop_length: LoopLengthBounds { min, max },
+            min_inside_cells: 1,
+            ..Default::default()
+        };
+
+        let result = bounded(Some(16), None).check(&map);
+        expect_that!(result, err(contains_substring("loop length 14 < 16")));
+        let result = bounded(None, Some(12)).check(&map);
+        expect_that!(result, err(contains_substring("loop length 14 > 12")));
+        let result = bounded(Some(14), Some(14)).check(&map);
+        expect_that!(result, ok(anything()));
+    }
+
+    #[googletest::test]
+    fn single_cell_board_is_accepted() {
+        let map = map_from_inside(0, &HashSet::from([Coord::new(0, 0)]));
+        let result = GenerationConstraints::default().check(&map);
+        expect_that!(result, ok(anything()));
+    }
+
+    #[googletest::test]
+    fn gives_up_after_max_attempts() {
+        let constraints = GenerationConstraints {
+            min_inside_cells: 1000,
+            max_attempts: 3,
+            ..Default::default()
+        };
+        let result = generate_constrained(
+            &LoopGenerator,
+            3,
+            Difficulty::Easy,
+            &constraints,
+            &NoProgress,
+            &mut rand::rng(),
+        );
+        expect_that!(
+            result,
+            err(matches_pattern!(SlitherError::Generation {
+                attempts: eq(&3),
+                ..
+            }))
+        );
+    }
+
+    #[googletest::test]
+    fn accepted_answers_meet_constraints() {
+        let constraints = GenerationConstraints::default();
+        let map = generate_constrained(
+            &LoopGenerator,
+            5,
+            Difficulty::Medium,
+            &constraints,
+            &NoProgress,
+            &mut rand::rng(),
+        )
+        .unwrap();
+        let result = constraints.check(&map);
+        expect_that!(result, ok(anything()));
+    }
+
+    #[googletest::test]
+    fn expired_timeout_keeps_all_clues_and_marks_map() {
+        let constraints = GenerationConstraints {
+            timeout: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let map = generate_constrained(
+            &LoopGenerator,
+            4,
+            Difficulty::Hard,
+            &constraints,
+            &NoProgress,
+            &mut rand::rng(),
+        )
+        .unwrap();
+        expect_that!(map.timed_out, eq(true));
+        expect_that!(map.cells.values().all(|c| c.clue_visible()), eq(true));
+    }
+
+    #[googletest::test]
+    fn straight_run_follows_rows_of_cells() {
+        // A single hex turns the same way at every corner
+        let map = map_from_inside(3, &HashSet::from([Coord::new(0, 0)]));
+        expect_that!(longest_straight_run(&map), eq(2));
+
+        // Along a row of three cells the boundary zigzags over two cells' worth of edges
+        // plus the corners where it bends around the ends
+        let row: HashSet<Coord> = (-1..=1).map(|q| Coord::new(q, 0)).collect();
+        let map = map_from_inside(3, &row);
+        expect_that!(longest_straight_run(&map), eq(6));
+    }
+
+    #[googletest::test]
+    fn zero_clusters_are_measured() {
+        let blob: HashSet<Coord> = Map::new(2).iter_coords().collect();
+        let map = map_from_inside(2, &blob);
+        // Only the seven cells of the inner radius-1 hex are away from the loop on the border
+        expect_that!(largest_zero_cluster(&map), eq(7));
+
+        let strict = GenerationConstraints {
+            style: Style::Strict,
+            ..Default::default()
+        };
+        let result = strict.check(&map);
+        expect_that!(result, err(contains_substring("adjacent 0 clues")));
+    }
+
+    #[googletest::test]
+    fn technique_sets_limit_generated_puzzles() {
+        let techniques: TechniqueSet = "zero-six,counting".parse().unwrap();
+        let constraints = GenerationConstraints {
+            techniques: Some(techniques),
+            ..Default::default()
+        };
+        let map = generate_constrained(
+            &LoopGenerator,
+            4,
+            Difficulty::Hard,
+            &constraints,
+            &NoProgress,
+            &mut rand::rng(),
+        )
+        .unwrap();
+        let rater = DifficultyRater::default();
+        expect_that!(rater.rate_with(&map, &techniques), some(anything()));
+
+        let zero_six_only = GenerationConstraints {
+            techniques: Some("zero-six".parse().unwrap()),
+            ..GenerationConstraints::unconstrained()
+        };
+        let answer = LoopGenerator.generate_solution(3, &mut StdRng::seed_from_u64(5));
+        let result = zero_six_only.check(&answer);
+        expect_that!(result, err(contains_substring("beyond zero-six")));
+    }
+
+    #[googletest::test]
+    fn puzzles_can_leave_rim_edges_out_of_their_clues() {
+        let constraints = GenerationConstraints {
+            rim_edges: RimEdges::Excluded,
+            ..Default::default()
+        };
+        // Full clues without the rim often fit several answers, so those must be drawn again
+        for seed in 0..30 {
+            let map = generate_constrained(
+                &LoopGenerator,
+                2,
+                Difficulty::Hard,
+                &constraints,
+                &NoProgress,
+                &mut StdRng::seed_from_u64(seed),
+            )
+            .unwrap();
+            expect_that!(map.rim_edges, eq(RimEdges::Excluded));
+            expect_that!(map.is_consistent(), eq(true));
+            let solutions = crate::solver::solve(&map.view(), 2);
+            expect_that!(solutions.len(), eq(1), "seed {}", seed);
+            expect_that!(
+                RegionBitmap::from(&solutions[0].answer),
+                eq(&RegionBitmap::from(&map))
+            );
+        }
+    }
+
+    #[googletest::test]
+    fn rated_puzzles_land_in_band() {
+        let constraints = GenerationConstraints {
+            rate_difficulty: true,
+            ..Default::default()
+        };
+        let rater = DifficultyRater::default();
+        for difficulty in [Difficulty::Easy, Difficulty::Medium] {
+            let map = generate_constrained(
+                &LoopGenerator,
+                3,
+                difficulty,
+                &constraints,
+                &NoProgress,
+                &mut StdRng::seed_from_u64(7),
+            )
+            .unwrap();
+            let (floor, ceiling) = rater.band(difficulty).unwrap();
+            expect_that!(rater.rate(&map), some(ge(floor)));
+            expect_that!(rater.rate(&map), some(le(ceiling.unwrap())));
+        }
+    }
+
+    #[googletest::test]
+    fn progress_hears_about_attempts_and_hidden_clues() {
+        let constraints = GenerationConstraints::default();
+        let progress = RecordingProgress::default();
+        let map = generate_constrained(
+            &LoopGenerator,
+            4,
+            Difficulty::Medium,
+            &constraints,
+            &progress,
+            &mut rand::rng(),
+        )
+        .unwrap();
+
+        let attempts = progress.attempts.lock().unwrap();
+        expect_that!(
+            attempts.last(),
+            some(eq(&(attempts.len() as u32, loop_length(&map))))
+        );
+        let hidden = map.cells.values().filter(|c| !c.clue_visible()).count();
+        expect_that!(progress.hidden.lock().unwrap().last(), some(eq(&hidden)));
+    }
+
+    #[googletest::test]
+    fn rejections_are_tallied_by_kind() {
+        let constraints = GenerationConstraints {
+            loop_length: LoopLengthBounds {
+                min: Some(1000),
+                max: None,
+            },
+            max_attempts: 5,
+            ..Default::default()
+        };
+        let tally = Tally::new(&NoProgress);
+        let result = generate_constrained(
+            &LoopGenerator,
+            3,
+            Difficulty::Easy,
+            &constraints,
+            &tally,
+            &mut rand::rng(),
+        );
+        expect_that!(result, err(displays_as(contains_substring("< 1000"))));
+        let stats = tally.into_stats();
+        expect_that!(stats.answers, eq(5));
+        expect_that!(stats.rejected, eq(&BTreeMap::from([("loop-too-short", 5)])));
+    }
+}
@@ -0,0 +1,85 @@
+//! Difficulty ramps: picking puzzles of strictly increasing measured difficulty from a pool.
+
+use super::rating::Score;
+
+/// Difficulties the candidate pool cycles through, so it covers the whole scale
+pub const POOL_LEVELS: std::ops::RangeInclusive<u8> = 1..=10;
+
+/// Size of the candidate pool for a curriculum of `count` puzzles
+pub fn pool_size(count: u32) -> u32 {
+    (count * 3).max(*POOL_LEVELS.end() as u32)
+}
+
+/// Pick `count` candidates with strictly increasing scores, spread evenly from the easiest
+/// to the hardest score in the pool. Candidates sharing a score count once.
+pub fn select<T>(mut pool: Vec<(Score, T)>, count: usize) -> Result<Vec<(Score, T)>, String> {
+    let candidates = pool.len();
+    pool.sort_by_key(|(score, _)| *score);
+    pool.dedup_by_key(|(score, _)| *score);
+    if pool.len() < count {
+        return Err(format!(
+            "only {} distinct difficulties among {} candidates, {} needed",
+            pool.len(),
+            candidates,
+            count
+        ));
+    }
+    if count <= 1 {
+        pool.truncate(count);
+        return Ok(pool);
+    }
+
+    // Rounded even spacing; steps are at least 1 since there are at least `count` scores
+    let last = pool.len() - 1;
+    let picks: Vec<usize> = (0..count)
+        .map(|i| (i * last + (count - 1) / 2) / (count - 1))
+        .collect();
+    Ok(pool
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| picks.binary_search(i).is_ok())
+        .map(|(_, candidate)| candidate)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::Technique;
+    use googletest::prelude::*;
+
+    fn score(hardest: Technique, hidden_clues: usize) -> Score {
+        Score {
+            hardest,
+            hidden_clues,
+            hardest_steps: 1,
+        }
+    }
+
+    #[googletest::test]
+    fn picks_strictly_increasing_spread_scores() {
+        let pool: Vec<(Score, usize)> = (0..20)
+            .map(|i| (score(Technique::Counting, (i * 7) % 20), i))
+            .collect();
+        let picked = select(pool, 5).unwrap();
+        let hidden: Vec<usize> = picked.iter().map(|(s, _)| s.hidden_clues).collect();
+        expect_that!(
+            hidden,
+            elements_are![eq(&0), eq(&5), eq(&10), eq(&14), eq(&19)]
+        );
+    }
+
+    #[googletest::test]
+    fn ties_count_once() {
+        let pool = vec![
+            (score(Technique::Chain, 3), 'a'),
+            (score(Technique::Chain, 3), 'b'),
+            (score(Technique::ZeroSix, 8), 'c'),
+        ];
+        expect_that!(select(pool.clone(), 2).map(|p| p.len()), ok(eq(&2)));
+        expect_that!(
+            select(pool, 3),
+            err(contains_substring("only 2 distinct difficulties among 3"))
+        );
+    }
+}
@@ -1,12 +1,14 @@
+use rand::RngCore;
+
 use crate::args::Difficulty;
 use crate::model::{Cell, Coord, Map, Region};
 
-use super::Generator;
+use super::{Generator, HideOptions};
 
 pub struct DummyGenerator;
 
 impl Generator for DummyGenerator {
-    fn generate(&self, radius: u8, _difficulty: Difficulty) -> Map {
+    fn generate_solution(&self, radius: u8, _rng: &mut dyn RngCore) -> Map {
         let mut map = Map::new(radius);
         // Fill with dummy data for testing viewer/io
         let coords: Vec<Coord> = map.iter_coords().collect();
@@ -15,4 +17,14 @@ impl Generator for DummyGenerator {
         }
         map
     }
+
+    // The dummy answer isn't a real loop, so there is nothing to hide clues against
+    fn hide_clues(
+        &self,
+        _map: &mut Map,
+        _difficulty: Difficulty,
+        _options: &HideOptions,
+        _rng: &mut dyn RngCore,
+    ) {
+    }
 }
@@ -0,0 +1,603 @@
+//! A logical solver that never guesses: it applies a catalog of named deduction rules, always
+//! the easiest one that makes progress, until none of them decides anything more.
+//!
+//! 1. Zero-six: a 0 puts every neighbor in the cell's region, a 6 puts every neighbor in
+//!    the other one.
+//! 2. Counting: a clue whose cell and neighbors are mostly decided forces the rest.
+//! 3. Adjacent pair: two neighboring cells are either in the same region or not. If one of
+//!    the two leaves a clue on either cell unable to reach its count, given what is known
+//!    relative to that cell, the other holds. This decides the side between them even while
+//!    both regions are unknown.
+//! 4. Loop closure: a region choice that would cut Outside cells off from the board edge, or
+//!    split the Inside cells into more groups than there are loops, is ruled out.
+//! 5. Trial (optional, see [`LogicConfig`]): assuming a cell's region and running the rules
+//!    above into a contradiction proves the other region. At depth 2 the rules may make one
+//!    more assumption of their own while looking for the contradiction, and so on.
+//!
+//! Corner rules (every corner touches zero or two loop edges) and loop parity (any path of
+//! cells crosses the loop an even number of times to return to its region) need no rule of
+//! their own: the solver tracks which cells share a region, and every assignment of regions
+//! satisfies them.
+//!
+//! Which rules fired, and in what order, is reported for rating and hints. Hints start from a
+//! player's edge marks as well as the clues, and stop at the first step that decides an edge
+//! the player hasn't marked.
+
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Serializer};
+
+use crate::model::{Coord, Edge, KnownRegion, PuzzleView};
+use crate::solver::{EdgeGraph, EdgeMark, Hint, PlayerState, find_conflict};
+
+use super::rating::{DecidedCell, count_clue};
+use super::uniqueness::{Contradiction, Puzzle, State, connectivity_possible};
+
+/// Deduction rules, from easiest to hardest
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Rule {
+    ZeroSix,
+    Counting,
+    AdjacentPair,
+    LoopClosure,
+    /// A what-if with assumptions nested this many levels deep
+    Trial(u32),
+}
+
+impl Rule {
+    /// The direct rules, in the order they are tried. Trials come after them, shallowest first.
+    pub const CATALOG: [Rule; 4] = [
+        Rule::ZeroSix,
+        Rule::Counting,
+        Rule::AdjacentPair,
+        Rule::LoopClosure,
+    ];
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rule::ZeroSix => f.write_str("zero-six"),
+            Rule::Counting => f.write_str("counting"),
+            Rule::AdjacentPair => f.write_str("adjacent-pair"),
+            Rule::LoopClosure => f.write_str("loop-closure"),
+            Rule::Trial(depth) => write!(f, "trial-{}", depth),
+        }
+    }
+}
+
+impl Serialize for Rule {
+    /// The rule's name, e.g. "adjacent-pair" or "trial-2"
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// How far the logical solver may go beyond the direct rules
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LogicConfig {
+    /// Deepest nesting of trials once the direct rules run out. 0, the default, allows none, so
+    /// puzzles that need a hypothetical are left partly undecided.
+    pub max_trial_depth: u32,
+}
+
+/// One application of a rule
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RuleStep {
+    pub rule: Rule,
+    /// Cells the rule was applied to: the clue for zero-six and counting, both cells of an
+    /// adjacent pair, the cell ruled on for loop closure
+    pub at: Vec<Coord>,
+    /// Cells whose region became known, in board order. An adjacent pair may only relate its
+    /// two cells, deciding none.
+    pub cells: Vec<DecidedCell>,
+}
+
+/// What the rules could work out about a puzzle
+#[derive(Clone, Debug, PartialEq)]
+pub struct Deductions {
+    /// Every rule application that made progress, in order
+    pub steps: Vec<RuleStep>,
+    /// Cells left undecided once no rule applies
+    pub undecided: usize,
+}
+
+impl Deductions {
+    /// Whether the rules alone decided every cell
+    pub fn is_solved(&self) -> bool {
+        self.undecided == 0
+    }
+
+    /// How many times each rule made progress
+    pub fn fired(&self) -> BTreeMap<Rule, usize> {
+        let mut fired = BTreeMap::new();
+        for step in &self.steps {
+            *fired.entry(step.rule).or_default() += 1;
+        }
+        fired
+    }
+}
+
+/// Apply the rule catalog to the puzzle until nothing changes. Fails if the clues contradict
+/// each other.
+pub fn deduce(view: &PuzzleView) -> Result<Deductions, String> {
+    deduce_with(view, &LogicConfig::default())
+}
+
+/// Like [`deduce`], also trying trials as deep as `config` allows when the direct rules run
+/// out
+pub fn deduce_with(view: &PuzzleView, config: &LogicConfig) -> Result<Deductions, String> {
+    let puzzle = Puzzle::new(view);
+    let mut solver = Solver::new(&puzzle, view);
+    solver.max_trial_depth = config.max_trial_depth;
+
+    while solver
+        .step()
+        .map_err(|Contradiction| "the clues contradict each other".to_string())?
+        .is_some()
+    {}
+    Ok(Deductions {
+        undecided: solver.known.iter().filter(|r| !r.is_known()).count(),
+        steps: solver.steps.unwrap_or_default(),
+    })
+}
+
+/// The next edge the rules decide, given the clues and markers of the puzzle and the edges the
+/// player has marked. `None` if nothing more can be worked out, or if the marks contradict the
+/// clues or each other.
+pub fn next_hint(view: &PuzzleView, player: &PlayerState) -> Option<Hint> {
+    if find_conflict(view, player).is_some() {
+        return None;
+    }
+    let graph = EdgeGraph::new(view.radius);
+    let puzzle = Puzzle::new(view);
+    let mut solver = Solver::new(&puzzle, view);
+    solver.single_step = true;
+    for (&edge, &mark) in &player.marks {
+        let [a, b] = edge_nodes(&puzzle, graph.edges().get(edge)?);
+        solver.state.link(a, b, mark == EdgeMark::Line).ok()?;
+    }
+    let open: Vec<usize> = (0..graph.edges().len())
+        .filter(|edge| !player.marks.contains_key(edge))
+        .collect();
+
+    let hint = |solver: &mut Solver, rule: Option<Rule>, at: Vec<Coord>| {
+        let decided: Vec<(usize, EdgeMark)> = open
+            .iter()
+            .filter_map(|&edge| {
+                let [a, b] = edge_nodes(&puzzle, &graph.edges()[edge]);
+                let ((a_root, a_differs), (b_root, b_differs)) =
+                    (solver.state.find(a), solver.state.find(b));
+                let mark = if a_differs != b_differs {
+                    EdgeMark::Line
+                } else {
+                    EdgeMark::Cross
+                };
+                (a_root == b_root).then_some((edge, mark))
+            })
+            .collect();
+        // Prefer an edge of a cell the rule was applied to
+        let near = |&&(edge, _): &&(usize, EdgeMark)| {
+            graph.edges()[edge].cells().iter().any(|c| at.contains(c))
+        };
+        let &(edge, mark) = decided.iter().find(near).or(decided.first())?;
+        Some(Hint {
+            edge,
+            mark,
+            rule,
+            at,
+        })
+    };
+
+    if let Some(hint) = hint(&mut solver, None, Vec::new()) {
+        return Some(hint);
+    }
+    loop {
+        let rule = solver.step().ok()??;
+        let at = solver.steps.as_ref()?.last()?.at.clone();
+        if let Some(hint) = hint(&mut solver, Some(rule), at) {
+            return Some(hint);
+        }
+    }
+}
+
+/// The nodes of the puzzle on either side of an edge, the exterior for a cell off the board
+fn edge_nodes(puzzle: &Puzzle, edge: &Edge) -> [usize; 2] {
+    edge.cells()
+        .map(|c| puzzle.index.get(&c).copied().unwrap_or(puzzle.exterior))
+}
+
+struct Solver<'a> {
+    puzzle: &'a Puzzle,
+    state: State,
+    coords: Vec<Coord>,
+    /// Region of every cell as of the last step
+    known: Vec<KnownRegion>,
+    /// Steps taken so far, `None` while testing a trial
+    steps: Option<Vec<RuleStep>>,
+    /// Stop applying a rule after its first step
+    single_step: bool,
+    max_trial_depth: u32,
+}
+
+impl<'a> Solver<'a> {
+    fn new(puzzle: &'a Puzzle, view: &PuzzleView) -> Self {
+        let mut state = State::new(puzzle);
+        Self {
+            puzzle,
+            // Cells fixed by markers are given rather than deduced
+            known: state.known_regions(puzzle),
+            state,
+            coords: view.iter_coords().collect(),
+            steps: Some(Vec::new()),
+            single_step: false,
+            max_trial_depth: 0,
+        }
+    }
+
+    /// Apply the easiest rule that makes progress, returning it, or `None` if none does
+    fn step(&mut self) -> Result<Option<Rule>, Contradiction> {
+        let trials = (1..=self.max_trial_depth).map(Rule::Trial);
+        for rule in Rule::CATALOG.into_iter().chain(trials) {
+            if self.apply(rule)? {
+                return Ok(Some(rule));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether the rules, with trials nested up to `depth` deep, run `state` into a
+    /// contradiction
+    fn refutes(&self, state: State, depth: u32) -> bool {
+        let mut test = Solver {
+            puzzle: self.puzzle,
+            state,
+            coords: Vec::new(),
+            known: Vec::new(),
+            steps: None,
+            single_step: false,
+            max_trial_depth: depth,
+        };
+        loop {
+            match test.step() {
+                Err(Contradiction) => return true,
+                Ok(Some(_)) => continue,
+                Ok(None) => return false,
+            }
+        }
+    }
+
+    /// Apply `rule` everywhere it makes progress. Returns whether it did.
+    fn apply(&mut self, rule: Rule) -> Result<bool, Contradiction> {
+        let puzzle = self.puzzle;
+        let mut progress = false;
+        match rule {
+            Rule::ZeroSix | Rule::Counting => {
+                for cell in puzzle.cells() {
+                    let Some(allowed) = puzzle.clues[cell] else {
+                        continue;
+                    };
+                    let zero_six = allowed == 1 || allowed == 1 << 6;
+                    if zero_six != (rule == Rule::ZeroSix) {
+                        continue;
+                    }
+                    if count_clue(puzzle, &mut self.state, cell, allowed)? {
+                        self.record(rule, &[cell]);
+                        if self.single_step {
+                            return Ok(true);
+                        }
+                        progress = true;
+                    }
+                }
+            }
+            Rule::AdjacentPair => {
+                for a in puzzle.cells() {
+                    for &b in &puzzle.sides[a] {
+                        if b < a || b == puzzle.exterior || self.related(a, b) {
+                            continue;
+                        }
+                        let ruled_out = [false, true].map(|differ| {
+                            let mut test = self.state.clone();
+                            test.link(a, b, differ).is_err()
+                                || !self.clue_possible(&mut test, a)
+                                || !self.clue_possible(&mut test, b)
+                        });
+                        match ruled_out {
+                            [true, true] => return Err(Contradiction),
+                            [false, false] => continue,
+                            [same_ruled_out, _] => {
+                                self.state.link(a, b, same_ruled_out)?;
+                                self.record(rule, &[a, b]);
+                                if self.single_step {
+                                    return Ok(true);
+                                }
+                                progress = true;
+                            }
+                        }
+                    }
+                }
+            }
+            Rule::LoopClosure => {
+                let mut regions = self.state.regions(puzzle);
+                for cell in puzzle.cells() {
+                    if regions[cell].is_some() {
+                        continue;
+                    }
+                    let ruled_out = [true, false].map(|inside| {
+                        regions[cell] = Some(inside);
+                        let closes = !connectivity_possible(puzzle, &regions);
+                        regions[cell] = None;
+                        closes
+                    });
+                    match ruled_out {
+                        [true, true] => return Err(Contradiction),
+                        [false, false] => continue,
+                        [inside_ruled_out, _] => {
+                            self.state.link(cell, puzzle.exterior, !inside_ruled_out)?;
+                            regions = self.state.regions(puzzle);
+                            self.record(rule, &[cell]);
+                            if self.single_step {
+                                return Ok(true);
+                            }
+                            progress = true;
+                        }
+                    }
+                }
+            }
+            Rule::Trial(depth) => {
+                // One trial at a time, so the easier rules follow up on it before the next
+                let regions = self.state.regions(puzzle);
+                for cell in puzzle.cells().filter(|&c| regions[c].is_none()) {
+                    for inside in [true, false] {
+                        let mut test = self.state.clone();
+                        if test.link(cell, puzzle.exterior, inside).is_err()
+                            || self.refutes(test, depth - 1)
+                        {
+                            self.state.link(cell, puzzle.exterior, !inside)?;
+                            self.record(rule, &[cell]);
+                            return Ok(true);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(progress)
+    }
+
+    /// Whether the relation between `a` and `b` is already known
+    fn related(&mut self, a: usize, b: usize) -> bool {
+        self.state.find(a).0 == self.state.find(b).0
+    }
+
+    /// Whether the clue of `cell`, if any, can still reach its count given which neighbors are
+    /// known to be in the same region as the cell or a different one
+    fn clue_possible(&self, state: &mut State, cell: usize) -> bool {
+        let Some(allowed) = self.puzzle.clues[cell] else {
+            return true;
+        };
+        let (root, cell_differs) = state.find(cell);
+        let (mut across, mut unknown) = (0, 0);
+        for &n in &self.puzzle.clue_sides[cell] {
+            match state.find(n) {
+                (n_root, n_differs) if n_root == root => {
+                    across += (n_differs != cell_differs) as u8
+                }
+                _ => unknown += 1,
+            }
+        }
+        (across..=across + unknown).any(|n| allowed & (1 << n) != 0)
+    }
+
+    /// Add a step for `rule` applied at `cells`, with every cell decided since the last step
+    fn record(&mut self, rule: Rule, cells: &[usize]) {
+        // Trials being tested keep no steps
+        let Some(steps) = self.steps.as_mut() else {
+            return;
+        };
+        let regions = self.state.known_regions(self.puzzle);
+        let mut decided = Vec::new();
+        for (cell, (&region, known)) in regions.iter().zip(&mut self.known).enumerate() {
+            if let (Some(region), KnownRegion::Unknown) = (region.region(), *known) {
+                decided.push(DecidedCell {
+                    coord: self.coords[cell],
+                    region,
+                });
+                *known = region.into();
+            }
+        }
+        steps.push(RuleStep {
+            rule,
+            at: cells.iter().map(|&c| self.coords[c]).collect(),
+            cells: decided,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Difficulty;
+    use crate::generator::{Generator, LoopGenerator, map_from_inside};
+    use crate::model::{Clue, Map};
+    use crate::solver::EdgeGraph;
+    use googletest::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::collections::HashSet;
+
+    #[googletest::test]
+    fn fully_clued_puzzles_need_only_clue_rules() {
+        let map = LoopGenerator.generate_solution(3, &mut StdRng::seed_from_u64(5));
+        let deductions = deduce(&map.view()).unwrap();
+        expect_that!(deductions.is_solved(), eq(true));
+        expect_that!(
+            deductions.fired().keys().copied().collect::<Vec<_>>(),
+            each(le(&Rule::Counting))
+        );
+    }
+
+    #[googletest::test]
+    fn deductions_agree_with_the_answer() {
+        for seed in 0..5 {
+            let map = LoopGenerator.generate(4, Difficulty::Hard, &mut StdRng::seed_from_u64(seed));
+            let deductions = deduce(&map.view()).unwrap();
+            for cell in deductions.steps.iter().flat_map(|s| &s.cells) {
+                expect_that!(cell.region, eq(map.cells[&cell.coord].region));
+            }
+        }
+    }
+
+    #[googletest::test]
+    fn adjacent_pairs_solve_more_than_counting() {
+        let fired = (0..10)
+            .map(|seed| {
+                LoopGenerator.generate(4, Difficulty::Medium, &mut StdRng::seed_from_u64(seed))
+            })
+            .filter_map(|map| deduce(&map.view()).ok())
+            .flat_map(|d| d.fired().into_keys())
+            .collect::<Vec<_>>();
+        expect_that!(fired, contains(eq(&Rule::AdjacentPair)));
+    }
+
+    #[googletest::test]
+    fn enclosed_cells_join_the_loop() {
+        // Every ring cell is marked Inside, so the center can't be Outside: it would never
+        // reach the board edge
+        let ring: HashSet<Coord> = Map::new(1)
+            .iter_coords()
+            .filter(|&c| c != Coord::new(0, 0))
+            .collect();
+        let mut map = map_from_inside(1, &ring.iter().copied().chain([Coord::new(0, 0)]).collect());
+        for (coord, cell) in map.cells.iter_mut() {
+            cell.clue = Clue::Hidden;
+            if ring.contains(coord) {
+                cell.clue = Clue::Marker;
+            }
+        }
+        let deductions = deduce(&map.view()).unwrap();
+        expect_that!(deductions.is_solved(), eq(true));
+        expect_that!(
+            deductions.fired(),
+            eq(&BTreeMap::from([(Rule::LoopClosure, 1)]))
+        );
+    }
+
+    #[googletest::test]
+    fn contradictory_clues_are_reported() {
+        let mut map = LoopGenerator.generate_solution(2, &mut StdRng::seed_from_u64(4));
+        let cell = map.cells.get_mut(&Coord::new(0, 0)).unwrap();
+        cell.full_neighbor_count = (cell.full_neighbor_count + 1) % 7;
+        expect_that!(deduce(&map.view()), err(contains_substring("contradict")));
+    }
+
+    #[googletest::test]
+    fn blank_board_decides_nothing() {
+        let mut map = LoopGenerator.generate_solution(2, &mut rand::rng());
+        for cell in map.cells.values_mut() {
+            cell.clue = Clue::Hidden;
+        }
+        let deductions = deduce(&map.view()).unwrap();
+        expect_that!(deductions.steps, is_empty());
+        expect_that!(deductions.undecided, eq(map.cells.len()));
+    }
+
+    /// Edges meeting at a corner inside the board, which has three
+    fn inner_corner(graph: &EdgeGraph) -> Vec<usize> {
+        (0..graph.corner_count())
+            .map(|corner| {
+                (0..graph.edges().len())
+                    .filter(|&e| graph.corners(e).contains(&corner))
+                    .collect::<Vec<_>>()
+            })
+            .find(|edges| edges.len() == 3)
+            .unwrap()
+    }
+
+    #[googletest::test]
+    fn following_hints_solves_the_puzzle() {
+        let map = LoopGenerator.generate(3, Difficulty::Medium, &mut StdRng::seed_from_u64(6));
+        expect_that!(deduce(&map.view()).unwrap().is_solved(), eq(true));
+        let graph = EdgeGraph::new(map.radius);
+        let on_loop = graph.loop_edges(&map);
+
+        let mut player = PlayerState::default();
+        while let Some(hint) = next_hint(&map.view(), &player) {
+            let expected = if on_loop.contains(&hint.edge) {
+                EdgeMark::Line
+            } else {
+                EdgeMark::Cross
+            };
+            expect_that!(hint.mark, eq(expected));
+            player.marks.insert(hint.edge, hint.mark);
+        }
+        expect_that!(player.marks.len(), eq(graph.edges().len()));
+    }
+
+    #[googletest::test]
+    fn hints_name_the_rule_used() {
+        let map = LoopGenerator.generate_solution(2, &mut StdRng::seed_from_u64(3));
+        let hint = next_hint(&map.view(), &PlayerState::default()).unwrap();
+        expect_that!(hint.rule, some(le(Rule::Counting)));
+        expect_that!(hint.at, len(eq(1)));
+    }
+
+    #[googletest::test]
+    fn marks_at_a_corner_decide_its_last_side() {
+        let mut map = map_from_inside(2, &HashSet::from([Coord::new(0, 0)]));
+        for cell in map.cells.values_mut() {
+            cell.clue = Clue::Hidden;
+        }
+        let graph = EdgeGraph::new(2);
+        let corner = inner_corner(&graph);
+
+        let mut player = PlayerState::default();
+        player.marks.insert(corner[0], EdgeMark::Line);
+        player.marks.insert(corner[1], EdgeMark::Cross);
+        expect_that!(
+            next_hint(&map.view(), &player),
+            some(eq(&Hint {
+                edge: corner[2],
+                mark: EdgeMark::Line,
+                rule: None,
+                at: Vec::new(),
+            }))
+        );
+
+        player.marks.insert(corner[1], EdgeMark::Line);
+        player.marks.insert(corner[2], EdgeMark::Line);
+        expect_that!(next_hint(&map.view(), &player), none());
+    }
+
+    #[googletest::test]
+    fn trials_decide_what_the_direct_rules_cannot() {
+        let config = LogicConfig { max_trial_depth: 2 };
+        let (map, direct) = (0..20)
+            .map(|seed| {
+                LoopGenerator.generate(4, Difficulty::Hard, &mut StdRng::seed_from_u64(seed))
+            })
+            .map(|map| {
+                let direct = deduce(&map.view()).unwrap();
+                (map, direct)
+            })
+            .find(|(_, direct)| !direct.is_solved())
+            .unwrap();
+        let with_trials = deduce_with(&map.view(), &config).unwrap();
+
+        expect_that!(with_trials.undecided, lt(direct.undecided));
+        expect_that!(
+            with_trials.fired().keys().copied().collect::<Vec<_>>(),
+            contains(ge(&Rule::Trial(1)))
+        );
+        for cell in with_trials.steps.iter().flat_map(|s| &s.cells) {
+            expect_that!(cell.region, eq(map.cells[&cell.coord].region));
+        }
+    }
+
+    #[googletest::test]
+    fn rules_serialize_by_name() {
+        expect_that!(
+            serde_json::to_string(&[Rule::AdjacentPair, Rule::Trial(2)]).unwrap(),
+            eq(r#"["adjacent-pair","trial-2"]"#)
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use rand::{Rng, RngCore};
+
+use crate::model::{Coord, Map};
+
+use super::region_growth::grow_region;
+use super::{Generator, Symmetry};
+
+/// Grows a random simply-connected Inside region, so that its boundary is a single loop.
+pub struct LoopGenerator;
+
+impl Generator for LoopGenerator {
+    fn generate_solution(&self, radius: u8, rng: &mut dyn RngCore) -> Map {
+        let coords: Vec<Coord> = Map::new(radius).iter_coords().collect();
+        let start = coords[rng.random_range(0..coords.len())];
+        let target = ((coords.len() as f64 * rng.random_range(0.4..0.6)).round() as usize).max(1);
+
+        let inside = grow_region(&Map::new(radius), start, target, Symmetry::None, rng);
+        inside.to_map(Map::new(radius))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Difficulty;
+    use crate::generator::testing::component_count;
+    use crate::model::{KnownRegion, Region};
+    use googletest::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[googletest::test]
+    fn generated_regions_form_a_single_loop() {
+        for radius in [1, 2, 4, 8] {
+            let map = LoopGenerator.generate(radius, Difficulty::Medium, &mut rand::rng());
+            expect_that!(map.cells.len(), eq(map.iter_coords().count()));
+            expect_that!(component_count(&map, Region::Inside), eq(1));
+            expect_that!(component_count(&map, Region::Outside), eq(1));
+        }
+    }
+
+    #[googletest::test]
+    fn clue_counts_match_regions() {
+        let map = LoopGenerator.generate(4, Difficulty::Easy, &mut rand::rng());
+        for coord in map.iter_coords() {
+            let cell = &map.cells[&coord];
+            let expected = coord
+                .neighbors()
+                .iter()
+                .filter(|n| {
+                    map.cells.get(n).map_or(KnownRegion::Outside, |c| c.region) != cell.region
+                })
+                .count() as u8;
+            expect_that!(cell.full_neighbor_count, eq(expected));
+        }
+    }
+
+    #[googletest::test]
+    fn harder_difficulties_hide_more_clues() {
+        let visible = |difficulty| {
+            let map = LoopGenerator.generate(4, difficulty, &mut StdRng::seed_from_u64(3));
+            map.cells.values().filter(|c| c.clue_visible()).count()
+        };
+        expect_that!(visible(Difficulty::Easy), gt(visible(Difficulty::Medium)));
+        expect_that!(visible(Difficulty::Medium), gt(visible(Difficulty::Hard)));
+    }
+}
@@ -0,0 +1,91 @@
+use rand::RngCore;
+
+use crate::error::SlitherError;
+use crate::model::{Map, RegionMask};
+
+use super::{Generator, map_from_inside};
+
+/// Uses a user-supplied region mask as the answer key instead of generating one.
+/// Only clue hiding is random.
+pub struct MaskGenerator {
+    pub mask: RegionMask,
+}
+
+impl MaskGenerator {
+    /// Check that the mask's boundary is a single loop (see [`Map::validate_topology`])
+    pub fn new(mask: RegionMask) -> Result<Self, SlitherError> {
+        map_from_inside(mask.radius, &mask.inside)
+            .validate_topology()
+            .map_err(|e| {
+                SlitherError::Topology(format!("Mask boundary is not a single loop: {}", e))
+            })?;
+        Ok(Self { mask })
+    }
+}
+
+impl Generator for MaskGenerator {
+    // The mask fixes the board, so the requested radius is ignored
+    fn generate_solution(&self, _radius: u8, _rng: &mut dyn RngCore) -> Map {
+        map_from_inside(self.mask.radius, &self.mask.inside)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::args::Difficulty;
+    use crate::model::{Coord, Region};
+    use googletest::prelude::*;
+
+    fn validate(radius: u8, inside: &[(i8, i8)]) -> std::result::Result<(), String> {
+        MaskGenerator::new(mask(radius, inside))
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    fn mask(radius: u8, inside: &[(i8, i8)]) -> RegionMask {
+        RegionMask {
+            radius,
+            inside: inside.iter().map(|&(q, r)| Coord::new(q, r)).collect(),
+        }
+    }
+
+    #[googletest::test]
+    fn accepts_simple_regions() {
+        expect_that!(validate(2, &[(0, 0), (1, 0)]), ok(anything()));
+        let full: Vec<(i8, i8)> = Map::new(1).iter_coords().map(|c| (c.q, c.r)).collect();
+        expect_that!(validate(1, &full), ok(anything()));
+    }
+
+    #[googletest::test]
+    fn rejects_empty_disconnected_and_holed_masks() {
+        expect_that!(validate(2, &[]), err(anything()));
+        expect_that!(
+            validate(2, &[(-1, 0), (1, 0)]),
+            err(contains_substring("not connected"))
+        );
+        // Ring around the center hex
+        let ring: Vec<(i8, i8)> = Coord::new(0, 0)
+            .neighbors()
+            .iter()
+            .map(|c| (c.q, c.r))
+            .collect();
+        expect_that!(validate(2, &ring), err(contains_substring("encloses")));
+    }
+
+    #[googletest::test]
+    fn generated_puzzle_keeps_mask_as_answer() {
+        let generator = MaskGenerator::new(mask(3, &[(0, 0), (1, 0), (0, 1)])).unwrap();
+        let map = generator.generate(3, Difficulty::Medium, &mut rand::rng());
+        let inside: HashSet<Coord> = map
+            .cells
+            .iter()
+            .filter(|(_, c)| c.region == Region::Inside)
+            .map(|(&coord, _)| coord)
+            .collect();
+        expect_that!(inside, eq(&generator.mask.inside));
+        expect_that!(map.cells[&Coord::new(0, 0)].full_neighbor_count, eq(4));
+    }
+}
@@ -1,10 +1,556 @@
+use std::collections::HashSet;
+use std::time::Instant;
+
+use rand::{Rng, RngCore};
+
 use crate::args::Difficulty;
-use crate::model::Map;
+use crate::model::{Cell, Clue, Coord, Map, Region};
 
+pub mod annealing;
+pub mod batch;
+pub mod board;
+pub mod constraints;
+pub mod curriculum;
 pub mod dummy;
+pub mod logic;
+pub mod loops;
+pub mod mask;
+pub mod multi_loop;
+pub mod placement;
+pub mod post;
+pub mod progress;
+pub mod random_walk;
+pub mod rating;
+pub mod region_growth;
+#[cfg(feature = "sat")]
+pub(crate) mod sat;
+pub mod shape;
+pub mod symmetry;
+pub(crate) mod uniqueness;
 
+pub use annealing::AnnealingGenerator;
+pub use batch::SolverPool;
+pub use board::{BoardGenerator, BoardShape};
+pub use constraints::{
+    GenerationConstraints, LoopLengthBounds, Style, generate_constrained, generate_with_pipeline,
+};
 pub use dummy::DummyGenerator;
+pub use loops::LoopGenerator;
+pub use mask::MaskGenerator;
+pub use multi_loop::MultiLoopGenerator;
+pub use placement::CluePlacement;
+pub use post::{Pipeline, PostProcessor};
+pub use progress::{ConsoleProgress, GeneratorProgress, NoProgress};
+pub use random_walk::RandomWalkGenerator;
+pub use rating::{DifficultyRater, Score, Technique, TechniqueSet};
+pub use region_growth::RegionGrowthGenerator;
+pub use shape::{ShapeGenerator, ShapeWeights};
+pub use symmetry::Symmetry;
+pub use uniqueness::SolverBackend;
 
 pub trait Generator {
-    fn generate(&self, radius: u8, difficulty: Difficulty) -> Map;
+    /// Generate a fully clued answer: a single-loop region (or `Map::loop_count` separate ones)
+    /// with every clue visible.
+    /// All randomness is drawn from `rng`, so a seeded RNG always produces the same map.
+    fn generate_solution(&self, radius: u8, rng: &mut dyn RngCore) -> Map;
+
+    /// Hide clues of an answer according to the difficulty
+    fn hide_clues(
+        &self,
+        map: &mut Map,
+        difficulty: Difficulty,
+        options: &HideOptions,
+        rng: &mut dyn RngCore,
+    ) {
+        hide_clues(map, difficulty, Symmetry::None, options, rng);
+    }
+
+    /// Generate a puzzle: an answer with clues hidden according to the difficulty
+    fn generate(&self, radius: u8, difficulty: Difficulty, rng: &mut dyn RngCore) -> Map {
+        let mut map = self.generate_solution(radius, rng);
+        self.hide_clues(&mut map, difficulty, &HideOptions::default(), rng);
+        map.level = difficulty.level();
+        map
+    }
+}
+
+/// How clues are hidden once an answer has been accepted
+#[derive(Clone, Copy)]
+pub struct HideOptions<'a> {
+    /// Where the remaining visible clues should end up
+    pub placement: CluePlacement,
+    /// Stop hiding clues at this point, keeping the ones hidden so far
+    pub deadline: Option<Instant>,
+    /// Hide as many clues as possible while the puzzle stays solvable with techniques no harder
+    /// than this, instead of hiding the difficulty's fraction
+    pub rating_ceiling: Option<Technique>,
+    /// Only hide clues while the puzzle stays solvable with these techniques
+    pub techniques: Option<TechniqueSet>,
+    /// Replace clues that must stay visible with sheep and wolf markers wherever the puzzle
+    /// stays unique (sheep-and-wolves variant)
+    pub markers: bool,
+    /// Show only the parity of clues that must stay visible wherever the puzzle stays unique
+    /// (parity variant)
+    pub parity: bool,
+    /// Told about every clue hidden
+    pub progress: &'a dyn GeneratorProgress,
+    /// Solver checking that the puzzle stays unique
+    pub solver: SolverBackend,
+    /// Spare threads to check several clues at once
+    pub pool: Option<&'a SolverPool>,
+}
+
+impl HideOptions<'_> {
+    /// Techniques the puzzle has to stay solvable with, if they are limited at all
+    fn allowed_techniques(&self) -> Option<TechniqueSet> {
+        match (
+            self.rating_ceiling.map(TechniqueSet::up_to),
+            self.techniques,
+        ) {
+            (Some(ceiling), Some(techniques)) => Some(ceiling.intersect(techniques)),
+            (ceiling, techniques) => ceiling.or(techniques),
+        }
+    }
+}
+
+impl Default for HideOptions<'_> {
+    fn default() -> Self {
+        Self {
+            placement: CluePlacement::default(),
+            deadline: None,
+            rating_ceiling: None,
+            techniques: None,
+            markers: false,
+            parity: false,
+            progress: &NoProgress,
+            solver: SolverBackend::default(),
+            pool: None,
+        }
+    }
+}
+
+/// Tuning knobs shared by the generators, resolved from the command line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GeneratorConfig {
+    /// Target fraction of cells inside the loop (region-growth algorithm)
+    pub fill_ratio: f64,
+    /// Desired solution loop length in edges (annealing algorithm)
+    pub target_loop_length: Option<u32>,
+    /// Symmetry of the region and visible clues (region-growth algorithm)
+    pub symmetry: Symmetry,
+    /// Number of disjoint loops in each answer (multi-loop variant)
+    pub loop_count: u8,
+    /// Shapes to pick the Inside region's outline from (loop algorithm)
+    pub shapes: Option<ShapeWeights>,
+    /// Quality requirements every generated answer must meet
+    pub constraints: GenerationConstraints,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            fill_ratio: 0.5,
+            target_loop_length: None,
+            symmetry: Symmetry::None,
+            loop_count: 2,
+            shapes: None,
+            constraints: GenerationConstraints::default(),
+        }
+    }
+}
+
+/// Build a fully visible map whose Inside region is exactly `inside`, with clue counts filled in
+pub(crate) fn map_from_inside(radius: u8, inside: &HashSet<Coord>) -> Map {
+    board_from_inside(Map::new(radius), inside)
+}
+
+/// Like [`map_from_inside`], on the cells of an empty, possibly irregular, board
+pub(crate) fn board_from_inside(mut map: Map, inside: &HashSet<Coord>) -> Map {
+    let coords: Vec<Coord> = map.iter_coords().collect();
+    for coord in coords {
+        let region = if inside.contains(&coord) {
+            Region::Inside
+        } else {
+            Region::Outside
+        };
+        map.cells.insert(coord, Cell::new(region, 0, true));
+    }
+    map.recompute_clues();
+    map
+}
+
+/// Number of edges in the solution loop
+pub(crate) fn loop_length(map: &Map) -> u32 {
+    map.stats().loop_length
+}
+
+/// Fraction of clues hidden from the player for each difficulty.
+/// `None` means hide as many as possible.
+fn hidden_fraction(difficulty: Difficulty) -> Option<f64> {
+    match difficulty {
+        Difficulty::Easy => Some(0.15),
+        Difficulty::Medium => Some(0.30),
+        Difficulty::Hard => None,
+        Difficulty::Custom(density) => Some(1.0 - density as f64),
+        // 10% at level 1 up to 50% at level 9, with Easy and Medium at levels 2 and 5
+        Difficulty::Level(level @ 1..=9) => Some((level + 1) as f64 / 20.0),
+        Difficulty::Level(_) => None,
+    }
+}
+
+/// Hide a random subset of clues according to the difficulty.
+/// Clues are hidden a whole symmetry orbit at a time, so the visible pattern keeps the symmetry.
+/// A removal is only kept if the puzzle still has a unique solution afterwards, so fewer clues
+/// than the difficulty asks for may end up hidden.
+///
+/// Without a target fraction every orbit is tried once, which leaves a locally minimal puzzle:
+/// hiding clues can only add solutions, so a removal that failed earlier would still fail.
+///
+/// With `options.markers` (or `options.parity`), a second pass then tries showing a marker
+/// (or only the parity) instead of each clue that couldn't be hidden.
+///
+/// Every kept removal leaves a unique puzzle, so when the deadline passes the clues hidden so
+/// far are kept and the map is marked `timed_out`.
+pub(crate) fn hide_clues<R: Rng + ?Sized>(
+    map: &mut Map,
+    difficulty: Difficulty,
+    symmetry: Symmetry,
+    options: &HideOptions,
+    rng: &mut R,
+) {
+    // Removal checks assume the puzzle starts out unique. If it isn't with every clue shown, no
+    // removal could fix it; generation draws such answers again before they get here.
+    if !is_unique(map, options.solver) {
+        return;
+    }
+
+    let coords: Vec<Coord> = map.iter_coords().collect();
+    let hide_count = match options.rating_ceiling {
+        Some(_) => coords.len(),
+        None => hidden_fraction(difficulty)
+            .map_or(coords.len(), |f| (coords.len() as f64 * f).round() as usize),
+    };
+
+    let mut seen = HashSet::new();
+    let mut orbits: Vec<Vec<Coord>> = Vec::new();
+    for coord in coords {
+        if seen.insert(coord) {
+            let orbit = symmetry.orbit(coord);
+            seen.extend(orbit.iter().copied());
+            orbits.push(orbit);
+        }
+    }
+    let forced = options.placement.arrange(map.radius, &mut orbits, rng);
+
+    let mut hidden = 0;
+    // Orbits whose clues couldn't be hidden
+    let mut needed = Vec::new();
+    // Orbits already checked ahead of time with spare threads, against the clues visible now
+    let mut checked: Vec<Option<bool>> = vec![None; orbits.len()];
+    for (i, orbit) in orbits.iter().enumerate() {
+        // Skip orbits that would overshoot, so the requested count is hit exactly when possible
+        let skipped =
+            |i: usize, hidden: usize| i >= forced && hidden + orbits[i].len() > hide_count;
+        if skipped(i, hidden) {
+            continue;
+        }
+        if options.deadline.is_some_and(|d| Instant::now() >= d) {
+            map.timed_out = true;
+            return;
+        }
+        let lease = options
+            .pool
+            .filter(|_| checked[i].is_none())
+            .map(|pool| pool.lease(orbits.len() - i - 1));
+        if let Some(lease) = lease.filter(|lease| lease.threads > 0) {
+            let ahead: Vec<usize> = (i..orbits.len())
+                .filter(|&j| checked[j].is_none() && !skipped(j, hidden))
+                .take(lease.threads + 1)
+                .collect();
+            for (j, relaxes) in ahead.iter().zip(check_ahead(map, &ahead, &orbits, options)) {
+                checked[*j] = Some(relaxes);
+            }
+        }
+        if checked[i]
+            .take()
+            .unwrap_or_else(|| can_relax(map, orbit, Clue::Hidden, options))
+        {
+            set_clues(map, orbit, Clue::Hidden);
+            hidden += orbit.len();
+            options.progress.clue_hidden(hidden, map.cells.len());
+            // Hiding more clues never makes a puzzle unique again, so orbits that failed still
+            // fail, but the ones that passed have to be checked again
+            for result in &mut checked {
+                if *result == Some(true) {
+                    *result = None;
+                }
+            }
+        } else {
+            needed.push(orbit);
+        }
+    }
+
+    if !options.markers && !options.parity {
+        return;
+    }
+    for orbit in needed {
+        if options.deadline.is_some_and(|d| Instant::now() >= d) {
+            map.timed_out = true;
+            return;
+        }
+        let relaxed = if options.markers {
+            Clue::Marker
+        } else {
+            Clue::Parity
+        };
+        if can_relax(map, orbit, relaxed, options) {
+            set_clues(map, orbit, relaxed);
+        }
+    }
+}
+
+/// Whether the exact counts shown at `orbit` can be changed to the `relaxed` kind of clue,
+/// keeping the puzzle unique and (with a rating ceiling or technique set) solvable with the
+/// allowed techniques
+fn can_relax(map: &mut Map, orbit: &[Coord], relaxed: Clue, options: &HideOptions) -> bool {
+    if let Some(allowed) = options.allowed_techniques() {
+        set_clues(map, orbit, relaxed);
+        let solvable = DifficultyRater::default()
+            .rate_with(map, &allowed)
+            .is_some();
+        set_clues(map, orbit, Clue::Count);
+        if !solvable {
+            return false;
+        }
+    }
+    match options.solver {
+        SolverBackend::Backtracking => match relaxed {
+            Clue::Parity => uniqueness::remains_unique_as_parity(map, orbit),
+            Clue::Marker => uniqueness::remains_unique_as_markers(map, orbit),
+            Clue::Hidden | Clue::Count => uniqueness::remains_unique_without(map, orbit),
+        },
+        #[cfg(feature = "sat")]
+        SolverBackend::Sat => sat::remains_unique_relaxing(map, orbit, relaxed),
+    }
+}
+
+/// Run `can_relax` for the orbits at `indices` at once, each on its own thread and its own copy
+/// of the map
+fn check_ahead(
+    map: &Map,
+    indices: &[usize],
+    orbits: &[Vec<Coord>],
+    options: &HideOptions,
+) -> Vec<bool> {
+    std::thread::scope(|scope| {
+        let checks: Vec<_> = indices
+            .iter()
+            .map(|&i| {
+                let mut map = map.clone();
+                scope.spawn(move || can_relax(&mut map, &orbits[i], Clue::Hidden, options))
+            })
+            .collect();
+        checks
+            .into_iter()
+            .map(|check| check.join().expect("clue checks don't panic"))
+            .collect()
+    })
+}
+
+/// Whether the visible clues of `map` admit exactly one solution, checked with `solver`
+fn is_unique(map: &Map, solver: SolverBackend) -> bool {
+    match solver {
+        SolverBackend::Backtracking => uniqueness::is_unique(map),
+        #[cfg(feature = "sat")]
+        SolverBackend::Sat => sat::is_unique(map),
+    }
+}
+
+fn set_clues(map: &mut Map, coords: &[Coord], clue: Clue) {
+    for coord in coords {
+        map.cells.get_mut(coord).unwrap().clue = clue;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Marker;
+    use googletest::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[googletest::test]
+    fn same_seed_generates_same_map() {
+        let generators: Vec<Box<dyn Generator>> = vec![
+            Box::new(LoopGenerator),
+            Box::new(RandomWalkGenerator),
+            Box::new(RegionGrowthGenerator {
+                fill_ratio: 0.5,
+                symmetry: Symmetry::Mirror,
+            }),
+            Box::new(AnnealingGenerator {
+                target_loop_length: None,
+            }),
+        ];
+        for generator in generators {
+            let first = generator.generate(4, Difficulty::Medium, &mut StdRng::seed_from_u64(7));
+            let second = generator.generate(4, Difficulty::Medium, &mut StdRng::seed_from_u64(7));
+            expect_that!(first, eq(&second));
+        }
+    }
+
+    #[googletest::test]
+    fn clue_removal_keeps_solution_unique() {
+        for difficulty in Difficulty::all() {
+            for radius in [1, 3, 4] {
+                let map = LoopGenerator.generate(radius, *difficulty, &mut rand::rng());
+                expect_that!(uniqueness::is_unique(&map), eq(true));
+            }
+        }
+    }
+
+    #[googletest::test]
+    fn levels_hide_more_clues_as_they_rise() {
+        expect_that!(
+            hidden_fraction(Difficulty::Level(2)),
+            eq(hidden_fraction(Difficulty::Easy))
+        );
+        expect_that!(
+            hidden_fraction(Difficulty::Level(5)),
+            eq(hidden_fraction(Difficulty::Medium))
+        );
+        expect_that!(hidden_fraction(Difficulty::Level(10)), none());
+        let fractions: Vec<f64> = (1..=9)
+            .filter_map(|level| hidden_fraction(Difficulty::Level(level)))
+            .collect();
+        expect_that!(fractions.windows(2).all(|w| w[0] < w[1]), eq(true));
+
+        let map = LoopGenerator.generate(3, Difficulty::Level(7), &mut rand::rng());
+        expect_that!(map.level, some(eq(7)));
+    }
+
+    #[googletest::test]
+    fn custom_density_sets_visible_clue_count() {
+        let mut map = LoopGenerator.generate(5, Difficulty::Custom(0.6), &mut rand::rng());
+        let visible = map.cells.values().filter(|c| c.clue_visible()).count();
+        expect_that!(visible, eq((map.cells.len() as f64 * 0.6).round() as usize));
+        expect_that!(uniqueness::is_unique(&map), eq(true));
+
+        map = LoopGenerator.generate(3, Difficulty::Custom(1.0), &mut rand::rng());
+        expect_that!(
+            map.cells
+                .values()
+                .map(|c| c.clue_visible())
+                .collect::<Vec<_>>(),
+            each(eq(&true))
+        );
+    }
+
+    #[googletest::test]
+    fn markers_replace_clues_that_must_stay_visible() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut map = LoopGenerator.generate_solution(4, &mut rng);
+        let options = HideOptions {
+            markers: true,
+            ..HideOptions::default()
+        };
+        hide_clues(
+            &mut map,
+            Difficulty::Hard,
+            Symmetry::None,
+            &options,
+            &mut rng,
+        );
+
+        let marked: Vec<&Cell> = map
+            .cells
+            .values()
+            .filter(|c| c.clue == Clue::Marker)
+            .collect();
+        expect_that!(marked, not(is_empty()));
+        for cell in marked {
+            expect_that!(cell.clue_visible(), eq(false));
+            expect_that!(cell.marker().map(Marker::region), some(eq(cell.region)));
+        }
+        expect_that!(uniqueness::is_unique(&map), eq(true));
+    }
+
+    #[googletest::test]
+    fn parity_clues_keep_solution_unique() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut map = LoopGenerator.generate_solution(4, &mut rng);
+        let options = HideOptions {
+            parity: true,
+            ..HideOptions::default()
+        };
+        hide_clues(
+            &mut map,
+            Difficulty::Hard,
+            Symmetry::None,
+            &options,
+            &mut rng,
+        );
+
+        let parity = map
+            .cells
+            .values()
+            .filter(|c| c.clue == Clue::Parity)
+            .collect::<Vec<_>>();
+        expect_that!(parity, not(is_empty()));
+        expect_that!(
+            parity.iter().map(|c| c.clue_visible()).collect::<Vec<_>>(),
+            each(eq(&true))
+        );
+        expect_that!(uniqueness::is_unique(&map), eq(true));
+    }
+
+    #[googletest::test]
+    fn hard_puzzles_are_locally_minimal() {
+        let mut map = LoopGenerator.generate(4, Difficulty::Hard, &mut rand::rng());
+        let visible: Vec<Coord> = map
+            .iter_coords()
+            .filter(|c| map.cells[c].clue_visible())
+            .collect();
+        for coord in visible {
+            set_clues(&mut map, &[coord], Clue::Hidden);
+            expect_that!(uniqueness::is_unique(&map), eq(false));
+            set_clues(&mut map, &[coord], Clue::Count);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::collections::HashSet;
+
+    use crate::model::{Map, Region};
+
+    /// Count connected components of cells in `region`. For Outside, the board exterior
+    /// is always one component, joined with every Outside cell on the rim.
+    pub(crate) fn component_count(map: &Map, region: Region) -> usize {
+        let mut seen = HashSet::new();
+        let mut components = usize::from(region == Region::Outside);
+        for start in map.iter_coords() {
+            if map.cells[&start].region != region || seen.contains(&start) {
+                continue;
+            }
+            let mut stack = vec![start];
+            seen.insert(start);
+            let mut touches_exterior = false;
+            while let Some(c) = stack.pop() {
+                for n in c.neighbors() {
+                    if !map.on_board(n) {
+                        touches_exterior = true;
+                    } else if map.cells[&n].region == region && seen.insert(n) {
+                        stack.push(n);
+                    }
+                }
+            }
+            if !(region == Region::Outside && touches_exterior) {
+                components += 1;
+            }
+        }
+        components
+    }
 }
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use rand::{Rng, RngCore};
+
+use crate::model::{Coord, Map, RegionBitmap};
+
+use super::Generator;
+use super::region_growth::is_simple;
+
+/// Multi-loop variant: grows several separate hole-free Inside regions, so the answer is
+/// that many disjoint loops. Regions never touch, so no two loops share an edge or corner.
+pub struct MultiLoopGenerator {
+    /// Number of loops to aim for. Boards too small to fit them all get as many as fit,
+    /// and `Map::loop_count` records how many there are.
+    pub loops: u8,
+}
+
+impl Generator for MultiLoopGenerator {
+    fn generate_solution(&self, radius: u8, rng: &mut dyn RngCore) -> Map {
+        let coords: Vec<Coord> = Map::new(radius).iter_coords().collect();
+        let target = ((coords.len() as f64 * rng.random_range(0.4..0.6)).round() as usize).max(1);
+
+        // Region each Inside cell belongs to
+        let mut owner: HashMap<Coord, usize> = HashMap::new();
+        for region in 0..self.loops.max(1) as usize {
+            let free: Vec<Coord> = coords
+                .iter()
+                .copied()
+                .filter(|&c| !owner.contains_key(&c) && separate(&owner, c, region))
+                .collect();
+            if free.is_empty() {
+                break;
+            }
+            owner.insert(free[rng.random_range(0..free.len())], region);
+        }
+        let regions = owner.len();
+        let mut inside = RegionBitmap::new(radius);
+        for &coord in owner.keys() {
+            inside.set(coord, true);
+        }
+
+        // Take turns growing each region by one cell until the target is met or none can grow
+        let mut stuck = vec![false; regions];
+        let mut region = 0;
+        while owner.len() < target && stuck.contains(&false) {
+            if !stuck[region] {
+                let candidates: Vec<Coord> = coords
+                    .iter()
+                    .copied()
+                    .filter(|&c| {
+                        !inside.get(c) && separate(&owner, c, region) && is_simple(&inside, c)
+                    })
+                    .collect();
+                if candidates.is_empty() {
+                    stuck[region] = true;
+                } else {
+                    let cell = candidates[rng.random_range(0..candidates.len())];
+                    owner.insert(cell, region);
+                    inside.set(cell, true);
+                }
+            }
+            region = (region + 1) % regions;
+        }
+
+        let mut map = inside.to_map(Map::new(radius));
+        map.loop_count = regions as u8;
+        map
+    }
+}
+
+/// Whether `coord` touches no region other than `region`
+fn separate(owner: &HashMap<Coord, usize>, coord: Coord, region: usize) -> bool {
+    coord
+        .neighbors()
+        .iter()
+        .all(|n| owner.get(n).is_none_or(|&o| o == region))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Difficulty;
+    use crate::generator::testing::component_count;
+    use crate::generator::uniqueness::is_unique;
+    use crate::model::Region;
+    use googletest::prelude::*;
+
+    #[googletest::test]
+    fn generates_the_requested_number_of_loops() {
+        for (radius, loops) in [(3, 2), (5, 3), (8, 4)] {
+            let map =
+                MultiLoopGenerator { loops }.generate(radius, Difficulty::Medium, &mut rand::rng());
+            expect_that!(map.loop_count, eq(loops));
+            expect_that!(component_count(&map, Region::Inside), eq(loops as usize));
+            expect_that!(component_count(&map, Region::Outside), eq(1));
+            expect_that!(is_unique(&map), eq(true));
+        }
+    }
+
+    #[googletest::test]
+    fn small_boards_get_as_many_loops_as_fit() {
+        // A single hex has room for one loop only
+        let map = MultiLoopGenerator { loops: 3 }.generate_solution(0, &mut rand::rng());
+        expect_that!(map.loop_count, eq(1));
+        expect_that!(component_count(&map, Region::Inside), eq(1));
+    }
+}
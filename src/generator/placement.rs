@@ -0,0 +1,198 @@
+use std::str::FromStr;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::model::Coord;
+
+/// Where visible clues should end up once the rest are hidden
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CluePlacement {
+    /// Hide clues in random order
+    #[default]
+    Random,
+    /// Hide every interior clue that uniqueness allows, keeping clues in the outer rings
+    Border,
+    /// Keep visible clues evenly distributed over the board
+    Spread,
+    /// Keep visible clues bunched around a random point
+    Clustered,
+}
+
+impl CluePlacement {
+    /// Put symmetry orbits in the order clue removal should try them. The returned number of
+    /// leading orbits are hidden whenever uniqueness allows, even beyond the difficulty's target.
+    pub(super) fn arrange<R: Rng + ?Sized>(
+        self,
+        radius: u8,
+        orbits: &mut [Vec<Coord>],
+        rng: &mut R,
+    ) -> usize {
+        orbits.shuffle(rng);
+        match self {
+            CluePlacement::Random => 0,
+            CluePlacement::Border => {
+                // Orbits never mix rings, since every symmetry keeps the distance to the center
+                orbits.sort_by_key(|orbit| is_border(radius, orbit[0]));
+                orbits
+                    .iter()
+                    .take_while(|orbit| !is_border(radius, orbit[0]))
+                    .count()
+            }
+            CluePlacement::Spread => {
+                // Farthest-point order: each orbit kept is as far as possible from those
+                // already kept. Removal tries them in reverse, crowded orbits first.
+                let mut kept: Vec<Coord> = Vec::new();
+                for i in 0..orbits.len() {
+                    let gap = |orbit: &Vec<Coord>| {
+                        orbit
+                            .iter()
+                            .flat_map(|&a| kept.iter().map(move |&b| a.distance(b)))
+                            .min()
+                            .unwrap_or(u32::MAX)
+                    };
+                    let farthest = (i..orbits.len())
+                        .max_by_key(|&j| (gap(&orbits[j]), std::cmp::Reverse(j)))
+                        .unwrap();
+                    orbits.swap(i, farthest);
+                    kept.extend(orbits[i].iter().copied());
+                }
+                orbits.reverse();
+                0
+            }
+            CluePlacement::Clustered => {
+                // Hide the clues farthest from a random center first
+                let center = orbits[rng.random_range(0..orbits.len())][0];
+                orbits.sort_by_key(|orbit| {
+                    std::cmp::Reverse(orbit.iter().map(|&c| c.distance(center)).min())
+                });
+                0
+            }
+        }
+    }
+}
+
+/// Whether `coord` lies in the outer ring, or the outer two rings on boards of radius 4 or more
+fn is_border(radius: u8, coord: Coord) -> bool {
+    let rings = if radius >= 4 { 2 } else { 1 };
+    coord.distance(Coord::new(0, 0)) + rings > radius as u32
+}
+
+impl std::fmt::Display for CluePlacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CluePlacement::Random => f.write_str("random"),
+            CluePlacement::Border => f.write_str("border"),
+            CluePlacement::Spread => f.write_str("spread"),
+            CluePlacement::Clustered => f.write_str("clustered"),
+        }
+    }
+}
+
+impl FromStr for CluePlacement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(CluePlacement::Random),
+            "border" => Ok(CluePlacement::Border),
+            "spread" => Ok(CluePlacement::Spread),
+            "clustered" => Ok(CluePlacement::Clustered),
+            _ => Err(format!("Invalid clue placement: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Difficulty;
+    use crate::generator::{Generator, HideOptions, LoopGenerator, Symmetry, hide_clues};
+    use crate::model::Map;
+    use googletest::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[googletest::test]
+    fn border_rings_depend_on_radius() {
+        expect_that!(is_border(3, Coord::new(3, -1)), eq(true));
+        expect_that!(is_border(3, Coord::new(2, 0)), eq(false));
+        expect_that!(is_border(5, Coord::new(4, -1)), eq(true));
+        expect_that!(is_border(5, Coord::new(3, 0)), eq(false));
+    }
+
+    #[googletest::test]
+    fn border_orbits_come_last() {
+        let mut orbits: Vec<Vec<Coord>> = Map::new(4).iter_coords().map(|c| vec![c]).collect();
+        let interior = CluePlacement::Border.arrange(4, &mut orbits, &mut rand::rng());
+        expect_that!(interior, eq(19));
+        expect_that!(
+            orbits[..interior].iter().all(|o| !is_border(4, o[0])),
+            eq(true)
+        );
+        expect_that!(
+            orbits[interior..].iter().all(|o| is_border(4, o[0])),
+            eq(true)
+        );
+    }
+
+    #[googletest::test]
+    fn border_placement_prefers_border_clues() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut map = LoopGenerator.generate_solution(5, &mut rng);
+        let options = HideOptions {
+            placement: CluePlacement::Border,
+            ..Default::default()
+        };
+        hide_clues(
+            &mut map,
+            Difficulty::Easy,
+            Symmetry::None,
+            &options,
+            &mut rng,
+        );
+
+        let visible = |border: bool| {
+            map.cells
+                .iter()
+                .filter(|(c, cell)| cell.clue_visible() && is_border(5, **c) == border)
+                .count()
+        };
+        // Easy only asks for 15% hidden, but interior clues go whenever uniqueness allows
+        expect_that!(visible(false), lt(visible(true)));
+    }
+
+    fn singleton_orbits(radius: u8) -> Vec<Vec<Coord>> {
+        Map::new(radius).iter_coords().map(|c| vec![c]).collect()
+    }
+
+    /// Smallest distance between any two of the given cells
+    fn min_gap(cells: &[Vec<Coord>]) -> u32 {
+        let mut gap = u32::MAX;
+        for (i, a) in cells.iter().enumerate() {
+            for b in &cells[i + 1..] {
+                gap = gap.min(a[0].distance(b[0]));
+            }
+        }
+        gap
+    }
+
+    #[googletest::test]
+    fn spread_keeps_clues_apart() {
+        let mut orbits = singleton_orbits(4);
+        CluePlacement::Spread.arrange(4, &mut orbits, &mut rand::rng());
+        // The last orbits are the ones removal reaches last, so they stay visible
+        let kept = &orbits[orbits.len() - 7..];
+        expect_that!(min_gap(kept), ge(3));
+    }
+
+    #[googletest::test]
+    fn clustered_keeps_clues_together() {
+        let mut orbits = singleton_orbits(4);
+        CluePlacement::Clustered.arrange(4, &mut orbits, &mut rand::rng());
+        // Removal reaches the cells nearest the center last, so they stay visible
+        let center = orbits[orbits.len() - 1][0];
+        let distances: Vec<u32> = orbits.iter().map(|o| o[0].distance(center)).collect();
+        expect_that!(distances.windows(2).all(|w| w[0] >= w[1]), eq(true));
+    }
+}
@@ -0,0 +1,340 @@
+//! Post-processing of accepted answers: the steps that turn an answer into a finished puzzle,
+//! run in order by a [`Pipeline`]. Library users can add their own steps with
+//! [`Pipeline::then`] instead of forking the generator.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use rand::RngCore;
+
+use crate::args::Difficulty;
+use crate::model::{Clue, Coord, Map};
+
+use super::{
+    DifficultyRater, GenerationConstraints, Generator, GeneratorProgress, HideOptions, Symmetry,
+};
+
+/// What a post-processing step knows about the puzzle being made
+pub struct PostContext<'a> {
+    /// Generator that made the answer
+    pub generator: &'a dyn Generator,
+    pub difficulty: Difficulty,
+    /// How clues are hidden, including the generation deadline
+    pub hide_options: HideOptions<'a>,
+}
+
+impl<'a> PostContext<'a> {
+    /// Context for generating one puzzle under `constraints`. The deadline for hiding clues
+    /// counts from now.
+    pub fn new(
+        generator: &'a dyn Generator,
+        difficulty: Difficulty,
+        constraints: &GenerationConstraints,
+        progress: &'a dyn GeneratorProgress,
+    ) -> Self {
+        let band = constraints
+            .rate_difficulty
+            .then(|| DifficultyRater::default().band(difficulty))
+            .flatten();
+        PostContext {
+            generator,
+            difficulty,
+            hide_options: HideOptions {
+                placement: constraints.clue_placement,
+                deadline: constraints.timeout.map(|timeout| Instant::now() + timeout),
+                rating_ceiling: band.and_then(|(_, ceiling)| ceiling),
+                techniques: constraints.techniques,
+                markers: constraints.markers,
+                parity: constraints.parity,
+                progress,
+                solver: constraints.solver,
+                pool: None,
+            },
+        }
+    }
+}
+
+/// One step of a [`Pipeline`]
+pub trait PostProcessor {
+    /// Process the map in place. An error rejects the puzzle, which counts as a failed attempt.
+    fn process(
+        &self,
+        map: &mut Map,
+        context: &PostContext,
+        rng: &mut dyn RngCore,
+    ) -> Result<(), String>;
+
+    /// Short name counting the puzzles this step rejects in tuning reports
+    fn name(&self) -> &'static str {
+        "post-processing"
+    }
+}
+
+/// Ordered post-processing steps, run on every answer that meets the generation constraints
+#[derive(Default)]
+pub struct Pipeline {
+    steps: Vec<Box<dyn PostProcessor + Send + Sync>>,
+}
+
+impl Pipeline {
+    /// A pipeline with no steps, which leaves answers fully clued
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The steps `generate_constrained` runs: hide clues, stamp the difficulty level, and with
+    /// `rate_difficulty` reject puzzles rated outside the difficulty's band
+    pub fn standard(constraints: &GenerationConstraints) -> Self {
+        let pipeline = Self::new().then(HideClues).then(StampLevel);
+        if constraints.rate_difficulty {
+            pipeline.then(CheckDifficulty)
+        } else {
+            pipeline
+        }
+    }
+
+    /// Add a step after the existing ones
+    pub fn then(mut self, step: impl PostProcessor + Send + Sync + 'static) -> Self {
+        self.steps.push(Box::new(step));
+        self
+    }
+
+    /// Run every step in order, stopping at the first that rejects the puzzle. A rejection
+    /// comes with the name of the step.
+    pub fn run(
+        &self,
+        map: &mut Map,
+        context: &PostContext,
+        rng: &mut dyn RngCore,
+    ) -> Result<(), (&'static str, String)> {
+        self.steps.iter().try_for_each(|step| {
+            step.process(map, context, rng)
+                .map_err(|reason| (step.name(), reason))
+        })
+    }
+}
+
+/// Hide clues the way the generator does for the difficulty
+pub struct HideClues;
+
+impl PostProcessor for HideClues {
+    fn process(
+        &self,
+        map: &mut Map,
+        context: &PostContext,
+        rng: &mut dyn RngCore,
+    ) -> Result<(), String> {
+        context
+            .generator
+            .hide_clues(map, context.difficulty, &context.hide_options, rng);
+        Ok(())
+    }
+}
+
+/// Record the difficulty level in the map, so it is saved in the file header
+pub struct StampLevel;
+
+impl PostProcessor for StampLevel {
+    fn process(
+        &self,
+        map: &mut Map,
+        context: &PostContext,
+        _rng: &mut dyn RngCore,
+    ) -> Result<(), String> {
+        map.level = context.difficulty.level();
+        Ok(())
+    }
+}
+
+/// Reject puzzles rated easier than the difficulty's band. Timed-out puzzles are kept as is.
+pub struct CheckDifficulty;
+
+impl PostProcessor for CheckDifficulty {
+    fn process(
+        &self,
+        map: &mut Map,
+        context: &PostContext,
+        _rng: &mut dyn RngCore,
+    ) -> Result<(), String> {
+        if map.timed_out {
+            return Ok(());
+        }
+        DifficultyRater::default().check_difficulty(map, context.difficulty)
+    }
+
+    fn name(&self) -> &'static str {
+        "difficulty"
+    }
+}
+
+/// Show every clue in the symmetry orbit of a shown one, so the visible pattern has the
+/// symmetry. Showing clues never adds solutions, so the puzzle stays unique.
+pub struct EnforceSymmetry(pub Symmetry);
+
+impl PostProcessor for EnforceSymmetry {
+    fn process(
+        &self,
+        map: &mut Map,
+        _context: &PostContext,
+        _rng: &mut dyn RngCore,
+    ) -> Result<(), String> {
+        let coords: Vec<Coord> = map.iter_coords().collect();
+        for coord in coords {
+            let shown = |map: &Map, c: &Coord| map.cells[c].clue != Clue::Hidden;
+            let orbit = self.0.orbit(coord);
+            if orbit.iter().any(|c| shown(map, c)) {
+                for c in &orbit {
+                    if !shown(map, c) {
+                        map.cells.get_mut(c).unwrap().clue = Clue::Count;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reject puzzles that repeat one this step has already passed, up to rotation or reflection.
+/// With several workers, which of two repeats is kept depends on scheduling.
+#[derive(Default)]
+pub struct Dedup {
+    seen: Mutex<HashSet<Vec<u8>>>,
+}
+
+impl PostProcessor for Dedup {
+    fn process(
+        &self,
+        map: &mut Map,
+        _context: &PostContext,
+        _rng: &mut dyn RngCore,
+    ) -> Result<(), String> {
+        let mut seen = self.seen.lock().unwrap();
+        if seen.insert(map.canonical_form()) {
+            Ok(())
+        } else {
+            Err("repeats an earlier puzzle".to_string())
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "duplicate"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{
+        LoopGenerator, NoProgress, RegionGrowthGenerator, generate_with_pipeline,
+    };
+    use googletest::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Rejects every puzzle, counting how often it was asked
+    struct RejectAll(Arc<AtomicU32>);
+
+    impl PostProcessor for RejectAll {
+        fn process(
+            &self,
+            _map: &mut Map,
+            _context: &PostContext,
+            _rng: &mut dyn RngCore,
+        ) -> std::result::Result<(), String> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Err("rejected".to_string())
+        }
+    }
+
+    #[googletest::test]
+    fn custom_steps_can_reject_puzzles() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let constraints = GenerationConstraints {
+            max_attempts: 3,
+            ..GenerationConstraints::unconstrained()
+        };
+        let pipeline = Pipeline::standard(&constraints).then(RejectAll(calls.clone()));
+        let result = generate_with_pipeline(
+            &LoopGenerator,
+            3,
+            Difficulty::Easy,
+            &constraints,
+            &pipeline,
+            &NoProgress,
+            &mut rand::rng(),
+        );
+        expect_that!(result, err(displays_as(contains_substring("rejected"))));
+        expect_that!(calls.load(Ordering::Relaxed), eq(3));
+    }
+
+    #[googletest::test]
+    fn empty_pipeline_leaves_answer_fully_clued() {
+        let map = generate_with_pipeline(
+            &LoopGenerator,
+            3,
+            Difficulty::Hard,
+            &GenerationConstraints::unconstrained(),
+            &Pipeline::new(),
+            &NoProgress,
+            &mut rand::rng(),
+        )
+        .unwrap();
+        expect_that!(
+            map.cells
+                .values()
+                .map(|c| c.clue_visible())
+                .collect::<Vec<_>>(),
+            each(eq(&true))
+        );
+        expect_that!(map.level, none());
+    }
+
+    #[googletest::test]
+    fn enforce_symmetry_shows_whole_orbits() {
+        let symmetry = Symmetry::Rotational3;
+        let generator = RegionGrowthGenerator {
+            fill_ratio: 0.5,
+            symmetry,
+        };
+        let map = generate_with_pipeline(
+            &generator,
+            4,
+            Difficulty::Hard,
+            &GenerationConstraints::unconstrained(),
+            &Pipeline::new()
+                .then(HideClues)
+                .then(EnforceSymmetry(Symmetry::Rotational2)),
+            &NoProgress,
+            &mut StdRng::seed_from_u64(3),
+        )
+        .unwrap();
+        for coord in map.iter_coords() {
+            for image in Symmetry::Rotational2.orbit(coord) {
+                expect_that!(
+                    map.cells[&image].clue_visible(),
+                    eq(map.cells[&coord].clue_visible())
+                );
+            }
+        }
+    }
+
+    #[googletest::test]
+    fn dedup_rejects_repeats() {
+        let dedup = Dedup::default();
+        let context = PostContext {
+            generator: &LoopGenerator,
+            difficulty: Difficulty::Easy,
+            hide_options: HideOptions::default(),
+        };
+        let mut map = LoopGenerator.generate(3, Difficulty::Easy, &mut StdRng::seed_from_u64(1));
+        let mut rng = rand::rng();
+        expect_that!(dedup.process(&mut map, &context, &mut rng), ok(anything()));
+        expect_that!(
+            dedup.process(&mut map, &context, &mut rng),
+            err(contains_substring("repeats"))
+        );
+    }
+}
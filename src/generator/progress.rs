@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use serde::Serialize;
+
+use crate::model::Map;
+
+/// Receives progress reports while puzzles are generated, e.g. to show progress in the CLI
+/// or collect telemetry. Every method does nothing by default.
+///
+/// Reports may come from several worker threads at once.
+pub trait GeneratorProgress: Sync {
+    /// A candidate answer was generated. `attempt` counts from 1 for each puzzle.
+    fn answer_generated(&self, _attempt: u32, _loop_length: u32) {}
+
+    /// The answer of attempt `attempt` was rejected. `kind` names the check that failed, e.g.
+    /// "loop-too-short" or "difficulty", and `reason` describes the failure.
+    fn answer_rejected(&self, _attempt: u32, _kind: &'static str, _reason: &str) {}
+
+    /// Another clue was hidden: `hidden` of the board's `total` clues are now hidden
+    fn clue_hidden(&self, _hidden: usize, _total: usize) {}
+
+    /// A puzzle is complete
+    fn puzzle_finished(&self, _map: &Map) {}
+}
+
+/// Ignores every report
+pub struct NoProgress;
+
+impl GeneratorProgress for NoProgress {}
+
+/// How many candidate answers were tried, and how many were rejected by each kind of check
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct AttemptStats {
+    pub answers: u32,
+    pub rejected: BTreeMap<&'static str, u32>,
+}
+
+impl AttemptStats {
+    /// Count a rejection of the given kind
+    pub fn reject(&mut self, kind: &'static str) {
+        *self.rejected.entry(kind).or_default() += 1;
+    }
+
+    /// Add the counts of `other` to these
+    pub fn add(&mut self, other: &AttemptStats) {
+        self.answers += other.answers;
+        for (&kind, &count) in &other.rejected {
+            *self.rejected.entry(kind).or_default() += count;
+        }
+    }
+}
+
+/// Collects [`AttemptStats`] from the reports it receives, passing every report on to `inner`
+pub struct Tally<'a> {
+    inner: &'a dyn GeneratorProgress,
+    stats: Mutex<AttemptStats>,
+}
+
+impl<'a> Tally<'a> {
+    pub fn new(inner: &'a dyn GeneratorProgress) -> Self {
+        Self {
+            inner,
+            stats: Mutex::new(AttemptStats::default()),
+        }
+    }
+
+    pub fn into_stats(self) -> AttemptStats {
+        self.stats.into_inner().unwrap()
+    }
+}
+
+impl GeneratorProgress for Tally<'_> {
+    fn answer_generated(&self, attempt: u32, loop_length: u32) {
+        self.stats.lock().unwrap().answers += 1;
+        self.inner.answer_generated(attempt, loop_length);
+    }
+
+    fn answer_rejected(&self, attempt: u32, kind: &'static str, reason: &str) {
+        self.stats.lock().unwrap().reject(kind);
+        self.inner.answer_rejected(attempt, kind, reason);
+    }
+
+    fn clue_hidden(&self, hidden: usize, total: usize) {
+        self.inner.clue_hidden(hidden, total);
+    }
+
+    fn puzzle_finished(&self, map: &Map) {
+        self.inner.puzzle_finished(map);
+    }
+}
+
+/// Prints a status line to stderr, rewritten in place as reports come in. The caller ends the
+/// line once generation is over.
+pub struct ConsoleProgress {
+    total: u32,
+    finished: AtomicU32,
+}
+
+impl ConsoleProgress {
+    /// Report progress towards `total` puzzles
+    pub fn new(total: u32) -> Self {
+        Self {
+            total,
+            finished: AtomicU32::new(0),
+        }
+    }
+
+    fn status(&self, detail: std::fmt::Arguments) {
+        let finished = self.finished.load(Ordering::Relaxed);
+        // Pad so a shorter line fully covers the previous one
+        eprint!(
+            "\r{:<72}",
+            format!("[{}/{}] {}", finished, self.total, detail)
+        );
+    }
+}
+
+impl GeneratorProgress for ConsoleProgress {
+    fn answer_generated(&self, attempt: u32, loop_length: u32) {
+        self.status(format_args!(
+            "attempt {}: loop length {}",
+            attempt, loop_length
+        ));
+    }
+
+    fn clue_hidden(&self, hidden: usize, total: usize) {
+        self.status(format_args!("{}/{} clues hidden", hidden, total));
+    }
+
+    fn puzzle_finished(&self, _map: &Map) {
+        self.finished.fetch_add(1, Ordering::Relaxed);
+        self.status(format_args!("puzzle done"));
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+
+    /// Records every report for inspection
+    #[derive(Default)]
+    pub(crate) struct RecordingProgress {
+        pub(crate) attempts: Mutex<Vec<(u32, u32)>>,
+        pub(crate) hidden: Mutex<Vec<usize>>,
+        pub(crate) finished: AtomicU32,
+    }
+
+    impl GeneratorProgress for RecordingProgress {
+        fn answer_generated(&self, attempt: u32, loop_length: u32) {
+            self.attempts.lock().unwrap().push((attempt, loop_length));
+        }
+
+        fn clue_hidden(&self, hidden: usize, _total: usize) {
+            self.hidden.lock().unwrap().push(hidden);
+        }
+
+        fn puzzle_finished(&self, _map: &Map) {
+            self.finished.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
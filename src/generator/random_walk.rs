@@ -0,0 +1,187 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use rand::{Rng, RngCore};
+
+use crate::model::{Coord, Map};
+
+use super::{Generator, map_from_inside};
+
+/// Give up on random walks after this many restarts and fall back to the loop around the center hex
+const MAX_ATTEMPTS: usize = 10_000;
+
+/// A hex corner, identified by the three cells that meet there (sorted)
+type Vertex = [Coord; 3];
+
+/// A hex side, identified by the two cells it separates (sorted)
+type Edge = (Coord, Coord);
+
+/// Builds the solution loop directly, as a self-avoiding random walk over hex corners
+/// that is closed as soon as it runs into itself far enough back.
+pub struct RandomWalkGenerator;
+
+impl Generator for RandomWalkGenerator {
+    fn generate_solution(&self, radius: u8, rng: &mut dyn RngCore) -> Map {
+        let min_length = min_loop_length(radius);
+        let edges = (0..MAX_ATTEMPTS)
+            .find_map(|_| random_loop(radius, min_length, rng))
+            .unwrap_or_else(|| {
+                let center = Coord::new(0, 0);
+                center
+                    .neighbors()
+                    .iter()
+                    .map(|&n| edge(center, n))
+                    .collect()
+            });
+
+        let inside = enclosed_cells(radius, &edges);
+        map_from_inside(radius, &inside)
+    }
+}
+
+/// Shortest loop we accept: one hex's worth of edges per ring of the board
+fn min_loop_length(radius: u8) -> usize {
+    6 * (radius as usize + 1)
+}
+
+fn edge(a: Coord, b: Coord) -> Edge {
+    if (a.q, a.r) <= (b.q, b.r) {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn vertex(mut cells: [Coord; 3]) -> Vertex {
+    cells.sort_by_key(|c| (c.q, c.r));
+    cells
+}
+
+/// Edges may be walked if they border at least one cell of the board
+fn edge_on_board(radius: u8, (a, b): Edge) -> bool {
+    a.in_bounds(radius) || b.in_bounds(radius)
+}
+
+/// The three corners adjacent to `v`, each paired with the edge leading there
+fn adjacent_vertices(v: Vertex) -> [(Vertex, Edge); 3] {
+    [(0, 1, 2), (0, 2, 1), (1, 2, 0)].map(|(i, j, k)| {
+        let (a, b) = (v[i], v[j]);
+        // a and b share exactly two neighbors: the third cell of `v`, and the one across the edge
+        let across = a
+            .neighbors()
+            .into_iter()
+            .find(|n| *n != v[k] && b.neighbors().contains(n))
+            .expect("adjacent hexes share two neighbors");
+        (vertex([a, b, across]), edge(a, b))
+    })
+}
+
+/// One attempt at a self-avoiding walk that closes into a loop of at least `min_length` edges
+fn random_loop<R: Rng + ?Sized>(radius: u8, min_length: usize, rng: &mut R) -> Option<Vec<Edge>> {
+    let coords: Vec<Coord> = Map::new(radius).iter_coords().collect();
+    let start_cell = coords[rng.random_range(0..coords.len())];
+    let ring = start_cell.neighbors();
+    let corner = rng.random_range(0..6);
+    let start = vertex([start_cell, ring[corner], ring[(corner + 1) % 6]]);
+
+    let mut path = vec![start];
+    let mut edges: Vec<Edge> = Vec::new();
+    let mut position: HashMap<Vertex, usize> = HashMap::from([(start, 0)]);
+
+    loop {
+        let current = *path.last().unwrap();
+        let previous = path.len().checked_sub(2).map(|i| path[i]);
+
+        let mut moves: Vec<(Vertex, Edge)> = adjacent_vertices(current)
+            .into_iter()
+            .filter(|&(next, e)| edge_on_board(radius, e) && Some(next) != previous)
+            .filter(|(next, _)| match position.get(next) {
+                // Running into the walk closes a loop; only allow it if the loop is long enough
+                Some(&i) => path.len() - i >= min_length,
+                None => true,
+            })
+            .collect();
+        if moves.is_empty() {
+            return None;
+        }
+
+        let (next, e) = moves.swap_remove(rng.random_range(0..moves.len()));
+        edges.push(e);
+        if let Some(&i) = position.get(&next) {
+            // Keep only the closed part of the walk
+            return Some(edges.split_off(i));
+        }
+        position.insert(next, path.len());
+        path.push(next);
+    }
+}
+
+/// Cells of the board enclosed by the loop, found by flooding in from one ring beyond the rim
+fn enclosed_cells(radius: u8, loop_edges: &[Edge]) -> HashSet<Coord> {
+    let loop_edges: HashSet<Edge> = loop_edges.iter().copied().collect();
+    let outer = radius + 1;
+    let all: Vec<Coord> = Map::new(outer).iter_coords().collect();
+
+    let mut outside: HashSet<Coord> = all
+        .iter()
+        .copied()
+        .filter(|c| !c.in_bounds(radius))
+        .collect();
+    let mut queue: VecDeque<Coord> = outside.iter().copied().collect();
+    while let Some(c) = queue.pop_front() {
+        for n in c.neighbors() {
+            if n.in_bounds(outer) && !loop_edges.contains(&edge(c, n)) && outside.insert(n) {
+                queue.push_back(n);
+            }
+        }
+    }
+
+    all.into_iter()
+        .filter(|c| c.in_bounds(radius) && !outside.contains(c))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Difficulty;
+    use crate::generator::testing::component_count;
+    use crate::model::Region;
+    use googletest::prelude::*;
+
+    #[googletest::test]
+    fn generated_regions_form_a_single_loop() {
+        for radius in [0, 1, 2, 4, 8] {
+            let map = RandomWalkGenerator.generate(radius, Difficulty::Medium, &mut rand::rng());
+            expect_that!(map.cells.len(), eq(map.iter_coords().count()));
+            expect_that!(component_count(&map, Region::Inside), eq(1));
+            expect_that!(component_count(&map, Region::Outside), eq(1));
+        }
+    }
+
+    #[googletest::test]
+    fn loops_are_at_least_the_minimum_length() {
+        for radius in [0, 2, 4] {
+            let map = RandomWalkGenerator.generate(radius, Difficulty::Easy, &mut rand::rng());
+            // Every loop edge is counted by the Inside cell it borders
+            let loop_length: usize = map
+                .cells
+                .values()
+                .filter(|c| c.region == Region::Inside)
+                .map(|c| c.full_neighbor_count as usize)
+                .sum();
+            expect_that!(loop_length, ge(min_loop_length(radius)));
+        }
+    }
+
+    #[googletest::test]
+    fn corners_have_three_distinct_neighbors() {
+        let v = vertex([Coord::new(0, 0), Coord::new(1, 0), Coord::new(1, -1)]);
+        let adjacent = adjacent_vertices(v);
+        let distinct: HashSet<Vertex> = adjacent.iter().map(|(n, _)| *n).collect();
+        expect_that!(distinct.len(), eq(3));
+        for (n, _) in adjacent {
+            // Each neighboring corner leads straight back
+            expect_that!(adjacent_vertices(n).map(|(m, _)| m).contains(&v), eq(true));
+        }
+    }
+}
@@ -0,0 +1,693 @@
+//! Difficulty rating by the deduction techniques a logical solver needs.
+//!
+//! The solver only ever applies the easiest technique that makes progress:
+//! 1. Zero-six: a 0 puts every neighbor in the cell's region, a 6 puts every neighbor in
+//!    the other one.
+//! 2. Counting: a clue whose cell and neighbors are mostly decided forces the rest.
+//! 3. Chain: the same clue reasoning over linked groups of undecided cells, so one
+//!    deduction can travel across the board before any region is known.
+//! 4. Trial: assume a cell's region and rule it out by contradiction, nesting trials up
+//!    to a depth limit.
+//!
+//! The hardest technique used is the puzzle's rating. The solver can also be limited to a
+//! [`TechniqueSet`], e.g. to make tutorial puzzles that only need the techniques taught so far.
+//! Its steps can be recorded as a trace of [`Deduction`]s, e.g. for step-by-step hints.
+
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+
+use crate::args::Difficulty;
+use crate::model::{Coord, KnownRegion, Map, Region};
+
+use super::uniqueness::{Contradiction, Puzzle, State, connectivity_possible, propagate};
+
+/// Deduction techniques, from easiest to hardest
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Technique {
+    ZeroSix,
+    Counting,
+    Chain,
+    /// Trial and error with assumptions nested this many levels deep
+    Trial(u32),
+}
+
+impl std::fmt::Display for Technique {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Technique::ZeroSix => f.write_str("zero-six"),
+            Technique::Counting => f.write_str("counting"),
+            Technique::Chain => f.write_str("chain"),
+            Technique::Trial(depth) => write!(f, "trial depth {}", depth),
+        }
+    }
+}
+
+impl Technique {
+    /// The technique's name as `--techniques` spells it, e.g. "counting" or "trial-2"
+    pub fn name(&self) -> String {
+        match self {
+            Technique::Trial(depth) => format!("trial-{}", depth),
+            technique => technique.to_string(),
+        }
+    }
+}
+
+impl Serialize for Technique {
+    /// The technique's [name](Technique::name)
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name())
+    }
+}
+
+/// One step of a logical solve
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Deduction {
+    pub technique: Technique,
+    /// Clue the step was read from (zero-six and counting steps only)
+    pub clue: Option<Coord>,
+    /// Cells the step decided, in board order
+    pub cells: Vec<DecidedCell>,
+}
+
+/// A cell and the region a deduction put it in
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct DecidedCell {
+    #[serde(flatten)]
+    pub coord: Coord,
+    pub region: Region,
+}
+
+/// Finer-grained measure of difficulty than the rating alone, for ordering puzzles that need
+/// the same hardest technique. Compares by the hardest technique first, then by how many clues
+/// are hidden, then by how often the hardest technique is needed.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Score {
+    pub hardest: Technique,
+    pub hidden_clues: usize,
+    /// Solver steps that used the hardest technique
+    pub hardest_steps: usize,
+}
+
+impl std::fmt::Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} x{}, {} clues hidden",
+            self.hardest, self.hardest_steps, self.hidden_clues
+        )
+    }
+}
+
+/// Records each step of a solve as the cells it newly decided
+struct Trace {
+    coords: Vec<Coord>,
+    known: Vec<KnownRegion>,
+    steps: Vec<Deduction>,
+}
+
+impl Trace {
+    fn new(map: &Map) -> Self {
+        // Numbered like the cells of the puzzle, holes included
+        let coords: Vec<Coord> = map.view().iter_coords().collect();
+        Self {
+            known: vec![KnownRegion::Unknown; coords.len()],
+            coords,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Add a step for every cell decided since the last one, unless there are none
+    fn record(
+        &mut self,
+        puzzle: &Puzzle,
+        state: &mut State,
+        technique: Technique,
+        clue: Option<usize>,
+    ) {
+        let regions = state.known_regions(puzzle);
+        let mut cells = Vec::new();
+        for (cell, (&region, known)) in regions.iter().zip(&mut self.known).enumerate() {
+            if let (Some(region), KnownRegion::Unknown) = (region.region(), *known) {
+                cells.push(DecidedCell {
+                    coord: self.coords[cell],
+                    region,
+                });
+                *known = region.into();
+            }
+        }
+        if !cells.is_empty() {
+            self.steps.push(Deduction {
+                technique,
+                clue: clue.map(|c| self.coords[c]),
+                cells,
+            });
+        }
+    }
+}
+
+/// Techniques a solver may use
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TechniqueSet {
+    zero_six: bool,
+    counting: bool,
+    chain: bool,
+    /// Deepest nesting of trials allowed, 0 for none
+    max_trial_depth: u32,
+}
+
+impl TechniqueSet {
+    /// Every technique up to and including `ceiling`
+    pub fn up_to(ceiling: Technique) -> Self {
+        Self {
+            zero_six: true,
+            counting: ceiling >= Technique::Counting,
+            chain: ceiling >= Technique::Chain,
+            max_trial_depth: match ceiling {
+                Technique::Trial(depth) => depth,
+                _ => 0,
+            },
+        }
+    }
+
+    /// Whether the set allows `technique`
+    pub fn contains(&self, technique: Technique) -> bool {
+        match technique {
+            Technique::ZeroSix => self.zero_six,
+            Technique::Counting => self.counting,
+            Technique::Chain => self.chain,
+            Technique::Trial(depth) => depth <= self.max_trial_depth,
+        }
+    }
+
+    /// Techniques in both sets
+    pub fn intersect(self, other: TechniqueSet) -> Self {
+        Self {
+            zero_six: self.zero_six && other.zero_six,
+            counting: self.counting && other.counting,
+            chain: self.chain && other.chain,
+            max_trial_depth: self.max_trial_depth.min(other.max_trial_depth),
+        }
+    }
+}
+
+impl std::fmt::Display for TechniqueSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<String> = [
+            (self.zero_six, "zero-six"),
+            (self.counting, "counting"),
+            (self.chain, "chain"),
+        ]
+        .iter()
+        .filter(|(allowed, _)| *allowed)
+        .map(|(_, name)| name.to_string())
+        .collect();
+        match self.max_trial_depth {
+            0 => {}
+            u32::MAX => names.push("trial".to_string()),
+            depth => names.push(format!("trial-{}", depth)),
+        }
+        f.write_str(&names.join(","))
+    }
+}
+
+impl FromStr for TechniqueSet {
+    type Err = String;
+
+    /// Comma-separated technique names: zero-six, counting, chain, and trial (any depth)
+    /// or trial-N (nested at most N deep)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut set = Self {
+            zero_six: false,
+            counting: false,
+            chain: false,
+            max_trial_depth: 0,
+        };
+        for name in s.split(',').map(str::trim) {
+            match name {
+                "zero-six" => set.zero_six = true,
+                "counting" => set.counting = true,
+                "chain" => set.chain = true,
+                "trial" => set.max_trial_depth = u32::MAX,
+                _ => match name.strip_prefix("trial-").map(str::parse) {
+                    Some(Ok(depth)) => set.max_trial_depth = set.max_trial_depth.max(depth),
+                    _ => return Err(format!("Invalid technique: {}", name)),
+                },
+            }
+        }
+        Ok(set)
+    }
+}
+
+/// Rates puzzles by the hardest technique needed to solve them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DifficultyRater {
+    /// Deepest nesting of trials to attempt before giving up on a puzzle
+    pub max_trial_depth: u32,
+}
+
+impl Default for DifficultyRater {
+    fn default() -> Self {
+        Self { max_trial_depth: 2 }
+    }
+}
+
+impl DifficultyRater {
+    /// Hardest technique needed to solve the puzzle formed by the visible clues, or `None`
+    /// if it can't be solved within the trial depth limit (or has no solution at all)
+    pub fn rate(&self, map: &Map) -> Option<Technique> {
+        self.rate_within(map, Technique::Trial(self.max_trial_depth))
+    }
+
+    /// Like [`rate`](Self::rate), but gives up as soon as a technique harder than `ceiling`
+    /// would be needed
+    pub fn rate_within(&self, map: &Map, ceiling: Technique) -> Option<Technique> {
+        self.rate_with(map, &TechniqueSet::up_to(ceiling))
+    }
+
+    /// Like [`rate`](Self::rate), but only using the techniques in `allowed`
+    pub fn rate_with(&self, map: &Map, allowed: &TechniqueSet) -> Option<Technique> {
+        self.solve(map, allowed, None)
+    }
+
+    /// Every step the solver takes on the puzzle, in order, or `None` if it can't be solved
+    /// within the trial depth limit. Markers count as given, so they are not steps.
+    pub fn trace(&self, map: &Map) -> Option<Vec<Deduction>> {
+        let mut trace = Trace::new(map);
+        let allowed = TechniqueSet::up_to(Technique::Trial(self.max_trial_depth));
+        self.solve(map, &allowed, Some(&mut trace))?;
+        Some(trace.steps)
+    }
+
+    /// Score the puzzle from its solving trace, or `None` if it can't be solved within the trial
+    /// depth limit
+    pub fn score(&self, map: &Map) -> Option<Score> {
+        let steps = self.trace(map)?;
+        let hardest = steps
+            .iter()
+            .map(|s| s.technique)
+            .max()
+            .unwrap_or(Technique::ZeroSix);
+        Some(Score {
+            hardest,
+            hidden_clues: map.cells.values().filter(|c| !c.clue_visible()).count(),
+            hardest_steps: steps.iter().filter(|s| s.technique == hardest).count(),
+        })
+    }
+
+    fn solve(
+        &self,
+        map: &Map,
+        allowed: &TechniqueSet,
+        mut trace: Option<&mut Trace>,
+    ) -> Option<Technique> {
+        let puzzle = Puzzle::new(&map.view());
+        let mut state = State::new(&puzzle);
+        if let Some(trace) = trace.as_deref_mut() {
+            // Cells fixed by markers are given rather than deduced
+            let regions = state.known_regions(&puzzle);
+            let cells = trace.known.len();
+            trace.known.copy_from_slice(&regions[..cells]);
+        }
+        let mut hardest = Technique::ZeroSix;
+        loop {
+            if allowed.contains(Technique::ZeroSix) {
+                apply_counting(&puzzle, &mut state, true, trace.as_deref_mut()).ok()?;
+            }
+            let undecided = undecided_count(&puzzle, &mut state);
+            if undecided == 0 {
+                return Some(hardest);
+            }
+
+            if allowed.contains(Technique::Counting) {
+                apply_counting(&puzzle, &mut state, false, trace.as_deref_mut()).ok()?;
+                if undecided_count(&puzzle, &mut state) < undecided {
+                    hardest = hardest.max(Technique::Counting);
+                    continue;
+                }
+            }
+
+            if allowed.contains(Technique::Chain) {
+                let mut chained = state.clone();
+                if propagate(&puzzle, &mut chained).is_ok()
+                    && undecided_count(&puzzle, &mut chained) < undecided
+                {
+                    state = chained;
+                    hardest = hardest.max(Technique::Chain);
+                    if let Some(trace) = trace.as_deref_mut() {
+                        trace.record(&puzzle, &mut state, Technique::Chain, None);
+                    }
+                    continue;
+                }
+            }
+
+            let depth = (1..=self.max_trial_depth)
+                .filter(|&depth| allowed.contains(Technique::Trial(depth)))
+                .find(|&depth| matches!(trial_step(&puzzle, &mut state, depth), Ok(true)))?;
+            hardest = hardest.max(Technique::Trial(depth));
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.record(&puzzle, &mut state, Technique::Trial(depth), None);
+            }
+        }
+    }
+
+    /// Techniques bounding each difficulty: the easiest rating that counts, and the hardest
+    /// one allowed while hiding clues (`None` hides as many as uniqueness allows).
+    /// Custom densities aren't rated.
+    pub fn band(&self, difficulty: Difficulty) -> Option<(Technique, Option<Technique>)> {
+        match difficulty {
+            Difficulty::Easy => Some((Technique::ZeroSix, Some(Technique::Counting))),
+            Difficulty::Medium => Some((Technique::Chain, Some(Technique::Chain))),
+            Difficulty::Hard => Some((Technique::Trial(1), None)),
+            Difficulty::Custom(_) => None,
+            Difficulty::Level(1..=2) => Some((Technique::ZeroSix, Some(Technique::Counting))),
+            Difficulty::Level(3..=4) => Some((Technique::Counting, Some(Technique::Counting))),
+            Difficulty::Level(5..=6) => Some((Technique::Chain, Some(Technique::Chain))),
+            Difficulty::Level(7..=8) => Some((Technique::Trial(1), Some(Technique::Trial(1)))),
+            Difficulty::Level(9) => Some((Technique::Trial(1), Some(Technique::Trial(2)))),
+            Difficulty::Level(_) => Some((Technique::Trial(1), None)),
+        }
+    }
+
+    /// The easiest of easy, medium and hard whose band allows a puzzle needing `hardest`
+    pub fn estimate(&self, hardest: Technique) -> Difficulty {
+        [Difficulty::Easy, Difficulty::Medium]
+            .into_iter()
+            .find(|&difficulty| {
+                self.band(difficulty)
+                    .and_then(|(_, ceiling)| ceiling)
+                    .is_some_and(|ceiling| hardest <= ceiling)
+            })
+            .unwrap_or(Difficulty::Hard)
+    }
+
+    /// Check that the puzzle is rated at least as hard as `difficulty` asks for
+    pub fn check_difficulty(&self, map: &Map, difficulty: Difficulty) -> Result<(), String> {
+        let Some((floor, _)) = self.band(difficulty) else {
+            return Ok(());
+        };
+        match self.rate(map) {
+            Some(rating) if rating >= floor => Ok(()),
+            rating => Err(format!(
+                "rated {} for {}",
+                rating.map_or("unsolvable".to_string(), |t| t.to_string()),
+                difficulty
+            )),
+        }
+    }
+}
+
+fn undecided_count(puzzle: &Puzzle, state: &mut State) -> usize {
+    state.regions(puzzle).iter().filter(|r| r.is_none()).count()
+}
+
+/// Basic counting until nothing changes: for each clue, compare the neighbors known to be
+/// across the loop and the undecided ones against the clue, using decided regions only.
+/// With `zero_six_only`, only exact clues of 0 and 6 are used.
+fn apply_counting(
+    puzzle: &Puzzle,
+    state: &mut State,
+    zero_six_only: bool,
+    mut trace: Option<&mut Trace>,
+) -> Result<(), Contradiction> {
+    let technique = if zero_six_only {
+        Technique::ZeroSix
+    } else {
+        Technique::Counting
+    };
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for cell in puzzle.cells() {
+            let Some(allowed) = puzzle.clues[cell] else {
+                continue;
+            };
+            if zero_six_only && allowed != 1 && allowed != 1 << 6 {
+                continue;
+            }
+            if count_clue(puzzle, state, cell, allowed)? {
+                changed = true;
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.record(puzzle, state, technique, Some(cell));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Apply counting to the clue of `cell`, which allows the loop side counts in `allowed`.
+/// Returns whether any cell was decided.
+pub(super) fn count_clue(
+    puzzle: &Puzzle,
+    state: &mut State,
+    cell: usize,
+    allowed: u8,
+) -> Result<bool, Contradiction> {
+    let mut changed = false;
+    let regions = state.regions(puzzle);
+    // Sides the clue leaves out lead back to the cell, and are never on the loop
+    let counted: Vec<usize> = puzzle.clue_sides[cell]
+        .into_iter()
+        .filter(|&n| n != cell)
+        .collect();
+    let around: Vec<Option<bool>> = counted
+        .iter()
+        .map(|&n| {
+            if n == puzzle.exterior {
+                Some(false)
+            } else {
+                regions[n]
+            }
+        })
+        .collect();
+    let unknown = around.iter().filter(|r| r.is_none()).count() as u8;
+    let across = |inside: bool| around.iter().filter(|&&r| r == Some(!inside)).count() as u8;
+    // Loop side counts the clue allows, given what is known around the cell
+    let possible = |inside: bool| {
+        let known = across(inside);
+        (known..=known + unknown)
+            .filter(|&n| allowed & (1 << n) != 0)
+            .collect::<Vec<u8>>()
+    };
+    let feasible = |inside: bool| !possible(inside).is_empty();
+
+    let inside = match regions[cell] {
+        Some(inside) => inside,
+        None => match (feasible(true), feasible(false)) {
+            (true, true) => return Ok(false),
+            (false, false) => return Err(Contradiction),
+            (only_inside, _) => {
+                state.link(cell, puzzle.exterior, only_inside)?;
+                changed = true;
+                only_inside
+            }
+        },
+    };
+    if !feasible(inside) {
+        return Err(Contradiction);
+    }
+    if unknown == 0 {
+        return Ok(changed);
+    }
+    // Every undecided neighbor is forced once the clue is already met, or can only be met
+    // by all of them
+    let forced = match possible(inside)[..] {
+        [n] if n == across(inside) => Some(inside),
+        [n] if n == across(inside) + unknown => Some(!inside),
+        _ => None,
+    };
+    if let Some(region) = forced {
+        for (&n, r) in counted.iter().zip(around) {
+            if r.is_none() {
+                state.link(n, puzzle.exterior, region)?;
+            }
+        }
+        changed = true;
+    }
+    Ok(changed)
+}
+
+/// Decide one cell by showing that the other region leads to a contradiction within
+/// trials nested `depth` deep. Returns whether a cell was decided.
+fn trial_step(puzzle: &Puzzle, state: &mut State, depth: u32) -> Result<bool, Contradiction> {
+    let regions = state.regions(puzzle);
+    for cell in puzzle.cells().filter(|&c| regions[c].is_none()) {
+        for inside in [true, false] {
+            let mut test = state.clone();
+            if test.link(cell, puzzle.exterior, inside).is_err() || refutes(puzzle, test, depth - 1)
+            {
+                state.link(cell, puzzle.exterior, !inside)?;
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Whether `state` leads to a contradiction using chains and trials nested `depth` deep
+fn refutes(puzzle: &Puzzle, mut state: State, depth: u32) -> bool {
+    loop {
+        if propagate(puzzle, &mut state).is_err() {
+            return true;
+        }
+        let regions = state.regions(puzzle);
+        if !connectivity_possible(puzzle, &regions) {
+            return true;
+        }
+        if depth == 0 {
+            return false;
+        }
+        match trial_step(puzzle, &mut state, depth) {
+            Err(Contradiction) => return true,
+            Ok(true) => continue,
+            Ok(false) => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{Generator, LoopGenerator};
+    use crate::model::Clue;
+    use googletest::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[googletest::test]
+    fn fully_clued_puzzles_need_only_counting() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let map = LoopGenerator.generate_solution(3, &mut rng);
+        expect_that!(
+            DifficultyRater::default().rate(&map),
+            some(eq(Technique::Counting))
+        );
+    }
+
+    #[googletest::test]
+    fn harder_puzzles_need_harder_techniques() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let easy = LoopGenerator.generate(3, Difficulty::Easy, &mut rng);
+        let mut rng = StdRng::seed_from_u64(5);
+        let hard = LoopGenerator.generate(3, Difficulty::Hard, &mut rng);
+
+        let rater = DifficultyRater::default();
+        let (easy, hard) = (rater.rate(&easy), rater.rate(&hard));
+        expect_that!(easy, some(anything()));
+        expect_that!(hard, some(anything()));
+        expect_that!(easy, le(hard));
+    }
+
+    #[googletest::test]
+    fn blank_board_is_unsolvable() {
+        let mut map = LoopGenerator.generate_solution(2, &mut rand::rng());
+        for cell in map.cells.values_mut() {
+            cell.clue = Clue::Hidden;
+        }
+        expect_that!(DifficultyRater::default().rate(&map), none());
+        expect_that!(
+            DifficultyRater::default().check_difficulty(&map, Difficulty::Hard),
+            err(contains_substring("unsolvable"))
+        );
+    }
+
+    #[googletest::test]
+    fn ceiling_stops_before_harder_techniques() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let hard = LoopGenerator.generate(4, Difficulty::Hard, &mut rng);
+        let rater = DifficultyRater::default();
+        let rating = rater.rate(&hard).unwrap();
+        expect_that!(rating, gt(Technique::Counting));
+        expect_that!(rater.rate_within(&hard, Technique::Counting), none());
+    }
+
+    #[googletest::test]
+    fn trace_decides_every_cell_once_matching_the_answer() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let map = LoopGenerator.generate(4, Difficulty::Hard, &mut rng);
+        let rater = DifficultyRater::default();
+        let steps = rater.trace(&map).unwrap();
+
+        let decided: Vec<DecidedCell> = steps.iter().flat_map(|s| s.cells.clone()).collect();
+        expect_that!(decided, len(eq(map.cells.len())));
+        for cell in &decided {
+            expect_that!(map.cells[&cell.coord].region, eq(cell.region));
+        }
+        let hardest = steps.iter().map(|s| s.technique).max();
+        expect_that!(hardest, eq(rater.rate(&map)));
+        for step in &steps {
+            let from_clue = step.technique <= Technique::Counting;
+            expect_that!(step.clue.is_some(), eq(from_clue));
+        }
+    }
+
+    #[googletest::test]
+    fn scores_order_by_technique_before_hidden_clues() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let map = LoopGenerator.generate(4, Difficulty::Hard, &mut rng);
+        let rater = DifficultyRater::default();
+        let score = rater.score(&map).unwrap();
+        expect_that!(Some(score.hardest), eq(rater.rate(&map)));
+
+        let easier = Score {
+            hardest: Technique::Counting,
+            hidden_clues: 60,
+            hardest_steps: 50,
+        };
+        let harder = Score {
+            hardest: Technique::Chain,
+            hidden_clues: 10,
+            hardest_steps: 1,
+        };
+        expect_that!(easier, lt(harder));
+        let more_hidden = Score {
+            hidden_clues: 61,
+            hardest_steps: 1,
+            ..easier
+        };
+        expect_that!(easier, lt(more_hidden));
+    }
+
+    #[googletest::test]
+    fn techniques_serialize_by_name() {
+        expect_that!(
+            serde_json::to_string(&[Technique::ZeroSix, Technique::Trial(2)]).unwrap(),
+            eq(r#"["zero-six","trial-2"]"#)
+        );
+    }
+
+    #[googletest::test]
+    fn technique_sets_parse_and_display() {
+        let set: TechniqueSet = "zero-six, chain,trial-2".parse().unwrap();
+        expect_that!(set.contains(Technique::ZeroSix), eq(true));
+        expect_that!(set.contains(Technique::Counting), eq(false));
+        expect_that!(set.contains(Technique::Trial(2)), eq(true));
+        expect_that!(set.contains(Technique::Trial(3)), eq(false));
+        expect_that!(set.to_string(), eq("zero-six,chain,trial-2"));
+        expect_that!("counting,guessing".parse::<TechniqueSet>(), err(anything()));
+    }
+
+    #[googletest::test]
+    fn restricted_techniques_solve_less() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let map = LoopGenerator.generate_solution(3, &mut rng);
+        let rater = DifficultyRater::default();
+        let zero_six: TechniqueSet = "zero-six".parse().unwrap();
+        let counting: TechniqueSet = "counting".parse().unwrap();
+        expect_that!(rater.rate_with(&map, &zero_six), none());
+        expect_that!(
+            rater.rate_with(&map, &counting),
+            some(eq(Technique::Counting))
+        );
+    }
+
+    #[googletest::test]
+    fn estimates_follow_the_bands() {
+        let rater = DifficultyRater::default();
+        expect_that!(rater.estimate(Technique::ZeroSix), eq(Difficulty::Easy));
+        expect_that!(rater.estimate(Technique::Counting), eq(Difficulty::Easy));
+        expect_that!(rater.estimate(Technique::Chain), eq(Difficulty::Medium));
+        expect_that!(rater.estimate(Technique::Trial(2)), eq(Difficulty::Hard));
+    }
+}
@@ -0,0 +1,186 @@
+use rand::{Rng, RngCore};
+
+use crate::args::Difficulty;
+use crate::model::{Coord, Map, RegionBitmap};
+
+use super::{Generator, HideOptions, Symmetry, hide_clues};
+
+/// Grows the Inside region outward from the center hex until `fill_ratio` of the board is inside.
+pub struct RegionGrowthGenerator {
+    /// Target fraction of cells inside the loop, in (0.0, 1.0]
+    pub fill_ratio: f64,
+    /// Symmetry the region and visible clues must respect
+    pub symmetry: Symmetry,
+}
+
+impl Generator for RegionGrowthGenerator {
+    fn generate_solution(&self, radius: u8, rng: &mut dyn RngCore) -> Map {
+        let total = Map::new(radius).iter_coords().count();
+        let target = ((total as f64 * self.fill_ratio).round() as usize).max(1);
+
+        // The center hex is fixed by every symmetry, so growth from it can stay symmetric
+        let inside = grow_region(
+            &Map::new(radius),
+            Coord::new(0, 0),
+            target,
+            self.symmetry,
+            rng,
+        );
+        inside.to_map(Map::new(radius))
+    }
+
+    fn hide_clues(
+        &self,
+        map: &mut Map,
+        difficulty: Difficulty,
+        options: &HideOptions,
+        rng: &mut dyn RngCore,
+    ) {
+        hide_clues(map, difficulty, self.symmetry, options, rng);
+    }
+}
+
+/// Grow the Inside region one random frontier cell (and its symmetry orbit) at a time from
+/// `start`, only ever adding cells that keep it free of holes. Stops early if nothing can be added.
+///
+/// With a symmetry, `start` must be a fixed point of it for the region to stay symmetric.
+/// Only cells of `board` are added, so holes in it stay Outside.
+pub(super) fn grow_region<R: Rng + ?Sized>(
+    board: &Map,
+    start: Coord,
+    target: usize,
+    symmetry: Symmetry,
+    rng: &mut R,
+) -> RegionBitmap {
+    let coords: Vec<Coord> = board.iter_coords().collect();
+
+    let mut inside = RegionBitmap::new(board.radius);
+    for coord in symmetry.orbit(start) {
+        inside.set(coord, true);
+    }
+
+    while inside.count_inside() < target {
+        let mut candidates: Vec<Coord> = coords
+            .iter()
+            .copied()
+            .filter(|&c| !inside.get(c) && is_simple(&inside, c))
+            .collect();
+
+        let mut grown = false;
+        while !candidates.is_empty() {
+            let candidate = candidates.swap_remove(rng.random_range(0..candidates.len()));
+            let orbit = symmetry.orbit(candidate);
+            if orbit.iter().all(|c| board.on_board(*c)) && add_orbit(&mut inside, &orbit) {
+                grown = true;
+                break;
+            }
+        }
+        if !grown {
+            break;
+        }
+    }
+
+    inside
+}
+
+/// Add every cell of `orbit` to `inside` in turn, as long as each one keeps the region
+/// hole-free when it is added. Leaves `inside` untouched and returns false otherwise.
+fn add_orbit(inside: &mut RegionBitmap, orbit: &[Coord]) -> bool {
+    for (i, &cell) in orbit.iter().enumerate() {
+        if !is_simple(inside, cell) {
+            for &added in &orbit[..i] {
+                inside.set(added, false);
+            }
+            return false;
+        }
+        inside.set(cell, true);
+    }
+    true
+}
+
+/// Whether adding `coord` to `inside` keeps both regions connected (no holes, no splits).
+///
+/// On a hex grid this is a local check: the Inside neighbors of the cell must form
+/// exactly one contiguous run when walking around it.
+pub(super) fn is_simple(inside: &RegionBitmap, coord: Coord) -> bool {
+    let around = coord.neighbors().map(|n| inside.get(n));
+    let runs = (0..6)
+        .filter(|&i| around[i] && !around[(i + 5) % 6])
+        .count();
+    runs == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::testing::component_count;
+    use crate::model::Region;
+    use googletest::prelude::*;
+
+    #[googletest::test]
+    fn fills_requested_fraction_of_board() {
+        for (radius, fill_ratio) in [(2, 0.4), (4, 0.5), (8, 0.6)] {
+            let map = RegionGrowthGenerator {
+                fill_ratio,
+                symmetry: Symmetry::None,
+            }
+            .generate(radius, Difficulty::Easy, &mut rand::rng());
+            let inside = map
+                .cells
+                .values()
+                .filter(|c| c.region == Region::Inside)
+                .count();
+            let expected = (map.cells.len() as f64 * fill_ratio).round() as usize;
+            expect_that!(inside, eq(expected));
+            expect_that!(component_count(&map, Region::Inside), eq(1));
+            expect_that!(component_count(&map, Region::Outside), eq(1));
+        }
+    }
+
+    #[googletest::test]
+    fn region_contains_center() {
+        let map = RegionGrowthGenerator {
+            fill_ratio: 0.3,
+            symmetry: Symmetry::None,
+        }
+        .generate(4, Difficulty::Easy, &mut rand::rng());
+        expect_that!(map.cells[&Coord::new(0, 0)].region, eq(Region::Inside));
+    }
+
+    #[googletest::test]
+    fn full_ratio_covers_whole_board() {
+        let map = RegionGrowthGenerator {
+            fill_ratio: 1.0,
+            symmetry: Symmetry::None,
+        }
+        .generate(2, Difficulty::Easy, &mut rand::rng());
+        expect_that!(
+            map.cells.values().map(|c| c.region).collect::<Vec<_>>(),
+            each(eq(&Region::Inside))
+        );
+    }
+
+    #[googletest::test]
+    fn symmetric_growth_keeps_region_and_clues_symmetric() {
+        for symmetry in [
+            Symmetry::Rotational2,
+            Symmetry::Rotational3,
+            Symmetry::Mirror,
+        ] {
+            let generator = RegionGrowthGenerator {
+                fill_ratio: 0.5,
+                symmetry,
+            };
+            let map = generator.generate(5, Difficulty::Medium, &mut rand::rng());
+            expect_that!(component_count(&map, Region::Inside), eq(1));
+            expect_that!(component_count(&map, Region::Outside), eq(1));
+            for coord in map.iter_coords() {
+                let cell = &map.cells[&coord];
+                for image in symmetry.orbit(coord) {
+                    expect_that!(map.cells[&image].region, eq(cell.region));
+                    expect_that!(map.cells[&image].clue_visible(), eq(cell.clue_visible()));
+                }
+            }
+        }
+    }
+}
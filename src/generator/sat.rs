@@ -0,0 +1,569 @@
+//! SAT backend for uniqueness checks, an alternative to the backtracking search of
+//! [`uniqueness`](super::uniqueness) for very large boards (`--solver sat`, with the `sat`
+//! feature).
+//!
+//! Each cell is a variable, true when the cell is Inside. Every visible clue becomes the
+//! clauses ruling out each assignment of the cell and its neighbors that gives a side count
+//! the clue doesn't allow, and every marker a unit clause. The single-loop rule is added
+//! lazily: whenever a model breaks it, clauses ruling out its Outside pockets and stray Inside
+//! groups are added and the solver runs again. Puzzles whose clues leave room for many ways
+//! of splitting the Inside region can take many such rounds.
+//!
+//! The solver is a small conflict-driven clause learning (CDCL) solver with two watched
+//! literals, first-UIP learning and activity-based branching.
+
+use std::collections::HashSet;
+
+use crate::model::{Clue, Coord, Map, PARITY_COUNTS, PuzzleView, Region};
+
+use super::uniqueness::Puzzle;
+
+/// Whether the visible clues of `map` admit exactly one solution
+pub(crate) fn is_unique(map: &Map) -> bool {
+    count_solutions(&map.view(), 2) == 1
+}
+
+/// Count solutions of the puzzle, stopping once `limit` are found
+pub(crate) fn count_solutions(view: &PuzzleView, limit: usize) -> usize {
+    count_solutions_and_nodes(view, limit).0
+}
+
+/// Like [`count_solutions`], also returning the number of decisions the solver made
+pub(crate) fn count_solutions_and_nodes(view: &PuzzleView, limit: usize) -> (usize, usize) {
+    let puzzle = Puzzle::new(view);
+    let mut encoding = Encoding::new(&puzzle);
+    let mut found = 0;
+    while found < limit {
+        let Some(inside) = encoding.next_solution() else {
+            break;
+        };
+        found += 1;
+        encoding.block(&inside);
+    }
+    (found, encoding.solver.decisions)
+}
+
+/// Whether a puzzle that is currently unique stays unique when the clues at `coords` are
+/// changed to the `relaxed` kind
+pub(crate) fn remains_unique_relaxing(map: &Map, coords: &[Coord], relaxed: Clue) -> bool {
+    let mut puzzle = Puzzle::new(&map.view());
+    for coord in coords {
+        let cell = &map.cells[coord];
+        let i = puzzle.index[coord];
+        puzzle.clues[i] = (relaxed == Clue::Parity)
+            .then(|| PARITY_COUNTS[(cell.full_neighbor_count % 2) as usize]);
+        if relaxed == Clue::Marker {
+            puzzle.markers[i] = Some(cell.region == Region::Inside);
+        }
+    }
+    let mut answer = vec![false; puzzle.exterior];
+    for (coord, cell) in &map.cells {
+        answer[puzzle.index[coord]] = cell.region == Region::Inside;
+    }
+    let mut encoding = Encoding::new(&puzzle);
+    encoding.block(&answer);
+    encoding.next_solution().is_none()
+}
+
+/// A puzzle as clauses over its cells, with the single-loop rule added as needed
+struct Encoding<'a> {
+    puzzle: &'a Puzzle,
+    solver: Solver,
+}
+
+impl<'a> Encoding<'a> {
+    fn new(puzzle: &'a Puzzle) -> Self {
+        let mut solver = Solver::new(puzzle.exterior);
+        for cell in puzzle.cells() {
+            if !puzzle.rim.contains(&cell) {
+                // The smallest pockets are ruled out up front: an Outside cell can't have
+                // only Inside neighbors
+                let clause = std::iter::once(Lit::new(cell, true))
+                    .chain(puzzle.sides[cell].iter().map(|&n| Lit::new(n, false)))
+                    .collect();
+                solver.add_clause(clause);
+            }
+            if let Some(inside) = puzzle.markers[cell] {
+                solver.add_clause(vec![Lit::new(cell, inside)]);
+            }
+            let Some(allowed) = puzzle.clues[cell] else {
+                continue;
+            };
+            // Sides the clue leaves out lead back to the cell, and are never on the loop
+            let vars: Vec<usize> = std::iter::once(cell)
+                .chain(
+                    puzzle.clue_sides[cell]
+                        .iter()
+                        .copied()
+                        .filter(|&n| n != puzzle.exterior && n != cell),
+                )
+                .collect();
+            let off_board = puzzle.clue_sides[cell]
+                .iter()
+                .filter(|&&n| n == puzzle.exterior)
+                .count() as u8;
+            for bits in 0u32..1 << vars.len() {
+                let value = |i: usize| bits & (1 << i) != 0;
+                let inside = value(0);
+                let across = (1..vars.len()).filter(|&i| value(i) != inside).count() as u8
+                    + if inside { off_board } else { 0 };
+                if allowed & (1 << across) == 0 {
+                    // Rule out this assignment: some cell has to differ from it
+                    let clause = vars
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &v)| Lit::new(v, !value(i)))
+                        .collect();
+                    solver.add_clause(clause);
+                }
+            }
+        }
+        Self { puzzle, solver }
+    }
+
+    /// A solution not blocked yet, as the region of every cell (`true` is Inside)
+    fn next_solution(&mut self) -> Option<Vec<bool>> {
+        loop {
+            let model = self.solver.solve()?;
+            let cuts = self.loop_cuts(&model);
+            if cuts.is_empty() {
+                return Some(model);
+            }
+            for clause in cuts {
+                self.solver.add_clause(clause);
+            }
+        }
+    }
+
+    /// Rule out the solution `inside` from now on
+    fn block(&mut self, inside: &[bool]) {
+        let clause = inside
+            .iter()
+            .enumerate()
+            .map(|(cell, &inside)| Lit::new(cell, !inside))
+            .collect();
+        self.solver.add_clause(clause);
+    }
+
+    /// Clauses the model breaks that every solution meets, if the model's boundary isn't the
+    /// puzzle's number of loops: one for every Outside pocket, and one for every stray Inside
+    /// group
+    fn loop_cuts(&self, model: &[bool]) -> Vec<Vec<Lit>> {
+        let puzzle = self.puzzle;
+        // Cells of each connected group of one region, and the cells around it
+        let group = |start: usize| {
+            let mut members = vec![start];
+            let mut seen = HashSet::from([start]);
+            let mut border = HashSet::new();
+            let mut i = 0;
+            while i < members.len() {
+                for &n in &puzzle.sides[members[i]] {
+                    if n == puzzle.exterior {
+                        continue;
+                    }
+                    if model[n] != model[start] {
+                        border.insert(n);
+                    } else if seen.insert(n) {
+                        members.push(n);
+                    }
+                }
+                i += 1;
+            }
+            (members, border)
+        };
+        // "The cell isn't closed off by the group's border": the cell is in the other region, or
+        // a border cell is. The border separates every member from the rest of the board.
+        let isolation = |cell: usize, border: &HashSet<usize>, inside: bool| {
+            std::iter::once(Lit::new(cell, !inside))
+                .chain(border.iter().map(|&c| Lit::new(c, inside)))
+                .collect::<Vec<Lit>>()
+        };
+
+        let mut cuts = Vec::new();
+        let mut grouped = vec![false; model.len()];
+        let mut inside_groups = Vec::new();
+        for cell in puzzle.cells() {
+            if grouped[cell] {
+                continue;
+            }
+            let found = group(cell);
+            for &m in &found.0 {
+                grouped[m] = true;
+            }
+            if model[cell] {
+                inside_groups.push(found);
+            } else if !found.0.iter().any(|m| puzzle.rim.contains(m)) {
+                // An Outside pocket away from the board edge
+                let (members, border) = &found;
+                cuts.extend(members.iter().map(|&m| isolation(m, border, false)));
+            }
+        }
+
+        if inside_groups.len() > puzzle.loops {
+            // No `loops` of the groups can all be closed off while a cell of another one is
+            // Inside. Each group in turn is paired with the ones after it, and the stray
+            // group's own border is a separator too.
+            let count = inside_groups.len();
+            for first in 0..count {
+                let closed: Vec<_> = (first..first + puzzle.loops)
+                    .map(|i| &inside_groups[i % count])
+                    .collect();
+                let (stray, stray_border) = &inside_groups[(first + puzzle.loops) % count];
+                let mut clause: Vec<Lit> = closed
+                    .iter()
+                    .flat_map(|(members, border)| isolation(members[0], border, true))
+                    .collect();
+                clause.push(Lit::new(stray[0], false));
+                cuts.push(clause);
+                if puzzle.loops == 1 {
+                    let mut clause = isolation(stray[0], stray_border, true);
+                    clause.push(Lit::new(closed[0].0[0], false));
+                    cuts.push(clause);
+                }
+            }
+        } else if inside_groups.len() < puzzle.loops {
+            cuts.push(
+                model
+                    .iter()
+                    .enumerate()
+                    .map(|(cell, &inside)| Lit::new(cell, !inside))
+                    .collect(),
+            );
+        }
+        cuts
+    }
+}
+
+/// A variable or its negation, as `2 * var + negated`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Lit(usize);
+
+impl Lit {
+    /// The literal that holds when `var` is `value`
+    fn new(var: usize, value: bool) -> Self {
+        Lit(2 * var + !value as usize)
+    }
+
+    fn var(self) -> usize {
+        self.0 / 2
+    }
+
+    fn negated(self) -> bool {
+        self.0 % 2 == 1
+    }
+}
+
+impl std::ops::Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+/// Incremental CDCL solver. Clauses can be added between calls to [`solve`](Self::solve).
+struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    /// Clauses watching each literal, i.e. with it among their first two literals
+    watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    /// Trail length at the start of each decision level
+    levels: Vec<usize>,
+    /// Trail entries already propagated
+    head: usize,
+    activity: Vec<f64>,
+    bump: f64,
+    /// Value each variable had last, tried first when branching on it
+    phase: Vec<bool>,
+    /// A clause with no literal left that can be true was added
+    unsatisfiable: bool,
+    /// Branches taken so far, over every call to `solve`
+    decisions: usize,
+}
+
+impl Solver {
+    fn new(vars: usize) -> Self {
+        Self {
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2 * vars],
+            values: vec![None; vars],
+            level: vec![0; vars],
+            reason: vec![None; vars],
+            trail: Vec::new(),
+            levels: Vec::new(),
+            head: 0,
+            activity: vec![0.0; vars],
+            bump: 1.0,
+            phase: vec![false; vars],
+            unsatisfiable: false,
+            decisions: 0,
+        }
+    }
+
+    fn value(&self, lit: Lit) -> Option<bool> {
+        self.values[lit.var()].map(|v| v != lit.negated())
+    }
+
+    fn add_clause(&mut self, clause: Vec<Lit>) {
+        self.backtrack(0);
+        if self.unsatisfiable || clause.iter().any(|&l| self.value(l) == Some(true)) {
+            return;
+        }
+        let mut clause: Vec<Lit> = clause
+            .into_iter()
+            .filter(|&l| self.value(l).is_none())
+            .collect();
+        clause.dedup();
+        match clause.len() {
+            0 => self.unsatisfiable = true,
+            1 => {
+                self.assign(clause[0], None);
+                if self.propagate().is_some() {
+                    self.unsatisfiable = true;
+                }
+            }
+            _ => {
+                self.attach(clause);
+            }
+        }
+    }
+
+    fn attach(&mut self, clause: Vec<Lit>) -> usize {
+        let index = self.clauses.len();
+        self.watches[clause[0].0].push(index);
+        self.watches[clause[1].0].push(index);
+        self.clauses.push(clause);
+        index
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var();
+        self.values[var] = Some(!lit.negated());
+        self.level[var] = self.levels.len();
+        self.reason[var] = reason;
+        self.trail.push(lit);
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.levels.len() <= level {
+            return;
+        }
+        let start = self.levels[level];
+        for lit in self.trail.drain(start..) {
+            let var = lit.var();
+            self.phase[var] = !lit.negated();
+            self.values[var] = None;
+            self.reason[var] = None;
+        }
+        self.levels.truncate(level);
+        self.head = self.head.min(start);
+    }
+
+    /// Propagate every assignment on the trail, returning a clause with every literal false
+    /// if there is a conflict
+    fn propagate(&mut self) -> Option<usize> {
+        while self.head < self.trail.len() {
+            let falsified = !self.trail[self.head];
+            self.head += 1;
+            let watching = std::mem::take(&mut self.watches[falsified.0]);
+            let mut kept = Vec::with_capacity(watching.len());
+            let mut conflict = None;
+            for (i, &c) in watching.iter().enumerate() {
+                if conflict.is_some() {
+                    kept.extend_from_slice(&watching[i..]);
+                    break;
+                }
+                let clause = &mut self.clauses[c];
+                if clause[0] == falsified {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                if self.values[first.var()].map(|v| v != first.negated()) == Some(true) {
+                    kept.push(c);
+                    continue;
+                }
+                let replacement = (2..clause.len()).find(|&k| {
+                    let lit = clause[k];
+                    self.values[lit.var()].map(|v| v != lit.negated()) != Some(false)
+                });
+                match replacement {
+                    Some(k) => {
+                        clause.swap(1, k);
+                        let watch = clause[1];
+                        self.watches[watch.0].push(c);
+                    }
+                    None => {
+                        kept.push(c);
+                        if self.value(first) == Some(false) {
+                            conflict = Some(c);
+                        } else {
+                            self.assign(first, Some(c));
+                        }
+                    }
+                }
+            }
+            self.watches[falsified.0] = kept;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    /// Learn the first-UIP clause of a conflict, returning it with the asserting literal
+    /// first and the level to jump back to
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let current = self.levels.len();
+        let mut seen = vec![false; self.values.len()];
+        let mut learnt = vec![Lit(0)];
+        let mut pending = 0;
+        let mut clause = conflict;
+        let mut index = self.trail.len();
+        let uip = loop {
+            // A reason clause starts with the literal it implied, which is resolved away
+            let skip = (clause != conflict) as usize;
+            for k in skip..self.clauses[clause].len() {
+                let lit = self.clauses[clause][k];
+                let var = lit.var();
+                if seen[var] || self.level[var] == 0 {
+                    continue;
+                }
+                seen[var] = true;
+                self.activity[var] += self.bump;
+                if self.level[var] == current {
+                    pending += 1;
+                } else {
+                    learnt.push(lit);
+                }
+            }
+            // The most recent assignment involved in the conflict
+            loop {
+                index -= 1;
+                if seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            seen[lit.var()] = false;
+            pending -= 1;
+            if pending == 0 {
+                break lit;
+            }
+            clause = self.reason[lit.var()].expect("only decisions lack a reason");
+        };
+        learnt[0] = !uip;
+
+        self.bump *= 1.05;
+        if self.bump > 1e100 {
+            for a in &mut self.activity {
+                *a *= 1e-100;
+            }
+            self.bump *= 1e-100;
+        }
+
+        let mut jump = 0;
+        if learnt.len() > 1 {
+            let (k, _) = learnt
+                .iter()
+                .enumerate()
+                .skip(1)
+                .max_by_key(|(_, l)| self.level[l.var()])
+                .expect("learnt clause has a second literal");
+            learnt.swap(1, k);
+            jump = self.level[learnt[1].var()];
+        }
+        (learnt, jump)
+    }
+
+    /// A model of the clauses, or `None` if there is none
+    fn solve(&mut self) -> Option<Vec<bool>> {
+        if self.unsatisfiable {
+            return None;
+        }
+        self.backtrack(0);
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.levels.is_empty() {
+                    self.unsatisfiable = true;
+                    return None;
+                }
+                let (learnt, jump) = self.analyze(conflict);
+                self.backtrack(jump);
+                if learnt.len() == 1 {
+                    self.assign(learnt[0], None);
+                } else {
+                    let asserting = learnt[0];
+                    let c = self.attach(learnt);
+                    self.assign(asserting, Some(c));
+                }
+                continue;
+            }
+
+            let next = (0..self.values.len())
+                .filter(|&v| self.values[v].is_none())
+                .max_by(|&a, &b| self.activity[a].total_cmp(&self.activity[b]));
+            let Some(var) = next else {
+                return Some(self.values.iter().map(|v| v.unwrap()).collect());
+            };
+            self.decisions += 1;
+            self.levels.push(self.trail.len());
+            self.assign(Lit::new(var, self.phase[var]), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Difficulty;
+    use crate::generator::uniqueness;
+    use crate::generator::{Generator, LoopGenerator, MultiLoopGenerator};
+    use googletest::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[googletest::test]
+    fn counts_match_the_backtracking_search() {
+        let mut rng = StdRng::seed_from_u64(9);
+        for radius in [1, 2, 3] {
+            let mut map = LoopGenerator.generate(radius, Difficulty::Hard, &mut rng);
+            expect_that!(count_solutions(&map.view(), 2), eq(1));
+            let coords: Vec<Coord> = map.iter_coords().collect();
+            for coord in coords.iter().step_by(2) {
+                map.cells.get_mut(coord).unwrap().clue = Clue::Hidden;
+            }
+            expect_that!(
+                count_solutions(&map.view(), 50),
+                eq(uniqueness::count_solutions(&map.view(), 50))
+            );
+        }
+    }
+
+    #[googletest::test]
+    fn multi_loop_puzzles_stay_unique() {
+        let map = MultiLoopGenerator { loops: 2 }.generate(
+            3,
+            Difficulty::Hard,
+            &mut StdRng::seed_from_u64(4),
+        );
+        expect_that!(is_unique(&map), eq(true));
+    }
+
+    #[googletest::test]
+    fn relaxing_clues_matches_the_backtracking_search() {
+        let map = LoopGenerator.generate(3, Difficulty::Medium, &mut StdRng::seed_from_u64(2));
+        let visible: Vec<Coord> = map
+            .iter_coords()
+            .filter(|c| map.cells[c].clue_visible())
+            .collect();
+        for coord in visible {
+            expect_that!(
+                remains_unique_relaxing(&map, &[coord], Clue::Hidden),
+                eq(uniqueness::remains_unique_without(&map, &[coord]))
+            );
+        }
+    }
+}
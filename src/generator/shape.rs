@@ -0,0 +1,358 @@
+use std::str::FromStr;
+
+use rand::{Rng, RngCore};
+
+use crate::model::{Coord, DIRECTIONS, Map, RegionBitmap};
+
+use super::Generator;
+use super::region_growth::is_simple;
+
+/// Overall outline the Inside region is grown towards
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    /// Compact and roughly round
+    Blob,
+    /// A blob with a round bite taken out of one side
+    Crescent,
+    /// A long path one cell wide
+    Snake,
+}
+
+impl Shape {
+    /// Range of the fraction of the board the region aims to cover. A snake can't stay
+    /// one cell wide over half the board, so it aims lower.
+    fn fill_range(self) -> std::ops::Range<f64> {
+        match self {
+            Shape::Blob => 0.4..0.6,
+            Shape::Crescent => 0.35..0.5,
+            Shape::Snake => 0.2..0.35,
+        }
+    }
+}
+
+impl std::fmt::Display for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Shape::Blob => f.write_str("blob"),
+            Shape::Crescent => f.write_str("crescent"),
+            Shape::Snake => f.write_str("snake"),
+        }
+    }
+}
+
+impl FromStr for Shape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blob" => Ok(Shape::Blob),
+            "crescent" => Ok(Shape::Crescent),
+            "snake" => Ok(Shape::Snake),
+            _ => Err(format!("Invalid shape: {}", s)),
+        }
+    }
+}
+
+/// Relative weights for picking the shape of each puzzle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapeWeights {
+    pub blob: u32,
+    pub crescent: u32,
+    pub snake: u32,
+}
+
+impl Default for ShapeWeights {
+    /// The mix used by `--shape random`: half blobs, the rest split between the other shapes
+    fn default() -> Self {
+        Self {
+            blob: 2,
+            crescent: 1,
+            snake: 1,
+        }
+    }
+}
+
+impl ShapeWeights {
+    fn entries(&self) -> [(Shape, u32); 3] {
+        [
+            (Shape::Blob, self.blob),
+            (Shape::Crescent, self.crescent),
+            (Shape::Snake, self.snake),
+        ]
+    }
+
+    fn weight_mut(&mut self, shape: Shape) -> &mut u32 {
+        match shape {
+            Shape::Blob => &mut self.blob,
+            Shape::Crescent => &mut self.crescent,
+            Shape::Snake => &mut self.snake,
+        }
+    }
+
+    /// Pick a shape with probability proportional to its weight
+    pub fn choose<R: Rng + ?Sized>(&self, rng: &mut R) -> Shape {
+        let total: u32 = self.entries().iter().map(|&(_, w)| w).sum();
+        let mut pick = rng.random_range(0..total);
+        for (shape, weight) in self.entries() {
+            if pick < weight {
+                return shape;
+            }
+            pick -= weight;
+        }
+        unreachable!("pick is below the total weight")
+    }
+}
+
+impl std::fmt::Display for ShapeWeights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries: Vec<String> = self
+            .entries()
+            .iter()
+            .filter(|&&(_, weight)| weight > 0)
+            .map(|(shape, weight)| format!("{}:{}", shape, weight))
+            .collect();
+        f.write_str(&entries.join(","))
+    }
+}
+
+impl FromStr for ShapeWeights {
+    type Err = String;
+
+    /// "random" for the default mix, or a comma-separated list of shapes with optional
+    /// weights, e.g. "snake" or "blob:3,crescent:1"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "random" {
+            return Ok(Self::default());
+        }
+        let mut weights = Self {
+            blob: 0,
+            crescent: 0,
+            snake: 0,
+        };
+        for entry in s.split(',') {
+            let (name, weight) = match entry.split_once(':') {
+                Some((name, weight)) => (
+                    name,
+                    weight
+                        .parse()
+                        .map_err(|_| format!("Invalid shape weight: {}", entry))?,
+                ),
+                None => (entry, 1),
+            };
+            let slot = weights.weight_mut(name.parse()?);
+            if *slot > 0 {
+                return Err(format!("Shape listed twice: {}", name));
+            }
+            *slot = weight;
+        }
+        if weights.entries().iter().all(|&(_, weight)| weight == 0) {
+            return Err(format!("Invalid shape weights: {} (all zero)", s));
+        }
+        Ok(weights)
+    }
+}
+
+/// Grows a single hole-free Inside region towards a shape archetype picked per puzzle
+pub struct ShapeGenerator {
+    pub shapes: ShapeWeights,
+}
+
+impl Generator for ShapeGenerator {
+    fn generate_solution(&self, radius: u8, rng: &mut dyn RngCore) -> Map {
+        let shape = self.shapes.choose(rng);
+        let coords: Vec<Coord> = Map::new(radius).iter_coords().collect();
+        let fill = rng.random_range(shape.fill_range());
+        let target = ((coords.len() as f64 * fill).round() as usize).max(1);
+
+        let inside = grow_shape(radius, shape, target, rng);
+        inside.to_map(Map::new(radius))
+    }
+}
+
+/// Grow a hole-free Inside region of up to `target` cells, one cell at a time, choosing each
+/// cell by the heuristic of `shape`:
+///
+/// - Blob adds the frontier cell with the most Inside neighbors, filling in dents first.
+/// - Crescent grows like a blob but avoids a disc placed next to the start, leaving a bite.
+/// - Snake extends from its head into cells touching nothing else Inside, so it stays one cell
+///   wide. When the head is boxed in, it restarts from the thinnest cell anywhere on the region.
+fn grow_shape<R: Rng + ?Sized>(
+    radius: u8,
+    shape: Shape,
+    target: usize,
+    rng: &mut R,
+) -> RegionBitmap {
+    let coords: Vec<Coord> = Map::new(radius).iter_coords().collect();
+    let start = coords[rng.random_range(0..coords.len())];
+
+    // Radius of a round blob of `target` cells, which has 3R(R + 1) + 1 of them
+    let blob_radius = ((target as f64 / 3.0).sqrt().round() as i16).max(1);
+    let (dq, dr) = DIRECTIONS[rng.random_range(0..DIRECTIONS.len())];
+    let bite = (
+        start.q as i16 + dq as i16 * blob_radius,
+        start.r as i16 + dr as i16 * blob_radius,
+    );
+    let bitten = |c: Coord| shape == Shape::Crescent && distance(c, bite) < blob_radius;
+
+    let mut inside = RegionBitmap::new(radius);
+    inside.set(start, true);
+    let mut size = 1;
+    let mut head = start;
+    while size < target {
+        let candidates: Vec<Coord> = coords
+            .iter()
+            .copied()
+            .filter(|&c| !inside.get(c) && is_simple(&inside, c))
+            .collect();
+        if candidates.is_empty() {
+            break;
+        }
+        let inside_neighbors = |c: Coord| c.neighbors().iter().filter(|&&n| inside.get(n)).count();
+
+        let pool: Vec<(Coord, usize)> = match shape {
+            Shape::Blob | Shape::Crescent => {
+                let unbitten: Vec<Coord> =
+                    candidates.iter().copied().filter(|&c| !bitten(c)).collect();
+                let pool = if unbitten.is_empty() {
+                    candidates
+                } else {
+                    unbitten
+                };
+                pool.into_iter().map(|c| (c, inside_neighbors(c))).collect()
+            }
+            Shape::Snake => {
+                let ahead: Vec<Coord> = candidates
+                    .iter()
+                    .copied()
+                    .filter(|&c| head.neighbors().contains(&c) && inside_neighbors(c) == 1)
+                    .collect();
+                if ahead.is_empty() {
+                    // Fewest Inside neighbors scores highest
+                    candidates
+                        .into_iter()
+                        .map(|c| (c, 6 - inside_neighbors(c)))
+                        .collect()
+                } else {
+                    ahead.into_iter().map(|c| (c, 0)).collect()
+                }
+            }
+        };
+
+        let best = pool.iter().map(|&(_, score)| score).max().unwrap_or(0);
+        let best: Vec<Coord> = pool
+            .into_iter()
+            .filter(|&(_, score)| score == best)
+            .map(|(c, _)| c)
+            .collect();
+        let cell = best[rng.random_range(0..best.len())];
+        inside.set(cell, true);
+        size += 1;
+        head = cell;
+    }
+
+    inside
+}
+
+/// Number of steps between `coord` and the axial position `(q, r)`, which may be off the board
+fn distance(coord: Coord, (q, r): (i16, i16)) -> i16 {
+    let (dq, dr) = (coord.q as i16 - q, coord.r as i16 - r);
+    (dq.abs() + dr.abs() + (dq + dr).abs()) / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Difficulty;
+    use crate::generator::testing::component_count;
+    use crate::model::Region;
+    use googletest::prelude::*;
+
+    /// Average number of Inside neighbors of an Inside cell
+    fn thickness(map: &Map) -> f64 {
+        let inside: Vec<Coord> = map
+            .iter_coords()
+            .filter(|c| map.cells[c].region == Region::Inside)
+            .collect();
+        let touching: usize = inside
+            .iter()
+            .map(|&c| {
+                c.neighbors()
+                    .iter()
+                    .filter(|n| map.cells.get(n).is_some_and(|n| n.region == Region::Inside))
+                    .count()
+            })
+            .sum();
+        touching as f64 / inside.len() as f64
+    }
+
+    #[googletest::test]
+    fn every_shape_forms_a_single_loop() {
+        for shape in ["blob", "crescent", "snake"] {
+            let generator = ShapeGenerator {
+                shapes: shape.parse().unwrap(),
+            };
+            for radius in [1, 3, 6] {
+                let map = generator.generate(radius, Difficulty::Medium, &mut rand::rng());
+                expect_that!(component_count(&map, Region::Inside), eq(1));
+                expect_that!(component_count(&map, Region::Outside), eq(1));
+            }
+        }
+    }
+
+    #[googletest::test]
+    fn snakes_are_thinner_than_blobs() {
+        let grow = |shape: &str| {
+            ShapeGenerator {
+                shapes: shape.parse().unwrap(),
+            }
+            .generate_solution(6, &mut rand::rng())
+        };
+        expect_that!(thickness(&grow("snake")), lt(thickness(&grow("blob"))));
+        expect_that!(thickness(&grow("snake")), lt(3.0));
+    }
+
+    #[googletest::test]
+    fn parses_names_and_weighted_lists() {
+        expect_that!(
+            "snake".parse::<ShapeWeights>(),
+            ok(eq(&ShapeWeights {
+                blob: 0,
+                crescent: 0,
+                snake: 1
+            }))
+        );
+        expect_that!(
+            "blob:3,crescent:1".parse::<ShapeWeights>(),
+            ok(eq(&ShapeWeights {
+                blob: 3,
+                crescent: 1,
+                snake: 0
+            }))
+        );
+        expect_that!(
+            "random".parse::<ShapeWeights>(),
+            ok(eq(&ShapeWeights::default()))
+        );
+        expect_that!(
+            "ring".parse::<ShapeWeights>(),
+            err(contains_substring("Invalid shape"))
+        );
+        expect_that!(
+            "blob,blob".parse::<ShapeWeights>(),
+            err(contains_substring("twice"))
+        );
+        expect_that!(
+            "snake:0".parse::<ShapeWeights>(),
+            err(contains_substring("all zero"))
+        );
+    }
+
+    #[googletest::test]
+    fn zero_weight_shapes_are_never_chosen() {
+        let weights: ShapeWeights = "crescent:1,snake:0".parse().unwrap();
+        let mut rng = rand::rng();
+        for _ in 0..50 {
+            expect_that!(weights.choose(&mut rng), eq(Shape::Crescent));
+        }
+    }
+}
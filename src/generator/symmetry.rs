@@ -0,0 +1,99 @@
+use std::str::FromStr;
+
+use crate::model::Coord;
+
+/// Symmetry of the hexagon that generated regions and clue patterns must respect
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Symmetry {
+    #[default]
+    None,
+    /// 2-fold: unchanged by a 180° turn about the center hex
+    Rotational2,
+    /// 3-fold: unchanged by a 120° turn about the center hex
+    Rotational3,
+    /// Unchanged by reflection across the q = r axis
+    Mirror,
+}
+
+impl Symmetry {
+    /// All cells that `coord` is mapped to by the symmetry group, starting with `coord` itself
+    pub fn orbit(self, coord: Coord) -> Vec<Coord> {
+        let mut orbit = vec![coord];
+        let mut next = self.apply(coord);
+        while next != coord {
+            orbit.push(next);
+            next = self.apply(next);
+        }
+        orbit
+    }
+
+    /// Apply the generating transform of the group once
+    fn apply(self, c: Coord) -> Coord {
+        let s = -c.q - c.r;
+        match self {
+            Symmetry::None => c,
+            Symmetry::Rotational2 => Coord::new(-c.q, -c.r),
+            Symmetry::Rotational3 => Coord::new(s, c.q),
+            Symmetry::Mirror => Coord::new(c.r, c.q),
+        }
+    }
+}
+
+impl std::fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Symmetry::None => f.write_str("none"),
+            Symmetry::Rotational2 => f.write_str("rotational"),
+            Symmetry::Rotational3 => f.write_str("rotational3"),
+            Symmetry::Mirror => f.write_str("mirror"),
+        }
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Symmetry::None),
+            "rotational" => Ok(Symmetry::Rotational2),
+            "rotational2" => Ok(Symmetry::Rotational2),
+            "rotational3" => Ok(Symmetry::Rotational3),
+            "mirror" => Ok(Symmetry::Mirror),
+            _ => Err(format!("Invalid symmetry: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::prelude::*;
+
+    #[googletest::test]
+    fn orbit_sizes_match_group_order() {
+        let c = Coord::new(2, -1);
+        expect_that!(Symmetry::None.orbit(c), elements_are![eq(&c)]);
+        expect_that!(Symmetry::Rotational2.orbit(c), len(eq(2)));
+        expect_that!(Symmetry::Rotational3.orbit(c), len(eq(3)));
+        expect_that!(Symmetry::Mirror.orbit(c), len(eq(2)));
+    }
+
+    #[googletest::test]
+    fn center_is_fixed_by_every_symmetry() {
+        let center = Coord::new(0, 0);
+        for symmetry in [
+            Symmetry::Rotational2,
+            Symmetry::Rotational3,
+            Symmetry::Mirror,
+        ] {
+            expect_that!(symmetry.orbit(center), elements_are![eq(&center)]);
+        }
+    }
+
+    #[googletest::test]
+    fn cells_on_mirror_axis_are_fixed() {
+        let c = Coord::new(1, 1);
+        expect_that!(Symmetry::Mirror.orbit(c), elements_are![eq(&c)]);
+    }
+}
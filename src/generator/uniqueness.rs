@@ -0,0 +1,825 @@
+//! Solution counting for uniqueness checks during clue removal.
+//!
+//! The search reads the [`PuzzleView`] of a map, never the answer key stored with it; only
+//! the checks for hiding clues look at the answer, to try it first.
+//! A candidate solution assigns every cell Inside or Outside such that each visible clue
+//! matches the number of neighbors in the other region (off-board counts as Outside),
+//! the Inside cells are connected, and every Outside cell reaches the board edge.
+//! On a hex grid those conditions are exactly "the boundary is a single loop".
+//! Sheep and wolf markers fix the region of their cell outright.
+//!
+//! Clues are handled as the set of loop side counts they allow: a single number for an
+//! exact clue, every even or every odd number for a parity clue.
+//! In the multi-loop variant the Inside cells instead form exactly as many connected groups
+//! as the map has loops, each bounded by its own loop (loops are never nested).
+//!
+//! The search state is a union-find over cells (plus the board exterior) where every link
+//! records whether the two ends are in the same region or not. A side of a cell is on the
+//! loop exactly when its two cells are in different regions, so this tracks loop edges even
+//! before any region is known, and a cell's region is known once it is linked to the exterior.
+//!
+//! Propagation is incremental: the state remembers which cells changed relations since the
+//! last pass, so after a branch or a changed clue only the clues next to those cells are
+//! looked at again rather than the whole board.
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use crate::model::{Cell, Coord, KnownRegion, Map, PARITY_COUNTS, PuzzleView, Region, RimEdges};
+
+/// Which solver checks that puzzles stay unique while clues are hidden
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SolverBackend {
+    /// The backtracking search of this module
+    #[default]
+    Backtracking,
+    /// A SAT solver, which copes better with very large boards
+    #[cfg(feature = "sat")]
+    Sat,
+}
+
+impl SolverBackend {
+    /// Every backend this build includes
+    pub fn all() -> &'static [SolverBackend] {
+        &[
+            SolverBackend::Backtracking,
+            #[cfg(feature = "sat")]
+            SolverBackend::Sat,
+        ]
+    }
+}
+
+impl std::fmt::Display for SolverBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolverBackend::Backtracking => f.write_str("backtracking"),
+            #[cfg(feature = "sat")]
+            SolverBackend::Sat => f.write_str("sat"),
+        }
+    }
+}
+
+impl FromStr for SolverBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "backtracking" => Ok(SolverBackend::Backtracking),
+            #[cfg(feature = "sat")]
+            "sat" => Ok(SolverBackend::Sat),
+            #[cfg(not(feature = "sat"))]
+            "sat" => Err("The sat solver needs slithergen built with --features sat".to_string()),
+            _ => Err(format!("Invalid solver: {}", s)),
+        }
+    }
+}
+
+/// Whether the visible clues of `map` admit exactly one solution
+pub(crate) fn is_unique(map: &Map) -> bool {
+    count_solutions(&map.view(), 2) == 1
+}
+
+/// Search nodes allowed per alternative clue value in [`remains_unique_without`]
+const REMOVAL_BUDGET: usize = 20_000;
+
+/// Bit set of every possible number of loop sides, 0 to 6
+const ANY_COUNT: u8 = 0x7f;
+
+/// Whether a puzzle that is currently unique stays unique when the clues at `coords` are hidden.
+///
+/// Any new solution would have to break at least one of those clues (otherwise it would have
+/// been a solution all along), so rather than counting to two this looks for a solution where
+/// one of the clues takes a different value, which fails or succeeds much faster.
+///
+/// Each of those searches is capped at [`REMOVAL_BUDGET`] nodes; if the cap is hit the answer
+/// is a conservative `false`, so the clue stays visible and the puzzle stays unique.
+pub(crate) fn remains_unique_without(map: &Map, coords: &[Coord]) -> bool {
+    remains_unique_relaxing(map, coords, false, |_| None)
+}
+
+/// Like [`remains_unique_without`], but the clues at `coords` only show their parity
+pub(crate) fn remains_unique_as_parity(map: &Map, coords: &[Coord]) -> bool {
+    remains_unique_relaxing(map, coords, false, |cell| {
+        Some(PARITY_COUNTS[(cell.full_neighbor_count % 2) as usize])
+    })
+}
+
+/// Like [`remains_unique_without`], but the clues at `coords` show a marker instead
+pub(crate) fn remains_unique_as_markers(map: &Map, coords: &[Coord]) -> bool {
+    remains_unique_relaxing(map, coords, true, |_| None)
+}
+
+/// Whether the puzzle stays unique when each clue at `coords` is relaxed to allow the counts
+/// given by `relaxed` (`None` hides it), with `marked` fixing the regions of those cells. A new
+/// solution would have to pick one of the newly allowed counts somewhere, so each of those is
+/// tried in turn.
+fn remains_unique_relaxing(
+    map: &Map,
+    coords: &[Coord],
+    marked: bool,
+    relaxed: impl Fn(&Cell) -> Option<u8>,
+) -> bool {
+    let mut puzzle = Puzzle::new(&map.view());
+    let indices: Vec<usize> = coords.iter().map(|c| puzzle.index[c]).collect();
+    if marked {
+        for (&i, coord) in indices.iter().zip(coords) {
+            puzzle.markers[i] = Some(map.cells[coord].region == Region::Inside);
+        }
+    }
+    puzzle.guide_towards(map, coords);
+    let originals: Vec<Option<u8>> = indices.iter().map(|&i| puzzle.clues[i]).collect();
+    let relaxed: Vec<Option<u8>> = coords.iter().map(|c| relaxed(&map.cells[c])).collect();
+    for (&i, &relaxed) in indices.iter().zip(&relaxed) {
+        puzzle.clues[i] = relaxed;
+    }
+
+    // Everything the relaxed clues imply holds for every alternative too, so it is worked out
+    // once and each search only has to follow up on the clue it changes
+    let mut base = State::new(&puzzle);
+    if propagate(&puzzle, &mut base).is_err() {
+        // Not even the relaxed clues can be met, so there is no other solution to find
+        return true;
+    }
+
+    for ((&i, original), relaxed) in indices.iter().zip(originals).zip(relaxed) {
+        let Some(original) = original else { continue };
+        let allowed = relaxed.unwrap_or(ANY_COUNT);
+        for other in (0..=6).filter(|&v| allowed & !original & (1 << v) != 0) {
+            puzzle.clues[i] = Some(1 << other);
+            let search = Search::new(&puzzle, 1, REMOVAL_BUDGET);
+            if search.run_from(base.clone(), vec![i]) != Some(0) {
+                return false;
+            }
+        }
+        puzzle.clues[i] = relaxed;
+    }
+    true
+}
+
+/// Count solutions of the puzzle, stopping once `limit` are found
+pub(crate) fn count_solutions(view: &PuzzleView, limit: usize) -> usize {
+    count_solutions_and_nodes(view, limit).0
+}
+
+/// Like [`count_solutions`], also returning the number of search nodes visited
+pub(crate) fn count_solutions_and_nodes(view: &PuzzleView, limit: usize) -> (usize, usize) {
+    if limit == 0 {
+        return (0, 0);
+    }
+    let puzzle = Puzzle::new(view);
+    let mut search = Search::new(&puzzle, limit, usize::MAX);
+    search.visit(State::new(&puzzle), puzzle.cells().collect());
+    (search.found, usize::MAX - search.budget)
+}
+
+/// Up to `limit` solutions of the puzzle, each as the set of Inside cells
+pub(crate) fn find_solutions(view: &PuzzleView, limit: usize) -> Vec<HashSet<Coord>> {
+    if limit == 0 {
+        return Vec::new();
+    }
+    let puzzle = Puzzle::new(view);
+    let mut search = Search::new(&puzzle, limit, usize::MAX);
+    search.solutions = Some(Vec::new());
+    search.visit(State::new(&puzzle), puzzle.cells().collect());
+    let coords: Vec<Coord> = view.iter_coords().collect();
+    search
+        .solutions
+        .unwrap_or_default()
+        .into_iter()
+        .map(|inside| {
+            coords
+                .iter()
+                .zip(inside)
+                .filter(|&(_, inside)| inside)
+                .map(|(c, _)| *c)
+                .collect()
+        })
+        .collect()
+}
+
+pub(super) struct Puzzle {
+    /// The cell across each side of every cell, in cyclic order (`exterior` when off the board)
+    pub(super) sides: Vec<[usize; 6]>,
+    /// Like `sides`, for counting towards the cell's clue: sides the clue leaves out, facing off
+    /// the board when its rim edges are excluded, lead back to the cell itself so they are never
+    /// on the loop
+    pub(super) clue_sides: Vec<[usize; 6]>,
+    /// Loop side counts allowed by each cell's visible clue, as a bit set
+    pub(super) clues: Vec<Option<u8>>,
+    /// Region fixed by each cell's marker (`true` is Inside)
+    pub(super) markers: Vec<Option<bool>>,
+    /// Node standing for everything beyond the board edge, which is always Outside
+    pub(super) exterior: usize,
+    /// Cells on the board edge, i.e. next to the exterior
+    pub(super) rim: Vec<usize>,
+    /// Number of loops, i.e. connected groups of Inside cells, in every solution
+    pub(super) loops: usize,
+    pub(super) index: HashMap<Coord, usize>,
+    /// Region tried first when branching on each cell (`true` is Inside)
+    first_guess: Vec<bool>,
+    /// Cells with lower priority are branched on first
+    priority: Vec<u32>,
+}
+
+impl Puzzle {
+    pub(super) fn new(view: &PuzzleView) -> Self {
+        let coords: Vec<Coord> = view.iter_coords().collect();
+        let index: HashMap<Coord, usize> =
+            coords.iter().enumerate().map(|(i, c)| (*c, i)).collect();
+        let exterior = coords.len();
+        let sides: Vec<[usize; 6]> = coords
+            .iter()
+            .map(|&c| {
+                c.neighbors()
+                    .map(|n| index.get(&n).copied().unwrap_or(exterior))
+            })
+            .collect();
+        let rim = (0..exterior)
+            .filter(|&c| sides[c].contains(&exterior))
+            .collect();
+        let clue_sides = match view.rim_edges {
+            RimEdges::Counted => sides.clone(),
+            RimEdges::Excluded => {
+                // Holes are cells of the puzzle, but off the board all the same
+                let off_board = |n: usize| n == exterior || view.holes.contains(&coords[n]);
+                (0..exterior)
+                    .map(|c| sides[c].map(|n| if off_board(n) { c } else { n }))
+                    .collect()
+            }
+        };
+        let clues = coords
+            .iter()
+            .map(|c| {
+                view.cells
+                    .get(c)
+                    .and_then(|cell| cell.clue)
+                    .map(|clue| clue.allowed_counts())
+            })
+            .collect();
+        let markers = coords
+            .iter()
+            .map(|c| {
+                view.cells
+                    .get(c)
+                    .and_then(|cell| cell.marker)
+                    .map(|marker| marker.region() == Region::Inside)
+            })
+            .collect();
+        Self {
+            sides,
+            clue_sides,
+            clues,
+            markers,
+            exterior,
+            rim,
+            loops: view.loop_count as usize,
+            index,
+            first_guess: vec![true; exterior],
+            priority: vec![0; exterior],
+        }
+    }
+
+    /// Have the search try the map's own answer first, starting next to `coords`.
+    /// A different solution usually only differs from the answer close to a changed clue,
+    /// so this finds one quickly when it exists. It only reorders the search, never prunes it.
+    fn guide_towards(&mut self, map: &Map, coords: &[Coord]) {
+        // Holes are cells of the puzzle too, though not of the map; their wolves decide them
+        for (coord, cell) in &map.cells {
+            let i = self.index[coord];
+            self.first_guess[i] = cell.region == Region::Inside;
+            self.priority[i] = coords
+                .iter()
+                .map(|t| distance(*coord, *t))
+                .min()
+                .unwrap_or(0);
+        }
+    }
+
+    pub(super) fn cells(&self) -> std::ops::Range<usize> {
+        0..self.exterior
+    }
+}
+
+/// Union-find where each node stores whether it is in a different region from its parent
+#[derive(Clone)]
+pub(super) struct State {
+    parent: Vec<usize>,
+    differs: Vec<bool>,
+    /// Number of nodes under each root
+    size: Vec<usize>,
+    /// Next node of the same group, linking each group into a cycle so its members can be listed
+    next: Vec<usize>,
+    /// Nodes whose relations to other nodes changed since the last propagation
+    touched: Vec<usize>,
+}
+
+/// Returned when the clues cannot all be satisfied
+pub(super) struct Contradiction;
+
+impl State {
+    /// Nothing known yet beyond the regions of marked cells
+    pub(super) fn new(puzzle: &Puzzle) -> Self {
+        let size = puzzle.exterior + 1;
+        let mut state = Self {
+            parent: (0..size).collect(),
+            differs: vec![false; size],
+            size: vec![1; size],
+            next: (0..size).collect(),
+            touched: Vec::new(),
+        };
+        for (cell, marker) in puzzle.markers.iter().enumerate() {
+            if let Some(inside) = *marker {
+                // Every cell is linked at most once, so this can't contradict anything
+                let _ = state.link(cell, puzzle.exterior, inside);
+            }
+        }
+        state
+    }
+
+    /// Root of `node`, and whether `node` is in a different region from it
+    pub(super) fn find(&mut self, node: usize) -> (usize, bool) {
+        let parent = self.parent[node];
+        if parent == node {
+            return (node, false);
+        }
+        let (root, parent_differs) = self.find(parent);
+        self.parent[node] = root;
+        self.differs[node] ^= parent_differs;
+        (root, self.differs[node])
+    }
+
+    /// Record that `a` and `b` are in different regions (`differ`) or the same one.
+    /// Returns whether this was new information.
+    pub(super) fn link(&mut self, a: usize, b: usize, differ: bool) -> Result<bool, Contradiction> {
+        let (root_a, differs_a) = self.find(a);
+        let (root_b, differs_b) = self.find(b);
+        if root_a == root_b {
+            return if differs_a ^ differs_b == differ {
+                Ok(false)
+            } else {
+                Err(Contradiction)
+            };
+        }
+        // Attach the smaller group, whose members are the ones with new relations to note
+        let (small, large) = if self.size[root_a] <= self.size[root_b] {
+            (root_a, root_b)
+        } else {
+            (root_b, root_a)
+        };
+        let mut member = small;
+        loop {
+            self.touched.push(member);
+            member = self.next[member];
+            if member == small {
+                break;
+            }
+        }
+        self.next.swap(small, large);
+        self.parent[small] = large;
+        self.differs[small] = differs_a ^ differs_b ^ differ;
+        self.size[large] += self.size[small];
+        Ok(true)
+    }
+
+    /// Known region of every cell: `Some(true)` Inside, `Some(false)` Outside
+    pub(super) fn regions(&mut self, puzzle: &Puzzle) -> Vec<Option<bool>> {
+        let (exterior_root, exterior_differs) = self.find(puzzle.exterior);
+        puzzle
+            .cells()
+            .map(|c| {
+                let (root, differs) = self.find(c);
+                (root == exterior_root).then_some(differs ^ exterior_differs)
+            })
+            .collect()
+    }
+
+    /// Known region of every cell, as the player-facing tri-state
+    pub(super) fn known_regions(&mut self, puzzle: &Puzzle) -> Vec<KnownRegion> {
+        self.regions(puzzle)
+            .into_iter()
+            .map(|inside| match inside {
+                Some(true) => KnownRegion::Inside,
+                Some(false) => KnownRegion::Outside,
+                None => KnownRegion::Unknown,
+            })
+            .collect()
+    }
+}
+
+/// A depth-first search for solutions, stopping at `limit` solutions or after `budget` nodes
+struct Search<'a> {
+    puzzle: &'a Puzzle,
+    limit: usize,
+    budget: usize,
+    found: usize,
+    /// Region of every cell (`true` is Inside) in each solution found, if they are kept
+    solutions: Option<Vec<Vec<bool>>>,
+}
+
+impl<'a> Search<'a> {
+    fn new(puzzle: &'a Puzzle, limit: usize, budget: usize) -> Self {
+        Self {
+            puzzle,
+            limit,
+            budget,
+            found: 0,
+            solutions: None,
+        }
+    }
+
+    /// Number of solutions found searching from `start`, which already has every clue
+    /// propagated except those of the cells in `changed`, or `None` if the budget ran out
+    /// before the search finished
+    fn run_from(mut self, start: State, changed: Vec<usize>) -> Option<usize> {
+        self.visit(start, changed);
+        (self.found >= self.limit || self.budget > 0).then_some(self.found)
+    }
+
+    fn done(&self) -> bool {
+        self.found >= self.limit || self.budget == 0
+    }
+
+    fn visit(&mut self, mut state: State, changed: Vec<usize>) {
+        self.budget -= 1;
+        let puzzle = self.puzzle;
+        if propagate_from(puzzle, &mut state, changed).is_err() {
+            return;
+        }
+        let regions = state.regions(puzzle);
+        if !connectivity_possible(puzzle, &regions) {
+            return;
+        }
+
+        let Some(cell) = branch_cell(puzzle, &regions) else {
+            self.found += 1;
+            if let Some(solutions) = &mut self.solutions {
+                solutions.push(regions.into_iter().map(|r| r == Some(true)).collect());
+            }
+            return;
+        };
+
+        let guess = puzzle.first_guess[cell];
+        for inside in [guess, !guess] {
+            if self.done() {
+                return;
+            }
+            let mut next = state.clone();
+            if next.link(cell, puzzle.exterior, inside).is_ok() {
+                self.visit(next, Vec::new());
+            }
+        }
+    }
+}
+
+/// Apply the clue constraints until nothing changes
+pub(super) fn propagate(puzzle: &Puzzle, state: &mut State) -> Result<(), Contradiction> {
+    propagate_from(puzzle, state, puzzle.cells().collect())
+}
+
+/// Apply the clue constraints until nothing changes, given that `state` is already up to date
+/// with every clue except those of the cells in `changed` and next to its touched nodes.
+///
+/// A clue's deductions only depend on how its cell and neighbors relate to each other, and
+/// linking two groups only changes relations between their members, so only the clues at or
+/// next to members of the smaller group need another look.
+fn propagate_from(
+    puzzle: &Puzzle,
+    state: &mut State,
+    mut queue: Vec<usize>,
+) -> Result<(), Contradiction> {
+    let mut queued = vec![false; puzzle.exterior];
+    queue.retain(|&c| !std::mem::replace(&mut queued[c], true));
+    loop {
+        for node in std::mem::take(&mut state.touched) {
+            let around: &[usize] = if node == puzzle.exterior {
+                &puzzle.rim
+            } else {
+                &puzzle.sides[node]
+            };
+            for &cell in around.iter().chain(std::iter::once(&node)) {
+                if cell != puzzle.exterior && !queued[cell] {
+                    queued[cell] = true;
+                    queue.push(cell);
+                }
+            }
+        }
+        let Some(cell) = queue.pop() else {
+            return Ok(());
+        };
+        queued[cell] = false;
+        if let Some(allowed) = puzzle.clues[cell] {
+            apply_clue(puzzle, state, cell, allowed)?;
+        }
+    }
+}
+
+/// Deduce everything a single clue implies about how its cell relates to its neighbors.
+///
+/// Neighbors already linked to each other move together: deciding whether the cell differs
+/// from one of them decides it for the whole group. With at most six groups, every combination
+/// is checked, and a link is recorded wherever all combinations matching the clue agree.
+fn apply_clue(
+    puzzle: &Puzzle,
+    state: &mut State,
+    cell: usize,
+    allowed: u8,
+) -> Result<(), Contradiction> {
+    let (cell_root, cell_differs) = state.find(cell);
+
+    let mut on_loop = 0u8;
+    // (group root, loop sides if the cell matches the root, loop sides if it differs)
+    let mut groups: Vec<(usize, u8, u8)> = Vec::new();
+    for &side in &puzzle.clue_sides[cell] {
+        let (root, differs) = state.find(side);
+        if root == cell_root {
+            on_loop += (differs ^ cell_differs) as u8;
+            continue;
+        }
+        let index = match groups.iter().position(|g| g.0 == root) {
+            Some(i) => i,
+            None => {
+                groups.push((root, 0, 0));
+                groups.len() - 1
+            }
+        };
+        if differs {
+            groups[index].1 += 1;
+        } else {
+            groups[index].2 += 1;
+        }
+    }
+
+    // Bit i of a mask set means the cell differs from the root of group i
+    let mut agree_set = u8::MAX;
+    let mut agree_clear = u8::MAX;
+    let mut any = false;
+    for mask in 0u8..(1 << groups.len()) {
+        let total: u8 = on_loop
+            + groups
+                .iter()
+                .enumerate()
+                .map(|(i, g)| if mask & (1 << i) != 0 { g.2 } else { g.1 })
+                .sum::<u8>();
+        if allowed & (1 << total) != 0 {
+            any = true;
+            agree_set &= mask;
+            agree_clear &= !mask;
+        }
+    }
+    if !any {
+        return Err(Contradiction);
+    }
+
+    for (i, group) in groups.iter().enumerate() {
+        if agree_set & (1 << i) != 0 {
+            state.link(cell, group.0, true)?;
+        } else if agree_clear & (1 << i) != 0 {
+            state.link(cell, group.0, false)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether the Inside cells can still be joined up into the puzzle's number of loops, and every
+/// Outside cell can still reach the board edge, through cells not yet known to be in the
+/// other region
+pub(super) fn connectivity_possible(puzzle: &Puzzle, regions: &[Option<bool>]) -> bool {
+    let reach = |starts: Vec<usize>, blocked: bool| {
+        let mut seen = vec![false; regions.len()];
+        for &s in &starts {
+            seen[s] = true;
+        }
+        let mut stack = starts;
+        while let Some(cell) = stack.pop() {
+            for &n in &puzzle.sides[cell] {
+                if n != puzzle.exterior && !seen[n] && regions[n] != Some(blocked) {
+                    seen[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+        seen
+    };
+
+    let rim: Vec<usize> = puzzle
+        .cells()
+        .filter(|&c| puzzle.sides[c].contains(&puzzle.exterior) && regions[c] != Some(true))
+        .collect();
+    let outside_reach = reach(rim, true);
+    if puzzle
+        .cells()
+        .any(|c| regions[c] == Some(false) && !outside_reach[c])
+    {
+        return false;
+    }
+
+    // Inside cells that can't reach each other end up bounded by different loops
+    let mut inside_reach = vec![false; regions.len()];
+    let mut groups = 0;
+    for cell in puzzle.cells() {
+        if regions[cell] == Some(true) && !inside_reach[cell] {
+            groups += 1;
+            if groups > puzzle.loops {
+                return false;
+            }
+            for (seen, reached) in inside_reach.iter_mut().zip(reach(vec![cell], false)) {
+                *seen |= reached;
+            }
+        }
+    }
+    // Undecided cells may still form the missing loops
+    groups == puzzle.loops || regions.iter().any(Option::is_none)
+}
+
+/// The undecided cell to branch on next: lowest priority first, then the one with the most
+/// decided neighbors. `None` once every cell is decided.
+fn branch_cell(puzzle: &Puzzle, regions: &[Option<bool>]) -> Option<usize> {
+    puzzle
+        .cells()
+        .filter(|&c| regions[c].is_none())
+        .min_by_key(|&c| {
+            let decided = puzzle.sides[c]
+                .iter()
+                .filter(|&&n| n == puzzle.exterior || regions[n].is_some())
+                .count();
+            (puzzle.priority[c], std::cmp::Reverse(decided))
+        })
+}
+
+/// Hex distance between two cells
+fn distance(a: Coord, b: Coord) -> u32 {
+    let dq = (a.q as i32 - b.q as i32).abs();
+    let dr = (a.r as i32 - b.r as i32).abs();
+    let ds = (a.q as i32 + a.r as i32 - b.q as i32 - b.r as i32).abs();
+    dq.max(dr).max(ds) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Difficulty;
+    use crate::generator::testing::component_count;
+    use crate::generator::{Generator, LoopGenerator};
+    use crate::model::{Cell, Clue};
+    use googletest::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    /// A map whose only Inside cell is the center, with every clue visible
+    fn single_hex_map(radius: u8) -> Map {
+        let mut map = Map::new(radius);
+        let coords: Vec<Coord> = map.iter_coords().collect();
+        for coord in coords {
+            let cell = if coord == Coord::new(0, 0) {
+                Cell::new(Region::Inside, 6, true)
+            } else if Coord::new(0, 0).neighbors().contains(&coord) {
+                Cell::new(Region::Outside, 1, true)
+            } else {
+                Cell::new(Region::Outside, 0, true)
+            };
+            map.cells.insert(coord, cell);
+        }
+        map
+    }
+
+    #[googletest::test]
+    fn fully_clued_map_is_unique() {
+        expect_that!(is_unique(&single_hex_map(2)), eq(true));
+    }
+
+    #[googletest::test]
+    fn clueless_map_has_many_solutions() {
+        let mut map = single_hex_map(2);
+        for cell in map.cells.values_mut() {
+            cell.clue = Clue::Hidden;
+        }
+        expect_that!(count_solutions(&map.view(), 5), eq(5));
+    }
+
+    #[googletest::test]
+    fn single_cell_board_needs_no_clues() {
+        // The only loop on a radius 0 board goes around the center hex
+        let mut map = single_hex_map(0);
+        map.cells.get_mut(&Coord::new(0, 0)).unwrap().clue = Clue::Hidden;
+        expect_that!(count_solutions(&map.view(), 2), eq(1));
+    }
+
+    #[googletest::test]
+    fn contradictory_clues_have_no_solution() {
+        let mut map = single_hex_map(1);
+        map.cells
+            .get_mut(&Coord::new(0, 0))
+            .unwrap()
+            .full_neighbor_count = 5;
+        expect_that!(count_solutions(&map.view(), 2), eq(0));
+    }
+
+    #[googletest::test]
+    fn counts_every_loop_on_a_tiny_board() {
+        // With no clues, every non-empty hole-free connected Inside region is a solution
+        let mut map = single_hex_map(1);
+        for cell in map.cells.values_mut() {
+            cell.clue = Clue::Hidden;
+        }
+        expect_that!(
+            count_solutions(&map.view(), usize::MAX),
+            eq(brute_force_count(&map))
+        );
+    }
+
+    #[googletest::test]
+    fn incremental_propagation_matches_a_full_pass() {
+        let map = LoopGenerator.generate(5, Difficulty::Hard, &mut StdRng::seed_from_u64(2));
+        let mut puzzle = Puzzle::new(&map.view());
+        let mut full = State::new(&puzzle);
+        let full_result = propagate(&puzzle, &mut full).is_ok();
+
+        let clued: Vec<usize> = puzzle
+            .cells()
+            .filter(|&c| puzzle.clues[c].is_some())
+            .collect();
+        for cell in clued.into_iter().step_by(3) {
+            let clue = puzzle.clues[cell].take();
+            let mut incremental = State::new(&puzzle);
+            let _ = propagate(&puzzle, &mut incremental);
+            puzzle.clues[cell] = clue;
+            let incremental_result = propagate_from(&puzzle, &mut incremental, vec![cell]).is_ok();
+
+            expect_that!(incremental_result, eq(full_result));
+            expect_that!(incremental.regions(&puzzle), eq(&full.regions(&puzzle)));
+        }
+    }
+
+    #[googletest::test]
+    fn counts_every_pair_of_loops_on_a_tiny_board() {
+        let mut map = single_hex_map(1);
+        for cell in map.cells.values_mut() {
+            cell.clue = Clue::Hidden;
+        }
+        map.loop_count = 2;
+        let solutions = count_solutions(&map.view(), usize::MAX);
+        expect_that!(solutions, gt(0));
+        expect_that!(solutions, eq(brute_force_count(&map)));
+    }
+
+    #[googletest::test]
+    fn loop_count_is_part_of_the_puzzle() {
+        // Two opposite rim cells: as two loops the clues are met exactly one way, but no
+        // single loop can meet them
+        let mut map = single_hex_map(1);
+        for cell in map.cells.values_mut() {
+            cell.region = Region::Outside.into();
+        }
+        for coord in [Coord::new(1, 0), Coord::new(-1, 0)] {
+            map.cells.get_mut(&coord).unwrap().region = Region::Inside.into();
+        }
+        map.recompute_clues();
+        expect_that!(count_solutions(&map.view(), 2), eq(0));
+        map.loop_count = 2;
+        expect_that!(count_solutions(&map.view(), 2), eq(1));
+    }
+
+    #[googletest::test]
+    fn parity_clues_allow_more_solutions() {
+        let mut map = single_hex_map(1);
+        expect_that!(count_solutions(&map.view(), usize::MAX), eq(1));
+        for cell in map.cells.values_mut() {
+            cell.clue = Clue::Parity;
+        }
+        let solutions = count_solutions(&map.view(), usize::MAX);
+        expect_that!(solutions, gt(1));
+        expect_that!(solutions, eq(brute_force_count(&map)));
+        expect_that!(
+            remains_unique_as_parity(&single_hex_map(1), &[Coord::new(0, 0)]),
+            eq(true)
+        );
+    }
+
+    /// Count Inside regions with `map.loop_count` loops that meet the visible clues, by trying
+    /// every assignment
+    fn brute_force_count(map: &Map) -> usize {
+        let coords: Vec<Coord> = map.iter_coords().collect();
+        (1u32..(1 << coords.len()))
+            .filter(|mask| {
+                let mut test = map.clone();
+                for (i, c) in coords.iter().enumerate() {
+                    let region = if mask & (1 << i) != 0 {
+                        Region::Inside
+                    } else {
+                        Region::Outside
+                    };
+                    test.cells.get_mut(c).unwrap().region = region.into();
+                }
+                test.recompute_clues();
+                component_count(&test, Region::Inside) == map.loop_count as usize
+                    && component_count(&test, Region::Outside) == 1
+                    && test.cells.iter().all(|(c, cell)| {
+                        let clue = &map.cells[c];
+                        clue.visible_clue().is_none_or(|clue| {
+                            clue.allowed_counts() & (1 << cell.full_neighbor_count) != 0
+                        })
+                    })
+            })
+            .count()
+    }
+}
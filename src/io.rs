@@ -1,102 +1,1596 @@
-use crate::model::{Cell, Coord, Map, Region};
+use crate::error::SlitherError;
+use crate::generator::progress::AttemptStats;
+use crate::generator::rating::Deduction;
+use crate::model::{
+    Cell, Clue, Coord, KnownRegion, MAX_RADIUS, Map, MapMeta, Marker, PlayerState, Region,
+    RegionMask, RimEdges, VisibleClue, cell_count, check_board, check_radius,
+};
+use crate::solver::Solution;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs::File;
-use std::io::{self, Read, Write};
-use std::path::Path;
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
 
-/// Save a map to a binary file
-/// Format: [Flags: u8] [Radius: u8] [HexBytes...]
+/// Flags bit 0: clue removal timed out (see `Map::timed_out`)
+const FLAG_TIMED_OUT: u8 = 0x1;
+/// Flags bit 1: the level byte holds a difficulty level (see `Map::level`). Version 1 only has
+/// the byte, after the radius, if this is set.
+const FLAG_LEVEL: u8 = 0x2;
+/// Flags bit 2: a metadata block follows the hexagon bytes (see `Map::meta`)
+const FLAG_META: u8 = 0x4;
+/// Flags bit 3: clues leave out the sides facing off the board (see `Map::rim_edges`)
+const FLAG_RIM_EXCLUDED: u8 = 0x8;
+/// Flags bit 4: the board has holes (see `Map::holes`)
+const FLAG_HOLES: u8 = 0x10;
+/// Flags bit 5: the hexagons are packed two to a byte, without their counts (see
+/// `MapEncoding::Compressed`)
+const FLAG_COMPRESSED: u8 = 0x20;
+/// Flags bit 6: the map has no answer, so regions are unknown except behind markers (see
+/// `Map::without_answer`)
+const FLAG_NO_ANSWER: u8 = 0x40;
+/// Flags bits a reader of this version understands; bit 7 is reserved
+const KNOWN_FLAGS: u8 = FLAG_TIMED_OUT
+    | FLAG_LEVEL
+    | FLAG_META
+    | FLAG_RIM_EXCLUDED
+    | FLAG_HOLES
+    | FLAG_COMPRESSED
+    | FLAG_NO_ANSWER;
+/// Version 1 flags bits 4-7: number of loops in the multi-loop variant, 0 for a single loop.
+/// Version 2 gives the loop count a byte of its own.
+const V1_LOOP_COUNT_SHIFT: u8 = 4;
+/// Clue types bit 0: some hexagon shows a marker (see `Clue::Marker`)
+const CLUE_MARKERS: u8 = 0x1;
+/// Clue types bit 1: some hexagon shows only a parity (see `Clue::Parity`)
+const CLUE_PARITY: u8 = 0x2;
+/// Clue types bits a reader of this version understands; bits 2-7 are reserved
+const KNOWN_CLUE_TYPES: u8 = CLUE_MARKERS | CLUE_PARITY;
+/// First bytes of a versioned map file, so loading needn't guess the format from its length
+const MAGIC: &[u8; 4] = b"SLTH";
+/// Format version written by `save_map`: version 2 with a checksum at the end. Files without
+/// the magic number are version 1, or legacy if they have no flags byte.
+const VERSION: u8 = 3;
+/// Version without the checksum, still read
+const VERSION_UNCHECKED: u8 = 2;
+/// First bytes of a puzzle pack (see `save_pack`)
+const PACK_MAGIC: &[u8; 4] = b"SLPK";
+const PACK_VERSION: u8 = 1;
+/// Hexagon byte of a hole cut out of an irregular board (see `Map::holes`)
+const HOLE: u8 = 0x80;
+/// Version of the save files written by `save_progress`
+pub const SAVE_VERSION: u32 = 1;
+/// The specification, whose format sections `format_spec` prints
+const SPEC: &str = include_str!("../slitherlink_specs.md");
+
+/// How `save_map` lays out a binary map
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MapEncoding {
+    /// Just the radius and the hexagons, for older readers
+    Legacy,
+    #[default]
+    Standard,
+    /// Half a byte per hexagon: the counts are left out, and worked out from the answer again
+    /// on loading. Only maps whose clues match their answer can be saved this way.
+    Compressed,
+}
+
+/// The section of the specification laying out the binary map format `save_map` writes, or
+/// with `pack` the pack format, from its heading to the next section
+pub fn format_spec(pack: bool) -> &'static str {
+    let heading = if pack {
+        "## Puzzle Pack Format\n"
+    } else {
+        "## Map Binary Format\n"
+    };
+    let start = SPEC
+        .find(heading)
+        .expect("the spec has a section for each format");
+    let len = SPEC[start + heading.len()..]
+        .find("\n## ")
+        .map_or(SPEC.len() - start, |end| heading.len() + end + 1);
+    &SPEC[start..start + len]
+}
+
+/// Layout of a binary map file, as told apart by [`map_version`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MapVersion {
+    /// The radius and the hexagons, from before the flags byte
+    Legacy,
+    /// A flags byte first, without the magic number
+    V1,
+    /// The magic number and a header, without a checksum
+    V2,
+    /// Version 2 with a checksum, as `save_map` writes
+    V3,
+}
+
+impl std::fmt::Display for MapVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MapVersion::Legacy => "legacy",
+            MapVersion::V1 => "v1",
+            MapVersion::V2 => "v2",
+            MapVersion::V3 => "v3",
+        })
+    }
+}
+
+/// Which layout the bytes of a binary map file are in, the way loading tells them apart:
+/// by the magic number and version byte, or failing those by the file size
+pub fn map_version(buffer: &[u8]) -> Result<MapVersion, SlitherError> {
+    match buffer.strip_prefix(MAGIC).map(|rest| rest.first()) {
+        Some(Some(&VERSION)) => Ok(MapVersion::V3),
+        Some(Some(&VERSION_UNCHECKED)) => Ok(MapVersion::V2),
+        Some(Some(version)) => Err(SlitherError::InvalidData(format!(
+            "Unsupported map format version {}",
+            version
+        ))),
+        Some(None) => Err(SlitherError::InvalidData("File too short".to_string())),
+        None => match buffer.first() {
+            None => Err(SlitherError::InvalidData("File empty".to_string())),
+            Some(&radius) if buffer.len() == 1 + cell_count(radius) => Ok(MapVersion::Legacy),
+            Some(_) => Ok(MapVersion::V1),
+        },
+    }
+}
+
+/// Re-encode the bytes of a binary map file of any version with `encoding`, keeping everything
+/// the target layout can hold
+pub fn convert_map(buffer: &[u8], encoding: MapEncoding) -> Result<Vec<u8>, SlitherError> {
+    let mut bytes = Vec::new();
+    write_map(&decode_file(buffer)?, &mut bytes, encoding)?;
+    Ok(bytes)
+}
+
+/// Whether `path` names a JSON map file rather than a binary one
+fn is_json<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().extension().is_some_and(|ext| ext == "json")
+}
+
+/// Whether `path` names a map in the text format
+fn is_text<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().extension().is_some_and(|ext| ext == "txt")
+}
+
+/// Save a map to a binary file with [`write_map`], as JSON with [`write_map_json`] if the
+/// path ends in `.json`, or as text with [`write_map_text`] if it ends in `.txt`
+pub fn save_map<P: AsRef<Path>>(
+    map: &Map,
+    path: P,
+    encoding: MapEncoding,
+) -> Result<(), SlitherError> {
+    // Encoded in full first, so a map that can't be saved leaves no file behind
+    let mut bytes = Vec::new();
+    if is_json(&path) {
+        write_map_json(map, &mut bytes)?;
+    } else if is_text(&path) {
+        write_map_text(map, &mut bytes)?;
+    } else {
+        write_map(map, &mut bytes, encoding)?;
+    }
+    Ok(write_atomic(path, &bytes)?)
+}
+
+/// Write `bytes` to `path` through a temporary file in the same folder, renamed over `path`
+/// once it is complete and synced, so an interrupted write leaves either the old file or the
+/// new one and never part of either. The folder is synced too, so the rename survives a crash.
+pub fn write_atomic<P: AsRef<Path>>(path: P, bytes: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    let written = File::create(&temp).and_then(|mut file| {
+        file.write_all(bytes)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|()| std::fs::rename(&temp, path)) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e);
+    }
+    sync_dir(path.parent().filter(|dir| !dir.as_os_str().is_empty()))
+}
+
+/// Flush the entries of `dir`, the current folder if `None`, to disk. Only Unix can open a
+/// folder to sync it.
+#[cfg(unix)]
+fn sync_dir(dir: Option<&Path>) -> io::Result<()> {
+    File::open(dir.unwrap_or(Path::new(".")))?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: Option<&Path>) -> io::Result<()> {
+    Ok(())
+}
+
+/// Write a map in the binary format
+/// Format: [Magic: "SLTH"] [Version: u8 = 3] [Flags: u8] [Radius: u8] [Level: u8] [Loops: u8]
+///         [ClueTypes: u8] [HexBytes..., or HexNibbles... if compressed]
+///         [MetaLength: u16 LE, MetaJson..., if flagged] [CRC-32 of everything before it: u32 LE]
 /// Legacy Format: [Radius: u8] [HexBytes...]
-pub fn save_map<P: AsRef<Path>>(map: &Map, path: P, legacy: bool) -> io::Result<()> {
-    let mut file = File::create(path)?;
+/// The radius byte could hold up to 255, but only radii up to `MAX_RADIUS` load.
+/// The legacy format has no room for flags, the level or metadata, so they are dropped. Maps
+/// whose clues exclude the rim, or with more than one loop, can't be saved in it, as they
+/// would read back as different puzzles.
+pub fn write_map<W: Write>(
+    map: &Map,
+    mut writer: W,
+    encoding: MapEncoding,
+) -> Result<(), SlitherError> {
+    let bytes = match encoding {
+        MapEncoding::Legacy => encode_legacy(map)?,
+        MapEncoding::Standard => encode_map(map, false)?,
+        MapEncoding::Compressed => encode_map(map, true)?,
+    };
+    Ok(writer.write_all(&bytes)?)
+}
 
-    if !legacy {
-        // Byte 0: Flags (Reserved 0)
-        file.write_all(&[0u8])?;
+/// Write a map as JSON
+pub fn write_map_json<W: Write>(map: &Map, writer: W) -> Result<(), SlitherError> {
+    Ok(serde_json::to_writer_pretty(writer, map)?)
+}
+
+/// Write several maps as one JSON array
+pub fn write_maps_json<W: Write>(maps: &[Map], writer: W) -> Result<(), SlitherError> {
+    Ok(serde_json::to_writer_pretty(writer, maps)?)
+}
+
+fn encode_legacy(map: &Map) -> Result<Vec<u8>, SlitherError> {
+    if !map.rim_edges.is_counted() {
+        return Err(SlitherError::InvalidData(
+            "The legacy format can't store clues that exclude the rim".to_string(),
+        ));
+    }
+    if map.loop_count != 1 {
+        return Err(SlitherError::InvalidData(format!(
+            "The legacy format can't store maps with {} loops",
+            map.loop_count
+        )));
     }
+    if map.cells.values().any(|cell| !cell.region.is_known()) {
+        return Err(SlitherError::InvalidData(
+            "The legacy format can't store maps without an answer key".to_string(),
+        ));
+    }
+    let mut bytes = vec![map.radius];
+    push_cells(map, &mut bytes)?;
+    Ok(bytes)
+}
 
-    // Byte 1 (or 0 if legacy): Radius
-    file.write_all(&[map.radius])?;
+fn encode_map(map: &Map, compressed: bool) -> Result<Vec<u8>, SlitherError> {
+    // A map missing any of its answer is saved without all of it, so the region bits and
+    // hidden counts read as zero rather than as part of the answer
+    let stripped;
+    let no_answer = map.cells.values().any(|cell| !cell.region.is_known());
+    let map = if no_answer {
+        stripped = map.without_answer();
+        &stripped
+    } else {
+        map
+    };
+    if compressed && (no_answer || !map.is_consistent()) {
+        return Err(SlitherError::InvalidData(
+            "Only maps whose clues match their answer can be compressed".to_string(),
+        ));
+    }
+    let meta = map.meta.as_ref().map(serde_json::to_vec).transpose()?;
+    let mut flags = 0u8;
+    if map.timed_out {
+        flags |= FLAG_TIMED_OUT;
+    }
+    if map.level.is_some() {
+        flags |= FLAG_LEVEL;
+    }
+    if meta.is_some() {
+        flags |= FLAG_META;
+    }
+    if !map.rim_edges.is_counted() {
+        flags |= FLAG_RIM_EXCLUDED;
+    }
+    if !map.holes.is_empty() {
+        flags |= FLAG_HOLES;
+    }
+    if compressed {
+        flags |= FLAG_COMPRESSED;
+    }
+    if no_answer {
+        flags |= FLAG_NO_ANSWER;
+    }
 
-    // Hexagon Data
-    // We must iterate in the specific order defined by iter_coords
-    for coord in map.iter_coords() {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend([
+        VERSION,
+        flags,
+        map.radius,
+        map.level.unwrap_or(0),
+        map.loop_count,
+        clue_types(map),
+    ]);
+    if compressed {
+        let mut cells = Vec::new();
+        push_cells(map, &mut cells)?;
+        bytes.extend(compress_cells(&cells));
+    } else {
+        push_cells(map, &mut bytes)?;
+    }
+    if let Some(meta) = meta {
+        let len = u16::try_from(meta.len())
+            .map_err(|_| SlitherError::InvalidData("Map metadata is too long".to_string()))?;
+        bytes.extend(len.to_le_bytes());
+        bytes.extend(meta);
+    }
+    bytes.extend(crc32(&bytes).to_le_bytes());
+    Ok(bytes)
+}
+
+/// The clue types bits of the kinds of clue `map` shows beyond plain numbers
+fn clue_types(map: &Map) -> u8 {
+    map.cells.values().fold(0, |types, cell| match cell.clue {
+        Clue::Marker => types | CLUE_MARKERS,
+        Clue::Parity => types | CLUE_PARITY,
+        Clue::Count | Clue::Hidden => types,
+    })
+}
+
+/// Append a byte per hexagon, in the order of `iter_coords` with holes included. Unknown
+/// regions are written as Outside.
+fn push_cells(map: &Map, bytes: &mut Vec<u8>) -> Result<(), SlitherError> {
+    for coord in Map::new(map.radius).iter_coords() {
+        if map.is_hole(coord) {
+            bytes.push(HOLE);
+            continue;
+        }
         let cell = map.cells.get(&coord).ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Map incomplete or missing cells",
-            )
+            SlitherError::InvalidData("Map incomplete or missing cells".to_string())
         })?;
+        bytes.push(pack_cell(cell));
+    }
+    Ok(())
+}
 
-        let byte = pack_cell(cell);
-        file.write_all(&[byte])?;
+/// Load a map from a binary, JSON or text file
+pub fn load_map<P: AsRef<Path>>(path: P) -> Result<Map, SlitherError> {
+    if is_json(&path) || is_text(&path) {
+        check_answer(load_puzzle(path)?)
+    } else {
+        read_map(BufReader::new(File::open(path)?))
     }
+}
 
-    Ok(())
+/// Load a map from a binary file, JSON if the path ends in `.json` or text if it ends in
+/// `.txt`, without checking the answer it stores, for when only its visible clues and markers
+/// matter
+pub fn load_puzzle<P: AsRef<Path>>(path: P) -> Result<Map, SlitherError> {
+    let reader = BufReader::new(File::open(&path)?);
+    if is_json(&path) {
+        read_puzzle_json(reader)
+    } else if is_text(&path) {
+        read_puzzle_text(reader)
+    } else {
+        read_puzzle(reader)
+    }
+}
+
+/// Read a map in the binary format, of any version, and check the answer it stores
+pub fn read_map<R: Read>(reader: R) -> Result<Map, SlitherError> {
+    check_answer(read_puzzle(reader)?)
 }
 
-/// Load a map from a binary file
-pub fn load_map<P: AsRef<Path>>(path: P) -> io::Result<Map> {
-    let mut file = File::open(path)?;
+/// Read the maps of a stream without a file name to go by, like stdin, and check the answers
+/// they store. A pack (starting with `SLPK`), a JSON array (`[`), a JSON map (`{`) and a
+/// binary map are all told apart by how they start, so `generate --output -` of any format but
+/// text reads back.
+pub fn read_maps_any<R: Read>(mut reader: R) -> Result<Vec<Map>, SlitherError> {
     let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
+    reader.read_to_end(&mut buffer)?;
+    let text = buffer.trim_ascii_start();
+    let maps = if buffer.starts_with(PACK_MAGIC) {
+        read_pack(buffer.as_slice())?
+    } else if text.starts_with(b"[") {
+        serde_json::from_slice(text)?
+    } else if text.starts_with(b"{") {
+        vec![serde_json::from_slice(text)?]
+    } else {
+        vec![decode_file(&buffer)?]
+    };
+    maps.into_iter().map(check_answer).collect()
+}
+
+/// Read a map in the binary format, of any version, without checking the answer it stores
+pub fn read_puzzle<R: Read>(mut reader: R) -> Result<Map, SlitherError> {
+    let mut buffer = Vec::new();
+    reader.read_to_end(&mut buffer)?;
+    decode_file(&buffer)
+}
+
+/// Read a map from JSON without checking the answer it stores. A save file gives its puzzle.
+pub fn read_puzzle_json<R: Read>(reader: R) -> Result<Map, SlitherError> {
+    Ok(read_progress(reader)?.0)
+}
+
+/// A puzzle part way through being solved, as saved by `save_progress`
+#[derive(Deserialize, Serialize)]
+struct SavedGame<'a> {
+    save_version: u32,
+    puzzle: std::borrow::Cow<'a, Map>,
+    player: std::borrow::Cow<'a, PlayerState>,
+}
+
+/// Save a puzzle with the player's progress on it as JSON, to resume later with
+/// `load_progress`
+pub fn save_progress<P: AsRef<Path>>(
+    puzzle: &Map,
+    player: &PlayerState,
+    path: P,
+) -> Result<(), SlitherError> {
+    let saved = SavedGame {
+        save_version: SAVE_VERSION,
+        puzzle: std::borrow::Cow::Borrowed(puzzle),
+        player: std::borrow::Cow::Borrowed(player),
+    };
+    Ok(write_atomic(path, &serde_json::to_vec_pretty(&saved)?)?)
+}
+
+/// Load a puzzle and the player's progress on it from a save file, or from any puzzle file
+/// with no progress yet
+pub fn load_progress<P: AsRef<Path>>(path: P) -> Result<(Map, PlayerState), SlitherError> {
+    if is_json(&path) {
+        read_progress(BufReader::new(File::open(path)?))
+    } else {
+        Ok((load_puzzle(path)?, PlayerState::default()))
+    }
+}
+
+/// Read a save file, or a JSON map with no progress yet, told apart by the `save_version`
+/// that save files start with. Fields added by later versions of the same major layout are
+/// ignored; saves from a newer version than this one are rejected.
+pub fn read_progress<R: Read>(reader: R) -> Result<(Map, PlayerState), SlitherError> {
+    let json: serde_json::Value = serde_json::from_reader(reader)?;
+    let Some(version) = json.get("save_version") else {
+        return Ok((serde_json::from_value(json)?, PlayerState::default()));
+    };
+    match version.as_u64() {
+        Some(version) if version <= SAVE_VERSION as u64 => {}
+        _ => {
+            return Err(SlitherError::InvalidData(format!(
+                "Unsupported save version {}",
+                version
+            )));
+        }
+    }
+    let saved: SavedGame = serde_json::from_value(json)?;
+    Ok((saved.puzzle.into_owned(), saved.player.into_owned()))
+}
+
+/// `map`, once its answer is a valid loop and its clues match it
+fn check_answer(map: Map) -> Result<Map, SlitherError> {
+    map.validate_topology()?;
+    if !map.is_consistent() {
+        return Err(SlitherError::InconsistentClues);
+    }
+    Ok(map)
+}
+
+/// Load every map of a pack file, or the one map of any other file, without checking the
+/// answers they store
+pub fn load_puzzles<P: AsRef<Path>>(path: P) -> Result<Vec<Map>, SlitherError> {
+    if is_pack(&path) {
+        load_pack(path)
+    } else {
+        Ok(vec![load_puzzle(path)?])
+    }
+}
+
+/// Decode the bytes of a binary map file, whichever version it is
+fn decode_file(buffer: &[u8]) -> Result<Map, SlitherError> {
+    match buffer.strip_prefix(MAGIC) {
+        Some(rest) => match rest.split_first() {
+            Some((&VERSION, _)) => decode_map(checked_payload(buffer)?),
+            Some((&VERSION_UNCHECKED, rest)) => decode_map(rest),
+            Some((version, _)) => Err(SlitherError::InvalidData(format!(
+                "Unsupported map format version {}",
+                version
+            ))),
+            None => Err(SlitherError::InvalidData("File too short".to_string())),
+        },
+        None => decode_unversioned(buffer),
+    }
+}
+
+/// The bytes of a version 3 file between its version and its checksum, once the checksum
+/// matches them
+fn checked_payload(buffer: &[u8]) -> Result<&[u8], SlitherError> {
+    let (body, stored) = buffer
+        .split_last_chunk::<4>()
+        .filter(|(body, _)| body.len() > MAGIC.len())
+        .ok_or_else(|| SlitherError::InvalidData("File too short".to_string()))?;
+    let expected = u32::from_le_bytes(*stored);
+    let found = crc32(body);
+    if found != expected {
+        return Err(SlitherError::Corrupt { expected, found });
+    }
+    Ok(&body[MAGIC.len() + 1..])
+}
+
+/// CRC-32 of `bytes`, as in zip and PNG (reflected polynomial 0xEDB88320)
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Decode a version 2 or 3 map, from the flags byte that follows the version to the end of the
+/// metadata
+fn decode_map(bytes: &[u8]) -> Result<Map, SlitherError> {
+    let (&[flags, radius, level, loops, types], rest) = bytes
+        .split_first_chunk::<5>()
+        .ok_or_else(|| SlitherError::InvalidData("File too short".to_string()))?;
+    // Later layouts flag new kinds of clue or board in the reserved bits, which this reader
+    // would misread
+    if flags & !KNOWN_FLAGS != 0 || types & !KNOWN_CLUE_TYPES != 0 {
+        return Err(SlitherError::InvalidData(format!(
+            "Unsupported map features (flags {:#x}, clue types {:#x})",
+            flags & !KNOWN_FLAGS,
+            types & !KNOWN_CLUE_TYPES
+        )));
+    }
+    let mut map = Map::try_new(radius)?;
+    if loops == 0 {
+        return Err(SlitherError::InvalidData(
+            "Loop count must be at least 1".to_string(),
+        ));
+    }
+    map.timed_out = flags & FLAG_TIMED_OUT != 0;
+    map.loop_count = loops;
+    if flags & FLAG_RIM_EXCLUDED != 0 {
+        map.rim_edges = RimEdges::Excluded;
+    }
+    if flags & FLAG_LEVEL != 0 {
+        map.level = Some(level);
+    }
 
+    let compressed = flags & FLAG_COMPRESSED != 0;
+    let no_answer = flags & FLAG_NO_ANSWER != 0;
+    if compressed && no_answer {
+        return Err(SlitherError::InvalidData(
+            "Compressed maps must have an answer".to_string(),
+        ));
+    }
+    let len = if compressed {
+        cell_count(radius).div_ceil(2)
+    } else {
+        cell_count(radius)
+    };
+    let (cells, rest) = rest
+        .split_at_checked(len)
+        .ok_or_else(|| SlitherError::InvalidData("Missing cell data".to_string()))?;
+    if compressed {
+        read_cells(&mut map, &expand_cells(cells, cell_count(radius)))?;
+        map.recompute_clues();
+    } else {
+        read_cells(&mut map, cells)?;
+    }
+    if no_answer {
+        for cell in map.cells.values_mut() {
+            if cell.clue != Clue::Marker {
+                cell.region = KnownRegion::Unknown;
+            }
+        }
+    }
+    if flags & FLAG_META != 0 {
+        map.meta = Some(read_meta(rest)?);
+    } else if !rest.is_empty() {
+        return Err(SlitherError::InvalidData(
+            "File size does not match radius".to_string(),
+        ));
+    }
+    let holes = !map.holes.is_empty();
+    if (flags & FLAG_HOLES != 0) != holes || types != clue_types(&map) {
+        return Err(SlitherError::InvalidData(
+            "Header does not match the hexagon data".to_string(),
+        ));
+    }
+    Ok(map)
+}
+
+/// Decode a map without the magic number: the legacy format, or version 1, which has a flags
+/// byte but keeps the loop count in its high bits and the level only if flagged
+fn decode_unversioned(buffer: &[u8]) -> Result<Map, SlitherError> {
     if buffer.is_empty() {
-        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "File empty"));
+        return Err(SlitherError::InvalidData("File empty".to_string()));
     }
 
     // Auto-detect legacy format
     // Legacy: [Radius] [Data...]
-    // Modern: [Flags] [Radius] [Data...]
+    // Version 1: [Flags] [Radius] [Level, if flagged] [Data...] [Metadata, if flagged]
 
     let candidate_legacy_radius = buffer[0];
-    let hex_count_legacy =
-        3 * (candidate_legacy_radius as u32) * (candidate_legacy_radius as u32 + 1) + 1;
-    let expected_size_legacy = 1 + hex_count_legacy as usize;
+    let expected_size_legacy = 1 + cell_count(candidate_legacy_radius);
 
-    let (radius, start_offset) = if buffer.len() == expected_size_legacy {
+    let (radius, flags, start_offset) = if buffer.len() == expected_size_legacy {
         // Detected Legacy
-        (candidate_legacy_radius, 1)
+        (candidate_legacy_radius, 0, 1)
     } else {
-        // Assume Modern
         if buffer.len() < 2 {
-            return Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                "File too short",
-            ));
+            return Err(SlitherError::InvalidData("File too short".to_string()));
         }
-        let _flags = buffer[0];
+        let flags = buffer[0];
         let radius = buffer[1];
-        (radius, 2)
+        if flags & FLAG_LEVEL != 0 {
+            (radius, flags, 3)
+        } else {
+            (radius, flags, 2)
+        }
+    };
+
+    let mut map = Map::try_new(radius)?;
+    map.timed_out = flags & FLAG_TIMED_OUT != 0;
+    map.loop_count = (flags >> V1_LOOP_COUNT_SHIFT).max(1);
+    if flags & FLAG_RIM_EXCLUDED != 0 {
+        map.rim_edges = RimEdges::Excluded;
+    }
+    if flags & FLAG_LEVEL != 0 {
+        let level = *buffer
+            .get(2)
+            .ok_or_else(|| SlitherError::InvalidData("Missing difficulty level".to_string()))?;
+        map.level = Some(level);
+    }
+
+    let data_end = start_offset + cell_count(radius);
+    if flags & FLAG_META != 0 {
+        map.meta = Some(read_meta(buffer.get(data_end..).unwrap_or_default())?);
+    } else if buffer.len() != data_end {
+        // Without metadata the hexagons run to the end of the file
+        return Err(SlitherError::InvalidData(
+            "File size does not match radius".to_string(),
+        ));
+    }
+    let cells = buffer
+        .get(start_offset..data_end)
+        .ok_or_else(|| SlitherError::InvalidData("Missing cell data".to_string()))?;
+    read_cells(&mut map, cells)?;
+    Ok(map)
+}
+
+/// Fill the cells and holes of a fresh `map` from its hexagon bytes, one per hex in spec order
+fn read_cells(map: &mut Map, bytes: &[u8]) -> Result<(), SlitherError> {
+    // iter_coords is stateless based on radius, so we can use it to rebuild keys.
+    let coords: Vec<Coord> = map.iter_coords().collect();
+    if bytes.len() != coords.len() {
+        return Err(SlitherError::InvalidData("Missing cell data".to_string()));
+    }
+    for (coord, &byte) in coords.into_iter().zip(bytes) {
+        if byte & HOLE != 0 {
+            // The other bytes with the top bit set are left for new kinds of hexagon
+            if byte != HOLE {
+                return Err(SlitherError::InvalidData(format!(
+                    "Unsupported hexagon byte {:#04x}",
+                    byte
+                )));
+            }
+            map.holes.insert(coord);
+            continue;
+        }
+        map.cells.insert(coord, unpack_cell(byte));
+    }
+    check_board(map.radius, &map.holes)
+}
+
+/// Read the metadata block that ends a modern map file: a little-endian `u16` length, then
+/// that many bytes of JSON
+fn read_meta(block: &[u8]) -> Result<MapMeta, SlitherError> {
+    let (len, json) = block
+        .split_first_chunk::<2>()
+        .ok_or_else(|| SlitherError::InvalidData("Missing map metadata".to_string()))?;
+    if json.len() != u16::from_le_bytes(*len) as usize {
+        return Err(SlitherError::InvalidData(
+            "Map metadata does not match its length".to_string(),
+        ));
+    }
+    Ok(serde_json::from_slice(json)?)
+}
+
+/// Whether `path` names a puzzle pack
+pub fn is_pack<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().extension().is_some_and(|ext| ext == "slpack")
+}
+
+/// Index entry of a puzzle in a pack, for picking puzzles without decoding them all
+struct PackEntry {
+    radius: u8,
+    level: Option<u8>,
+    /// Difficulty the puzzle was generated for, from its metadata, empty if it has none
+    difficulty: String,
+    /// Position and size of the puzzle's map bytes in the file
+    offset: usize,
+    len: usize,
+}
+
+/// The difficulty a pack indexes `map` under
+fn pack_difficulty(map: &Map) -> &str {
+    map.meta
+        .as_ref()
+        .and_then(|meta| meta.requested_difficulty.as_deref())
+        .unwrap_or_default()
+}
+
+/// Save several maps to one pack file with [`write_pack`]
+pub fn save_pack<P: AsRef<Path>>(maps: &[Map], path: P) -> Result<(), SlitherError> {
+    let mut bytes = Vec::new();
+    write_pack(maps, &mut bytes)?;
+    Ok(write_atomic(path, &bytes)?)
+}
+
+/// Write several maps as one pack
+/// Format: [Magic: "SLPK"] [Version: u8 = 1] [Count: u32 LE] [IndexEntries...]
+///         [CRC-32 of everything before it: u32 LE] [Maps...]
+/// Index Entry: [Radius: u8] [Level: u8, 0 if none] [DifficultyLength: u8] [Difficulty...]
+///              [Offset: u32 LE] [Length: u32 LE]
+/// Each map is stored as a whole binary map file, checksum included, at its offset from the
+/// start of the pack.
+pub fn write_pack<W: Write>(maps: &[Map], mut writer: W) -> Result<(), SlitherError> {
+    let count = u32::try_from(maps.len())
+        .map_err(|_| SlitherError::InvalidData("Too many maps for one pack".to_string()))?;
+    let records = maps
+        .iter()
+        .map(|map| encode_map(map, false))
+        .collect::<Result<Vec<_>, _>>()?;
+    let index_len = PACK_MAGIC.len()
+        + 1
+        + 4
+        + maps
+            .iter()
+            .map(|map| 11 + pack_difficulty(map).len())
+            .sum::<usize>()
+        + 4;
+
+    let mut bytes = PACK_MAGIC.to_vec();
+    bytes.push(PACK_VERSION);
+    bytes.extend(count.to_le_bytes());
+    let mut offset = index_len;
+    for (map, record) in maps.iter().zip(&records) {
+        let difficulty = pack_difficulty(map);
+        let difficulty_len = u8::try_from(difficulty.len()).map_err(|_| {
+            SlitherError::InvalidData(format!("Difficulty name {} is too long", difficulty))
+        })?;
+        let too_large = || SlitherError::InvalidData("Pack is too large".to_string());
+        bytes.extend([map.radius, map.level.unwrap_or(0), difficulty_len]);
+        bytes.extend(difficulty.as_bytes());
+        bytes.extend(
+            u32::try_from(offset)
+                .map_err(|_| too_large())?
+                .to_le_bytes(),
+        );
+        bytes.extend(
+            u32::try_from(record.len())
+                .map_err(|_| too_large())?
+                .to_le_bytes(),
+        );
+        offset += record.len();
+    }
+    bytes.extend(crc32(&bytes).to_le_bytes());
+    for record in records {
+        bytes.extend(record);
+    }
+    Ok(writer.write_all(&bytes)?)
+}
+
+/// Load every map of a pack file with [`read_pack`]
+pub fn load_pack<P: AsRef<Path>>(path: P) -> Result<Vec<Map>, SlitherError> {
+    read_pack(BufReader::new(File::open(path)?))
+}
+
+/// Read every map of a pack, in the order they were written, without checking the answers
+/// they store
+pub fn read_pack<R: Read>(mut reader: R) -> Result<Vec<Map>, SlitherError> {
+    let mut buffer = Vec::new();
+    reader.read_to_end(&mut buffer)?;
+    read_pack_index(&buffer)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let record = buffer
+                .get(entry.offset..entry.offset + entry.len)
+                .ok_or_else(|| {
+                    SlitherError::InvalidData(format!("Puzzle {} runs past the end of the pack", i))
+                })?;
+            let map = decode_file(record)?;
+            if map.radius != entry.radius
+                || map.level != entry.level
+                || pack_difficulty(&map) != entry.difficulty
+            {
+                return Err(SlitherError::InvalidData(format!(
+                    "Puzzle {} does not match its index entry",
+                    i
+                )));
+            }
+            Ok(map)
+        })
+        .collect()
+}
+
+/// Read the index at the start of a pack, once its checksum matches
+fn read_pack_index(buffer: &[u8]) -> Result<Vec<PackEntry>, SlitherError> {
+    let too_short = || SlitherError::InvalidData("Pack index is cut short".to_string());
+    let rest = buffer
+        .strip_prefix(PACK_MAGIC)
+        .ok_or_else(|| SlitherError::InvalidData("Not a puzzle pack".to_string()))?;
+    let (&version, rest) = rest.split_first().ok_or_else(too_short)?;
+    if version != PACK_VERSION {
+        return Err(SlitherError::InvalidData(format!(
+            "Unsupported pack format version {}",
+            version
+        )));
+    }
+    let (count, mut rest) = rest.split_first_chunk::<4>().ok_or_else(too_short)?;
+    let mut entries = Vec::new();
+    for _ in 0..u32::from_le_bytes(*count) {
+        let (&[radius, level, difficulty_len], tail) =
+            rest.split_first_chunk::<3>().ok_or_else(too_short)?;
+        let (difficulty, tail) = tail
+            .split_at_checked(difficulty_len as usize)
+            .ok_or_else(too_short)?;
+        let (offset, tail) = tail.split_first_chunk::<4>().ok_or_else(too_short)?;
+        let (len, tail) = tail.split_first_chunk::<4>().ok_or_else(too_short)?;
+        entries.push(PackEntry {
+            radius,
+            level: (level != 0).then_some(level),
+            difficulty: String::from_utf8(difficulty.to_vec()).map_err(|_| {
+                SlitherError::InvalidData("Difficulty name is not UTF-8".to_string())
+            })?,
+            offset: u32::from_le_bytes(*offset) as usize,
+            len: u32::from_le_bytes(*len) as usize,
+        });
+        rest = tail;
+    }
+    let stored = rest.first_chunk::<4>().ok_or_else(too_short)?;
+    let expected = u32::from_le_bytes(*stored);
+    let found = crc32(&buffer[..buffer.len() - rest.len()]);
+    if found != expected {
+        return Err(SlitherError::Corrupt { expected, found });
+    }
+    Ok(entries)
+}
+
+/// Entry of an output folder's `metadata.json`: how a saved puzzle was generated and what
+/// its answer came out as
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct PuzzleMetadata {
+    pub file: String,
+    pub seed: u64,
+    /// Fill ratio the answer was grown towards, if the task set one
+    pub fill_ratio: Option<f64>,
+    /// Fraction of the board actually inside the loop
+    pub inside_fraction: f64,
+}
+
+/// Save the metadata of the puzzles in one output folder as a JSON array
+pub fn save_metadata<P: AsRef<Path>>(entries: &[PuzzleMetadata], path: P) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(entries)?;
+    std::fs::write(path, json)
+}
+
+/// Load an output folder's metadata saved by `save_metadata`
+pub fn load_metadata<P: AsRef<Path>>(path: P) -> Result<Vec<PuzzleMetadata>, SlitherError> {
+    Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+}
+
+/// Layout of a batch manifest
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ManifestFormat {
+    /// Comma-separated, quoting fields as in RFC 4180
+    Csv,
+    /// Tab-separated
+    Tsv,
+}
+
+impl ManifestFormat {
+    /// Name of the manifest in the output folder
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ManifestFormat::Csv => "manifest.csv",
+            ManifestFormat::Tsv => "manifest.tsv",
+        }
+    }
+
+    fn separator(self) -> char {
+        match self {
+            ManifestFormat::Csv => ',',
+            ManifestFormat::Tsv => '\t',
+        }
+    }
+}
+
+/// Row of a batch manifest: a saved puzzle and what it came out as
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManifestEntry {
+    pub path: PathBuf,
+    pub radius: u8,
+    pub difficulty: Option<String>,
+    pub seed: Option<u64>,
+    pub loop_length: Option<u32>,
+    pub clues: usize,
+    /// Hardest technique needed to solve the puzzle, if it was rated
+    pub measured_difficulty: Option<String>,
+}
+
+impl ManifestEntry {
+    /// The row of `map`, saved at `path`, from its metadata
+    pub fn new(path: PathBuf, map: &Map) -> Self {
+        let meta = map.meta.clone().unwrap_or_default();
+        ManifestEntry {
+            path,
+            radius: map.radius,
+            difficulty: meta.requested_difficulty,
+            seed: meta.seed,
+            loop_length: meta.loop_length,
+            clues: map.stats().visible_clues,
+            measured_difficulty: meta.measured_difficulty,
+        }
+    }
+}
+
+/// Save a batch manifest with a header row and one row per puzzle. Paths are written relative to
+/// the manifest's folder, with `/` between components; missing values are left empty.
+pub fn save_manifest<P: AsRef<Path>>(
+    entries: &[ManifestEntry],
+    path: P,
+    format: ManifestFormat,
+) -> io::Result<()> {
+    let base = path.as_ref().parent().unwrap_or(Path::new(""));
+    let sep = format.separator();
+    let field = |value: String| {
+        if format == ManifestFormat::Csv && value.contains([',', '"', '\n']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value
+        }
     };
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    let number = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
 
-    let mut map = Map::new(radius);
+    let header = [
+        "file",
+        "radius",
+        "difficulty",
+        "seed",
+        "loop_length",
+        "clues",
+        "measured_difficulty",
+    ];
+    let mut out = header.join(&sep.to_string());
+    out.push('\n');
+    for entry in entries {
+        let file = entry.path.strip_prefix(base).unwrap_or(&entry.path);
+        let file: Vec<_> = file
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        let row = [
+            file.join("/"),
+            entry.radius.to_string(),
+            text(&entry.difficulty),
+            number(entry.seed),
+            number(entry.loop_length.map(u64::from)),
+            entry.clues.to_string(),
+            text(&entry.measured_difficulty),
+        ];
+        let row: Vec<String> = row.into_iter().map(field).collect();
+        out.push_str(&row.join(&sep.to_string()));
+        out.push('\n');
+    }
+    std::fs::write(path, out)
+}
+
+/// Entry of `tuning_report.json`: how hard one task's puzzles were to generate
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TaskTuning {
+    pub radius: u8,
+    pub difficulty: String,
+    pub fill_ratio: Option<f64>,
+    /// Puzzles asked for
+    pub count: u32,
+    pub generated: u32,
+    pub failed: u32,
+    /// Candidate answers tried, and rejections by kind of check
+    #[serde(flatten)]
+    pub stats: AttemptStats,
+}
+
+/// Save the tuning report of a run as a JSON array with one entry per task
+pub fn save_tuning_report<P: AsRef<Path>>(tasks: &[TaskTuning], path: P) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(tasks)?;
+    std::fs::write(path, json)
+}
+
+/// Save a solving trace as a JSON array of steps:
+/// `{ "technique": "counting", "clue": { "q": 0, "r": 1 }, "cells": [{ "q": 1, "r": 1, "region": "Inside" }, ...] }`
+pub fn save_trace<P: AsRef<Path>>(steps: &[Deduction], path: P) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(steps)?;
+    std::fs::write(path, json)
+}
 
-    // Expected hex count check for modern path (legacy implicitly checked by detection logic, but good to double check or simplify)
-    let expected_hexes = 3 * (radius as u32) * (radius as u32 + 1) + 1;
-    if buffer.len() - start_offset != expected_hexes as usize {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "File size does not match radius",
+/// Save the loops of several solutions of one puzzle as a JSON array with an entry per solution:
+/// its loops, each a list of edge indices of `EdgeGraph::new(radius)` in order around the loop
+pub fn save_solution_loops<P: AsRef<Path>>(solutions: &[Solution], path: P) -> io::Result<()> {
+    let loops: Vec<&Vec<Vec<usize>>> = solutions.iter().map(|s| &s.loops).collect();
+    let json = serde_json::to_string_pretty(&loops)?;
+    std::fs::write(path, json)
+}
+
+/// JSON region mask: `{ "radius": 2, "inside": [[q, r], ...] }`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaskFile {
+    radius: u8,
+    inside: Vec<[i8; 2]>,
+}
+
+/// Load a region mask from a JSON or ASCII file (JSON if it starts with `{`)
+///
+/// ASCII masks have one line per row `r` from -R to +R, each listing that row's cells
+/// in increasing `q` as `#` (Inside) or `.` (Outside). Other whitespace is ignored,
+/// so the rows may be indented to look like a hexagon:
+/// ```text
+///   # .
+///  # # #
+///   . #
+/// ```
+pub fn load_mask<P: AsRef<Path>>(path: P) -> Result<RegionMask, SlitherError> {
+    let contents = std::fs::read_to_string(path)?;
+    if contents.trim_start().starts_with('{') {
+        parse_mask_json(&contents)
+    } else {
+        parse_mask_ascii(&contents)
+    }
+}
+
+fn parse_mask_json(contents: &str) -> Result<RegionMask, SlitherError> {
+    let file: MaskFile = serde_json::from_str(contents)?;
+    check_radius(file.radius)?;
+    let mut inside = HashSet::new();
+    for [q, r] in file.inside {
+        if !Coord::new(q, r).in_bounds(file.radius) {
+            return Err(SlitherError::InvalidData(format!(
+                "Cell ({}, {}) is outside radius {}",
+                q, r, file.radius
+            )));
+        }
+        inside.insert(Coord::new(q, r));
+    }
+    Ok(RegionMask {
+        radius: file.radius,
+        inside,
+    })
+}
+
+fn parse_mask_ascii(contents: &str) -> Result<RegionMask, SlitherError> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let (radius, cells) = parse_ascii_rows(&lines)?;
+    let mut inside = HashSet::new();
+    for (coord, symbol) in cells {
+        match symbol {
+            '#' => {
+                inside.insert(coord);
+            }
+            '.' => {}
+            _ => {
+                return Err(SlitherError::InvalidData(format!(
+                    "Unexpected character '{}' in row r={}",
+                    symbol, coord.r
+                )));
+            }
+        }
+    }
+    Ok(RegionMask { radius, inside })
+}
+
+/// Read the hexagon drawn by `lines`: one line per row `r` from -R to +R, each listing that
+/// row's cells in increasing `q`, one character per cell. Whitespace is ignored, as are lines
+/// with nothing else on them. Returns the radius and every cell's character in board order.
+fn parse_ascii_rows(lines: &[&str]) -> Result<(u8, Vec<(Coord, char)>), SlitherError> {
+    let rows: Vec<Vec<char>> = lines
+        .iter()
+        .map(|line| {
+            line.chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<Vec<_>>()
+        })
+        .filter(|row| !row.is_empty())
+        .collect();
+    if rows.len().is_multiple_of(2) {
+        return Err(SlitherError::InvalidData(format!(
+            "Expected an odd number of rows (2 * radius + 1), found {}",
+            rows.len()
+        )));
+    }
+    if rows.len() > 2 * MAX_RADIUS as usize + 1 {
+        return Err(SlitherError::InvalidData(format!(
+            "{} rows is too many: the radius can be at most {}",
+            rows.len(),
+            MAX_RADIUS
+        )));
+    }
+
+    let radius = (rows.len() / 2) as i8;
+    let mut cells = Vec::new();
+    for (row, r) in rows.iter().zip(-radius..=radius) {
+        // -r - R goes below i8 on large boards, but the result never does
+        let q_min = (-(radius as i16)).max(-(r as i16) - radius as i16) as i8;
+        let expected = 2 * radius as usize + 1 - r.unsigned_abs() as usize;
+        if row.len() != expected {
+            return Err(SlitherError::InvalidData(format!(
+                "Row r={} has {} cells, expected {}",
+                r,
+                row.len(),
+                expected
+            )));
+        }
+        cells.extend(
+            row.iter()
+                .zip(q_min..)
+                .map(|(&symbol, q)| (Coord::new(q, r), symbol)),
+        );
+    }
+    // Rows run along q, the board order along r
+    cells.sort_by_key(|(coord, _)| (coord.q, coord.r));
+    Ok((radius as u8, cells))
+}
+
+/// Write a map in the text format: optional `key: value` header lines for a loop count other
+/// than 1, clues that exclude the rim and the level, then the puzzle as rows of hexagons, and
+/// the answer below it unless some region is unknown. Metadata isn't kept.
+///
+/// ```text
+/// loops: 2
+///
+///  2 .
+/// e 6 S
+///  x 1
+///
+///  . .
+/// . # .
+///  x .
+/// ```
+pub fn write_map_text<W: Write>(map: &Map, mut writer: W) -> Result<(), SlitherError> {
+    let mut header = Vec::new();
+    if map.loop_count != 1 {
+        header.push(format!("loops: {}", map.loop_count));
+    }
+    if !map.rim_edges.is_counted() {
+        header.push("rim-edges: excluded".to_string());
+    }
+    if let Some(level) = map.level {
+        header.push(format!("level: {}", level));
+    }
+    let mut blocks = Vec::new();
+    if !header.is_empty() {
+        blocks.push(header.join("\n"));
+    }
+
+    let symbols = |symbol: &dyn Fn(&Cell) -> char| -> Result<String, SlitherError> {
+        let radius = map.radius as i8;
+        let mut rows = Vec::new();
+        for r in -radius..=radius {
+            let q_min = (-(radius as i16)).max(-(r as i16) - radius as i16) as i8;
+            let len = 2 * radius as usize + 1 - r.unsigned_abs() as usize;
+            let row: Vec<String> = (q_min..)
+                .take(len)
+                .map(|q| {
+                    let coord = Coord::new(q, r);
+                    if map.is_hole(coord) {
+                        return Ok("x".to_string());
+                    }
+                    let cell = map.cells.get(&coord).ok_or_else(|| {
+                        SlitherError::InvalidData("Map incomplete or missing cells".to_string())
+                    })?;
+                    Ok(symbol(cell).to_string())
+                })
+                .collect::<Result<_, SlitherError>>()?;
+            rows.push(format!(
+                "{}{}",
+                " ".repeat(r.unsigned_abs() as usize),
+                row.join(" ")
+            ));
+        }
+        Ok(rows.join("\n"))
+    };
+    blocks.push(symbols(
+        &|cell| match (cell.visible_clue(), cell.marker()) {
+            (Some(VisibleClue::Count(count)), _) => (b'0' + count) as char,
+            (Some(VisibleClue::Parity(0)), _) => 'e',
+            (Some(VisibleClue::Parity(_)), _) => 'o',
+            (None, Some(Marker::Sheep)) => 'S',
+            (None, Some(Marker::Wolf)) => 'W',
+            (None, None) => '.',
+        },
+    )?);
+    if map.cells.values().all(|cell| cell.region.is_known()) {
+        blocks.push(symbols(&|cell| match cell.region.region() {
+            Some(Region::Inside) => '#',
+            _ => '.',
+        })?);
+    }
+    Ok(writer.write_all(format!("{}\n", blocks.join("\n\n")).as_bytes())?)
+}
+
+/// Read a map in the text format without checking the answer it stores. Without an answer
+/// block, regions are unknown except behind markers, and hidden clues count 0, as in
+/// [`Map::without_answer`].
+pub fn read_puzzle_text<R: Read>(mut reader: R) -> Result<Map, SlitherError> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    let mut blocks: Vec<Vec<&str>> = vec![Vec::new()];
+    for line in contents.lines() {
+        match blocks.last_mut() {
+            Some(block) if line.trim().is_empty() => {
+                if !block.is_empty() {
+                    blocks.push(Vec::new());
+                }
+            }
+            Some(block) => block.push(line),
+            None => unreachable!("blocks starts with one"),
+        }
+    }
+    blocks.retain(|block| !block.is_empty());
+
+    let mut map_header = Vec::new();
+    if blocks
+        .first()
+        .is_some_and(|block| block.iter().all(|line| line.contains(':')))
+    {
+        map_header = blocks.remove(0);
+    }
+    let (puzzle, answer) = match blocks.as_slice() {
+        [puzzle] => (puzzle, None),
+        [puzzle, answer] => (puzzle, Some(answer)),
+        _ => {
+            return Err(SlitherError::InvalidData(format!(
+                "Expected a puzzle and at most one answer, found {} blocks of rows",
+                blocks.len()
+            )));
+        }
+    };
+
+    let (radius, clues) = parse_ascii_rows(puzzle)?;
+    let holes = clues
+        .iter()
+        .filter(|(_, symbol)| *symbol == 'x')
+        .map(|(coord, _)| *coord)
+        .collect();
+    let mut map = Map::try_with_holes(radius, holes)?;
+    for line in map_header {
+        let (key, value) = line.split_once(':').expect("header lines hold a colon");
+        let (key, value) = (key.trim(), value.trim());
+        let number = || {
+            value
+                .parse::<u8>()
+                .map_err(|_| SlitherError::InvalidData(format!("Invalid {}: {}", key, value)))
+        };
+        match key {
+            "loops" => {
+                map.loop_count = number()?;
+                if map.loop_count == 0 {
+                    return Err(SlitherError::InvalidData(
+                        "Loop count must be at least 1".to_string(),
+                    ));
+                }
+            }
+            "rim-edges" => map.rim_edges = value.parse().map_err(SlitherError::InvalidData)?,
+            "level" => map.level = Some(number()?),
+            _ => {
+                return Err(SlitherError::InvalidData(format!(
+                    "Unknown header line: {}",
+                    line
+                )));
+            }
+        }
+    }
+
+    for &(coord, symbol) in &clues {
+        let cell = match symbol {
+            'x' => continue,
+            '0'..='6' => Cell {
+                region: KnownRegion::Unknown,
+                full_neighbor_count: symbol as u8 - b'0',
+                clue: Clue::Count,
+            },
+            'e' | 'o' => Cell {
+                region: KnownRegion::Unknown,
+                full_neighbor_count: (symbol == 'o') as u8,
+                clue: Clue::Parity,
+            },
+            'S' | 'W' => Cell {
+                region: if symbol == 'S' {
+                    Region::Inside
+                } else {
+                    Region::Outside
+                }
+                .into(),
+                full_neighbor_count: 0,
+                clue: Clue::Marker,
+            },
+            '.' => Cell {
+                region: KnownRegion::Unknown,
+                full_neighbor_count: 0,
+                clue: Clue::Hidden,
+            },
+            _ => {
+                return Err(SlitherError::InvalidData(format!(
+                    "Unexpected character '{}' in row r={}",
+                    symbol, coord.r
+                )));
+            }
+        };
+        map.cells.insert(coord, cell);
+    }
+
+    if let Some(answer) = answer {
+        let (answer_radius, regions) = parse_ascii_rows(answer)?;
+        if answer_radius != radius {
+            return Err(SlitherError::InvalidData(format!(
+                "The answer has radius {}, but the puzzle {}",
+                answer_radius, radius
+            )));
+        }
+        for (coord, symbol) in regions {
+            let region = match symbol {
+                '#' => Region::Inside,
+                '.' => Region::Outside,
+                'x' => Region::Outside,
+                _ => {
+                    return Err(SlitherError::InvalidData(format!(
+                        "Unexpected character '{}' in row r={} of the answer",
+                        symbol, coord.r
+                    )));
+                }
+            };
+            if (symbol == 'x') != map.is_hole(coord) {
+                return Err(SlitherError::InvalidData(format!(
+                    "Cell ({}, {}) is a hole in only one of the puzzle and the answer",
+                    coord.q, coord.r
+                )));
+            }
+            let Some(cell) = map.cells.get_mut(&coord) else {
+                continue;
+            };
+            if cell.region.is_known() && cell.region.region() != Some(region) {
+                return Err(SlitherError::InvalidData(format!(
+                    "The marker of cell ({}, {}) contradicts the answer",
+                    coord.q, coord.r
+                )));
+            }
+            cell.region = region.into();
+        }
+        // Hidden clues and markers take their counts from the answer. Shown ones keep theirs,
+        // so a clue that contradicts the answer fails the check on loading.
+        let shown: Vec<(Coord, Cell)> = map
+            .cells
+            .iter()
+            .map(|(&coord, cell)| (coord, cell.clone()))
+            .collect();
+        map.recompute_clues();
+        for (coord, written) in shown {
+            let cell = &mut map.cells[&coord];
+            match written.clue {
+                Clue::Count => cell.full_neighbor_count = written.full_neighbor_count,
+                Clue::Parity if cell.full_neighbor_count % 2 != written.full_neighbor_count => {
+                    cell.full_neighbor_count = written.full_neighbor_count
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(map)
+}
+
+/// URL-safe base64 alphabet (RFC 4648 section 5)
+const BASE64_URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A share code for `map`: the map in the compressed binary format, without its metadata,
+/// as unpadded URL-safe base64, short enough to paste in chat. The answer goes with it, so the
+/// map needs one, and its clues must match it.
+pub fn share_code(map: &Map) -> Result<String, SlitherError> {
+    let mut bytes = Vec::new();
+    let map = Map {
+        meta: None,
+        ..map.clone()
+    };
+    write_map(&map, &mut bytes, MapEncoding::Compressed)?;
+    let mut code = String::new();
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..=chunk.len() {
+            code.push(BASE64_URL[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    Ok(code)
+}
+
+/// Read the map behind a share code from [`share_code`], checking its answer
+pub fn from_share_code(code: &str) -> Result<Map, SlitherError> {
+    let code = code.trim().trim_end_matches('=');
+    let mut bytes = Vec::new();
+    for chunk in code.as_bytes().chunks(4) {
+        if chunk.len() == 1 {
+            return Err(SlitherError::InvalidData(
+                "Share code is cut short".to_string(),
+            ));
+        }
+        let mut bits = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let value = BASE64_URL.iter().position(|&b| b == c).ok_or_else(|| {
+                SlitherError::InvalidData(format!(
+                    "Unexpected character '{}' in share code",
+                    c as char
+                ))
+            })?;
+            bits |= (value as u32) << (18 - 6 * i);
+        }
+        bytes.extend(bits.to_be_bytes()[1..chunk.len()].iter());
+    }
+    read_map(bytes.as_slice())
+}
+
+/// Import a puzzle from another program. Only Simon Tatham's Loopy is understood so far, in
+/// its honeycomb grid (`t2`), the one grid of its made of hexagons: a game ID like
+/// `5x4t2dh:2a3b1...` lists the clues of a `w` by `h` rectangle row by row, each a digit or a
+/// letter for a run of 1 (`a`) to 26 (`z`) blank cells. Odd columns sit half a hexagon lower.
+///
+/// The rectangle is placed on the smallest hexagonal board around it, with the rest of the
+/// board cut out as holes. Game IDs carry no answer, so every region is unknown.
+pub fn import(id: &str) -> Result<Map, SlitherError> {
+    let invalid = |message: &str| SlitherError::InvalidData(format!("{}: {}", message, id));
+    let (params, clues) = id
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| invalid("Expected a Loopy game ID like 5x4t2:<clues>"))?;
+    let (size, options) = params.split_at(
+        params
+            .find(|c: char| c.is_ascii_alphabetic() && c != 'x')
+            .unwrap_or(params.len()),
+    );
+    let (width, height) = size
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse::<usize>().ok()?, h.parse::<usize>().ok()?)))
+        .filter(|&(w, h)| w > 0 && h > 0)
+        .ok_or_else(|| invalid("Invalid grid size"))?;
+    let cell_total = width
+        .checked_mul(height)
+        .ok_or_else(|| invalid("Grid too large"))?;
+    let grid = options.strip_prefix('t').map(|rest| {
+        &rest[..rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len())]
+    });
+    if grid != Some("2") {
+        return Err(invalid(
+            "Only Loopy's honeycomb grid (t2) is made of hexagons",
         ));
     }
 
-    let mut iter = buffer.iter().skip(start_offset);
-
-    // Reconstruct utilizing the determinstic iteration order
-    // iter_coords is stateless based on radius, so we can use it to rebuild keys.
-    let coords: Vec<Coord> = map.iter_coords().collect();
+    let mut shown = Vec::new();
+    for c in clues.chars() {
+        match c {
+            '0'..='6' => shown.push(Some(c as u8 - b'0')),
+            'a'..='z' => shown.extend(std::iter::repeat_n(None, (c as u8 - b'a' + 1) as usize)),
+            _ => {
+                return Err(SlitherError::InvalidData(format!(
+                    "Unexpected character '{}' in the clues of {}",
+                    c, id
+                )));
+            }
+        }
+    }
+    if shown.len() != cell_total {
+        return Err(SlitherError::InvalidData(format!(
+            "Expected {} clues for a {}x{} grid, found {}",
+            cell_total,
+            width,
+            height,
+            shown.len()
+        )));
+    }
+
+    // Axial coordinates of the cells, before centering
+    let cells: Vec<(i32, i32)> = (0..cell_total)
+        .map(|i| {
+            let (x, y) = ((i % width) as i32, (i / width) as i32);
+            (x, y - (x - (x & 1)) / 2)
+        })
+        .collect();
+    let reach = |(cq, cr): (i32, i32)| {
+        cells
+            .iter()
+            .map(|&(q, r)| {
+                let (dq, dr) = (q - cq, r - cr);
+                dq.abs().max(dr.abs()).max((dq + dr).abs())
+            })
+            .max()
+            .unwrap_or(0)
+    };
+    let middle = cells[(height / 2) * width + width / 2];
+    let center = (-2..=2)
+        .flat_map(|dq| (-2..=2).map(move |dr| (middle.0 + dq, middle.1 + dr)))
+        .min_by_key(|&c| reach(c))
+        .expect("the search covers some centers");
+    let radius = u8::try_from(reach(center))
+        .ok()
+        .filter(|&r| r <= MAX_RADIUS)
+        .ok_or_else(|| invalid("Grid too large"))?;
 
-    for coord in coords {
-        let byte = *iter
-            .next()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Missing cell data"))?;
-        let cell = unpack_cell(byte);
-        map.cells.insert(coord, cell);
+    let coords: Vec<Coord> = cells
+        .iter()
+        .map(|&(q, r)| Coord::new((q - center.0) as i8, (r - center.1) as i8))
+        .collect();
+    let on_grid: HashSet<Coord> = coords.iter().copied().collect();
+    let holes = Map::try_new(radius)?
+        .iter_coords()
+        .filter(|c| !on_grid.contains(c))
+        .collect();
+    let mut map = Map::try_with_holes(radius, holes)?;
+    for (coord, clue) in coords.into_iter().zip(shown) {
+        map.cells.insert(
+            coord,
+            Cell {
+                region: KnownRegion::Unknown,
+                full_neighbor_count: clue.unwrap_or(0),
+                clue: if clue.is_some() {
+                    Clue::Count
+                } else {
+                    Clue::Hidden
+                },
+            },
+        );
     }
-
     Ok(map)
 }
 
+/// Pack hexagon bytes two to a byte, low half first, keeping all but the count: bit 0 of each
+/// half is the region, bit 1 shows the number, bit 2 shows a marker and bit 3 shows only the
+/// parity. A hole, which shows nothing, is bit 3 alone.
+fn compress_cells(cells: &[u8]) -> Vec<u8> {
+    let nibble = |byte: u8| {
+        if byte == HOLE {
+            0x8
+        } else {
+            byte & 0x1 | (byte >> 3) & 0xe
+        }
+    };
+    cells
+        .chunks(2)
+        .map(|pair| nibble(pair[0]) | pair.get(1).map_or(0, |&byte| nibble(byte) << 4))
+        .collect()
+}
+
+/// Unpack `count` hexagon bytes from `compress_cells`, each with a count of 0
+fn expand_cells(packed: &[u8], count: usize) -> Vec<u8> {
+    packed
+        .iter()
+        .flat_map(|&byte| [byte & 0xf, byte >> 4])
+        .take(count)
+        .map(|nibble| {
+            if nibble == 0x8 {
+                HOLE
+            } else {
+                nibble & 0x1 | (nibble & 0xe) << 3
+            }
+        })
+        .collect()
+}
+
 fn pack_cell(cell: &Cell) -> u8 {
     let mut byte = 0u8;
 
@@ -109,10 +1603,20 @@ fn pack_cell(cell: &Cell) -> u8 {
     byte |= (cell.full_neighbor_count & 0x7) << 1;
 
     // Bit 4: Show Number
-    if cell.clue_visible {
+    if cell.clue_visible() {
         byte |= 0x10;
     }
 
+    // Bit 5: Show Marker (its kind follows from the region)
+    if cell.clue == Clue::Marker {
+        byte |= 0x20;
+    }
+
+    // Bit 6: Show Parity Only
+    if cell.clue == Clue::Parity {
+        byte |= 0x40;
+    }
+
     byte
 }
 
@@ -126,12 +1630,19 @@ fn unpack_cell(byte: u8) -> Cell {
     let count = (byte >> 1) & 0x7;
     let visible = (byte & 0x10) != 0;
 
-    Cell::new(region, count, visible)
+    let mut cell = Cell::new(region, count, visible);
+    if (byte & 0x20) != 0 {
+        cell.clue = Clue::Marker;
+    } else if visible && (byte & 0x40) != 0 {
+        cell.clue = Clue::Parity;
+    }
+    cell
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::MapBuilder;
     use googletest::prelude::*;
     use tempfile::tempdir;
 
@@ -154,6 +1665,117 @@ mod tests {
         expect_that!(b2, eq(0));
     }
 
+    #[googletest::test]
+    fn metadata_saves_as_json_array() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("metadata.json");
+        let entries = [
+            PuzzleMetadata {
+                file: "0.bin".to_string(),
+                seed: 7,
+                fill_ratio: Some(0.3),
+                inside_fraction: 0.25,
+            },
+            PuzzleMetadata {
+                file: "1.bin".to_string(),
+                seed: 8,
+                fill_ratio: None,
+                inside_fraction: 0.5,
+            },
+        ];
+        save_metadata(&entries, &path).unwrap();
+
+        let saved: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        expect_that!(
+            saved,
+            eq(&serde_json::json!([
+                { "file": "0.bin", "seed": 7, "fill_ratio": 0.3, "inside_fraction": 0.25 },
+                { "file": "1.bin", "seed": 8, "fill_ratio": null, "inside_fraction": 0.5 },
+            ]))
+        );
+        expect_that!(load_metadata(&path), ok(eq(&entries)));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn manifests_list_files_relative_to_their_folder() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let mut map = MapBuilder::new(1)
+            .fill(Region::Outside)
+            .inside([Coord::new(0, 0)])
+            .visible(Coord::new(1, 0), false)
+            .build()
+            .unwrap();
+        map.meta = Some(MapMeta {
+            seed: Some(7),
+            requested_difficulty: Some("density_0.5".to_string()),
+            measured_difficulty: Some("trial-1".to_string()),
+            loop_length: Some(6),
+            ..MapMeta::default()
+        });
+        let entries = [
+            ManifestEntry::new(dir.path().join("small").join("easy").join("0.bin"), &map),
+            ManifestEntry::new(dir.path().join("a,b.bin"), &map.without_answer()),
+        ];
+
+        let path = dir.path().join(ManifestFormat::Csv.file_name());
+        save_manifest(&entries, &path, ManifestFormat::Csv).unwrap();
+        expect_that!(
+            std::fs::read_to_string(&path).unwrap(),
+            eq(
+                "file,radius,difficulty,seed,loop_length,clues,measured_difficulty\n\
+                small/easy/0.bin,1,density_0.5,7,6,6,trial-1\n\
+                \"a,b.bin\",1,density_0.5,,,6,trial-1\n"
+            )
+        );
+
+        let path = dir.path().join(ManifestFormat::Tsv.file_name());
+        save_manifest(&entries[..1], &path, ManifestFormat::Tsv).unwrap();
+        expect_that!(
+            std::fs::read_to_string(&path).unwrap(),
+            ends_with("small/easy/0.bin\t1\tdensity_0.5\t7\t6\t6\ttrial-1\n")
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn solution_loops_save_as_nested_edge_lists() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("solutions.json");
+        let answer = crate::generator::map_from_inside(1, &HashSet::from([Coord::new(0, 0)]));
+        let solution = Solution {
+            answer,
+            loops: vec![vec![0, 1, 2, 3, 4, 5]],
+        };
+        save_solution_loops(&[solution], &path).unwrap();
+
+        let saved: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        expect_that!(saved, eq(&serde_json::json!([[[0, 1, 2, 3, 4, 5]]])));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn markers_pack_as_one_bit() {
+        for region in [Region::Inside, Region::Outside] {
+            let mut cell = Cell::new(region, 2, false);
+            cell.clue = Clue::Marker;
+            let byte = pack_cell(&cell);
+            expect_that!(byte & 0x20, eq(0x20));
+            expect_that!(unpack_cell(byte), eq(&cell));
+        }
+    }
+
+    #[googletest::test]
+    fn parity_clues_roundtrip() {
+        let mut cell = Cell::new(Region::Inside, 3, true);
+        cell.clue = Clue::Parity;
+        let byte = pack_cell(&cell);
+        expect_that!(byte, eq(0x57));
+        expect_that!(unpack_cell(byte), eq(&cell));
+    }
+
     #[googletest::test]
     fn test_save_load_roundtrip() -> Result<()> {
         let dir = tempdir().unwrap();
@@ -165,20 +1787,99 @@ mod tests {
         for coord in coords {
             map.cells.insert(coord, Cell::new(Region::Inside, 1, true));
         }
+        map.recompute_clues();
 
         // Save
-        save_map(&map, &path, false).unwrap();
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
 
         // Load
         let loaded = load_map(&path).unwrap();
 
-        expect_that!(loaded.radius, eq(1));
-        expect_that!(loaded.cells.len(), eq(map.cells.len()));
+        expect_that!(loaded, eq(&map));
+
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn json_maps_roundtrip_by_extension() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test_map.json");
 
-        for (coord, cell) in map.cells.iter() {
-            expect_that!(loaded.cells.get(coord), some(eq(cell)));
+        let mut map = Map::new(1);
+        for coord in map.iter_coords().collect::<Vec<_>>() {
+            map.cells.insert(coord, Cell::new(Region::Inside, 0, true));
         }
+        map.recompute_clues();
+        map.level = Some(3);
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
+
+        let saved: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        expect_that!(saved["cells"].as_array().map(Vec::len), some(eq(7)));
+        expect_that!(load_map(&path).unwrap(), eq(&map));
+
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn holes_roundtrip_as_their_own_bytes() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("holed_board.bin");
+
+        let holes = [Coord::new(1, 0), Coord::new(-1, 1)].into();
+        let inside = [Coord::new(0, 0)].into();
+        let map = crate::generator::board_from_inside(Map::with_holes(1, holes), &inside);
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        expect_that!(bytes.len(), eq(10 + 7 + 4));
+        expect_that!(bytes[5], eq(FLAG_HOLES));
+        expect_that!(bytes.iter().filter(|&&b| b == HOLE).count(), eq(2));
+        expect_that!(load_map(&path).unwrap(), eq(&map));
+
+        // Version 1 marks holes the same way
+        std::fs::write(&path, [0, 1, HOLE, HOLE, HOLE, HOLE, HOLE, HOLE, HOLE]).unwrap();
+        expect_that!(
+            load_map(&path).unwrap_err().to_string(),
+            eq("The board has no cells")
+        );
+
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn reserved_bits_are_turned_away_as_unsupported() -> Result<()> {
+        let mut map = crate::generator::map_from_inside(1, &[Coord::new(0, 0)].into());
+        map.cells.get_mut(&Coord::new(1, 0)).unwrap().clue = Clue::Marker;
+        map.cells.get_mut(&Coord::new(0, 1)).unwrap().clue = Clue::Parity;
+        let bytes = encode_map(&map, false)?;
+        expect_that!(decode_file(&bytes), ok(eq(&map)));
 
+        // Drop the checksum, so each change is read rather than reported as corruption
+        let unchecked = |offset: usize, byte: u8| {
+            let mut bytes = bytes[..bytes.len() - 4].to_vec();
+            bytes[4] = VERSION_UNCHECKED;
+            bytes[offset] |= byte;
+            decode_file(&bytes)
+        };
+        expect_that!(
+            unchecked(5, 0x80),
+            err(displays_as(eq(
+                "Unsupported map features (flags 0x80, clue types 0x0)"
+            )))
+        );
+        expect_that!(
+            unchecked(9, 0x4),
+            err(displays_as(eq(
+                "Unsupported map features (flags 0x0, clue types 0x4)"
+            )))
+        );
+        expect_that!(
+            unchecked(10, 0x80),
+            err(displays_as(contains_substring(
+                "Unsupported hexagon byte 0x"
+            )))
+        );
         Ok(())
     }
 
@@ -192,9 +1893,10 @@ mod tests {
         for coord in coords {
             map.cells.insert(coord, Cell::new(Region::Inside, 1, true));
         }
+        map.recompute_clues();
 
         // Save legacy
-        save_map(&map, &path, true).unwrap();
+        save_map(&map, &path, MapEncoding::Legacy).unwrap();
 
         // Check file size (should be 1 + 7 = 8 bytes, vs 9 bytes for modern)
         let metadata = std::fs::metadata(&path)?;
@@ -208,4 +1910,742 @@ mod tests {
 
         Ok(())
     }
+
+    #[googletest::test]
+    fn ascii_mask_reads_rows_by_r() {
+        let mask = parse_mask_ascii("  # .\n # # #\n  . #\n").unwrap();
+        expect_that!(mask.radius, eq(1));
+        expect_that!(
+            mask.inside,
+            unordered_elements_are![
+                eq(&Coord::new(0, -1)),
+                eq(&Coord::new(-1, 0)),
+                eq(&Coord::new(0, 0)),
+                eq(&Coord::new(1, 0)),
+                eq(&Coord::new(0, 1)),
+            ]
+        );
+    }
+
+    #[googletest::test]
+    fn ascii_mask_rejects_malformed_rows() {
+        expect_that!(parse_mask_ascii("# #\n# # #\n"), err(anything()));
+        expect_that!(parse_mask_ascii("# .\n# # #\n. # #\n"), err(anything()));
+        expect_that!(parse_mask_ascii("# .\n# x #\n. #\n"), err(anything()));
+    }
+
+    #[googletest::test]
+    fn json_mask_loads_from_file() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("mask.json");
+        std::fs::write(&path, r#"{ "radius": 2, "inside": [[0, 0], [1, -1]] }"#)?;
+
+        let mask = load_mask(&path).unwrap();
+        expect_that!(mask.radius, eq(2));
+        expect_that!(mask.inside, len(eq(2)));
+
+        let too_far = parse_mask_json(r#"{ "radius": 1, "inside": [[2, 0]] }"#);
+        expect_that!(
+            too_far,
+            err(displays_as(contains_substring("outside radius")))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn timed_out_flag_roundtrips() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("timed_out.bin");
+
+        let mut map = Map::new(1);
+        let coords: Vec<Coord> = map.iter_coords().collect();
+        for coord in coords {
+            map.cells.insert(coord, Cell::new(Region::Inside, 0, true));
+        }
+        map.recompute_clues();
+        map.timed_out = true;
+
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
+        expect_that!(std::fs::read(&path)?[5], eq(FLAG_TIMED_OUT));
+        expect_that!(load_map(&path).unwrap().timed_out, eq(true));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn excluded_rim_edges_roundtrip_as_a_flag() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("rim.bin");
+
+        let mut map = crate::generator::map_from_inside(1, &[Coord::new(1, 0)].into());
+        map.rim_edges = RimEdges::Excluded;
+        map.recompute_clues();
+
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
+        expect_that!(std::fs::read(&path)?[5], eq(FLAG_RIM_EXCLUDED));
+        expect_that!(load_map(&path).unwrap(), eq(&map));
+        expect_that!(
+            save_map(&map, &path, MapEncoding::Legacy),
+            err(displays_as(contains_substring("legacy")))
+        );
+
+        let path = dir.path().join("rim.json");
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
+        expect_that!(load_map(&path).unwrap(), eq(&map));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn loop_count_roundtrips() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("multi_loop.bin");
+
+        // Three corners of the ring, none touching another
+        let corners = [Coord::new(1, 0), Coord::new(-1, 1), Coord::new(0, -1)];
+        let mut map = crate::generator::map_from_inside(1, &corners.into_iter().collect());
+        map.loop_count = 3;
+
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
+        let bytes = std::fs::read(&path)?;
+        expect_that!(bytes[8], eq(3));
+        expect_that!(load_map(&path).unwrap().loop_count, eq(3));
+        expect_that!(
+            save_map(&map, dir.path().join("legacy.bin"), MapEncoding::Legacy),
+            err(displays_as(contains_substring("3 loops")))
+        );
+
+        // Version 1 keeps the loop count in the high bits of the flags
+        let mut v1 = vec![0x30, 1];
+        v1.extend(&bytes[10..bytes.len() - 4]);
+        std::fs::write(&path, v1)?;
+        expect_that!(load_map(&path).unwrap(), eq(&map));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn files_start_with_magic_and_version() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("versioned.bin");
+
+        let mut map = crate::generator::map_from_inside(1, &[Coord::new(0, 0)].into());
+        map.cells.get_mut(&Coord::new(1, 0)).unwrap().clue = Clue::Parity;
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
+        let bytes = std::fs::read(&path)?;
+        expect_that!(bytes[..5], eq(b"SLTH\x03"));
+        expect_that!(bytes[9], eq(CLUE_PARITY));
+        expect_that!(load_map(&path).unwrap(), eq(&map));
+
+        let mut wrong_types = bytes[..bytes.len() - 4].to_vec();
+        wrong_types[4] = VERSION_UNCHECKED;
+        wrong_types[9] = CLUE_MARKERS;
+        std::fs::write(&path, wrong_types)?;
+        expect_that!(
+            load_map(&path),
+            err(displays_as(eq("Header does not match the hexagon data")))
+        );
+
+        let mut future = bytes;
+        future[4] = 4;
+        std::fs::write(&path, future)?;
+        expect_that!(
+            load_map(&path),
+            err(displays_as(eq("Unsupported map format version 4")))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn packs_roundtrip_behind_an_index() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("set.slpack");
+
+        let small = crate::generator::map_from_inside(1, &[Coord::new(0, 0)].into());
+        let mut large = crate::generator::map_from_inside(2, &[Coord::new(1, 0)].into());
+        large.level = Some(4);
+        large.meta = Some(MapMeta {
+            requested_difficulty: Some("hard".to_string()),
+            ..MapMeta::default()
+        });
+        save_pack(&[small.clone(), large.clone()], &path).unwrap();
+        expect_that!(
+            load_pack(&path).unwrap(),
+            elements_are![eq(&small), eq(&large)]
+        );
+        expect_that!(load_puzzles(&path).unwrap(), len(eq(2)));
+
+        let bytes = std::fs::read(&path)?;
+        expect_that!(bytes[..5], eq(b"SLPK\x01"));
+        // The second entry, after the first's 11 bytes: radius, level, then "hard"
+        expect_that!(bytes[20..27], eq(b"\x02\x04\x04hard"));
+
+        let mut damaged = bytes.clone();
+        damaged[20] = 3;
+        std::fs::write(&path, damaged)?;
+        expect_that!(
+            load_pack(&path),
+            err(matches_pattern!(SlitherError::Corrupt { .. }))
+        );
+
+        std::fs::write(&path, &bytes[..bytes.len() - 1])?;
+        expect_that!(load_pack(&path), err(anything()));
+
+        save_pack(&[], &path).unwrap();
+        expect_that!(load_pack(&path).unwrap(), is_empty());
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn compressed_maps_roundtrip_at_half_a_byte_per_hexagon() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("compressed.bin");
+
+        let holes = [Coord::new(2, 0), Coord::new(-2, 2)].into();
+        let inside = [Coord::new(0, 0), Coord::new(1, 0)].into();
+        let mut map = crate::generator::board_from_inside(Map::with_holes(2, holes), &inside);
+        map.rim_edges = RimEdges::Excluded;
+        map.recompute_clues();
+        map.cells.get_mut(&Coord::new(0, 0)).unwrap().clue = Clue::Marker;
+        map.cells.get_mut(&Coord::new(0, 1)).unwrap().clue = Clue::Parity;
+        map.cells.get_mut(&Coord::new(-1, 0)).unwrap().clue = Clue::Hidden;
+        save_map(&map, &path, MapEncoding::Compressed).unwrap();
+
+        let bytes = std::fs::read(&path)?;
+        // 19 hexagons take 10 bytes
+        expect_that!(bytes.len(), eq(10 + 10 + 4));
+        expect_that!(bytes[5] & FLAG_COMPRESSED, eq(FLAG_COMPRESSED));
+        expect_that!(load_map(&path).unwrap(), eq(&map));
+
+        map.cells
+            .get_mut(&Coord::new(1, 0))
+            .unwrap()
+            .full_neighbor_count += 1;
+        expect_that!(
+            save_map(&map, &path, MapEncoding::Compressed),
+            err(displays_as(contains_substring("can be compressed")))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn maps_roundtrip_through_memory() {
+        let map = crate::generator::map_from_inside(2, &[Coord::new(0, 0)].into());
+
+        let mut bytes = Vec::new();
+        write_map(&map, &mut bytes, MapEncoding::Standard).unwrap();
+        expect_that!(read_map(bytes.as_slice()).unwrap(), eq(&map));
+
+        let mut json = Vec::new();
+        write_map_json(&map, &mut json).unwrap();
+        expect_that!(read_puzzle_json(json.as_slice()).unwrap(), eq(&map));
+
+        expect_that!(
+            read_maps_any(json.as_slice()).unwrap(),
+            elements_are![eq(&map)]
+        );
+        expect_that!(
+            read_maps_any(bytes.as_slice()).unwrap(),
+            elements_are![eq(&map)]
+        );
+
+        // What `generate --output -` writes reads back through `--load -`
+        let other = crate::generator::map_from_inside(1, &[Coord::new(0, 0)].into());
+        let both = [map.clone(), other.clone()];
+        let mut array = Vec::new();
+        write_maps_json(&both, &mut array).unwrap();
+        let maps: Vec<Map> = serde_json::from_slice(&array).unwrap();
+        expect_that!(maps, elements_are![eq(&map), eq(&other)]);
+        expect_that!(
+            read_maps_any(array.as_slice()).unwrap(),
+            elements_are![eq(&map), eq(&other)]
+        );
+
+        let mut pack = Vec::new();
+        write_pack(&both, &mut pack).unwrap();
+        expect_that!(
+            read_pack(pack.as_slice()).unwrap(),
+            elements_are![eq(&map), eq(&other)]
+        );
+        expect_that!(
+            read_maps_any(pack.as_slice()).unwrap(),
+            elements_are![eq(&map), eq(&other)]
+        );
+
+        let mut wrong = map.clone();
+        wrong
+            .cells
+            .get_mut(&Coord::new(0, 0))
+            .unwrap()
+            .full_neighbor_count = 0;
+        let mut bytes = Vec::new();
+        write_map(&wrong, &mut bytes, MapEncoding::Standard).unwrap();
+        expect_that!(read_puzzle(bytes.as_slice()).unwrap(), eq(&wrong));
+        expect_that!(
+            read_map(bytes.as_slice()),
+            err(matches_pattern!(SlitherError::InconsistentClues))
+        );
+    }
+
+    #[googletest::test]
+    fn text_maps_roundtrip_with_or_without_their_answer() {
+        let mut map = MapBuilder::new(2)
+            .fill(Region::Outside)
+            .inside([Coord::new(0, 0), Coord::new(1, -1)])
+            .hole(Coord::new(2, 0))
+            .parity(Coord::new(0, 1))
+            .marker(Coord::new(-1, 0))
+            .visible(Coord::new(-2, 2), false)
+            .rim_edges(RimEdges::Excluded)
+            .build()
+            .unwrap();
+        map.level = Some(4);
+
+        let mut text = Vec::new();
+        write_map_text(&map, &mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        expect_that!(text, starts_with("rim-edges: excluded\nlevel: 4\n\n  "));
+        expect_that!(read_puzzle_text(text.as_bytes()).unwrap(), eq(&map));
+
+        let player = map.without_answer();
+        let mut text = Vec::new();
+        write_map_text(&player, &mut text).unwrap();
+        expect_that!(
+            String::from_utf8(text.clone())
+                .unwrap()
+                .split("\n\n")
+                .count(),
+            eq(2)
+        );
+        expect_that!(read_puzzle_text(text.as_slice()).unwrap(), eq(&player));
+    }
+
+    #[googletest::test]
+    fn text_maps_are_checked_against_their_answer() {
+        let text = "loops: 1\n\n 1 1\n1 6 1\n 1 1\n\n . .\n. # .\n . .\n";
+        let map = read_puzzle_text(text.as_bytes()).unwrap();
+        expect_that!(check_answer(map).is_ok(), eq(true));
+
+        let wrong = text.replacen("6", "5", 1);
+        let map = read_puzzle_text(wrong.as_bytes()).unwrap();
+        expect_that!(
+            check_answer(map),
+            err(matches_pattern!(SlitherError::InconsistentClues))
+        );
+        for broken in [
+            "1 1\n1 6\n 1 1\n",
+            "loops: 0\n\n 1 1\n1 6 1\n 1 1\n",
+            " 1 1\n1 6 1\n 1 1\n\n . .\n. W .\n . .\n",
+            " 1 1\n1 S 1\n 1 1\n\n . .\n. . .\n . .\n",
+            " 1 1\n1 6 1\n 1 1\n\n . .\n. # .\n . x\n",
+        ] {
+            expect_that!(read_puzzle_text(broken.as_bytes()), err(anything()));
+        }
+    }
+
+    #[googletest::test]
+    fn loopy_honeycomb_ids_import_without_answers() {
+        let map = import("4x3t2dh:021115511120").unwrap();
+        expect_that!(map.radius, eq(2));
+        expect_that!(map.holes, len(eq(7)));
+        expect_that!(
+            map.cells.values().all(|c| c.region == KnownRegion::Unknown),
+            eq(true)
+        );
+        let solutions = crate::solver::solve(&map.view(), 2);
+        expect_that!(solutions.len(), eq(1));
+        expect_that!(solutions[0].answer.stats().inside_cells, eq(2));
+
+        let map = import("4x3t2:0a11155111a0").unwrap();
+        expect_that!(
+            map.cells
+                .values()
+                .filter(|c| c.clue == Clue::Hidden)
+                .count(),
+            eq(2)
+        );
+    }
+
+    #[googletest::test]
+    fn other_game_ids_are_rejected() {
+        for id in [
+            "4x3:021115511120",
+            "4x3t0:021115511120",
+            "4x3t2:02111551112",
+            "4x3t2:02111551112#",
+            "0x3t2:",
+            "021115511120",
+            "99999999999x99999999999t2:0",
+        ] {
+            expect_that!(import(id), err(anything()));
+        }
+    }
+
+    #[googletest::test]
+    fn saves_resume_with_the_players_marks() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("0.save.json");
+        let puzzle =
+            crate::generator::map_from_inside(2, &[Coord::new(0, 0)].into()).without_answer();
+        let mut player = PlayerState::default();
+        player.set_mark(3, Some(crate::model::EdgeMark::Line));
+        player.set_guess(Coord::new(0, 0), Some(Region::Inside));
+
+        save_progress(&puzzle, &player, &path).unwrap();
+        let saved: serde_json::Value = serde_json::from_slice(&std::fs::read(&path)?)?;
+        expect_that!(saved["save_version"], eq(&serde_json::json!(SAVE_VERSION)));
+        expect_that!(load_progress(&path), ok(eq(&(puzzle.clone(), player))));
+        // Anything that reads a puzzle reads the one in a save
+        expect_that!(load_puzzle(&path), ok(eq(&puzzle)));
+
+        // A puzzle file is a save with nothing marked yet
+        let plain = dir.path().join("0.puzzle.json");
+        save_map(&puzzle, &plain, MapEncoding::Standard).unwrap();
+        expect_that!(
+            load_progress(&plain),
+            ok(eq(&(puzzle.clone(), PlayerState::default())))
+        );
+
+        let mut newer = saved;
+        newer["save_version"] = serde_json::json!(SAVE_VERSION + 1);
+        expect_that!(
+            read_progress(newer.to_string().as_bytes()),
+            err(displays_as(eq(&format!(
+                "Unsupported save version {}",
+                SAVE_VERSION + 1
+            ))))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn share_codes_roundtrip_in_url_safe_text() {
+        let mut map = MapBuilder::new(4)
+            .fill(Region::Outside)
+            .inside([Coord::new(0, 0), Coord::new(1, -1), Coord::new(1, 0)])
+            .parity(Coord::new(0, 1))
+            .marker(Coord::new(2, -2))
+            .visible(Coord::new(-3, 1), false)
+            .build()
+            .unwrap();
+        map.level = Some(5);
+
+        let code = share_code(&map).unwrap();
+        // 10 header bytes, 31 of cells and a 4 byte checksum
+        expect_that!(code.len(), eq(60));
+        expect_that!(
+            code.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            eq(true)
+        );
+        expect_that!(from_share_code(&code).unwrap(), eq(&map));
+
+        map.meta = Some(MapMeta {
+            seed: Some(7),
+            ..MapMeta::default()
+        });
+        expect_that!(share_code(&map).unwrap(), eq(&code));
+
+        let mut damaged = code.clone().into_bytes();
+        damaged[30] = if damaged[30] == b'A' { b'B' } else { b'A' };
+        let damaged = String::from_utf8(damaged).unwrap();
+        expect_that!(
+            from_share_code(&damaged),
+            err(matches_pattern!(SlitherError::Corrupt { .. }))
+        );
+        expect_that!(from_share_code("SLTH!"), err(anything()));
+        expect_that!(from_share_code(&code[..code.len() - 3]), err(anything()));
+    }
+
+    #[googletest::test]
+    fn damaged_files_fail_their_checksum() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("damaged.bin");
+
+        let map = crate::generator::map_from_inside(2, &[Coord::new(0, 0)].into());
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
+        let bytes = std::fs::read(&path)?;
+        expect_that!(crc32(b"123456789"), eq(0xcbf4_3926));
+
+        std::fs::write(&path, &bytes[..bytes.len() - 3])?;
+        expect_that!(
+            load_map(&path),
+            err(matches_pattern!(SlitherError::Corrupt { .. }))
+        );
+
+        let mut flipped = bytes;
+        flipped[12] ^= 0x10;
+        std::fs::write(&path, flipped)?;
+        expect_that!(
+            load_puzzle(&path),
+            err(matches_pattern!(SlitherError::Corrupt { .. }))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn level_roundtrips_after_radius() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("level.bin");
+
+        let mut map = Map::new(1);
+        let coords: Vec<Coord> = map.iter_coords().collect();
+        for coord in coords {
+            map.cells.insert(coord, Cell::new(Region::Inside, 0, true));
+        }
+        map.recompute_clues();
+        map.level = Some(7);
+
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
+        expect_that!(
+            std::fs::read(&path)?[4..9],
+            eq(&[VERSION, FLAG_LEVEL, 1, 7, 1])
+        );
+        let loaded = load_map(&path).unwrap();
+        expect_that!(loaded.level, some(eq(7)));
+        expect_that!(loaded.cells.len(), eq(7));
+
+        save_map(&map, &path, MapEncoding::Legacy).unwrap();
+        expect_that!(load_map(&path).unwrap().level, none());
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn meta_roundtrips_after_the_hexagons() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("meta.bin");
+
+        let mut map = Map::new(1);
+        let coords: Vec<Coord> = map.iter_coords().collect();
+        for coord in coords {
+            map.cells.insert(coord, Cell::new(Region::Inside, 0, true));
+        }
+        map.recompute_clues();
+        map.meta = Some(MapMeta {
+            seed: Some(42),
+            algorithm: Some("loop".to_string()),
+            loop_length: Some(18),
+            ..MapMeta::default()
+        });
+
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
+        let bytes = std::fs::read(&path)?;
+        expect_that!(bytes[5], eq(FLAG_META));
+        expect_that!(bytes[17] as usize + 19 + 4, eq(bytes.len()));
+        expect_that!(load_map(&path).unwrap().meta, eq(&map.meta));
+
+        // Without the checksum, version 2 catches the metadata running short by itself
+        let mut unchecked = bytes[..bytes.len() - 5].to_vec();
+        unchecked[4] = VERSION_UNCHECKED;
+        std::fs::write(&path, unchecked)?;
+        expect_that!(
+            load_map(&path),
+            err(displays_as(contains_substring("does not match its length")))
+        );
+
+        let path = dir.path().join("meta.json");
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
+        expect_that!(load_map(&path).unwrap().meta, eq(&map.meta));
+
+        map.meta = None;
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
+        expect_that!(
+            std::fs::read_to_string(&path)?,
+            not(contains_substring("meta"))
+        );
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn puzzles_without_an_answer_key_save_without_their_regions() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let map = MapBuilder::new(1)
+            .fill(Region::Outside)
+            .inside([Coord::new(0, 0), Coord::new(1, 0)])
+            .visible(Coord::new(0, 1), false)
+            .marker(Coord::new(1, 0))
+            .build()
+            .unwrap();
+        let puzzle = map.without_answer();
+
+        let path = dir.path().join("puzzle.bin");
+        save_map(&puzzle, &path, MapEncoding::Standard).unwrap();
+        let bytes = std::fs::read(&path)?;
+        expect_that!(bytes[5] & FLAG_NO_ANSWER, eq(FLAG_NO_ANSWER));
+        // Only the sheep shows a region, and the hidden clue has no count
+        let coords: Vec<Coord> = map.iter_coords().collect();
+        for (coord, byte) in coords.into_iter().zip(&bytes[10..17]) {
+            expect_that!(byte & 0x1 != 0, eq(coord == Coord::new(1, 0)));
+            if coord == Coord::new(0, 1) {
+                expect_that!(byte & 0xe, eq(0));
+            }
+        }
+        expect_that!(load_puzzle(&path)?, eq(&puzzle));
+        expect_that!(
+            load_map(&path),
+            err(displays_as(contains_substring("no answer key")))
+        );
+
+        // Saving a map with part of its answer unknown leaves out the rest as well
+        let mut partial = map.clone();
+        partial.cells[&Coord::new(0, 0)].region = KnownRegion::Unknown;
+        save_map(&partial, &path, MapEncoding::Standard).unwrap();
+        expect_that!(std::fs::read(&path)?, eq(&bytes));
+
+        for encoding in [MapEncoding::Legacy, MapEncoding::Compressed] {
+            expect_that!(save_map(&puzzle, &path, encoding), err(anything()));
+        }
+        let path = dir.path().join("puzzle.json");
+        save_map(&puzzle, &path, MapEncoding::Standard).unwrap();
+        expect_that!(load_puzzle(&path)?, eq(&puzzle));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn saves_replace_files_whole_and_leave_no_temporary_files() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("0.bin");
+        let small = MapBuilder::new(1)
+            .fill(Region::Outside)
+            .inside([Coord::new(0, 0)])
+            .build()
+            .unwrap();
+        let large = MapBuilder::new(3)
+            .fill(Region::Outside)
+            .inside([Coord::new(0, 0)])
+            .build()
+            .unwrap();
+        save_map(&large, &path, MapEncoding::Standard).unwrap();
+        save_map(&small, &path, MapEncoding::Standard).unwrap();
+        expect_that!(load_map(&path)?, eq(&small));
+
+        // A rename that fails, here onto a folder, cleans up after itself
+        std::fs::create_dir(dir.path().join("taken.bin"))?;
+        expect_that!(
+            save_map(&small, dir.path().join("taken.bin"), MapEncoding::Standard),
+            err(anything())
+        );
+        let names: Vec<String> = std::fs::read_dir(dir.path())?
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        expect_that!(names, unordered_elements_are![eq("0.bin"), eq("taken.bin")]);
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn format_spec_documents_the_versions_and_flags_written() {
+        let map = format_spec(false);
+        expect_that!(map, starts_with("## Map Binary Format\n"));
+        expect_that!(map, not(contains_substring("\n## ")));
+        expect_that!(map, contains_substring(format!("currently `{}`", VERSION)));
+        for flag in [
+            FLAG_TIMED_OUT,
+            FLAG_LEVEL,
+            FLAG_META,
+            FLAG_RIM_EXCLUDED,
+            FLAG_HOLES,
+            FLAG_COMPRESSED,
+            FLAG_NO_ANSWER,
+            CLUE_MARKERS,
+            CLUE_PARITY,
+            HOLE,
+        ] {
+            expect_that!(map, contains_substring(format!("`{:#x}`", flag)));
+        }
+
+        let pack = format_spec(true);
+        expect_that!(pack, starts_with("## Puzzle Pack Format\n"));
+        expect_that!(
+            pack,
+            contains_substring(format!("currently `{}`", PACK_VERSION))
+        );
+        expect_that!(pack, contains_substring("`SLPK`"));
+    }
+
+    #[googletest::test]
+    fn old_layouts_convert_to_the_current_one() -> Result<()> {
+        let mut map = MapBuilder::new(2)
+            .fill(Region::Outside)
+            .inside([Coord::new(0, 0), Coord::new(1, 0)])
+            .visible(Coord::new(0, 1), false)
+            .build()
+            .unwrap();
+        map.level = Some(4);
+
+        let legacy = encode_legacy(&map)?;
+        expect_that!(map_version(&legacy)?, eq(MapVersion::Legacy));
+        let current = convert_map(&legacy, MapEncoding::Standard)?;
+        expect_that!(map_version(&current)?, eq(MapVersion::V3));
+        // The legacy layout has no level to carry over
+        let mut levelless = map.clone();
+        levelless.level = None;
+        expect_that!(read_map(current.as_slice())?, eq(&levelless));
+
+        let full = encode_map(&map, false)?;
+        let mut unchecked = full[..full.len() - 4].to_vec();
+        unchecked[4] = VERSION_UNCHECKED;
+        expect_that!(map_version(&unchecked)?, eq(MapVersion::V2));
+        expect_that!(convert_map(&unchecked, MapEncoding::Standard)?, eq(&full));
+        expect_that!(convert_map(&full, MapEncoding::Standard)?, eq(&full));
+        expect_that!(
+            map_version(&convert_map(&full, MapEncoding::Legacy)?)?,
+            eq(MapVersion::Legacy)
+        );
+
+        expect_that!(map_version(&[0x2, 1, 4]), ok(eq(&MapVersion::V1)));
+        expect_that!(map_version(b"SLTH\x09"), err(anything()));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn loading_rejects_enclosed_outside_cells() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("holed.bin");
+
+        let ring: HashSet<Coord> = Coord::new(0, 0).neighbors().into_iter().collect();
+        save_map(
+            &crate::generator::map_from_inside(2, &ring),
+            &path,
+            MapEncoding::Standard,
+        )
+        .unwrap();
+        expect_that!(
+            load_map(&path),
+            err(displays_as(contains_substring("encloses")))
+        );
+        expect_that!(load_puzzle(&path).map(|map| map.radius), ok(eq(&2)));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn loading_rejects_clues_that_contradict_the_answer() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("miscounted.bin");
+
+        let mut map = crate::generator::map_from_inside(2, &HashSet::from([Coord::new(0, 0)]));
+        map.cells
+            .get_mut(&Coord::new(2, 0))
+            .unwrap()
+            .full_neighbor_count = 1;
+        save_map(&map, &path, MapEncoding::Standard).unwrap();
+        expect_that!(
+            load_map(&path),
+            err(displays_as(contains_substring("don't match")))
+        );
+        expect_that!(load_puzzle(&path).map(|map| map.radius), ok(eq(&2)));
+        Ok(())
+    }
+
+    #[googletest::test]
+    fn loading_rejects_radii_beyond_the_coordinate_range() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("huge.bin");
+        std::fs::write(&path, [0, 200, 0, 0])?;
+        expect_that!(
+            load_puzzle(&path),
+            err(displays_as(contains_substring("too large")))
+        );
+        expect_that!(
+            parse_mask_json(r#"{ "radius": 130, "inside": [] }"#),
+            err(displays_as(contains_substring("too large")))
+        );
+        Ok(())
+    }
 }
@@ -1,88 +1,1444 @@
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
 mod args;
+pub mod bench;
+pub mod error;
 pub mod generator;
 pub mod io;
 pub mod model;
+pub mod pdf;
+pub mod qr;
+pub mod solver;
+pub mod svg;
+pub mod verify;
 pub mod web_viewer;
 
+/// Print a progress line to stdout, or to stderr while stdout carries the puzzles
+macro_rules! status {
+    ($to_stdout:expr, $($arg:tt)*) => {
+        if $to_stdout {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
 fn main() {
-    let args: args::Args = argh::from_env();
-    let config = args.normalize();
+    let strings: Vec<String> = std::env::args().collect();
+    let strs: Vec<&str> = strings.iter().map(String::as_str).collect();
+    let program = strs.first().copied().unwrap_or("slithergen");
+    let command = match args::Command::from_args(program, strs.get(1..).unwrap_or_default()) {
+        Ok(command) => command,
+        Err(exit) => match exit.status {
+            Ok(()) => {
+                println!("{}", exit.output);
+                return;
+            }
+            Err(()) => {
+                eprintln!(
+                    "{}\nRun {} --help for more information.",
+                    exit.output, program
+                );
+                std::process::exit(1);
+            }
+        },
+    };
+    let args = match command {
+        args::Command::Generate(args) => *args,
+        args::Command::Solve(args) => return solve_puzzle(args),
+        args::Command::Verify(args) => return verify_dir(args),
+        args::Command::Rate(args) => return rate_puzzles(args),
+        args::Command::Bench(args) => return bench_solvers(args),
+        args::Command::Compare(args) => return compare_maps(args),
+        args::Command::Print(args) => return print_puzzles(args),
+        args::Command::Import(args) => return import_puzzle(args),
+        args::Command::Encode(args) => return encode_map(args),
+        args::Command::Decode(args) => return decode_map(args),
+        args::Command::Convert(args) => return convert_maps(args),
+        args::Command::Play(args) => return play_puzzle(args),
+        args::Command::FormatSpec(args) => {
+            print!("{}", io::format_spec(args.pack));
+            return;
+        }
+    };
+    // Invalid arguments exit with status 1, so scripts don't mistake them for an empty run
+    let config = match args.normalize() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
     // Create output directory if it doesn't exist
-    if let Err(e) = std::fs::create_dir_all(&config.output_dir) {
+    if !config.to_stdout
+        && let Err(e) = std::fs::create_dir_all(&config.output_dir)
+    {
         eprintln!("Failed to create output directory: {}", e);
-        return;
+        std::process::exit(1);
     }
 
     if let Some(path) = config.load_path {
         // Load mode
-        match io::load_map(&path) {
-            Ok(map) => {
-                println!("Loaded map with radius {}", map.radius);
+        let loaded = if path == Path::new("-") {
+            io::read_maps_any(std::io::stdin().lock())
+        } else {
+            io::load_map(&path).map(|map| vec![map])
+        };
+        match loaded {
+            Ok(maps) => {
+                for map in &maps {
+                    println!("Loaded map with radius {}", map.radius);
+                }
                 if config.display {
-                    web_viewer::show_map(&map);
+                    web_viewer::show_maps(&maps);
                 }
             }
-            Err(e) => eprintln!("Failed to load map: {}", e),
+            Err(e) => {
+                eprintln!("Failed to load map: {}", e);
+                std::process::exit(1);
+            }
         }
     } else {
         // Generation mode
         if config.tasks.is_empty() {
-            println!("No generation tasks scheduled.");
+            status!(config.to_stdout, "No generation tasks scheduled.");
             return;
         }
 
         let task_count = config.tasks.len();
-        println!(
-            "Generating {} puzzles per task ({} tasks)...",
-            config.count_per_task, task_count
+        let total_count: u32 = config.tasks.iter().map(|t| t.count).sum();
+        match config.curriculum {
+            Some(count) => println!(
+                "Generating a curriculum of {} puzzles from {} candidates...",
+                count,
+                generator::curriculum::pool_size(count)
+            ),
+            None => status!(
+                config.to_stdout,
+                "Generating {} puzzles ({} tasks)...",
+                total_count,
+                task_count
+            ),
+        }
+
+        let is_mask = config.mask.is_some();
+        let algorithm = if is_mask {
+            "mask".to_string()
+        } else {
+            config.algorithm.to_string()
+        };
+        let generator: Box<dyn generator::Generator + Sync> = if let Some(mask) = config.mask {
+            match generator::MaskGenerator::new(mask) {
+                Ok(generator) => Box::new(generator),
+                Err(e) => {
+                    eprintln!("Invalid mask: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        } else if let Some(board) = config.board {
+            match generator::BoardGenerator::new(board) {
+                Ok(generator) => Box::new(generator),
+                Err(e) => {
+                    eprintln!("Invalid board: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        } else {
+            match config.algorithm {
+                args::Algorithm::Dummy => Box::new(generator::DummyGenerator),
+                args::Algorithm::Loop => match config.variant {
+                    args::Variant::MultiLoop => Box::new(generator::MultiLoopGenerator {
+                        loops: config.generator.loop_count,
+                    }),
+                    _ => match config.generator.shapes {
+                        Some(shapes) => Box::new(generator::ShapeGenerator { shapes }),
+                        None => Box::new(generator::LoopGenerator),
+                    },
+                },
+                args::Algorithm::RandomWalk => Box::new(generator::RandomWalkGenerator),
+                args::Algorithm::RegionGrowth => Box::new(generator::RegionGrowthGenerator {
+                    fill_ratio: config.generator.fill_ratio,
+                    symmetry: config.generator.symmetry,
+                }),
+                args::Algorithm::Annealing => Box::new(generator::AnnealingGenerator {
+                    target_loop_length: config.generator.target_loop_length,
+                }),
+            }
+        };
+        // A user-supplied answer is used as-is rather than held to the generation constraints
+        let constraints = if is_mask {
+            generator::GenerationConstraints {
+                timeout: config.generator.constraints.timeout,
+                clue_placement: config.generator.constraints.clue_placement,
+                techniques: config.generator.constraints.techniques,
+                markers: config.generator.constraints.markers,
+                parity: config.generator.constraints.parity,
+                solver: config.generator.constraints.solver,
+                ..generator::GenerationConstraints::unconstrained()
+            }
+        } else {
+            config.generator.constraints
+        };
+
+        // Tasks from --fill-ratios grow their answers towards their own ratio
+        let task_generators: Vec<Option<generator::RegionGrowthGenerator>> = config
+            .tasks
+            .iter()
+            .map(|task| {
+                task.fill_ratio
+                    .map(|fill_ratio| generator::RegionGrowthGenerator {
+                        fill_ratio,
+                        symmetry: config.generator.symmetry,
+                    })
+            })
+            .collect();
+
+        let master_seed = config.seed.unwrap_or_else(rand::random);
+        status!(config.to_stdout, "Using seed {}", master_seed);
+        let save = SaveOptions {
+            format: config.format,
+            encoding: config.encoding,
+            trace: config.trace,
+            player_files: config.player_files,
+            strip_answers: config.strip_answers,
+            svg_solution: config.svg_solution,
+            qr_prefix: config.qr_prefix.as_deref(),
+            pack: config.pack.as_deref(),
+            names: &config.name_template,
+            on_conflict: config.on_conflict,
+            timestamp: config.seed.is_none(),
+        };
+
+        if let Some(count) = config.curriculum {
+            let task = config.tasks[0];
+            let constraints = generator::GenerationConstraints {
+                loop_length: task.loop_length,
+                max_attempts: task.max_attempts.unwrap_or(constraints.max_attempts),
+                ..constraints
+            };
+            let pool: Vec<generator::batch::Job> = (0..generator::curriculum::pool_size(count))
+                .zip(generator::curriculum::POOL_LEVELS.cycle())
+                .map(|(n, level)| generator::batch::Job {
+                    generator: generator.as_ref(),
+                    radius: task.radius,
+                    difficulty: args::Difficulty::Level(level),
+                    constraints,
+                    seed: master_seed.wrapping_add(n as u64),
+                })
+                .collect();
+            let dir = config
+                .output_dir
+                .join(size_dir(task.radius))
+                .join("curriculum");
+            let manifest = run_curriculum(
+                &pool,
+                count as usize,
+                &algorithm,
+                &dir,
+                config.threads,
+                config.progress,
+                &save,
+            );
+            if let Some(format) = config.manifest {
+                save_manifest(&manifest, &config.output_dir, format);
+            }
+            return;
+        }
+
+        // Puzzle n of the run (counting from 0) uses seed master_seed + n
+        let mut jobs = Vec::new();
+        for (task, task_generator) in config.tasks.iter().zip(&task_generators) {
+            let task_generator: &(dyn generator::Generator + Sync) = match task_generator {
+                Some(task_generator) => task_generator,
+                None => generator.as_ref(),
+            };
+            let constraints = generator::GenerationConstraints {
+                loop_length: task.loop_length,
+                max_attempts: task.max_attempts.unwrap_or(constraints.max_attempts),
+                ..constraints
+            };
+            for _ in 0..task.count {
+                jobs.push(generator::batch::Job {
+                    generator: task_generator,
+                    radius: task.radius,
+                    difficulty: task.difficulty,
+                    constraints,
+                    seed: master_seed.wrapping_add(jobs.len() as u64),
+                });
+            }
+        }
+        let planned = plan_saves(
+            &config.tasks,
+            &jobs,
+            &config.output_dir,
+            &save,
+            config.to_stdout,
         );
+        // Canonical forms of every puzzle kept so far in this run. Puzzles skipped as already
+        // saved count too, so a rerun regenerates the same puzzles as the first run did.
+        let mut seen_puzzles = HashSet::new();
+        for plan in planned.iter().filter(|plan| plan.skip) {
+            let path = plan
+                .dir
+                .join(format!("{}.{}", plan.stem, config.format.extension()));
+            match io::load_puzzle(&path) {
+                Ok(map) => {
+                    seen_puzzles.insert(map.canonical_form());
+                }
+                Err(e) => eprintln!("Failed to load {:?} to check for duplicates: {}", path, e),
+            }
+        }
+        let to_run: Vec<generator::batch::Job> = jobs
+            .iter()
+            .zip(&planned)
+            .filter(|(_, plan)| !plan.skip)
+            .map(|(job, _)| job.clone())
+            .collect();
 
-        let generator: Box<dyn generator::Generator> = match config.strategy {
-            args::Strategy::Dummy => Box::new(generator::DummyGenerator),
+        let progress: Box<dyn generator::GeneratorProgress> = if config.progress {
+            Box::new(generator::ConsoleProgress::new(to_run.len() as u32))
+        } else {
+            Box::new(generator::NoProgress)
         };
+        let mut results =
+            generator::batch::run_jobs(&to_run, config.threads, progress.as_ref()).into_iter();
+        if config.progress {
+            eprintln!();
+        }
+        let mut jobs = jobs.into_iter().zip(planned);
 
         let mut displayed_first_map = false;
+        // (radius, difficulty, generated, failed), in the order the tasks were listed
+        let mut summary: Vec<(u8, args::Difficulty, u32, u32)> = Vec::new();
+        let mut tuning = Vec::new();
+        // Every puzzle saved, in order, for --pack
+        let mut packed = Vec::new();
+        let mut manifest = Vec::new();
 
-        for (radius, difficulty) in config.tasks {
-            for i in 0..config.count_per_task {
-                let map = generator.generate(radius, difficulty);
+        for task in config.tasks {
+            let (radius, difficulty) = (task.radius, task.difficulty);
+            let entry = match summary
+                .iter()
+                .position(|&(r, d, _, _)| r == radius && d == difficulty)
+            {
+                Some(index) => index,
+                None => {
+                    summary.push((radius, difficulty, 0, 0));
+                    summary.len() - 1
+                }
+            };
+            let (generated_before, failed_before) = (summary[entry].2, summary[entry].3);
+            let mut stats = generator::progress::AttemptStats::default();
+            // "<filename> <seed>" lines, so any single puzzle can be regenerated later
+            let mut seed_log = String::new();
+            let mut metadata = Vec::new();
+            let mut save_dir = None;
+            for i in 0..task.count {
+                let Some((job, PlannedSave { dir, stem, skip })) = jobs.next() else {
+                    break;
+                };
+                if skip {
+                    continue;
+                }
+                let Some(result) = results.next() else {
+                    break;
+                };
+                let seed = job.seed;
+                let mut rng = result.rng;
+                // Duplicates are regenerated from the same RNG, so the seed still reproduces the
+                // result. This runs in job order, so parallel output matches a serial run.
+                let mut retries = 0;
+                let mut generated = result.map;
+                stats.add(&result.stats);
+                let map = loop {
+                    let map = match generated {
+                        Ok(map) => map,
+                        Err(e) => {
+                            eprintln!(
+                                "Failed to generate puzzle {} (radius {}, {}): {}",
+                                i, radius, difficulty, e
+                            );
+                            break None;
+                        }
+                    };
+                    if config.allow_duplicates || seen_puzzles.insert(map.canonical_form()) {
+                        break Some(map);
+                    }
+                    stats.reject("duplicate");
+                    retries += 1;
+                    if retries >= job.constraints.max_attempts {
+                        eprintln!(
+                            "Puzzle {} (radius {}, {}) still repeats an earlier puzzle after {} retries; keeping it",
+                            i, radius, difficulty, retries
+                        );
+                        break Some(map);
+                    }
+                    let tally = generator::progress::Tally::new(&generator::NoProgress);
+                    generated = generator::generate_constrained(
+                        job.generator,
+                        radius,
+                        difficulty,
+                        &job.constraints,
+                        &tally,
+                        &mut rng,
+                    );
+                    stats.add(&tally.into_stats());
+                };
+                let Some(mut map) = map else {
+                    summary[entry].3 += 1;
+                    continue;
+                };
+                if map.timed_out {
+                    eprintln!(
+                        "Puzzle {} (radius {}, {}) timed out; keeping the clues hidden so far",
+                        i, radius, difficulty
+                    );
+                }
 
                 if config.display && !displayed_first_map {
                     web_viewer::show_map(&map);
                     displayed_first_map = true;
-                    if task_count > 1 || config.count_per_task > 1 {
+                    if total_count > 1 {
                         println!("(Displaying only the first generated map)");
                     }
                 }
 
+                if config.to_stdout {
+                    map.meta = Some(puzzle_meta(
+                        &map,
+                        seed,
+                        &algorithm,
+                        difficulty,
+                        None,
+                        save.timestamp,
+                    ));
+                    summary[entry].2 += 1;
+                    packed.push(if config.strip_answers {
+                        map.without_answer()
+                    } else {
+                        map
+                    });
+                    continue;
+                }
+
                 // Save
-                // Determine size folder name
-                let size_str = match radius {
-                    2 => "small",
-                    4 => "medium",
-                    8 => "large",
-                    11 => "huge",
-                    _ => "custom", // Fallback for custom radii
-                };
-                let size_dir = if size_str == "custom" {
-                    format!("radius_{}", radius)
+                if let Err(e) = std::fs::create_dir_all(&dir) {
+                    eprintln!("Failed to create directory {:?}: {}", dir, e);
+                    summary[entry].3 += 1;
+                    continue;
+                }
+                // A file that turned up since the check before generating
+                let stem =
+                    match claim_name(&dir, stem, config.format.extension(), config.on_conflict) {
+                        Ok(Some(stem)) => stem,
+                        Ok(None) => continue,
+                        Err(e) => {
+                            eprintln!(
+                                "Puzzle {} (radius {}, {}) not saved: {}",
+                                i, radius, difficulty, e
+                            );
+                            summary[entry].3 += 1;
+                            continue;
+                        }
+                    };
+
+                // The manifest reports the hardest technique, which takes a trace to find
+                let steps = if config.trace || config.manifest.is_some() {
+                    let steps = generator::DifficultyRater::default().trace(&map);
+                    if steps.is_none() && config.trace {
+                        eprintln!(
+                            "Puzzle {} (radius {}, {}) can't be solved within the trial depth limit; no trace saved",
+                            i, radius, difficulty
+                        );
+                    }
+                    steps
                 } else {
-                    size_str.to_string()
+                    None
                 };
+                // A puzzle solved without any steps had every cell given away by its markers
+                let hardest = steps.as_ref().map(|steps| {
+                    steps
+                        .iter()
+                        .map(|s| s.technique)
+                        .max()
+                        .unwrap_or(generator::Technique::ZeroSix)
+                });
+                map.meta = Some(puzzle_meta(
+                    &map,
+                    seed,
+                    &algorithm,
+                    difficulty,
+                    hardest,
+                    save.timestamp,
+                ));
 
-                let diff_str = difficulty.to_string(); // Difficulty implements Display
-
-                let save_dir = config.output_dir.join(&size_dir).join(&diff_str);
-                if let Err(e) = std::fs::create_dir_all(&save_dir) {
-                    eprintln!("Failed to create directory {:?}: {}", save_dir, e);
+                let inside_fraction = map.inside_fraction();
+                let Some(filename) = save_puzzle(
+                    map,
+                    &dir,
+                    &stem,
+                    steps.as_deref(),
+                    &save,
+                    &mut manifest,
+                    &mut packed,
+                ) else {
+                    summary[entry].3 += 1;
                     continue;
+                };
+                summary[entry].2 += 1;
+                let _ = writeln!(seed_log, "{} {}", filename, seed);
+                metadata.push(io::PuzzleMetadata {
+                    file: filename,
+                    seed,
+                    fill_ratio: task.fill_ratio,
+                    inside_fraction,
+                });
+                save_dir = Some(dir);
+            }
+
+            if let Some(dir) = save_dir {
+                save_folder_logs(&dir, &seed_log, metadata);
+            }
+            tuning.push(io::TaskTuning {
+                radius,
+                difficulty: difficulty.to_string(),
+                fill_ratio: task.fill_ratio,
+                count: task.count,
+                generated: summary[entry].2 - generated_before,
+                failed: summary[entry].3 - failed_before,
+                stats,
+            });
+        }
+
+        if let Some(path) = &config.pack {
+            save_pack(&packed, path, config.to_stdout);
+        }
+        if config.to_stdout {
+            let stdout = std::io::stdout().lock();
+            let written = match config.format {
+                args::OutputFormat::BinaryFull
+                | args::OutputFormat::Svg
+                | args::OutputFormat::Qr => io::write_pack(&packed, stdout),
+                args::OutputFormat::Html => std::io::Write::write_all(
+                    &mut { stdout },
+                    web_viewer::viewer_html(&packed, false).as_bytes(),
+                )
+                .map_err(error::SlitherError::from),
+                args::OutputFormat::Json => io::write_maps_json(&packed, stdout),
+                args::OutputFormat::Text => {
+                    unreachable!("--format text can't be combined with --output -")
                 }
+            };
+            if let Err(e) = written {
+                eprintln!("Failed to write puzzles to stdout: {}", e);
+            }
+        }
+
+        if let Some(format) = config.manifest {
+            save_manifest(&manifest, &config.output_dir, format);
+        }
+        if config.tuning_report {
+            let path = config.output_dir.join("tuning_report.json");
+            match io::save_tuning_report(&tuning, &path) {
+                Ok(()) => println!("Wrote tuning report to {:?}", path),
+                Err(e) => eprintln!("Failed to write tuning report to {:?}: {}", path, e),
+            }
+        }
+
+        status!(config.to_stdout, "");
+        status!(
+            config.to_stdout,
+            "{:>6}  {:<12} {:>9} {:>6}",
+            "radius",
+            "difficulty",
+            "generated",
+            "failed"
+        );
+        for (radius, difficulty, generated, failed) in summary {
+            status!(
+                config.to_stdout,
+                "{:>6}  {:<12} {:>9} {:>6}",
+                radius,
+                difficulty.to_string(),
+                generated,
+                failed
+            );
+        }
+    }
+}
+
+/// Output folder of a task's puzzles
+fn task_dir(output_dir: &Path, task: &args::Task) -> PathBuf {
+    // Difficulty implements Display; custom densities become e.g. "density_0.45"
+    let mut dir = output_dir
+        .join(size_dir(task.radius))
+        .join(task.difficulty.to_string());
+    if let Some(fill_ratio) = task.fill_ratio {
+        dir = dir.join(format!("fill_{}", fill_ratio));
+    }
+    dir
+}
+
+/// Where one job's puzzle is to be saved
+struct PlannedSave {
+    dir: PathBuf,
+    stem: String,
+    /// The file is there already and `--on-conflict skip` leaves it be
+    skip: bool,
+}
+
+/// The folder and name of every job's puzzle, planned before generating anything so files
+/// already there can be dealt with as `--on-conflict` says. Exits if the policy is to stop at
+/// them.
+fn plan_saves(
+    tasks: &[args::Task],
+    jobs: &[generator::batch::Job],
+    output_dir: &Path,
+    options: &SaveOptions,
+    to_stdout: bool,
+) -> Vec<PlannedSave> {
+    let planned: Vec<(PathBuf, String)> = tasks
+        .iter()
+        .flat_map(|task| {
+            let dir = task_dir(output_dir, task);
+            (0..task.count as usize).map(move |i| (dir.clone(), i))
+        })
+        .zip(jobs)
+        .map(|((dir, i), job)| {
+            let stem = options.names.stem(&args::NameFields {
+                size: &size_dir(job.radius),
+                radius: job.radius,
+                difficulty: &job.difficulty.to_string(),
+                seed: job.seed,
+                index: i,
+            });
+            (dir, stem)
+        })
+        .collect();
+    let extension = options.format.extension();
+    let existing: Vec<bool> = planned
+        .iter()
+        .map(|(dir, stem)| !to_stdout && dir.join(format!("{}.{}", stem, extension)).exists())
+        .collect();
+    let taken = existing.iter().filter(|&&exists| exists).count();
+    let skipped = match options.on_conflict {
+        args::OnConflict::Error if taken > 0 => {
+            let (dir, stem) = &planned[existing.iter().position(|&e| e).unwrap_or(0)];
+            eprintln!(
+                "{} of the puzzles to generate already exist, such as {:?}; pass --on-conflict skip, overwrite or suffix to choose what to do with them",
+                taken,
+                dir.join(format!("{}.{}", stem, extension))
+            );
+            std::process::exit(1);
+        }
+        args::OnConflict::Skip => existing,
+        _ => vec![false; planned.len()],
+    };
+    if options.on_conflict == args::OnConflict::Skip && taken > 0 {
+        status!(to_stdout, "Skipping {} puzzles that already exist", taken);
+    }
+    planned
+        .into_iter()
+        .zip(skipped)
+        .map(|((dir, stem), skip)| PlannedSave { dir, stem, skip })
+        .collect()
+}
+
+/// The name to save puzzle `stem` under in `dir`, given the files already there: `None` to
+/// leave the one there alone, or an error if the policy is to stop
+fn claim_name(
+    dir: &Path,
+    stem: String,
+    extension: &str,
+    policy: args::OnConflict,
+) -> Result<Option<String>, String> {
+    let path = |stem: &str| dir.join(format!("{}.{}", stem, extension));
+    if !path(&stem).exists() {
+        return Ok(Some(stem));
+    }
+    match policy {
+        args::OnConflict::Error => Err(format!("{:?} already exists", path(&stem))),
+        args::OnConflict::Skip => Ok(None),
+        args::OnConflict::Overwrite => Ok(Some(stem)),
+        args::OnConflict::Suffix => Ok((1..)
+            .map(|n| format!("{}-{}", stem, n))
+            .find(|stem| !path(stem).exists())),
+    }
+}
+
+/// Write a folder's seeds.txt and metadata.json, keeping the lines and entries of puzzles saved
+/// there before and not replaced by this run
+fn save_folder_logs(dir: &Path, seed_log: &str, metadata: Vec<io::PuzzleMetadata>) {
+    let saved: HashSet<String> = metadata.iter().map(|entry| entry.file.clone()).collect();
+
+    let path = dir.join("seeds.txt");
+    let mut seeds: String = std::fs::read_to_string(&path)
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| {
+            line.split(' ')
+                .next()
+                .is_some_and(|file| !saved.contains(file))
+        })
+        .map(|line| format!("{}\n", line))
+        .collect();
+    seeds.push_str(seed_log);
+    if let Err(e) = std::fs::write(&path, seeds) {
+        eprintln!("Failed to write seeds to {:?}: {}", path, e);
+    }
+
+    let path = dir.join("metadata.json");
+    let mut entries: Vec<io::PuzzleMetadata> = io::load_metadata(&path)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|entry| !saved.contains(&entry.file))
+        .collect();
+    entries.extend(metadata);
+    if let Err(e) = io::save_metadata(&entries, &path) {
+        eprintln!("Failed to write metadata to {:?}: {}", path, e);
+    }
+}
+
+/// Output folder name for a board size
+fn size_dir(radius: u8) -> String {
+    match radius {
+        2 => "small".to_string(),
+        4 => "medium".to_string(),
+        8 => "large".to_string(),
+        11 => "huge".to_string(),
+        _ => format!("radius_{}", radius), // Fallback for custom radii
+    }
+}
+
+/// How generated puzzles are saved, as chosen on the command line
+struct SaveOptions<'a> {
+    format: args::OutputFormat,
+    encoding: io::MapEncoding,
+    trace: bool,
+    player_files: bool,
+    strip_answers: bool,
+    svg_solution: bool,
+    qr_prefix: Option<&'a str>,
+    pack: Option<&'a Path>,
+    names: &'a args::NameTemplate,
+    on_conflict: args::OnConflict,
+    /// Record when each puzzle was made; left out with `--seed`, so a seeded run saves the
+    /// same bytes every time
+    timestamp: bool,
+}
+
+/// Save a generated puzzle to `dir` as `stem`, with the companion files `options` ask for,
+/// and add it to `manifest` and, for `--pack`, to `packed`. `steps` is its solving trace, if
+/// one was taken. Returns the puzzle's file name, or `None` if it couldn't be saved.
+fn save_puzzle(
+    map: model::Map,
+    dir: &Path,
+    stem: &str,
+    steps: Option<&[generator::rating::Deduction]>,
+    options: &SaveOptions,
+    manifest: &mut Vec<io::ManifestEntry>,
+    packed: &mut Vec<model::Map>,
+) -> Option<String> {
+    let filename = format!("{}.{}", stem, options.format.extension());
+    let path = dir.join(&filename);
+    // The manifest and metadata.json stay with the run, so they keep the answer
+    let published = options.strip_answers.then(|| map.without_answer());
+    let saved = published.as_ref().unwrap_or(&map);
+    if let Err(e) = io::save_map(saved, &path, options.encoding) {
+        eprintln!("Failed to save map to {:?}: {}", path, e);
+        return None;
+    }
+    manifest.push(io::ManifestEntry::new(path, &map));
+    if options.player_files {
+        save_player_file(&map, dir, stem);
+    }
+    match options.format {
+        args::OutputFormat::Svg => save_svg_files(saved, dir, stem, options.svg_solution),
+        args::OutputFormat::Qr => save_qr_file(&map, dir, stem, options.qr_prefix),
+        args::OutputFormat::Html => save_html_file(saved, dir, stem),
+        _ => {}
+    }
+    if options.trace
+        && let Some(steps) = steps
+    {
+        let path = dir.join(format!("{}.trace.json", stem));
+        if let Err(e) = io::save_trace(steps, &path) {
+            eprintln!("Failed to save trace to {:?}: {}", path, e);
+        }
+    }
+    if options.pack.is_some() {
+        packed.push(published.unwrap_or(map));
+    }
+    Some(filename)
+}
+
+/// Generate the candidate pool, then save the `count` puzzles picked for the difficulty ramp
+/// to `dir`, named by `options.names` with indices from 0 (0.bin, 1.bin, ... by default) from
+/// easiest to hardest. Candidates that fail, repeat another candidate or can't be scored are
+/// left out, and files already in `dir` are dealt with as `options.on_conflict` says. Returns
+/// the manifest rows of the puzzles saved.
+fn run_curriculum(
+    pool: &[generator::batch::Job],
+    count: usize,
+    algorithm: &str,
+    dir: &Path,
+    threads: usize,
+    show_progress: bool,
+    options: &SaveOptions,
+) -> Vec<io::ManifestEntry> {
+    // The names depend on the puzzles picked, so short of those, a folder holding any puzzle
+    // already is a conflict
+    let extension = format!(".{}", options.format.extension());
+    let existing = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .find(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.ends_with(&extension))
+        });
+    if let (args::OnConflict::Error, Some(entry)) = (options.on_conflict, existing) {
+        eprintln!(
+            "{:?} already holds puzzles, such as {:?}; pass --on-conflict skip, overwrite or suffix to choose what to do with them",
+            dir,
+            entry.file_name()
+        );
+        std::process::exit(1);
+    }
+
+    let progress: Box<dyn generator::GeneratorProgress> = if show_progress {
+        Box::new(generator::ConsoleProgress::new(pool.len() as u32))
+    } else {
+        Box::new(generator::NoProgress)
+    };
+    let results = generator::batch::run_jobs(pool, threads, progress.as_ref());
+    if show_progress {
+        eprintln!();
+    }
+
+    let rater = generator::DifficultyRater::default();
+    let mut seen_puzzles = HashSet::new();
+    let mut failed = 0;
+    let mut candidates = Vec::new();
+    for (result, job) in results.into_iter().zip(pool) {
+        let Ok(map) = result.map else {
+            failed += 1;
+            continue;
+        };
+        if !seen_puzzles.insert(map.canonical_form()) {
+            continue;
+        }
+        if let Some(score) = rater.score(&map) {
+            candidates.push((score, (map, job)));
+        }
+    }
+    if failed > 0 {
+        eprintln!("{} of {} candidates failed to generate", failed, pool.len());
+    }
+    let picked = match generator::curriculum::select(candidates, count) {
+        Ok(picked) => picked,
+        Err(e) => {
+            eprintln!("Failed to build a curriculum of {} puzzles: {}", count, e);
+            return Vec::new();
+        }
+    };
+
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        eprintln!("Failed to create directory {:?}: {}", dir, e);
+        return Vec::new();
+    }
+    // "<filename> <seed> <difficulty>" lines: each puzzle is regenerated from its seed at the
+    // difficulty it was drawn for
+    let mut seed_log = String::new();
+    let mut metadata = Vec::new();
+    let mut packed = Vec::new();
+    let mut manifest = Vec::new();
+    for (i, (score, (mut map, job))) in picked.into_iter().enumerate() {
+        map.meta = Some(puzzle_meta(
+            &map,
+            job.seed,
+            algorithm,
+            job.difficulty,
+            Some(score.hardest),
+            options.timestamp,
+        ));
+        let stem = options.names.stem(&args::NameFields {
+            size: &size_dir(job.radius),
+            radius: job.radius,
+            difficulty: &job.difficulty.to_string(),
+            seed: job.seed,
+            index: i,
+        });
+        let stem = match claim_name(dir, stem, options.format.extension(), options.on_conflict) {
+            Ok(Some(stem)) => stem,
+            Ok(None) => unreachable!("--on-conflict skip can't be combined with --curriculum"),
+            Err(e) => {
+                eprintln!("Puzzle {} not saved: {}", i, e);
+                continue;
+            }
+        };
+        let steps = if options.trace {
+            rater.trace(&map)
+        } else {
+            None
+        };
+        let inside_fraction = map.inside_fraction();
+        let Some(filename) = save_puzzle(
+            map,
+            dir,
+            &stem,
+            steps.as_deref(),
+            options,
+            &mut manifest,
+            &mut packed,
+        ) else {
+            continue;
+        };
+        println!("{:>3}  {}  ({})", i, score, job.difficulty);
+        let _ = writeln!(seed_log, "{} {} {}", filename, job.seed, job.difficulty);
+        metadata.push(io::PuzzleMetadata {
+            file: filename,
+            seed: job.seed,
+            fill_ratio: None,
+            inside_fraction,
+        });
+    }
 
-                let filename = format!("{}.bin", i);
-                let path = save_dir.join(filename);
-                if let Err(e) = io::save_map(&map, &path, config.legacy) {
-                    eprintln!("Failed to save map to {:?}: {}", path, e);
+    save_folder_logs(dir, &seed_log, metadata);
+    if let Some(path) = options.pack {
+        save_pack(&packed, path, false);
+    }
+    manifest
+}
+
+/// Solve a saved puzzle from its visible clues, ignoring the answer stored with it, and report
+/// whether the solution is unique. The solved map (the first, if there are several) can be
+/// saved, and every solution found saved for debugging or displayed side by side. Exits with
+/// status 1 if the map can't be loaded, 2 if it has no solution and 3 if it has several.
+fn solve_puzzle(args: args::SolveArgs) {
+    let map = match io::load_puzzle(&args.file) {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("Failed to load map: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("Solving map with radius {}", map.radius);
+
+    let solutions = solver::solve(&map.view(), args.solutions.max(2));
+    match solutions.len() {
+        0 => {
+            println!("No solution: the clues contradict each other");
+            std::process::exit(2);
+        }
+        1 => println!("Uniquely solvable"),
+        n => println!(
+            "Not uniquely solvable: {}{} answers fit the clues",
+            if n == args.solutions.max(2) {
+                "at least "
+            } else {
+                ""
+            },
+            n
+        ),
+    }
+
+    if let Some(dir) = &args.trace_dir {
+        if let Err(e) = save_solutions(&solutions, Path::new(dir)) {
+            eprintln!("Failed to save solutions to {}: {}", dir, e);
+        } else {
+            println!("Wrote {} solutions to {}", solutions.len(), dir);
+        }
+    }
+    let mut answers: Vec<model::Map> = solutions.into_iter().map(|s| s.answer).collect();
+    for answer in &mut answers {
+        answer.level = map.level;
+        answer.meta = map.meta.clone();
+    }
+    if let Some(path) = args.output {
+        match io::save_map(&answers[0], &path, io::MapEncoding::Standard) {
+            Ok(()) => println!("Wrote solved map to {}", path),
+            Err(e) => eprintln!("Failed to save solved map to {}: {}", path, e),
+        }
+    }
+    if args.display {
+        web_viewer::show_maps(&answers);
+    }
+    if answers.len() > 1 {
+        std::process::exit(3);
+    }
+}
+
+/// How a generated puzzle was made, with the hardest technique it needs if it was rated, and
+/// when it was made if `timestamp` is set
+fn puzzle_meta(
+    map: &model::Map,
+    seed: u64,
+    algorithm: &str,
+    difficulty: args::Difficulty,
+    hardest: Option<generator::Technique>,
+    timestamp: bool,
+) -> model::MapMeta {
+    let created_at = timestamp
+        .then(|| SystemTime::now().duration_since(UNIX_EPOCH).ok())
+        .flatten()
+        .map(|elapsed| elapsed.as_secs());
+    model::MapMeta {
+        seed: Some(seed),
+        algorithm: Some(algorithm.to_string()),
+        requested_difficulty: Some(difficulty.to_string()),
+        measured_difficulty: hardest.map(|technique| technique.name()),
+        loop_length: Some(generator::loop_length(map)),
+        created_at,
+    }
+}
+
+/// Save the manifest of a run to `dir`, reporting the outcome
+fn save_manifest(entries: &[io::ManifestEntry], dir: &Path, format: io::ManifestFormat) {
+    let path = dir.join(format.file_name());
+    match io::save_manifest(entries, &path, format) {
+        Ok(()) => println!("Wrote manifest of {} puzzles to {:?}", entries.len(), path),
+        Err(e) => eprintln!("Failed to write manifest to {:?}: {}", path, e),
+    }
+}
+
+/// Save the puzzles of a run to one pack file, reporting the outcome
+fn save_pack(maps: &[model::Map], path: &Path, to_stdout: bool) {
+    match io::save_pack(maps, path) {
+        Ok(()) => status!(to_stdout, "Packed {} puzzles into {:?}", maps.len(), path),
+        Err(e) => eprintln!("Failed to save pack to {:?}: {}", path, e),
+    }
+}
+
+/// Save puzzle `stem` without its answer to `dir` as <stem>.puzzle.json, next to the full map
+fn save_player_file(map: &model::Map, dir: &Path, stem: &str) {
+    let path = dir.join(format!("{}.puzzle.json", stem));
+    if let Err(e) = io::save_map(&map.without_answer(), &path, io::MapEncoding::Standard) {
+        eprintln!("Failed to save player file to {:?}: {}", path, e);
+    }
+}
+
+/// Draw puzzle `stem` to `dir` as <stem>.svg, and with `solution` its answer as
+/// <stem>.solution.svg
+fn save_svg_files(map: &model::Map, dir: &Path, stem: &str, solution: bool) {
+    let mut drawings = vec![(format!("{}.svg", stem), false)];
+    if solution {
+        drawings.push((format!("{}.solution.svg", stem), true));
+    }
+    for (filename, solution) in drawings {
+        let path = dir.join(filename);
+        if let Err(e) = svg::save_svg(map, &path, solution) {
+            eprintln!("Failed to save drawing to {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Save a QR code of the share code of `map`, after `prefix`, to `<stem>.qr.svg` in `dir`
+fn save_qr_file(map: &model::Map, dir: &Path, stem: &str, prefix: Option<&str>) {
+    let path = dir.join(format!("{}.qr.svg", stem));
+    let saved = io::share_code(map)
+        .and_then(|code| qr::QrCode::encode(format!("{}{}", prefix.unwrap_or(""), code).as_bytes()))
+        .and_then(|qr| Ok(std::fs::write(&path, qr.to_svg())?));
+    if let Err(e) = saved {
+        eprintln!("Failed to save QR code to {:?}: {}", path, e);
+    }
+}
+
+/// Save a standalone viewer page of `map`, with the answer hidden at first, to `<stem>.html`
+/// in `dir`
+fn save_html_file(map: &model::Map, dir: &Path, stem: &str) {
+    let path = dir.join(format!("{}.html", stem));
+    let html = web_viewer::viewer_html(std::slice::from_ref(map), false);
+    if let Err(e) = io::write_atomic(&path, html.as_bytes()) {
+        eprintln!("Failed to save page to {:?}: {}", path, e);
+    }
+}
+
+/// Save every solution of a puzzle to `dir` for debugging, as maps and as loop edge lists
+fn save_solutions(solutions: &[solver::Solution], dir: &Path) -> Result<(), error::SlitherError> {
+    std::fs::create_dir_all(dir)?;
+    for (i, solution) in solutions.iter().enumerate() {
+        io::save_map(
+            &solution.answer,
+            dir.join(format!("solution_{}.bin", i)),
+            io::MapEncoding::Standard,
+        )?;
+    }
+    Ok(io::save_solution_loops(
+        solutions,
+        dir.join("solutions.json"),
+    )?)
+}
+
+/// Check every saved puzzle under a folder and print a pass/fail line for each. Exits with
+/// status 1 if any puzzle fails, or if there are none to check.
+fn verify_dir(args: args::VerifyArgs) {
+    let dir = Path::new(&args.dir);
+    let reports = match verify::verify_dir(dir) {
+        Ok(reports) => reports,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", args.dir, e);
+            std::process::exit(1);
+        }
+    };
+    if reports.is_empty() {
+        eprintln!(
+            "No .bin, .map.json, .map.txt or .slpack files found in {}",
+            args.dir
+        );
+        std::process::exit(1);
+    }
+
+    let mut failed = 0;
+    for report in &reports {
+        let name = report.path.strip_prefix(dir).unwrap_or(&report.path);
+        match &report.result {
+            Ok(()) => println!("PASS {}", name.display()),
+            Err(e) => {
+                failed += 1;
+                println!("FAIL {}: {}", name.display(), e);
+            }
+        }
+    }
+    println!("{} passed, {} failed", reports.len() - failed, failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Print the share code of the map in `args.file`
+fn encode_map(args: args::EncodeArgs) {
+    match io::load_map(&args.file).and_then(|map| io::share_code(&map)) {
+        Ok(code) => println!("{}", code),
+        Err(e) => {
+            eprintln!("Failed to encode {}: {}", args.file, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Open a puzzle, or a save file of one part way solved, in the viewer to play
+fn play_puzzle(args: args::PlayArgs) {
+    match io::load_progress(&args.file) {
+        Ok((map, player)) => {
+            println!(
+                "Loaded puzzle with radius {}, {} edges marked",
+                map.radius,
+                player.marks.len()
+            );
+            web_viewer::show_progress(&map, &player);
+        }
+        Err(e) => {
+            eprintln!("Failed to load {}: {}", args.file, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Turn `args.code` back into a map, and save or print it. Exits with status 1 if the code
+/// can't be decoded or the map written.
+fn decode_map(args: args::DecodeArgs) {
+    let map = match io::from_share_code(&args.code) {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("Failed to decode share code: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let written = match &args.output {
+        Some(path) => io::save_map(&map, path, io::MapEncoding::Standard),
+        None => io::write_map_text(&map, std::io::stdout().lock()),
+    };
+    if let Err(e) = written {
+        eprintln!("Failed to write map: {}", e);
+        std::process::exit(1);
+    }
+    if args.display {
+        web_viewer::show_map(&map);
+    }
+}
+
+/// Rewrite the binary maps at `args.path` in the layout `args.to`, in place or under
+/// `args.output`. Files already in that layout are left alone.
+fn convert_maps(args: args::ConvertArgs) {
+    let root = Path::new(&args.path);
+    let files: Vec<PathBuf> = if root.is_dir() {
+        match verify::find_maps(root) {
+            Ok(files) => files
+                .into_iter()
+                .filter(|path| path.extension().is_some_and(|ext| ext == "bin"))
+                .collect(),
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", args.path, e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        vec![root.to_path_buf()]
+    };
+    if files.is_empty() {
+        eprintln!("No .bin files found in {}", args.path);
+        std::process::exit(1);
+    }
+
+    let (mut converted, mut unchanged, mut skipped, mut failed) = (0, 0, 0, 0);
+    for path in &files {
+        // Relative to the folder converted, or just the file name when given one file
+        let name = match path.strip_prefix(root) {
+            Ok(name) if !name.as_os_str().is_empty() => name,
+            _ => Path::new(path.file_name().unwrap_or(path.as_os_str())),
+        };
+        let target = match &args.output {
+            Some(dir) => Path::new(dir).join(name),
+            None => path.clone(),
+        };
+        let result = std::fs::read(path)
+            .map_err(error::SlitherError::from)
+            .and_then(|bytes| {
+                let version = io::map_version(&bytes)?;
+                if args.from.is_some_and(|from| from != version) {
+                    return Ok(None);
+                }
+                let rewritten = io::convert_map(&bytes, args.to)?;
+                if rewritten == bytes && args.output.is_none() {
+                    return Ok(Some((version, false)));
+                }
+                if let Some(dir) = target.parent() {
+                    std::fs::create_dir_all(dir)?;
                 }
+                io::write_atomic(&target, &rewritten)?;
+                Ok(Some((version, true)))
+            });
+        match result {
+            Ok(None) => skipped += 1,
+            Ok(Some((_, false))) => unchanged += 1,
+            Ok(Some((version, true))) => {
+                converted += 1;
+                println!("CONVERTED {} (from {})", name.display(), version);
+            }
+            Err(e) => {
+                failed += 1;
+                println!("FAIL {}: {}", name.display(), e);
+            }
+        }
+    }
+    println!(
+        "{} converted, {} already in that layout, {} in other layouts, {} failed",
+        converted, unchanged, skipped, failed
+    );
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Import the puzzle given by `args.source` and save it to `args.output`. Exits with status 1
+/// if it can't be read or saved.
+fn import_puzzle(args: args::ImportArgs) {
+    let source = if Path::new(&args.source).is_file() {
+        match std::fs::read_to_string(&args.source) {
+            Ok(contents) => contents,
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", args.source, e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        args.source
+    };
+    let map = match io::import(&source) {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("Failed to import puzzle: {}", e);
+            std::process::exit(1);
+        }
+    };
+    match io::save_map(&map, &args.output, io::MapEncoding::Standard) {
+        Ok(()) => println!(
+            "Imported a puzzle of {} cells to {}",
+            map.iter_coords().count(),
+            args.output
+        ),
+        Err(e) => {
+            eprintln!("Failed to save map to {}: {}", args.output, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Lay the puzzles at `args.path` out as print sheets. Exits with status 1 if any file can't be
+/// loaded, once the rest are printed, or if nothing could be printed.
+fn print_puzzles(args: args::PrintArgs) {
+    let path = Path::new(&args.path);
+    let files = if path.is_dir() {
+        match verify::find_maps(path) {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", args.path, e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut maps = Vec::new();
+    let mut skipped = 0;
+    for file in &files {
+        match io::load_puzzles(file) {
+            Ok(loaded) => maps.extend(loaded),
+            Err(e) => {
+                eprintln!("Skipping {}: {}", file.display(), e);
+                skipped += 1;
+            }
+        }
+    }
+    if maps.is_empty() {
+        eprintln!("No puzzles to print in {}", args.path);
+        std::process::exit(1);
+    }
+
+    let layout = pdf::PrintLayout {
+        paper: args.paper,
+        per_page: args.per_page,
+        title: args.title,
+    };
+    let written =
+        pdf::print_sheets(&maps, &layout).and_then(|pdf| Ok(std::fs::write(&args.output, pdf)?));
+    match written {
+        Ok(()) => println!("Printed {} puzzles to {}", maps.len(), args.output),
+        Err(e) => {
+            eprintln!("Failed to print to {}: {}", args.output, e);
+            std::process::exit(1);
+        }
+    }
+    if skipped > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Rate a saved puzzle, or every one under a folder, and print the difficulty it fits best
+/// along with its score. Exits with status 1 if any file can't be loaded, once the rest are
+/// rated.
+fn rate_puzzles(args: args::RateArgs) {
+    let path = Path::new(&args.path);
+    let files = if path.is_dir() {
+        match verify::find_maps(path) {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", args.path, e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let rater = generator::DifficultyRater::default();
+    let mut unreadable = 0;
+    for file in &files {
+        let name = file
+            .strip_prefix(path)
+            .ok()
+            .filter(|name| !name.as_os_str().is_empty())
+            .unwrap_or(file);
+        let maps = match io::load_puzzles(file) {
+            Ok(maps) => maps,
+            Err(e) => {
+                println!("{}: unreadable: {}", name.display(), e);
+                unreadable += 1;
+                continue;
+            }
+        };
+        for (i, map) in maps.iter().enumerate() {
+            // Puzzles of a pack are named by their position in it
+            let name = if io::is_pack(file) {
+                format!("{}#{}", name.display(), i)
+            } else {
+                name.display().to_string()
+            };
+            match rater.score(map) {
+                Some(score) => println!("{}: {} ({})", name, rater.estimate(score.hardest), score),
+                None => println!(
+                    "{}: unrated, not solvable with trials up to depth {}",
+                    name, rater.max_trial_depth
+                ),
+            }
+        }
+    }
+    if unreadable > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Print every cell where two saved maps differ. Exits with status 1 if either can't be
+/// loaded.
+fn compare_maps(args: args::CompareArgs) {
+    let mut maps = Vec::new();
+    for file in [&args.first, &args.second] {
+        match io::load_puzzle(file) {
+            Ok(map) => maps.push(map),
+            Err(e) => {
+                eprintln!("Failed to load {}: {}", file, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let diffs = maps[0].diff(&maps[1]);
+    for diff in &diffs {
+        println!("{}", diff);
+    }
+    match diffs.len() {
+        0 => println!("The maps have the same cells"),
+        n => println!("{} cells differ", n),
+    }
+}
+
+/// Time the solver backends on a corpus of puzzles per board size and print a table of the
+/// results
+fn bench_solvers(args: args::BenchArgs) {
+    let sizes = if args.size.is_empty() {
+        vec![
+            args::GridSize::Small,
+            args::GridSize::Medium,
+            args::GridSize::Large,
+        ]
+    } else {
+        args.size
+    };
+    let backends = if args.solver.is_empty() {
+        generator::SolverBackend::all().to_vec()
+    } else {
+        args.solver
+    };
+
+    println!(
+        "{:<8} {:<13} {:>7} {:>12} {:>12} {:>12}",
+        "size", "solver", "puzzles", "mean", "max", "nodes"
+    );
+    for size in sizes {
+        let corpus = bench::corpus(size as u8, args.count, args.seed);
+        for &backend in &backends {
+            let measurement = bench::measure(&corpus, backend);
+            println!(
+                "{:<8} {:<13} {:>7} {:>12} {:>12} {:>12}",
+                size.to_string(),
+                backend.to_string(),
+                measurement.puzzles,
+                format!("{:.2?}", measurement.mean()),
+                format!("{:.2?}", measurement.max),
+                measurement.nodes
+            );
+            if measurement.not_unique > 0 {
+                eprintln!(
+                    "  {} puzzles were not found unique by {}",
+                    measurement.not_unique, backend
+                );
             }
         }
     }
@@ -1,8 +1,62 @@
-use serde::Serialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
+
+use crate::error::SlitherError;
+
+/// Largest supported board radius. Coordinates are `i8`, and the neighbors of the rim cells,
+/// one step off the board, have to fit as well.
+pub const MAX_RADIUS: u8 = 126;
+
+/// Check that a board of `radius` fits the coordinate range
+pub fn check_radius(radius: u8) -> Result<(), SlitherError> {
+    if radius > MAX_RADIUS {
+        return Err(SlitherError::InvalidRadius(radius));
+    }
+    Ok(())
+}
+
+/// Check that a board of `radius` with `holes` cut out of it is a usable board: the holes lie
+/// within the hexagon and the remaining cells are one connected group
+pub fn check_board(radius: u8, holes: &BTreeSet<Coord>) -> Result<(), SlitherError> {
+    check_radius(radius)?;
+    if let Some(hole) = holes.iter().find(|c| !c.in_bounds(radius)) {
+        return Err(SlitherError::InvalidBoard(format!(
+            "Hole ({}, {}) is outside radius {}",
+            hole.q, hole.r, radius
+        )));
+    }
+    let mut cells = board_coords(radius).filter(|c| !holes.contains(c));
+    let Some(start) = cells.next() else {
+        return Err(SlitherError::InvalidBoard(
+            "The board has no cells".to_string(),
+        ));
+    };
+    let mut reached = HashSet::from([start]);
+    let mut stack = vec![start];
+    while let Some(coord) = stack.pop() {
+        for n in coord.neighbors() {
+            if n.in_bounds(radius) && !holes.contains(&n) && reached.insert(n) {
+                stack.push(n);
+            }
+        }
+    }
+    if cells.count() + 1 > reached.len() {
+        return Err(SlitherError::InvalidBoard(
+            "The board's cells are not connected".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Axial direction offsets, in cyclic order around a hex
+/// (each direction is adjacent to the ones before and after it).
+pub(crate) const DIRECTIONS: [(i8, i8); 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
 
 /// Axial coordinates (q, r)
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Coord {
     pub q: i8,
     pub r: i8,
@@ -12,80 +66,2455 @@ impl Coord {
     pub fn new(q: i8, r: i8) -> Self {
         Self { q, r }
     }
+
+    /// The six neighbors, in cyclic order. Some may lie outside the map.
+    pub fn neighbors(self) -> [Coord; 6] {
+        DIRECTIONS.map(|(dq, dr)| Coord::new(self.q + dq, self.r + dr))
+    }
+
+    /// Whether the coordinate lies within a hexagonal map of the given radius
+    pub fn in_bounds(self, radius: u8) -> bool {
+        let r = radius as i16;
+        let (q, s) = (self.q as i16, self.r as i16);
+        q.abs() <= r && s.abs() <= r && (q + s).abs() <= r
+    }
+
+    /// Number of steps between two hexes
+    pub fn distance(self, other: Coord) -> u32 {
+        let (dq, dr) = (
+            self.q as i32 - other.q as i32,
+            self.r as i32 - other.r as i32,
+        );
+        dq.unsigned_abs()
+            .max(dr.unsigned_abs())
+            .max((dq + dr).unsigned_abs())
+    }
+
+    /// Rotate 60 degrees about the center hex
+    pub fn rotate(self) -> Self {
+        Self::new(-self.r, self.q + self.r)
+    }
+
+    /// Reflect across the q = r axis
+    pub fn reflect(self) -> Self {
+        Self::new(self.r, self.q)
+    }
+
+    /// The six sides of this cell, by direction as in its neighbors
+    pub fn edges(self) -> [Edge; 6] {
+        self.neighbors().map(|n| Edge::new(self, n))
+    }
+
+    /// The six corners of this cell. Corner `d` lies between sides `d` and `d + 1`.
+    pub fn vertices(self) -> [Vertex; 6] {
+        let around = self.neighbors();
+        std::array::from_fn(|d| Vertex::new(self, around[d], around[(d + 1) % 6]))
+    }
+}
+
+/// One of the 12 symmetries of a hexagonal board: an optional reflection across the q = r
+/// axis, followed by some 60 degree turns about the center hex
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct HexSymmetry {
+    pub reflect: bool,
+    /// Number of 60 degree turns, 0-5
+    pub rotations: u8,
+}
+
+impl HexSymmetry {
+    /// Every symmetry of the board, starting with the identity
+    pub fn all() -> impl Iterator<Item = HexSymmetry> {
+        [false, true]
+            .into_iter()
+            .flat_map(|reflect| (0..6).map(move |rotations| HexSymmetry { reflect, rotations }))
+    }
+
+    pub fn apply(self, coord: Coord) -> Coord {
+        let mut out = if self.reflect { coord.reflect() } else { coord };
+        for _ in 0..self.rotations % 6 {
+            out = out.rotate();
+        }
+        out
+    }
+
+    /// The symmetry that undoes this one. Reflections are their own inverse, since a
+    /// reflection turns the board the other way.
+    pub fn inverse(self) -> Self {
+        if self.reflect {
+            self
+        } else {
+            HexSymmetry {
+                reflect: false,
+                rotations: (6 - self.rotations % 6) % 6,
+            }
+        }
+    }
+}
+
+/// A side shared by two neighboring cells, either of which may be off the board. Its canonical
+/// ID is the two cells in sorted order, so both cells name it the same way.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Edge {
+    cells: [Coord; 2],
+}
+
+impl Edge {
+    /// The side between `a` and `b`, which must be neighbors
+    pub fn new(a: Coord, b: Coord) -> Self {
+        debug_assert!(
+            a.neighbors().contains(&b),
+            "{:?} and {:?} aren't neighbors",
+            a,
+            b
+        );
+        Self {
+            cells: [a.min(b), a.max(b)],
+        }
+    }
+
+    /// The cells on either side
+    pub fn cells(self) -> [Coord; 2] {
+        self.cells
+    }
+
+    /// The corners at either end: where the two cells meet each cell neighboring both
+    pub fn vertices(self) -> [Vertex; 2] {
+        let [a, b] = self.cells;
+        let mut shared = a
+            .neighbors()
+            .into_iter()
+            .filter(|n| b.neighbors().contains(n));
+        let mut vertex = || Vertex::new(a, b, shared.next().expect("neighbors share two cells"));
+        [vertex(), vertex()]
+    }
+}
+
+/// A corner where three cells meet, any of which may be off the board. Its canonical ID is the
+/// three cells in sorted order.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Vertex {
+    cells: [Coord; 3],
+}
+
+impl Vertex {
+    /// The corner shared by `a`, `b` and `c`, which must all be neighbors of each other
+    pub fn new(a: Coord, b: Coord, c: Coord) -> Self {
+        let mut cells = [a, b, c];
+        cells.sort();
+        Self { cells }
+    }
+
+    /// The cells meeting at the corner
+    pub fn cells(self) -> [Coord; 3] {
+        self.cells
+    }
+
+    /// The three sides meeting at the corner
+    pub fn edges(self) -> [Edge; 3] {
+        let [a, b, c] = self.cells;
+        [Edge::new(a, b), Edge::new(a, c), Edge::new(b, c)]
+    }
+}
+
+/// The edges of a solution: every side between an Inside and an Outside cell, counting cells
+/// off the board as Outside. In the multi-loop variant it holds every loop.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Loop {
+    pub edges: BTreeSet<Edge>,
+}
+
+impl Loop {
+    /// The loop(s) bounding the Inside region of an answer
+    pub fn of(answer: &Map) -> Self {
+        let region = |c: &Coord| {
+            answer
+                .cells
+                .get(c)
+                .map_or(KnownRegion::Outside, |cell| cell.region)
+        };
+        let edges = answer
+            .iter_coords()
+            .flat_map(Coord::edges)
+            .filter(|edge| {
+                let [a, b] = edge.cells();
+                region(&a) != region(&b)
+            })
+            .collect();
+        Self { edges }
+    }
+
+    pub fn contains(&self, edge: Edge) -> bool {
+        self.edges.contains(&edge)
+    }
+
+    /// Number of the loop's edges meeting at `vertex`: 0 or 2 for a valid loop
+    pub fn degree(&self, vertex: Vertex) -> usize {
+        vertex
+            .edges()
+            .into_iter()
+            .filter(|&e| self.contains(e))
+            .count()
+    }
+
+    /// The separate cycles the edges form, each walked in order from its smallest edge. Fails
+    /// if the edges branch or break off somewhere, i.e. some corner doesn't touch exactly zero
+    /// or two of them.
+    pub fn cycles(&self) -> Result<Vec<LoopPath>, SlitherError> {
+        let mut left = self.edges.clone();
+        let mut cycles = Vec::new();
+        while let Some(first) = left.pop_first() {
+            let [start, mut at] = first.vertices();
+            let mut path = LoopPath {
+                vertices: vec![start],
+                edges: vec![first],
+            };
+            while at != start {
+                let degree = self.degree(at);
+                if degree != 2 {
+                    let [a, b, c] = at.cells();
+                    return Err(SlitherError::Topology(format!(
+                        "{} loop edges meet at the corner of ({}, {}), ({}, {}) and ({}, {})",
+                        degree, a.q, a.r, b.q, b.r, c.q, c.r
+                    )));
+                }
+                let edge = at
+                    .edges()
+                    .into_iter()
+                    .find(|e| left.contains(e))
+                    .expect("a corner of degree 2 leads on to an unwalked edge");
+                left.remove(&edge);
+                path.vertices.push(at);
+                path.edges.push(edge);
+                let [a, b] = edge.vertices();
+                at = if a == at { b } else { a };
+            }
+            cycles.push(path);
+        }
+        Ok(cycles)
+    }
+}
+
+/// A single loop in order around it: `edges[i]` runs from `vertices[i]` to the next vertex,
+/// wrapping around to the first
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LoopPath {
+    pub vertices: Vec<Vertex>,
+    pub edges: Vec<Edge>,
+}
+
+impl LoopPath {
+    /// Number of edges on the loop
+    pub fn len(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
 }
 
 /// Region type for a cell (Inside or Outside loop)
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Region {
     Inside,
     Outside,
 }
 
+/// What is known of a cell's region: in a solver's working state, and in imported puzzles that
+/// come without an answer key. Binary maps only hold them as a whole map without its answer
+/// (see [`Map::without_answer`]).
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum KnownRegion {
+    Inside,
+    Outside,
+    #[default]
+    Unknown,
+}
+
+impl KnownRegion {
+    /// The region, `None` while unknown
+    pub fn region(self) -> Option<Region> {
+        match self {
+            KnownRegion::Inside => Some(Region::Inside),
+            KnownRegion::Outside => Some(Region::Outside),
+            KnownRegion::Unknown => None,
+        }
+    }
+
+    pub fn is_known(self) -> bool {
+        self != KnownRegion::Unknown
+    }
+}
+
+impl From<Region> for KnownRegion {
+    fn from(region: Region) -> Self {
+        match region {
+            Region::Inside => KnownRegion::Inside,
+            Region::Outside => KnownRegion::Outside,
+        }
+    }
+}
+
+impl From<Option<Region>> for KnownRegion {
+    fn from(region: Option<Region>) -> Self {
+        region.map_or(KnownRegion::Unknown, KnownRegion::from)
+    }
+}
+
+impl PartialEq<Region> for KnownRegion {
+    fn eq(&self, region: &Region) -> bool {
+        self.region() == Some(*region)
+    }
+}
+
+impl PartialEq<KnownRegion> for Region {
+    fn eq(&self, known: &KnownRegion) -> bool {
+        known == self
+    }
+}
+
+/// What a player has drawn on an edge
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EdgeMark {
+    /// The edge is on the loop
+    Line,
+    /// The edge is crossed out: not on the loop
+    Cross,
+}
+
+/// A player's progress on a puzzle, kept apart from the answer in the [`Map`]: the edges they
+/// have marked, by index in `EdgeGraph::new(radius)`, and the regions they have guessed for
+/// cells. Edges and cells without an entry are unknown.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PlayerState {
+    pub marks: BTreeMap<usize, EdgeMark>,
+    /// Cells the player has shaded as Inside or Outside. Only a note to themselves: hints and
+    /// conflict checks go by the marks alone.
+    #[serde(default, with = "coord_pairs")]
+    pub guesses: BTreeMap<Coord, Region>,
+}
+
+impl PlayerState {
+    /// The mark on `edge`, `None` while unknown
+    pub fn mark(&self, edge: usize) -> Option<EdgeMark> {
+        self.marks.get(&edge).copied()
+    }
+
+    /// Mark `edge`, or with `None` erase its mark
+    pub fn set_mark(&mut self, edge: usize, mark: Option<EdgeMark>) {
+        match mark {
+            Some(mark) => self.marks.insert(edge, mark),
+            None => self.marks.remove(&edge),
+        };
+    }
+
+    /// The region guessed for `coord`, `None` while unknown
+    pub fn guess(&self, coord: Coord) -> Option<Region> {
+        self.guesses.get(&coord).copied()
+    }
+
+    /// Guess the region of `coord`, or with `None` erase the guess
+    pub fn set_guess(&mut self, coord: Coord, region: Option<Region>) {
+        match region {
+            Some(region) => self.guesses.insert(coord, region),
+            None => self.guesses.remove(&coord),
+        };
+    }
+}
+
+/// Coordinate-keyed maps as a list of `[coord, value]` pairs, since JSON keys must be strings
+mod coord_pairs {
+    use super::*;
+
+    pub fn serialize<S: Serializer, T: Serialize>(
+        map: &BTreeMap<Coord, T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(map)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<Coord, T>, D::Error> {
+        let pairs: Vec<(Coord, T)> = Vec::deserialize(deserializer)?;
+        Ok(pairs.into_iter().collect())
+    }
+}
+
+/// Sheep-and-wolves clue: a marker telling the player which region the cell is in,
+/// shown in place of its number
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Marker {
+    /// The cell is Inside the loop
+    Sheep,
+    /// The cell is Outside the loop
+    Wolf,
+}
+
+impl Marker {
+    /// The marker for a cell in `region`
+    pub fn for_region(region: Region) -> Self {
+        match region {
+            Region::Inside => Marker::Sheep,
+            Region::Outside => Marker::Wolf,
+        }
+    }
+
+    /// Region a cell with this marker must be in
+    pub fn region(self) -> Region {
+        match self {
+            Marker::Sheep => Region::Inside,
+            Marker::Wolf => Region::Outside,
+        }
+    }
+}
+
+/// What a cell shows the player of its number of sides on the loop, or of its region
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Clue {
+    /// Nothing
+    Hidden,
+    /// The exact number
+    #[default]
+    Count,
+    /// Only whether the number is even or odd (parity variant)
+    Parity,
+    /// A sheep or wolf for the cell's region in place of the number (sheep-and-wolves variant)
+    Marker,
+}
+
+/// Bit sets of the even and odd numbers of loop sides
+pub(crate) const PARITY_COUNTS: [u8; 2] = [0x55, 0x2a];
+
+/// A visible clue as the player reads it
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub enum VisibleClue {
+    /// The number of the cell's sides on the loop
+    Count(u8),
+    /// That number modulo 2
+    Parity(u8),
+}
+
+impl VisibleClue {
+    /// Loop side counts the clue allows, as a bit set
+    pub fn allowed_counts(self) -> u8 {
+        match self {
+            VisibleClue::Count(count) => 1 << count,
+            VisibleClue::Parity(parity) => PARITY_COUNTS[parity as usize % 2],
+        }
+    }
+}
+
+/// Whether the sides of a cell on the edge of the board count towards its clue. Apps differ
+/// on this, so a map records which convention its clues follow.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum RimEdges {
+    /// Everything beyond the board is Outside, so the rim sides of an Inside cell are on the
+    /// loop and count like any other
+    #[default]
+    Counted,
+    /// Clues only count the sides a cell shares with other cells of the board
+    Excluded,
+}
+
+impl RimEdges {
+    pub fn is_counted(&self) -> bool {
+        *self == RimEdges::Counted
+    }
+}
+
+impl FromStr for RimEdges {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "counted" => Ok(RimEdges::Counted),
+            "excluded" => Ok(RimEdges::Excluded),
+            _ => Err(format!(
+                "Invalid rim edges: {} (expected counted or excluded)",
+                s
+            )),
+        }
+    }
+}
+
 /// A single hexagonal cell on the grid
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct Cell {
-    /// True region of the cell (Answer Key)
-    pub region: Region,
+    /// True region of the cell (Answer Key), unknown in puzzles imported without one
+    pub region: KnownRegion,
     /// Number of edges around this cell that are part of the loop (0-6)
     pub full_neighbor_count: u8,
-    /// Whether the number clue is shown to the player
-    pub clue_visible: bool,
+    /// What the player is shown of the cell
+    pub clue: Clue,
 }
 
 impl Cell {
+    /// A cell showing its exact count if `visible`, nothing otherwise
     pub fn new(region: Region, count: u8, visible: bool) -> Self {
         Self {
-            region,
+            region: region.into(),
             full_neighbor_count: count,
-            clue_visible: visible,
+            clue: if visible { Clue::Count } else { Clue::Hidden },
+        }
+    }
+
+    /// Whether the player is shown the number, or its parity
+    pub fn clue_visible(&self) -> bool {
+        matches!(self.clue, Clue::Count | Clue::Parity)
+    }
+
+    /// The number as the player sees it, `None` if it isn't shown
+    pub fn visible_clue(&self) -> Option<VisibleClue> {
+        match self.clue {
+            Clue::Count => Some(VisibleClue::Count(self.full_neighbor_count)),
+            Clue::Parity => Some(VisibleClue::Parity(self.full_neighbor_count % 2)),
+            Clue::Hidden | Clue::Marker => None,
         }
     }
+
+    /// The marker shown in place of the number, which always matches the region. A cell whose
+    /// region is unknown can't show one.
+    pub fn marker(&self) -> Option<Marker> {
+        match (self.clue, self.region.region()) {
+            (Clue::Marker, Some(region)) => Some(Marker::for_region(region)),
+            _ => None,
+        }
+    }
+}
+
+/// What the player is shown of a cell
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct CellView {
+    pub clue: Option<VisibleClue>,
+    pub marker: Option<Marker>,
 }
 
-/// The game map
-#[derive(Clone, Debug, Serialize)]
+/// The game map. In JSON its cells are a flat list of `[coord, cell]` pairs in board order,
+/// since JSON keys must be strings; loading checks that the list covers the board exactly.
+///
+/// The board is the hexagon of `radius`, less any `holes` cut out of it for irregular shapes.
+/// A hole has no cell and counts as Outside, like the space beyond the edge of the hexagon.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(try_from = "MapRecord")]
 pub struct Map {
     pub radius: u8,
-    pub cells: HashMap<Coord, Cell>,
+    pub cells: Grid<Cell>,
+    /// Clue removal stopped at the generation timeout, so more clues may be visible than the
+    /// difficulty asks for. The puzzle still has a unique solution.
+    pub timed_out: bool,
+    /// Number of disjoint loops in the answer: 1 normally, more in the multi-loop variant.
+    /// Solvers need it, so it is part of the puzzle rather than just the answer.
+    pub loop_count: u8,
+    /// Difficulty on the 1-10 scale the puzzle was generated for, if known
+    pub level: Option<u8>,
+    /// Hexes of the hexagon that are not part of the board, empty for a full hexagon
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub holes: BTreeSet<Coord>,
+    /// How the map was generated, if it was recorded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<MapMeta>,
+    /// Whether the clues count the sides of rim cells facing off the board
+    #[serde(skip_serializing_if = "RimEdges::is_counted")]
+    pub rim_edges: RimEdges,
+}
+
+/// Maps are equal when they are the same puzzle: the same board, the same cell at every
+/// coordinate and the same flags. Cells are compared by coordinate, however the maps were
+/// filled in, and `meta` is left out, since how a map was made doesn't change it.
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.radius == other.radius
+            && self.holes == other.holes
+            && self.cells == other.cells
+            && self.timed_out == other.timed_out
+            && self.loop_count == other.loop_count
+            && self.level == other.level
+            && self.rim_edges == other.rim_edges
+    }
+}
+
+impl Eq for Map {}
+
+impl Hash for Map {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.radius.hash(state);
+        self.holes.hash(state);
+        self.cells.hash(state);
+        self.timed_out.hash(state);
+        self.loop_count.hash(state);
+        self.level.hash(state);
+        self.rim_edges.hash(state);
+    }
+}
+
+/// Where a generated map came from. Every field is optional, so maps from older files or
+/// other tools can say as much as they know.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct MapMeta {
+    /// Seed the generator's RNG started from
+    pub seed: Option<u64>,
+    /// Generation algorithm, as `--algorithm` spells it, or "mask" for a user-supplied answer
+    pub algorithm: Option<String>,
+    /// Difficulty asked for, as the output folders spell it, e.g. "hard" or "level_7"
+    pub requested_difficulty: Option<String>,
+    /// Hardest technique the logical solver needed, as `--techniques` spells it
+    pub measured_difficulty: Option<String>,
+    /// Number of edges in the solution loop
+    pub loop_length: Option<u32>,
+    /// When the map was generated, in seconds since the Unix epoch
+    pub created_at: Option<u64>,
+}
+
+/// [`Map`] as it is read from JSON, before its cells are checked against the board
+#[derive(Deserialize)]
+struct MapRecord {
+    radius: u8,
+    cells: Vec<(Coord, Cell)>,
+    #[serde(default)]
+    timed_out: bool,
+    #[serde(default = "one_loop")]
+    loop_count: u8,
+    #[serde(default)]
+    level: Option<u8>,
+    #[serde(default)]
+    holes: BTreeSet<Coord>,
+    #[serde(default)]
+    meta: Option<MapMeta>,
+    #[serde(default)]
+    rim_edges: RimEdges,
+}
+
+fn one_loop() -> u8 {
+    1
+}
+
+impl TryFrom<MapRecord> for Map {
+    type Error = SlitherError;
+
+    fn try_from(record: MapRecord) -> Result<Self, SlitherError> {
+        if record.loop_count == 0 {
+            return Err(SlitherError::InvalidData(
+                "Loop count must be at least 1".to_string(),
+            ));
+        }
+        let mut map = Map::try_with_holes(record.radius, record.holes)?;
+        map.timed_out = record.timed_out;
+        map.loop_count = record.loop_count;
+        map.level = record.level;
+        map.meta = record.meta;
+        map.rim_edges = record.rim_edges;
+        for (coord, cell) in record.cells {
+            if !coord.in_bounds(map.radius) {
+                return Err(SlitherError::InvalidData(format!(
+                    "Cell ({}, {}) is outside radius {}",
+                    coord.q, coord.r, map.radius
+                )));
+            }
+            if map.is_hole(coord) {
+                return Err(SlitherError::InvalidData(format!(
+                    "Cell ({}, {}) is in a hole",
+                    coord.q, coord.r
+                )));
+            }
+            if map.cells.contains_key(&coord) {
+                return Err(SlitherError::InvalidData(format!(
+                    "Cell ({}, {}) is listed twice",
+                    coord.q, coord.r
+                )));
+            }
+            if cell.full_neighbor_count > 6 {
+                return Err(SlitherError::InvalidData(format!(
+                    "Cell ({}, {}) has a count of {}, but a hexagon has 6 sides",
+                    coord.q, coord.r, cell.full_neighbor_count
+                )));
+            }
+            if cell.clue == Clue::Marker && !cell.region.is_known() {
+                return Err(SlitherError::InvalidData(format!(
+                    "Cell ({}, {}) shows a marker but its region is unknown",
+                    coord.q, coord.r
+                )));
+            }
+            map.cells.insert(coord, cell);
+        }
+        if let Some(coord) = map.iter_coords().find(|c| !map.cells.contains_key(c)) {
+            return Err(SlitherError::InvalidData(format!(
+                "Cell ({}, {}) is missing",
+                coord.q, coord.r
+            )));
+        }
+        Ok(map)
+    }
 }
 
 impl Map {
+    /// An empty board. Panics if `radius` is above [`MAX_RADIUS`]; use [`Map::try_new`] for
+    /// untrusted radii.
     pub fn new(radius: u8) -> Self {
-        Self {
+        Self::try_new(radius).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// An empty board, or an error if `radius` is above [`MAX_RADIUS`]
+    pub fn try_new(radius: u8) -> Result<Self, SlitherError> {
+        check_radius(radius)?;
+        Ok(Self {
             radius,
-            cells: HashMap::new(),
-        }
+            cells: Grid::new(radius),
+            timed_out: false,
+            loop_count: 1,
+            level: None,
+            holes: BTreeSet::new(),
+            meta: None,
+            rim_edges: RimEdges::Counted,
+        })
+    }
+
+    /// An empty board of `radius` with `holes` cut out of it. Panics if [`check_board`] fails;
+    /// use [`Map::try_with_holes`] for untrusted boards.
+    pub fn with_holes(radius: u8, holes: BTreeSet<Coord>) -> Self {
+        Self::try_with_holes(radius, holes).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// An empty board of `radius` with `holes` cut out of it, or the error [`check_board`] gives
+    pub fn try_with_holes(radius: u8, holes: BTreeSet<Coord>) -> Result<Self, SlitherError> {
+        check_board(radius, &holes)?;
+        Ok(Self {
+            holes,
+            ..Self::try_new(radius)?
+        })
+    }
+
+    /// Whether `coord` is a hex of the hexagon cut out of the board
+    pub fn is_hole(&self, coord: Coord) -> bool {
+        self.holes.contains(&coord)
+    }
+
+    /// Whether `coord` is a cell of the board: within the radius and not in a hole
+    pub fn on_board(&self, coord: Coord) -> bool {
+        coord.in_bounds(self.radius) && !self.is_hole(coord)
     }
 
-    /// Iterator over all coordinates in the map, following the spec order:
+    /// Iterator over all coordinates in the map, following the spec order and skipping holes:
     /// Outer loop: q from -R to +R
     /// Inner loop: r from max(-R, -q-R) to min(R, -q+R)
     pub fn iter_coords(&self) -> impl Iterator<Item = Coord> {
-        let r = self.radius as i8;
-        (-r..=r).flat_map(move |q| {
-            let r_min = (-r).max(-q - r);
-            let r_max = r.min(-q + r);
-            (r_min..=r_max).map(move |r| Coord::new(q, r))
-        })
+        board_coords(self.radius).filter(|c| !self.is_hole(*c))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use googletest::prelude::*;
+    /// The puzzle as the player sees it: visible clues and markers only, none of the answer.
+    /// Holes show as cells with a wolf and no clue, so solvers keep them Outside.
+    pub fn view(&self) -> PuzzleView {
+        let mut cells = self.cells.map(|cell| CellView {
+            clue: cell.visible_clue(),
+            marker: cell.marker(),
+        });
+        for &hole in &self.holes {
+            cells.insert(
+                hole,
+                CellView {
+                    clue: None,
+                    marker: Some(Marker::Wolf),
+                },
+            );
+        }
+        PuzzleView {
+            radius: self.radius,
+            loop_count: self.loop_count,
+            cells,
+            holes: self.holes.clone(),
+            rim_edges: self.rim_edges,
+        }
+    }
 
-    #[googletest::test]
-    fn test_iter_coords_count() {
-        // Formula: 3R(R+1) + 1
-        // R=2 -> 3*2*3 + 1 = 19
-        let map = Map::new(2);
-        let count = map.iter_coords().count();
-        expect_that!(count, eq(19));
+    /// A copy to ship to players: regions become unknown, except behind markers, and hidden
+    /// clues lose their counts. Parity clues keep only their parity, and the metadata drops
+    /// the seed and loop length, which give the answer away. Binary maps store the result with
+    /// a flag and zeroed region bits; the legacy and compressed layouts can't store it.
+    pub fn without_answer(&self) -> Map {
+        let mut map = self.clone();
+        for cell in map.cells.values_mut() {
+            match cell.clue {
+                Clue::Count => cell.region = KnownRegion::Unknown,
+                Clue::Parity => {
+                    cell.region = KnownRegion::Unknown;
+                    cell.full_neighbor_count %= 2;
+                }
+                Clue::Hidden => {
+                    cell.region = KnownRegion::Unknown;
+                    cell.full_neighbor_count = 0;
+                }
+                Clue::Marker => cell.full_neighbor_count = 0,
+            }
+        }
+        if let Some(meta) = &mut map.meta {
+            meta.seed = None;
+            meta.loop_length = None;
+        }
+        map
+    }
 
-        // R=0 -> 1
-        let map = Map::new(0);
-        let count = map.iter_coords().count();
-        expect_that!(count, eq(1));
+    /// Number of sides of the cell at `coord` on the loop, from the regions of it and its
+    /// neighbors. Positions off the edge of the board count as Outside, and their sides are
+    /// left out if the map's [`RimEdges`] exclude them.
+    fn derived_count(&self, coord: Coord) -> u8 {
+        let region = |c: &Coord| {
+            self.cells
+                .get(c)
+                .map_or(KnownRegion::Outside, |cell| cell.region)
+        };
+        let own = region(&coord);
+        coord
+            .neighbors()
+            .iter()
+            .filter(|n| self.rim_edges.is_counted() || self.on_board(**n))
+            .filter(|n| region(n) != own)
+            .count() as u8
+    }
+
+    /// Set every cell's `full_neighbor_count` from the regions, so the clues match the answer
+    pub fn recompute_clues(&mut self) {
+        let coords: Vec<Coord> = self.iter_coords().collect();
+        for coord in coords {
+            let count = self.derived_count(coord);
+            if let Some(cell) = self.cells.get_mut(&coord) {
+                cell.full_neighbor_count = count;
+            }
+        }
+    }
+
+    /// Whether every cell's count agrees with the regions. Markers always do, since their kind
+    /// follows from the region.
+    pub fn is_consistent(&self) -> bool {
+        self.cells
+            .iter()
+            .all(|(&coord, cell)| cell.full_neighbor_count == self.derived_count(coord))
+    }
+
+    /// The cells `k` steps from the center, in cyclic order starting from (-k, k) and taking
+    /// each direction in turn, skipping holes. Empty beyond the board's radius.
+    pub fn iter_ring(&self, k: u8) -> impl Iterator<Item = Coord> {
+        let k = if k > self.radius { None } else { Some(k as i8) };
+        let start = k.map_or(Coord::new(0, 0), |k| {
+            let (dq, dr) = DIRECTIONS[4];
+            Coord::new(dq * k, dr * k)
+        });
+        // The center is a ring of one cell, with no steps to take around it
+        let center = (k == Some(0)).then_some(start);
+        let steps = (0..6).flat_map(move |d| std::iter::repeat_n(d, k.unwrap_or(0) as usize));
+        let around = steps.scan(start, |at, d| {
+            let here = *at;
+            *at = at.neighbors()[d];
+            Some(here)
+        });
+        center
+            .into_iter()
+            .chain(around)
+            .filter(|c| !self.is_hole(*c))
+    }
+
+    /// The cells of the outermost ring, in the order of [`iter_ring`](Map::iter_ring)
+    pub fn iter_outer_ring(&self) -> impl Iterator<Item = Coord> {
+        self.iter_ring(self.radius)
+    }
+
+    /// Which ring around the center `coord` lies in, `None` if it isn't a cell of the board
+    pub fn ring_of(&self, coord: Coord) -> Option<u8> {
+        self.on_board(coord)
+            .then(|| coord.distance(Coord::new(0, 0)) as u8)
+    }
+
+    /// Whether `coord` is a cell with a side on the edge of the board, so that side can only be
+    /// on the loop if the cell is Inside. Cells next to holes count, not only the outer ring.
+    pub fn is_border(&self, coord: Coord) -> bool {
+        self.on_board(coord) && coord.neighbors().iter().any(|&n| !self.on_board(n))
+    }
+
+    /// Every cell, ring by ring outwards from the center
+    pub fn iter_spiral(&self) -> impl Iterator<Item = Coord> + use<'_> {
+        (0..=self.radius).flat_map(|k| self.iter_ring(k))
+    }
+
+    /// The neighbors of `coord` that lie on the board, in cyclic order
+    pub fn neighbors_in_bounds(&self, coord: Coord) -> impl Iterator<Item = Coord> {
+        coord.neighbors().into_iter().filter(|n| self.on_board(*n))
+    }
+
+    /// The loop of the answer, in order around it. Fails unless the boundary between Inside
+    /// and Outside is a single cycle.
+    pub fn solution_loop(&self) -> Result<LoopPath, SlitherError> {
+        let mut cycles = Loop::of(self).cycles()?;
+        match cycles.len() {
+            0 => Err(SlitherError::Topology(
+                "no loop: there are no Inside cells".to_string(),
+            )),
+            1 => Ok(cycles.remove(0)),
+            n => Err(SlitherError::Topology(format!(
+                "the boundary is {} separate loops",
+                n
+            ))),
+        }
+    }
+
+    /// Fraction of the board's cells that are Inside the loop
+    pub fn inside_fraction(&self) -> f64 {
+        if self.cells.is_empty() {
+            return 0.0;
+        }
+        let inside = self
+            .cells
+            .values()
+            .filter(|c| c.region == Region::Inside)
+            .count();
+        inside as f64 / self.cells.len() as f64
+    }
+
+    /// Summary counts of the answer and its clues, as [`MapStats`]
+    pub fn stats(&self) -> MapStats {
+        let mut stats = MapStats::default();
+        let inside = |c: &Coord| {
+            self.cells
+                .get(c)
+                .is_some_and(|c| c.region == Region::Inside)
+        };
+        for (coord, cell) in &self.cells {
+            if cell.region == Region::Inside {
+                stats.inside_cells += 1;
+                // Every loop edge borders exactly one Inside cell, whether or not clues count it
+                let outside = coord.neighbors().iter().filter(|n| !inside(n)).count();
+                stats.loop_length += outside as u32;
+            }
+            if cell.clue_visible() {
+                stats.visible_clues += 1;
+                stats.clue_histogram[cell.full_neighbor_count as usize] += 1;
+            }
+        }
+        stats
+    }
+
+    /// Check that the map is a well-formed answer: every cell of the board is present, every
+    /// Outside cell can reach the board edge without crossing the loop (no enclosed pockets of
+    /// Outside), and the Inside cells form exactly `loop_count` connected groups. Together these
+    /// make the region boundary exactly `loop_count` separate simple loops.
+    pub fn validate_topology(&self) -> Result<(), SlitherError> {
+        self.topology_problem().map_err(SlitherError::Topology)
+    }
+
+    /// The first way the map fails [`validate_topology`](Map::validate_topology)
+    fn topology_problem(&self) -> Result<(), String> {
+        if let Some(missing) = self.iter_coords().find(|c| !self.cells.contains_key(c)) {
+            return Err(format!("missing cell ({}, {})", missing.q, missing.r));
+        }
+        if let Some(hole) = self.holes.iter().find(|c| self.cells.contains_key(c)) {
+            return Err(format!("cell ({}, {}) is in a hole", hole.q, hole.r));
+        }
+        if let Some((c, _)) = self.cells.iter().find(|(_, cell)| !cell.region.is_known()) {
+            return Err(format!(
+                "cell ({}, {}) has no known region: the map has no answer key",
+                c.q, c.r
+            ));
+        }
+
+        // Flood the Outside region, and the holes, which are Outside too, inward from the edge
+        // of the hexagon
+        let is_outside = |c: &Coord| self.cells[c].region == Region::Outside;
+        let is_open = |c: &Coord| c.in_bounds(self.radius) && (self.is_hole(*c) || is_outside(c));
+        let mut reached: HashSet<Coord> = board_coords(self.radius)
+            .filter(|c| is_open(c) && c.neighbors().iter().any(|n| !n.in_bounds(self.radius)))
+            .collect();
+        let mut stack: Vec<Coord> = reached.iter().copied().collect();
+        while let Some(coord) = stack.pop() {
+            for n in coord.neighbors() {
+                if is_open(&n) && reached.insert(n) {
+                    stack.push(n);
+                }
+            }
+        }
+        let enclosed = self
+            .iter_coords()
+            .filter(|c| is_outside(c) && !reached.contains(c))
+            .count();
+        if enclosed > 0 {
+            return Err(format!(
+                "the loop encloses {} Outside cells away from the board edge",
+                enclosed
+            ));
+        }
+        let enclosed = self.holes.iter().filter(|c| !reached.contains(c)).count();
+        if enclosed > 0 {
+            return Err(format!("the loop encloses {} holes of the board", enclosed));
+        }
+
+        let mut seen = HashSet::new();
+        let mut groups = 0;
+        for start in self.iter_coords().filter(|c| !is_outside(c)) {
+            if !seen.insert(start) {
+                continue;
+            }
+            groups += 1;
+            let mut stack = vec![start];
+            while let Some(coord) = stack.pop() {
+                for n in self.neighbors_in_bounds(coord) {
+                    if !is_outside(&n) && seen.insert(n) {
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+        match (groups, self.loop_count as usize) {
+            (0, _) => Err("no Inside cells".to_string()),
+            (groups, 1) if groups > 1 => Err(format!(
+                "Inside cells are not connected ({} groups)",
+                groups
+            )),
+            (groups, loops) if groups != loops => Err(format!(
+                "{} separate Inside groups for {} loops",
+                groups, loops
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Representation shared by every rotation and reflection of this map, so two puzzles
+    /// are the same up to symmetry exactly when their canonical forms are equal.
+    ///
+    /// Of the 12 symmetries, the ones whose [`RegionBitmap`] is smallest are read back in spec
+    /// order as one byte per hex (region, clue count, visibility, marker and clue kind, or 0xff
+    /// for a hole), and the smallest sequence wins.
+    pub fn canonical_form(&self) -> Vec<u8> {
+        self.transformed_bytes(self.canonical_symmetry())
+    }
+
+    /// The rotation or reflection of this map that gives its [`canonical_form`], so maps that
+    /// are the same up to symmetry canonicalize to identical maps
+    ///
+    /// [`canonical_form`]: Map::canonical_form
+    pub fn canonicalize(&self) -> Map {
+        self.transform(self.canonical_symmetry())
+    }
+
+    /// The symmetry picked by [`canonical_form`](Map::canonical_form). Comparing region
+    /// bitmaps first leaves the full bytes to break ties, which only symmetric answers have.
+    fn canonical_symmetry(&self) -> HexSymmetry {
+        let regions = RegionBitmap::from(self);
+        let images: Vec<(HexSymmetry, RegionBitmap)> = HexSymmetry::all()
+            .map(|symmetry| (symmetry, regions.transform(symmetry)))
+            .collect();
+        let Some(smallest) = images.iter().map(|(_, image)| image).min() else {
+            return HexSymmetry::default();
+        };
+        images
+            .iter()
+            .filter(|(_, image)| image == smallest)
+            .map(|&(symmetry, _)| symmetry)
+            .min_by_key(|&symmetry| self.transformed_bytes(symmetry))
+            .unwrap_or_default()
+    }
+
+    /// This map with every cell moved by `symmetry`. Clues stay correct, since turning or
+    /// mirroring the board keeps neighbors together.
+    pub fn transform(&self, symmetry: HexSymmetry) -> Map {
+        let mut out = Map {
+            cells: Grid::new(self.radius),
+            holes: self.holes.iter().map(|&c| symmetry.apply(c)).collect(),
+            ..self.clone()
+        };
+        for (coord, cell) in &self.cells {
+            out.cells.insert(symmetry.apply(*coord), cell.clone());
+        }
+        out
+    }
+
+    /// Every hex where this map and `other` disagree on the region, count or clue of its cell,
+    /// or where only one of them has a cell, in spec order over the larger of the two boards
+    pub fn diff(&self, other: &Map) -> Vec<CellDiff> {
+        board_coords(self.radius.max(other.radius))
+            .filter_map(|coord| {
+                let (before, after) = (self.cells.get(&coord), other.cells.get(&coord));
+                (before != after).then(|| CellDiff {
+                    coord,
+                    before: before.cloned(),
+                    after: after.cloned(),
+                })
+            })
+            .collect()
+    }
+
+    /// The [`canonical_form`](Map::canonical_form) bytes of this map moved by `symmetry`
+    fn transformed_bytes(&self, symmetry: HexSymmetry) -> Vec<u8> {
+        let inverse = symmetry.inverse();
+        board_coords(self.radius)
+            .map(|coord| {
+                self.cells.get(&inverse.apply(coord)).map_or(0xff, |cell| {
+                    (cell.region == Region::Inside) as u8
+                        | (cell.full_neighbor_count & 0x7) << 1
+                        | (cell.clue_visible() as u8) << 4
+                        | ((cell.clue == Clue::Marker) as u8) << 5
+                        | ((cell.clue == Clue::Parity) as u8) << 6
+                })
+            })
+            .collect()
+    }
+}
+
+/// The player-visible part of a [`Map`]. Solvers work from this alone, so a hidden clue can
+/// never leak its count or region into a solution.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PuzzleView {
+    pub radius: u8,
+    /// Number of loops the solution has
+    pub loop_count: u8,
+    /// Every hex of the hexagon, holes included as cells fixed Outside by a wolf
+    pub cells: Grid<CellView>,
+    /// The holes of the map, for rebuilding answers on the same board
+    pub holes: BTreeSet<Coord>,
+    /// Whether clues count the sides facing off the board, holes included
+    pub rim_edges: RimEdges,
+}
+
+impl PuzzleView {
+    /// Coordinates of every hex of the hexagon, holes included, in spec order
+    pub fn iter_coords(&self) -> impl Iterator<Item = Coord> {
+        board_coords(self.radius)
+    }
+}
+
+/// Every coordinate of a board of `radius`, in spec order
+fn board_coords(radius: u8) -> impl Iterator<Item = Coord> {
+    // Bounds like -q + R reach 2R, beyond i8 on large boards
+    let r = radius as i16;
+    (-r..=r).flat_map(move |q| {
+        let r_min = (-r).max(-q - r);
+        let r_max = r.min(-q + r);
+        (r_min..=r_max).map(move |r| Coord::new(q as i8, r as i8))
+    })
+}
+
+/// Number of cells on a board of `radius`
+pub fn cell_count(radius: u8) -> usize {
+    let r = radius as usize;
+    3 * r * (r + 1) + 1
+}
+
+/// Position of `coord` in the spec order of a board of `radius`, `None` off the board
+fn board_position(radius: u8, coord: Coord) -> Option<usize> {
+    if !coord.in_bounds(radius) {
+        return None;
+    }
+    // Columns grow by one cell up to the middle one and shrink after it, so the cells in
+    // the columns before column c number c(2R + 1 + c) / 2 on the left half, and mirror that
+    // from the end on the right half
+    let r = radius as usize;
+    let column = (coord.q as i16 + r as i16) as usize;
+    let start = if column <= r {
+        column * (2 * r + 1 + column) / 2
+    } else {
+        let after = 2 * r + 1 - column;
+        cell_count(radius) - after * (2 * r + 1 + after) / 2
+    };
+    let r_min = (-(r as i16)).max(-(coord.q as i16) - r as i16);
+    Some(start + (coord.r as i16 - r_min) as usize)
+}
+
+/// The regions of a board as one bit per hex in spec order, set for Inside. Holes have a bit
+/// too, always clear, so the bitmaps of one radius line up. Cheap to copy, hash and compare,
+/// and ordered, for solver-heavy work that only needs the answer.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct RegionBitmap {
+    radius: u8,
+    words: Vec<u64>,
+}
+
+impl RegionBitmap {
+    /// A board of `radius` with every hex Outside
+    pub fn new(radius: u8) -> Self {
+        Self {
+            radius,
+            words: vec![0; cell_count(radius).div_ceil(64)],
+        }
+    }
+
+    pub fn radius(&self) -> u8 {
+        self.radius
+    }
+
+    /// Whether `coord` is Inside; hexes off the board never are
+    pub fn get(&self, coord: Coord) -> bool {
+        board_position(self.radius, coord)
+            .is_some_and(|position| self.words[position / 64] & (1 << (position % 64)) != 0)
+    }
+
+    /// Put `coord` Inside or Outside. Panics if it is off the board.
+    pub fn set(&mut self, coord: Coord, inside: bool) {
+        let position = board_position(self.radius, coord).unwrap_or_else(|| {
+            panic!(
+                "({}, {}) is off a board of radius {}",
+                coord.q, coord.r, self.radius
+            )
+        });
+        let bit = 1 << (position % 64);
+        if inside {
+            self.words[position / 64] |= bit;
+        } else {
+            self.words[position / 64] &= !bit;
+        }
+    }
+
+    /// Number of Inside hexes
+    pub fn count_inside(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// The Inside hexes, in spec order
+    pub fn iter_inside(&self) -> impl Iterator<Item = Coord> + '_ {
+        board_coords(self.radius).filter(|&coord| self.get(coord))
+    }
+
+    /// These regions with every hex moved by `symmetry`
+    pub fn transform(&self, symmetry: HexSymmetry) -> RegionBitmap {
+        let mut out = RegionBitmap::new(self.radius);
+        for coord in self.iter_inside() {
+            out.set(symmetry.apply(coord), true);
+        }
+        out
+    }
+
+    /// The answer these regions give on `board`, an empty board of the same radius, with every
+    /// clue shown. Bits in the board's holes are ignored.
+    pub fn to_map(&self, mut board: Map) -> Map {
+        let coords: Vec<Coord> = board.iter_coords().collect();
+        for coord in coords {
+            let region = if self.get(coord) {
+                Region::Inside
+            } else {
+                Region::Outside
+            };
+            board.cells.insert(coord, Cell::new(region, 0, true));
+        }
+        board.recompute_clues();
+        board
+    }
+}
+
+impl From<&Map> for RegionBitmap {
+    fn from(map: &Map) -> Self {
+        let mut bitmap = RegionBitmap::new(map.radius);
+        for (&coord, cell) in &map.cells {
+            if cell.region == Region::Inside {
+                bitmap.set(coord, true);
+            }
+        }
+        bitmap
+    }
+}
+
+/// Something stored for each cell of a hexagonal board, in a flat vector in spec order. The
+/// position of a coordinate is worked out from its column, so lookups need no hashing, and
+/// iteration always follows the board order. Cells may be missing, as while a map is built.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Grid<T> {
+    radius: u8,
+    slots: Vec<Slot<T>>,
+    len: usize,
+}
+
+/// A cell of a [`Grid`], with its coordinate so iteration can hand out references to it
+type Slot<T> = Option<(Coord, T)>;
+
+impl<T> Grid<T> {
+    /// A board of `radius` with no cells yet
+    pub fn new(radius: u8) -> Self {
+        Self {
+            radius,
+            slots: std::iter::repeat_with(|| None)
+                .take(cell_count(radius))
+                .collect(),
+            len: 0,
+        }
+    }
+
+    /// Position of `coord` in the board order, `None` off the board
+    pub fn position(&self, coord: Coord) -> Option<usize> {
+        board_position(self.radius, coord)
+    }
+
+    pub fn get(&self, coord: &Coord) -> Option<&T> {
+        let slot = self.slots.get(self.position(*coord)?)?;
+        slot.as_ref().map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, coord: &Coord) -> Option<&mut T> {
+        let position = self.position(*coord)?;
+        self.slots[position].as_mut().map(|(_, value)| value)
+    }
+
+    pub fn contains_key(&self, coord: &Coord) -> bool {
+        self.get(coord).is_some()
+    }
+
+    /// Store `value` at `coord`, returning what was there. Panics if `coord` is off the board.
+    pub fn insert(&mut self, coord: Coord, value: T) -> Option<T> {
+        let position = self.position(coord).unwrap_or_else(|| {
+            panic!(
+                "({}, {}) is off a board of radius {}",
+                coord.q, coord.r, self.radius
+            )
+        });
+        let old = self.slots[position].replace((coord, value));
+        self.len += old.is_none() as usize;
+        old.map(|(_, value)| value)
+    }
+
+    pub fn remove(&mut self, coord: &Coord) -> Option<T> {
+        let position = self.position(*coord)?;
+        let old = self.slots[position].take();
+        self.len -= old.is_some() as usize;
+        old.map(|(_, value)| value)
+    }
+
+    /// Number of cells present
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The cells present, in board order
+    pub fn iter(&self) -> impl Iterator<Item = (&Coord, &T)> {
+        self.slots
+            .iter()
+            .flatten()
+            .map(|(coord, value)| (coord, value))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&Coord, &mut T)> {
+        self.slots
+            .iter_mut()
+            .flatten()
+            .map(|(coord, value)| (&*coord, value))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.iter_mut().map(|(_, value)| value)
+    }
+
+    /// A grid of the same shape holding `f` of every cell present
+    pub fn map<U>(&self, f: impl Fn(&T) -> U) -> Grid<U> {
+        Grid {
+            radius: self.radius,
+            slots: self
+                .slots
+                .iter()
+                .map(|slot| slot.as_ref().map(|(coord, value)| (*coord, f(value))))
+                .collect(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T> Index<&Coord> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, coord: &Coord) -> &T {
+        self.get(coord)
+            .unwrap_or_else(|| panic!("no cell at ({}, {})", coord.q, coord.r))
+    }
+}
+
+impl<T> IndexMut<&Coord> for Grid<T> {
+    fn index_mut(&mut self, coord: &Coord) -> &mut T {
+        self.get_mut(coord)
+            .unwrap_or_else(|| panic!("no cell at ({}, {})", coord.q, coord.r))
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Grid<T> {
+    type Item = (&'a Coord, &'a T);
+    type IntoIter = std::iter::Map<
+        std::iter::Flatten<std::slice::Iter<'a, Slot<T>>>,
+        fn(&'a (Coord, T)) -> (&'a Coord, &'a T),
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.slots
+            .iter()
+            .flatten()
+            .map(|(coord, value)| (coord, value))
+    }
+}
+
+impl<T: Serialize> Serialize for Grid<T> {
+    /// The cells present, as `[coord, value]` pairs in board order
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+/// Why a [`MapBuilder`] couldn't build its map
+#[derive(Clone, Debug, PartialEq)]
+pub enum MapError {
+    /// The radius is beyond [`MAX_RADIUS`]
+    RadiusTooLarge(u8),
+    /// A cell was set that isn't on the board
+    OutOfBounds(Coord),
+    /// A cell of the board was never given a region
+    MissingCell(Coord),
+    /// The holes leave no board, or split it, as reported by [`check_board`]
+    Board(String),
+    /// The regions don't make a valid loop, as reported by [`Map::validate_topology`]
+    Topology(String),
+}
+
+impl std::fmt::Display for MapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapError::RadiusTooLarge(radius) => write!(
+                f,
+                "radius {} is too large: at most {} is supported",
+                radius, MAX_RADIUS
+            ),
+            MapError::OutOfBounds(c) => write!(f, "cell ({}, {}) is off the board", c.q, c.r),
+            MapError::MissingCell(c) => write!(f, "cell ({}, {}) has no region", c.q, c.r),
+            MapError::Board(reason) => write!(f, "invalid board: {}", reason),
+            MapError::Topology(reason) => write!(f, "invalid loop: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+/// Summary counts of a map, from [`Map::stats`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MapStats {
+    /// Cells Inside the loop
+    pub inside_cells: usize,
+    /// Clues shown to the player, as a count or by parity
+    pub visible_clues: usize,
+    /// Number of visible clues with each count, 0 to 6
+    pub clue_histogram: [usize; 7],
+    /// Number of edges in the solution loop, over all of its loops
+    pub loop_length: u32,
+}
+
+/// A hex where two maps differ, from [`Map::diff`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellDiff {
+    pub coord: Coord,
+    /// The cell in the first map, `None` if it isn't on that board
+    pub before: Option<Cell>,
+    /// The cell in the second map, `None` if it isn't on that board
+    pub after: Option<Cell>,
+}
+
+impl std::fmt::Display for CellDiff {
+    /// The parts of the cell that changed, e.g. "(1, 0): region Inside -> Outside, count 3 -> 2"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {}): ", self.coord.q, self.coord.r)?;
+        let (before, after) = match (&self.before, &self.after) {
+            (Some(before), Some(after)) => (before, after),
+            (Some(_), None) => return f.write_str("only in the first map"),
+            (None, _) => return f.write_str("only in the second map"),
+        };
+        let mut changes = Vec::new();
+        if before.region != after.region {
+            changes.push(format!("region {:?} -> {:?}", before.region, after.region));
+        }
+        if before.full_neighbor_count != after.full_neighbor_count {
+            changes.push(format!(
+                "count {} -> {}",
+                before.full_neighbor_count, after.full_neighbor_count
+            ));
+        }
+        if before.clue != after.clue {
+            changes.push(format!("clue {:?} -> {:?}", before.clue, after.clue));
+        }
+        f.write_str(&changes.join(", "))
+    }
+}
+
+/// Builds a map from its regions, working out the clues. Every clue starts visible as an
+/// exact count; cells can then be hidden, shown by parity or shown as markers.
+///
+/// Mistakes are remembered and reported by [`build`](MapBuilder::build), so calls can be
+/// chained freely.
+#[derive(Clone, Debug)]
+pub struct MapBuilder {
+    radius: u8,
+    loop_count: u8,
+    rim_edges: RimEdges,
+    regions: Grid<Region>,
+    holes: BTreeSet<Coord>,
+    hidden: HashSet<Coord>,
+    parity: HashSet<Coord>,
+    markers: HashSet<Coord>,
+    error: Option<MapError>,
+}
+
+impl MapBuilder {
+    pub fn new(radius: u8) -> Self {
+        Self {
+            radius,
+            loop_count: 1,
+            rim_edges: RimEdges::Counted,
+            regions: Grid::new(radius.min(MAX_RADIUS)),
+            holes: BTreeSet::new(),
+            hidden: HashSet::new(),
+            parity: HashSet::new(),
+            markers: HashSet::new(),
+            error: (radius > MAX_RADIUS).then_some(MapError::RadiusTooLarge(radius)),
+        }
+    }
+
+    /// Remember `coord` as the first mistake if it's off the board
+    fn check(&mut self, coord: Coord) -> bool {
+        let on_board = coord.in_bounds(self.radius);
+        if !on_board && self.error.is_none() {
+            self.error = Some(MapError::OutOfBounds(coord));
+        }
+        on_board
+    }
+
+    /// Put every cell of the board in `region`
+    pub fn fill(mut self, region: Region) -> Self {
+        for coord in board_coords(self.regions.radius) {
+            self.regions.insert(coord, region);
+        }
+        self
+    }
+
+    /// Cut `coord` out of the board, whatever region it was given
+    pub fn hole(mut self, coord: Coord) -> Self {
+        if self.check(coord) {
+            self.holes.insert(coord);
+        }
+        self
+    }
+
+    pub fn region(mut self, coord: Coord, region: Region) -> Self {
+        if self.check(coord) {
+            self.regions.insert(coord, region);
+        }
+        self
+    }
+
+    /// Put each of `coords` Inside the loop
+    pub fn inside(self, coords: impl IntoIterator<Item = Coord>) -> Self {
+        coords
+            .into_iter()
+            .fold(self, |builder, coord| builder.region(coord, Region::Inside))
+    }
+
+    /// Show or hide the clue of `coord`
+    pub fn visible(mut self, coord: Coord, visible: bool) -> Self {
+        if self.check(coord) {
+            if visible {
+                self.hidden.remove(&coord);
+            } else {
+                self.hidden.insert(coord);
+            }
+        }
+        self
+    }
+
+    /// Show only the parity of the clue of `coord`
+    pub fn parity(mut self, coord: Coord) -> Self {
+        if self.check(coord) {
+            self.parity.insert(coord);
+        }
+        self
+    }
+
+    /// Show the sheep or wolf of `coord`'s region in place of its clue
+    pub fn marker(mut self, coord: Coord) -> Self {
+        if self.check(coord) {
+            self.markers.insert(coord);
+        }
+        self
+    }
+
+    pub fn loop_count(mut self, loop_count: u8) -> Self {
+        self.loop_count = loop_count;
+        self
+    }
+
+    pub fn rim_edges(mut self, rim_edges: RimEdges) -> Self {
+        self.rim_edges = rim_edges;
+        self
+    }
+
+    /// The map, with clue counts worked out from the regions, once every cell has a region and
+    /// the regions are bounded by `loop_count` loops
+    pub fn build(self) -> Result<Map, MapError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let mut map = Map::try_with_holes(self.radius, self.holes.clone())
+            .map_err(|e| MapError::Board(e.to_string()))?;
+        map.loop_count = self.loop_count;
+        map.rim_edges = self.rim_edges;
+        for coord in board_coords(self.radius).filter(|c| !self.holes.contains(c)) {
+            let region = *self
+                .regions
+                .get(&coord)
+                .ok_or(MapError::MissingCell(coord))?;
+            let mut cell = Cell::new(region, 0, !self.hidden.contains(&coord));
+            if self.parity.contains(&coord) {
+                cell.clue = Clue::Parity;
+            }
+            if self.markers.contains(&coord) {
+                cell.clue = Clue::Marker;
+            }
+            map.cells.insert(coord, cell);
+        }
+        map.recompute_clues();
+        map.topology_problem().map_err(MapError::Topology)?;
+        Ok(map)
+    }
+}
+
+/// User-supplied answer key: which cells of a board are Inside the loop
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegionMask {
+    pub radius: u8,
+    pub inside: HashSet<Coord>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::prelude::*;
+
+    #[googletest::test]
+    fn test_iter_coords_count() {
+        // Formula: 3R(R+1) + 1
+        // R=2 -> 3*2*3 + 1 = 19
+        let map = Map::new(2);
+        let count = map.iter_coords().count();
+        expect_that!(count, eq(19));
+
+        // R=0 -> 1
+        let map = Map::new(0);
+        let count = map.iter_coords().count();
+        expect_that!(count, eq(1));
+    }
+
+    fn sample_map() -> Map {
+        let mut map = Map::new(2);
+        let coords: Vec<Coord> = map.iter_coords().collect();
+        for (i, coord) in coords.into_iter().enumerate() {
+            let region = if i % 3 == 0 {
+                Region::Inside
+            } else {
+                Region::Outside
+            };
+            map.cells
+                .insert(coord, Cell::new(region, (i % 7) as u8, i % 2 == 0));
+        }
+        map
+    }
+
+    fn transformed(map: &Map, transform: impl Fn(Coord) -> Coord) -> Map {
+        let mut out = Map::new(map.radius);
+        for (coord, cell) in &map.cells {
+            out.cells.insert(transform(*coord), cell.clone());
+        }
+        out
+    }
+
+    fn map_with_inside(radius: u8, inside: &[Coord]) -> Map {
+        crate::generator::map_from_inside(radius, &inside.iter().copied().collect())
+    }
+
+    #[googletest::test]
+    fn validate_topology_accepts_single_and_multiple_loops() {
+        let center = Coord::new(0, 0);
+        expect_that!(
+            map_with_inside(2, &[center, Coord::new(1, 0)]).validate_topology(),
+            ok(anything())
+        );
+
+        let mut map = map_with_inside(3, &[Coord::new(-2, 0), Coord::new(2, 0)]);
+        expect_that!(
+            map.validate_topology(),
+            err(displays_as(contains_substring("not connected")))
+        );
+        map.loop_count = 2;
+        expect_that!(map.validate_topology(), ok(anything()));
+        map.loop_count = 3;
+        expect_that!(
+            map.validate_topology(),
+            err(displays_as(contains_substring(
+                "2 separate Inside groups for 3 loops"
+            )))
+        );
+    }
+
+    #[googletest::test]
+    fn validate_topology_rejects_holes_and_missing_cells() {
+        let center = Coord::new(0, 0);
+        let ring: Vec<Coord> = center.neighbors().to_vec();
+        expect_that!(
+            map_with_inside(2, &ring).validate_topology(),
+            err(displays_as(contains_substring("encloses 1 Outside cells")))
+        );
+        expect_that!(
+            map_with_inside(2, &[]).validate_topology(),
+            err(displays_as(contains_substring("no Inside cells")))
+        );
+
+        let mut map = map_with_inside(2, &[center]);
+        map.cells.remove(&Coord::new(1, 1));
+        expect_that!(
+            map.validate_topology(),
+            err(displays_as(contains_substring("missing cell (1, 1)")))
+        );
+    }
+
+    #[googletest::test]
+    fn inside_fraction_counts_inside_cells() {
+        // Every third of the 19 cells is Inside
+        expect_that!(sample_map().inside_fraction(), eq(7.0 / 19.0));
+        expect_that!(Map::new(2).inside_fraction(), eq(0.0));
+    }
+
+    #[googletest::test]
+    fn stats_count_the_answer_and_visible_clues() {
+        let mut map = map_with_inside(2, &[Coord::new(0, 0)]);
+        expect_that!(
+            map.stats(),
+            eq(MapStats {
+                inside_cells: 1,
+                visible_clues: 19,
+                clue_histogram: [12, 6, 0, 0, 0, 0, 1],
+                loop_length: 6,
+            })
+        );
+
+        map.cells.get_mut(&Coord::new(0, 0)).unwrap().clue = Clue::Hidden;
+        map.cells.get_mut(&Coord::new(1, 0)).unwrap().clue = Clue::Parity;
+        let stats = map.stats();
+        expect_that!(stats.visible_clues, eq(18));
+        expect_that!(stats.clue_histogram, eq([12, 6, 0, 0, 0, 0, 0]));
+        expect_that!(stats.loop_length, eq(6));
+    }
+
+    #[googletest::test]
+    fn canonical_form_ignores_rotation_and_reflection() {
+        let map = sample_map();
+        let canonical = map.canonical_form();
+        expect_that!(
+            transformed(&map, Coord::rotate).canonical_form(),
+            eq(&canonical)
+        );
+        expect_that!(
+            transformed(&map, |c| c.rotate().rotate().reflect()).canonical_form(),
+            eq(&canonical)
+        );
+    }
+
+    #[googletest::test]
+    fn neighbors_surround_the_cell() {
+        let center = Coord::new(0, 0);
+        let around = center.neighbors();
+        for (d, n) in around.into_iter().enumerate() {
+            // Cyclic order: each neighbor touches the next
+            expect_that!(n.neighbors().contains(&around[(d + 1) % 6]), eq(true));
+            expect_that!(n.neighbors().contains(&center), eq(true));
+        }
+
+        let map = Map::new(1);
+        expect_that!(map.neighbors_in_bounds(center).count(), eq(6));
+        expect_that!(
+            map.neighbors_in_bounds(Coord::new(1, 0))
+                .collect::<Vec<_>>(),
+            elements_are![
+                eq(&Coord::new(1, -1)),
+                eq(&Coord::new(0, 0)),
+                eq(&Coord::new(0, 1))
+            ]
+        );
+        expect_that!(Coord::new(1, -1).in_bounds(1), eq(true));
+        expect_that!(Coord::new(1, 1).in_bounds(1), eq(false));
+    }
+
+    #[googletest::test]
+    fn radius_is_limited_to_the_coordinate_range() {
+        expect_that!(check_radius(MAX_RADIUS), ok(anything()));
+        expect_that!(
+            check_radius(MAX_RADIUS + 1),
+            err(matches_pattern!(SlitherError::InvalidRadius(eq(
+                &(MAX_RADIUS + 1)
+            ))))
+        );
+        expect_that!(
+            Map::try_new(MAX_RADIUS + 1),
+            err(matches_pattern!(SlitherError::InvalidRadius(eq(
+                &(MAX_RADIUS + 1)
+            ))))
+        );
+        expect_that!(
+            Map::try_with_holes(1, BTreeSet::from([Coord::new(3, 0)])),
+            err(matches_pattern!(SlitherError::InvalidBoard(anything())))
+        );
+        expect_that!(
+            SlitherError::from(MapError::RadiusTooLarge(MAX_RADIUS + 1)),
+            matches_pattern!(SlitherError::InvalidRadius(eq(&(MAX_RADIUS + 1))))
+        );
+        // Every neighbor of the largest board still fits in a coordinate
+        let map = Map::new(MAX_RADIUS);
+        let corner = map.iter_ring(MAX_RADIUS).next().unwrap();
+        expect_that!(
+            corner
+                .neighbors()
+                .iter()
+                .filter(|n| !n.in_bounds(MAX_RADIUS))
+                .count(),
+            eq(3)
+        );
+        expect_that!(map.iter_coords().count(), eq(3 * 126 * 127 + 1));
+    }
+
+    #[googletest::test]
+    fn distance_counts_steps_and_survives_symmetry() {
+        let (a, b) = (Coord::new(2, -1), Coord::new(-1, 2));
+        expect_that!(a.distance(b), eq(3));
+        expect_that!(a.distance(a), eq(0));
+        expect_that!(a.rotate().distance(b.rotate()), eq(3));
+        expect_that!(a.reflect().distance(b.reflect()), eq(3));
+        // Six rotations, or two reflections, are the identity
+        let mut c = a;
+        for _ in 0..6 {
+            c = c.rotate();
+        }
+        expect_that!(c, eq(a));
+        expect_that!(a.reflect().reflect(), eq(a));
+    }
+
+    #[googletest::test]
+    fn rings_walk_around_the_center() {
+        let map = Map::new(3);
+        expect_that!(
+            map.iter_ring(0).collect::<Vec<_>>(),
+            elements_are![eq(&Coord::new(0, 0))]
+        );
+        expect_that!(map.iter_ring(4).count(), eq(0));
+        for k in 1..=3 {
+            let ring: Vec<Coord> = map.iter_ring(k).collect();
+            expect_that!(ring.len(), eq(6 * k as usize));
+            expect_that!(ring[0], eq(Coord::new(-(k as i8), k as i8)));
+            for (i, c) in ring.iter().enumerate() {
+                expect_that!(c.distance(Coord::new(0, 0)), eq(k as u32));
+                expect_that!(c.distance(ring[(i + 1) % ring.len()]), eq(1));
+            }
+        }
+
+        let spiral: Vec<Coord> = map.iter_spiral().collect();
+        let mut sorted = spiral.clone();
+        sorted.sort();
+        let mut coords: Vec<Coord> = map.iter_coords().collect();
+        coords.sort();
+        expect_that!(sorted, eq(&coords));
+        expect_that!(
+            spiral[1..7].iter().all(|c| c.distance(spiral[0]) == 1),
+            eq(true)
+        );
+    }
+
+    #[googletest::test]
+    fn recompute_clues_makes_a_map_consistent() {
+        let mut map = map_with_inside(2, &[Coord::new(0, 0), Coord::new(1, 0)]);
+        expect_that!(map.is_consistent(), eq(true));
+        expect_that!(map.cells[&Coord::new(0, 0)].full_neighbor_count, eq(5));
+        // The rim cell next to the Inside pair has two board-edge sides, which don't count
+        expect_that!(map.cells[&Coord::new(2, -1)].full_neighbor_count, eq(1));
+
+        map.cells.get_mut(&Coord::new(1, 1)).unwrap().region = Region::Inside.into();
+        expect_that!(map.is_consistent(), eq(false));
+        map.recompute_clues();
+        expect_that!(map.is_consistent(), eq(true));
+    }
+
+    #[googletest::test]
+    fn edges_and_vertices_are_shared_between_cells() {
+        let (cell, n) = (Coord::new(0, 0), Coord::new(1, 0));
+        let edge = Edge::new(cell, n);
+        expect_that!(Edge::new(n, cell), eq(edge));
+        expect_that!(
+            cell.edges()
+                .iter()
+                .filter(|e| n.edges().contains(e))
+                .count(),
+            eq(1)
+        );
+
+        let [a, b] = edge.vertices();
+        expect_that!(a, not(eq(b)));
+        for vertex in [a, b] {
+            expect_that!(cell.vertices().contains(&vertex), eq(true));
+            expect_that!(n.vertices().contains(&vertex), eq(true));
+            expect_that!(vertex.edges().contains(&edge), eq(true));
+        }
+        // Every corner of a cell ends two of its sides
+        for (d, vertex) in cell.vertices().into_iter().enumerate() {
+            let sides = vertex
+                .edges()
+                .into_iter()
+                .filter(|e| cell.edges().contains(e));
+            expect_that!(sides.count(), eq(2));
+            expect_that!(cell.edges()[d].vertices().contains(&vertex), eq(true));
+        }
+    }
+
+    #[googletest::test]
+    fn loop_of_an_answer_has_degree_two_corners() {
+        let center = Coord::new(0, 0);
+        let map = map_with_inside(2, &[center, Coord::new(1, 0)]);
+        let answer = Loop::of(&map);
+        expect_that!(answer.edges.len(), eq(10));
+        expect_that!(
+            answer.contains(Edge::new(center, Coord::new(1, 0))),
+            eq(false)
+        );
+        let corners: BTreeSet<Vertex> = answer.edges.iter().flat_map(|e| e.vertices()).collect();
+        expect_that!(corners.len(), eq(10));
+        for vertex in corners {
+            expect_that!(answer.degree(vertex), eq(2));
+        }
+        expect_that!(
+            answer.degree(Vertex::new(center, Coord::new(1, 0), Coord::new(0, 1))),
+            eq(2)
+        );
+    }
+
+    #[googletest::test]
+    fn grid_positions_follow_the_board_order() {
+        for radius in [0, 1, 2, 5, MAX_RADIUS] {
+            let grid: Grid<()> = Grid::new(radius);
+            let positions: Vec<Option<usize>> =
+                board_coords(radius).map(|c| grid.position(c)).collect();
+            let expected: Vec<Option<usize>> = (0..cell_count(radius)).map(Some).collect();
+            expect_that!(positions, eq(&expected));
+            expect_that!(grid.position(Coord::new(radius as i8 + 1, 0)), none());
+        }
+    }
+
+    #[googletest::test]
+    fn grid_stores_cells_by_coordinate() {
+        let mut grid = Grid::new(2);
+        let (a, b) = (Coord::new(1, -2), Coord::new(-1, 0));
+        expect_that!(grid.insert(b, 'b'), none());
+        expect_that!(grid.insert(a, 'a'), none());
+        expect_that!(grid.insert(a, 'c'), some(eq('a')));
+        expect_that!(grid.len(), eq(2));
+        expect_that!(grid[&a], eq('c'));
+        expect_that!(grid.get(&Coord::new(0, 0)), none());
+        expect_that!(grid.get(&Coord::new(3, 0)), none());
+        // Board order, not insertion order
+        let cells: Vec<(Coord, char)> = grid.iter().map(|(c, v)| (*c, *v)).collect();
+        expect_that!(cells, elements_are![eq(&(b, 'b')), eq(&(a, 'c'))]);
+        expect_that!(grid.remove(&b), some(eq('b')));
+        expect_that!(grid.len(), eq(1));
+    }
+
+    #[googletest::test]
+    fn player_state_roundtrips_through_json() {
+        let mut player = PlayerState::default();
+        player.set_mark(4, Some(EdgeMark::Line));
+        player.set_mark(7, Some(EdgeMark::Cross));
+        player.set_mark(7, None);
+        player.set_guess(Coord::new(1, -1), Some(Region::Inside));
+        expect_that!(player.mark(4), some(eq(EdgeMark::Line)));
+        expect_that!(player.mark(7), none());
+        expect_that!(player.guess(Coord::new(1, -1)), some(eq(Region::Inside)));
+
+        let json = serde_json::to_string(&player).unwrap();
+        expect_that!(
+            json,
+            eq(r#"{"marks":{"4":"line"},"guesses":[[{"q":1,"r":-1},"Inside"]]}"#)
+        );
+        let loaded: PlayerState = serde_json::from_str(&json).unwrap();
+        expect_that!(loaded, eq(&player));
+        // Saves from before guesses existed still load
+        let loaded: PlayerState = serde_json::from_str(r#"{"marks":{"4":"line"}}"#).unwrap();
+        expect_that!(loaded.guesses, is_empty());
+    }
+
+    #[googletest::test]
+    fn map_roundtrips_through_json() {
+        let mut map = map_with_inside(1, &[Coord::new(0, 0)]);
+        map.cells[&Coord::new(1, 0)].clue = Clue::Parity;
+        map.cells[&Coord::new(0, 1)].clue = Clue::Marker;
+        map.loop_count = 2;
+        map.level = Some(4);
+        let json = serde_json::to_string(&map).unwrap();
+        let loaded: Map = serde_json::from_str(&json).unwrap();
+        expect_that!(loaded, eq(&map));
+    }
+
+    #[googletest::test]
+    fn maps_compare_by_cell_whatever_order_they_were_filled_in() {
+        let map = sample_map();
+        let mut reversed = Map::new(map.radius);
+        let cells: Vec<(Coord, Cell)> = map
+            .cells
+            .iter()
+            .map(|(&c, cell)| (c, cell.clone()))
+            .collect();
+        for (coord, cell) in cells.into_iter().rev() {
+            reversed.cells.insert(coord, cell);
+        }
+        reversed.meta = Some(MapMeta {
+            seed: Some(1),
+            ..MapMeta::default()
+        });
+        expect_that!(reversed, eq(&map));
+
+        let mut changed = map.clone();
+        changed.cells[&Coord::new(0, 0)].clue = Clue::Count;
+        expect_that!(changed, not(eq(&map)));
+        let distinct: HashSet<Map> = [map, reversed, changed].into_iter().collect();
+        expect_that!(distinct.len(), eq(2));
+    }
+
+    #[googletest::test]
+    fn map_json_lists_cells_in_board_order_whatever_order_they_were_filled_in() {
+        let map = sample_map();
+        let mut reversed = Map::new(map.radius);
+        for coord in map.iter_coords().collect::<Vec<_>>().into_iter().rev() {
+            reversed.cells.insert(coord, map.cells[&coord].clone());
+        }
+        let json = serde_json::to_string(&map).unwrap();
+        expect_that!(serde_json::to_string(&reversed).unwrap(), eq(&json));
+
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let coords: Vec<Coord> = parsed["cells"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|pair| serde_json::from_value(pair[0].clone()).unwrap())
+            .collect();
+        expect_that!(coords, eq(&map.iter_coords().collect::<Vec<_>>()));
+    }
+
+    #[googletest::test]
+    fn map_json_must_cover_the_board() {
+        let cell = r#"{"region":"Inside","full_neighbor_count":6,"clue":"Count"}"#;
+        let json = |cells: &[(i8, i8)]| {
+            let cells: Vec<String> = cells
+                .iter()
+                .map(|(q, r)| format!(r#"[{{"q":{},"r":{}}},{}]"#, q, r, cell))
+                .collect();
+            format!(r#"{{"radius":0,"cells":[{}]}}"#, cells.join(","))
+        };
+        let loaded: Map = serde_json::from_str(&json(&[(0, 0)])).unwrap();
+        expect_that!(loaded.loop_count, eq(1));
+        expect_that!(loaded.cells.len(), eq(1));
+        for (cells, error) in [
+            (&[][..], "Cell (0, 0) is missing"),
+            (&[(0, 0), (0, 0)][..], "Cell (0, 0) is listed twice"),
+            (&[(0, 0), (1, 0)][..], "Cell (1, 0) is outside radius 0"),
+        ] {
+            let error_text = serde_json::from_str::<Map>(&json(cells))
+                .unwrap_err()
+                .to_string();
+            expect_that!(error_text, starts_with(error));
+        }
+        let no_loops = json(&[(0, 0)]).replace(r#""radius":0"#, r#""radius":0,"loop_count":0"#);
+        let error_text = serde_json::from_str::<Map>(&no_loops)
+            .unwrap_err()
+            .to_string();
+        expect_that!(error_text, starts_with("Loop count must be at least 1"));
+        let too_many =
+            json(&[(0, 0)]).replace(r#""full_neighbor_count":6"#, r#""full_neighbor_count":9"#);
+        let error_text = serde_json::from_str::<Map>(&too_many)
+            .unwrap_err()
+            .to_string();
+        expect_that!(error_text, starts_with("Cell (0, 0) has a count of 9"));
+    }
+
+    #[googletest::test]
+    fn holes_are_left_out_of_the_board() {
+        let hole = Coord::new(1, 0);
+        let map = MapBuilder::new(1)
+            .fill(Region::Outside)
+            .inside([Coord::new(0, 0)])
+            .hole(hole)
+            .build()
+            .unwrap();
+        expect_that!(map.iter_coords().count(), eq(6));
+        expect_that!(map.iter_coords().any(|c| c == hole), eq(false));
+        expect_that!(map.iter_ring(1).count(), eq(5));
+        expect_that!(map.neighbors_in_bounds(Coord::new(0, 0)).count(), eq(5));
+        // The hole is Outside, so the center's side facing it is on the loop
+        expect_that!(map.cells[&Coord::new(0, 0)].full_neighbor_count, eq(6));
+        expect_that!(
+            map.view().cells.get(&hole),
+            some(eq(&CellView {
+                clue: None,
+                marker: Some(Marker::Wolf),
+            }))
+        );
+
+        let loaded: Map = serde_json::from_str(&serde_json::to_string(&map).unwrap()).unwrap();
+        expect_that!(loaded.holes, eq(&map.holes));
+        let moved = map.transform(HexSymmetry {
+            reflect: false,
+            rotations: 1,
+        });
+        expect_that!(moved.holes, elements_are![eq(&hole.rotate())]);
+        expect_that!(moved.is_consistent(), eq(true));
+    }
+
+    #[googletest::test]
+    fn topology_treats_holes_as_outside() {
+        let center = Coord::new(0, 0);
+        let ring: Vec<Coord> = center.neighbors().to_vec();
+        let built = MapBuilder::new(2)
+            .fill(Region::Outside)
+            .inside(ring)
+            .hole(center)
+            .build();
+        expect_that!(
+            built.unwrap_err().to_string(),
+            eq("invalid loop: the loop encloses 1 holes of the board")
+        );
+
+        let split = MapBuilder::new(1)
+            .fill(Region::Inside)
+            .hole(center)
+            .hole(Coord::new(1, 0))
+            .hole(Coord::new(-1, 0))
+            .build();
+        expect_that!(
+            split.unwrap_err(),
+            eq(&MapError::Board(
+                "The board's cells are not connected".to_string()
+            ))
+        );
+    }
+
+    #[googletest::test]
+    fn solution_loop_walks_the_boundary_in_order() {
+        let inside = [Coord::new(0, 0), Coord::new(1, 0)];
+        let path = map_with_inside(2, &inside).solution_loop().unwrap();
+        expect_that!(path.len(), eq(10));
+        expect_that!(path.vertices.len(), eq(10));
+        for (i, edge) in path.edges.iter().enumerate() {
+            let next = path.vertices[(i + 1) % path.vertices.len()];
+            let mut ends = edge.vertices();
+            ends.sort();
+            let mut expected = [path.vertices[i], next];
+            expected.sort();
+            expect_that!(ends, eq(expected));
+        }
+        let edges: BTreeSet<Edge> = path.edges.iter().copied().collect();
+        expect_that!(edges, eq(&Loop::of(&map_with_inside(2, &inside)).edges));
+    }
+
+    #[googletest::test]
+    fn solution_loop_needs_a_single_cycle() {
+        let apart = map_with_inside(2, &[Coord::new(-1, 0), Coord::new(1, 0)]);
+        expect_that!(
+            apart.solution_loop(),
+            err(displays_as(eq("the boundary is 2 separate loops")))
+        );
+        expect_that!(Loop::of(&apart).cycles().map(|c| c.len()), ok(eq(&2)));
+        expect_that!(
+            map_with_inside(1, &[]).solution_loop(),
+            err(displays_as(eq("no loop: there are no Inside cells")))
+        );
+        // A spur off the ring around the center branches at one corner and dead-ends at another
+        let branched = Loop {
+            edges: Coord::new(0, 0)
+                .edges()
+                .into_iter()
+                .chain([Edge::new(Coord::new(1, 0), Coord::new(1, -1))])
+                .collect(),
+        };
+        expect_that!(
+            branched.cycles(),
+            err(displays_as(contains_substring(
+                "loop edges meet at the corner"
+            )))
+        );
+    }
+
+    #[googletest::test]
+    fn excluded_rim_edges_leave_off_board_sides_out_of_clues() {
+        let (center, east) = (Coord::new(0, 0), Coord::new(1, 0));
+        let build = |rim_edges| {
+            MapBuilder::new(1)
+                .fill(Region::Outside)
+                .inside([center, east])
+                .rim_edges(rim_edges)
+                .build()
+                .unwrap()
+        };
+        let (counted, excluded) = (build(RimEdges::Counted), build(RimEdges::Excluded));
+        // East has three sides off the board, all on the loop
+        expect_that!(counted.cells[&east].full_neighbor_count, eq(5));
+        expect_that!(excluded.cells[&east].full_neighbor_count, eq(2));
+        expect_that!(excluded.cells[&center].full_neighbor_count, eq(5));
+        // Outside rim cells have no loop sides off the board either way
+        expect_that!(
+            excluded.cells[&Coord::new(-1, 0)].full_neighbor_count,
+            eq(1)
+        );
+        expect_that!(
+            excluded.stats().loop_length,
+            eq(counted.stats().loop_length)
+        );
+        expect_that!(excluded.is_consistent(), eq(true));
+        expect_that!(excluded, not(eq(&counted)));
+    }
+
+    #[googletest::test]
+    fn builder_works_out_the_clues() {
+        let (center, east) = (Coord::new(0, 0), Coord::new(1, 0));
+        let map = MapBuilder::new(2)
+            .fill(Region::Outside)
+            .inside([center, east])
+            .visible(center, false)
+            .parity(east)
+            .marker(Coord::new(2, 0))
+            .build()
+            .unwrap();
+        expect_that!(map.is_consistent(), eq(true));
+        let expected = map_with_inside(2, &[center, east]);
+        for coord in map.iter_coords() {
+            let (cell, expected) = (&map.cells[&coord], &expected.cells[&coord]);
+            expect_that!(cell.region, eq(expected.region));
+            expect_that!(cell.full_neighbor_count, eq(expected.full_neighbor_count));
+        }
+        expect_that!(map.cells[&center].clue, eq(Clue::Hidden));
+        expect_that!(map.cells[&east].clue, eq(Clue::Parity));
+        expect_that!(
+            map.cells[&Coord::new(2, 0)].marker(),
+            some(eq(Marker::Wolf))
+        );
+    }
+
+    #[googletest::test]
+    fn builder_reports_the_first_mistake() {
+        let builder = || MapBuilder::new(1).fill(Region::Outside);
+        expect_that!(
+            builder().inside([Coord::new(2, 0)]).build().unwrap_err(),
+            eq(&MapError::OutOfBounds(Coord::new(2, 0)))
+        );
+        expect_that!(
+            MapBuilder::new(1).build().unwrap_err(),
+            eq(&MapError::MissingCell(Coord::new(-1, 0)))
+        );
+        expect_that!(
+            builder().build().unwrap_err().to_string(),
+            eq("invalid loop: no Inside cells")
+        );
+        expect_that!(
+            MapBuilder::new(200)
+                .fill(Region::Inside)
+                .build()
+                .unwrap_err(),
+            eq(&MapError::RadiusTooLarge(200))
+        );
+    }
+
+    #[googletest::test]
+    fn view_shows_only_visible_clues_and_markers() {
+        let mut map = sample_map();
+        let cell = map.cells.get_mut(&Coord::new(0, 0)).unwrap();
+        cell.full_neighbor_count = 3;
+        cell.clue = Clue::Parity;
+        let cell = map.cells.get_mut(&Coord::new(1, 0)).unwrap();
+        cell.region = Region::Outside.into();
+        cell.clue = Clue::Marker;
+        let view = map.view();
+        expect_that!(
+            view.cells[&Coord::new(0, 0)],
+            eq(CellView {
+                clue: Some(VisibleClue::Parity(1)),
+                marker: None,
+            })
+        );
+        expect_that!(
+            view.cells[&Coord::new(1, 0)],
+            eq(CellView {
+                clue: None,
+                marker: Some(Marker::Wolf),
+            })
+        );
+        expect_that!(VisibleClue::Parity(1).allowed_counts(), eq(0x2a));
+
+        // Changing the answer under a hidden clue changes nothing the player sees
+        let hidden = map
+            .iter_coords()
+            .find(|c| map.cells[c].clue == Clue::Hidden)
+            .unwrap();
+        let cell = map.cells.get_mut(&hidden).unwrap();
+        cell.full_neighbor_count = 6 - cell.full_neighbor_count;
+        cell.region = Region::Inside.into();
+        expect_that!(map.view(), eq(&view));
+    }
+
+    #[googletest::test]
+    fn copies_without_answer_keep_the_view() {
+        let mut map = sample_map();
+        map.cells.get_mut(&Coord::new(1, 0)).unwrap().clue = Clue::Marker;
+        map.meta = Some(MapMeta {
+            seed: Some(7),
+            loop_length: Some(12),
+            ..Default::default()
+        });
+        let player = map.without_answer();
+
+        expect_that!(player.view(), eq(&map.view()));
+        expect_that!(
+            player.cells[&Coord::new(1, 0)].region,
+            eq(map.cells[&Coord::new(1, 0)].region)
+        );
+        for coord in map
+            .iter_coords()
+            .filter(|c| map.cells[c].clue != Clue::Marker)
+        {
+            expect_that!(player.cells[&coord].region, eq(KnownRegion::Unknown));
+        }
+        for coord in map
+            .iter_coords()
+            .filter(|c| map.cells[c].clue == Clue::Hidden)
+        {
+            expect_that!(player.cells[&coord].full_neighbor_count, eq(0));
+        }
+        expect_that!(player.meta, some(eq(&MapMeta::default())));
+    }
+
+    #[googletest::test]
+    fn symmetries_form_the_group_of_the_hexagon() {
+        // Off every mirror axis, so no two symmetries agree on it
+        let coord = Coord::new(3, -1);
+        let images: HashSet<Coord> = HexSymmetry::all().map(|s| s.apply(coord)).collect();
+        expect_that!(images.len(), eq(12));
+        for symmetry in HexSymmetry::all() {
+            expect_that!(symmetry.inverse().apply(symmetry.apply(coord)), eq(coord));
+        }
+    }
+
+    #[googletest::test]
+    fn canonicalize_picks_the_same_map_for_every_symmetry() {
+        let map = sample_map();
+        let canonical = map.canonicalize();
+        expect_that!(canonical.canonical_form(), eq(&map.canonical_form()));
+        for symmetry in HexSymmetry::all() {
+            let moved = map.transform(symmetry);
+            expect_that!(moved.canonicalize().cells, eq(&canonical.cells));
+        }
+        let inside = [Coord::new(0, 0), Coord::new(1, 0), Coord::new(1, -1)];
+        let moved = map_with_inside(2, &inside).transform(HexSymmetry {
+            reflect: true,
+            rotations: 2,
+        });
+        expect_that!(moved.is_consistent(), eq(true));
+        let turned = map.transform(HexSymmetry {
+            reflect: false,
+            rotations: 1,
+        });
+        expect_that!(turned.cells, eq(&transformed(&map, Coord::rotate).cells));
+    }
+
+    #[googletest::test]
+    fn border_cells_touch_the_edge_or_a_hole() {
+        let map = Map::new(3);
+        expect_that!(map.ring_of(Coord::new(0, 0)), some(eq(0)));
+        expect_that!(map.ring_of(Coord::new(2, -3)), some(eq(3)));
+        expect_that!(map.ring_of(Coord::new(4, 0)), none());
+        expect_that!(map.iter_outer_ring().count(), eq(18));
+        expect_that!(map.iter_outer_ring().all(|c| map.is_border(c)), eq(true));
+        expect_that!(
+            map.iter_coords().filter(|&c| map.is_border(c)).count(),
+            eq(18)
+        );
+
+        let holed = Map::with_holes(3, BTreeSet::from([Coord::new(0, 0)]));
+        expect_that!(holed.ring_of(Coord::new(0, 0)), none());
+        expect_that!(holed.is_border(Coord::new(1, 0)), eq(true));
+        expect_that!(holed.is_border(Coord::new(2, 0)), eq(false));
+        expect_that!(holed.is_border(Coord::new(0, 0)), eq(false));
+    }
+
+    #[googletest::test]
+    fn diff_lists_the_cells_that_changed() {
+        let map = map_with_inside(1, &[Coord::new(0, 0)]);
+        expect_that!(map.diff(&map.clone()), is_empty());
+
+        let mut other = map_with_inside(1, &[Coord::new(0, 0), Coord::new(1, 0)]);
+        other.cells.get_mut(&Coord::new(-1, 0)).unwrap().clue = Clue::Hidden;
+        let diffs = map.diff(&other);
+        expect_that!(
+            diffs.iter().map(|d| d.coord).collect::<Vec<_>>(),
+            elements_are![
+                eq(&Coord::new(-1, 0)),
+                eq(&Coord::new(0, 0)),
+                eq(&Coord::new(0, 1)),
+                eq(&Coord::new(1, -1)),
+                eq(&Coord::new(1, 0)),
+            ]
+        );
+        expect_that!(diffs[0].to_string(), eq("(-1, 0): clue Count -> Hidden"));
+        expect_that!(
+            diffs[4].to_string(),
+            eq("(1, 0): region Outside -> Inside, count 1 -> 5")
+        );
+
+        let larger = map_with_inside(2, &[Coord::new(0, 0)]);
+        let diffs = map.diff(&larger);
+        expect_that!(diffs, len(eq(12)));
+        expect_that!(diffs[0].to_string(), eq("(-2, 0): only in the second map"));
+    }
+
+    #[googletest::test]
+    fn region_bitmaps_roundtrip_and_move_with_the_map() {
+        let inside = [Coord::new(0, 0), Coord::new(1, 0), Coord::new(1, -1)];
+        let map = map_with_inside(2, &inside);
+        let regions = RegionBitmap::from(&map);
+        expect_that!(regions.count_inside(), eq(3));
+        expect_that!(
+            regions.iter_inside().collect::<Vec<_>>(),
+            elements_are![
+                eq(&Coord::new(0, 0)),
+                eq(&Coord::new(1, -1)),
+                eq(&Coord::new(1, 0))
+            ]
+        );
+        expect_that!(regions.to_map(Map::new(2)).cells, eq(&map.cells));
+
+        for symmetry in HexSymmetry::all() {
+            expect_that!(
+                regions.transform(symmetry),
+                eq(&RegionBitmap::from(&map.transform(symmetry)))
+            );
+        }
+
+        let board = Map::with_holes(2, BTreeSet::from([Coord::new(1, 0)]));
+        let cut = regions.to_map(board);
+        expect_that!(cut.cells.len(), eq(18));
+        expect_that!(RegionBitmap::from(&cut).get(Coord::new(1, 0)), eq(false));
+    }
+
+    #[googletest::test]
+    fn canonical_form_distinguishes_different_maps() {
+        let map = sample_map();
+        let mut other = map.clone();
+        let cell = other.cells.get_mut(&Coord::new(0, 0)).unwrap();
+        cell.clue = if cell.clue_visible() {
+            Clue::Hidden
+        } else {
+            Clue::Count
+        };
+        expect_that!(other.canonical_form(), not(eq(&map.canonical_form())));
     }
 }
@@ -0,0 +1,434 @@
+//! Print sheets: puzzles laid out several to a page in a single PDF, each titled with its
+//! number and difficulty, followed by an appendix of their answers. Pages are drawn from the
+//! same layout as the SVG drawings, in the standard Helvetica fonts, so no fonts are embedded.
+
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use crate::error::SlitherError;
+use crate::model::{Map, Marker};
+use crate::svg::{self, Drawing, HEX_SIZE, Label, Point};
+
+/// Blank space around the edge of the page, in points
+const PAGE_MARGIN: f64 = 36.0;
+/// Height of the page title above the puzzles
+const HEADER: f64 = 30.0;
+/// Height of the page number below them
+const FOOTER: f64 = 16.0;
+/// Height of the caption above each puzzle
+const CAPTION: f64 = 16.0;
+/// Space between neighbouring puzzles
+const GUTTER: f64 = 12.0;
+
+/// Most puzzles that fit legibly on one page
+pub const MAX_PER_PAGE: usize = 12;
+
+/// Paper to lay the sheets out for
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PaperSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl PaperSize {
+    /// Width and height in points
+    pub fn dimensions(self) -> (f64, f64) {
+        match self {
+            PaperSize::A4 => (595.28, 841.89),
+            PaperSize::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+impl FromStr for PaperSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "a4" => Ok(PaperSize::A4),
+            "letter" => Ok(PaperSize::Letter),
+            _ => Err(format!("Invalid paper size: {} (expected a4 or letter)", s)),
+        }
+    }
+}
+
+/// How to lay out the sheets
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrintLayout {
+    pub paper: PaperSize,
+    /// Puzzles on each page, from 1 to [`MAX_PER_PAGE`]
+    pub per_page: usize,
+    /// Heading of every page
+    pub title: String,
+}
+
+/// A puzzle with its caption
+struct Panel {
+    caption: String,
+    drawing: Drawing,
+}
+
+/// Lay out `maps` as a PDF: the puzzles in order, numbered from 1, then the answers of those
+/// that have one. Maps imported without an answer are left out of the appendix.
+pub fn print_sheets(maps: &[Map], layout: &PrintLayout) -> Result<Vec<u8>, SlitherError> {
+    let mut puzzles = Vec::new();
+    let mut answers = Vec::new();
+    for (i, map) in maps.iter().enumerate() {
+        let caption = match difficulty(map) {
+            Some(difficulty) => format!("Puzzle {} - {}", i + 1, difficulty),
+            None => format!("Puzzle {}", i + 1),
+        };
+        puzzles.push(Panel {
+            caption,
+            drawing: svg::draw(map, false)?,
+        });
+        if map.cells.values().all(|cell| cell.region.is_known()) {
+            answers.push(Panel {
+                caption: format!("Answer {}", i + 1),
+                drawing: svg::draw(map, true)?,
+            });
+        }
+    }
+
+    let per_page = layout.per_page.clamp(1, MAX_PER_PAGE);
+    let answers_title = format!("{} - Answers", layout.title);
+    let mut pages: Vec<(&str, &[Panel])> = Vec::new();
+    pages.extend(puzzles.chunks(per_page).map(|c| (layout.title.as_str(), c)));
+    pages.extend(
+        answers
+            .chunks(per_page)
+            .map(|c| (answers_title.as_str(), c)),
+    );
+    let contents: Vec<String> = pages
+        .iter()
+        .enumerate()
+        .map(|(i, (title, panels))| page_content(title, panels, i + 1, per_page, layout.paper))
+        .collect();
+    Ok(document(&contents, layout.paper))
+}
+
+/// The difficulty a puzzle was generated for, if it says
+fn difficulty(map: &Map) -> Option<String> {
+    map.meta
+        .as_ref()
+        .and_then(|meta| meta.requested_difficulty.clone())
+        .or_else(|| map.level.map(|level| format!("level {}", level)))
+}
+
+/// Rows and columns of the grid of `per_page` puzzles, taller than wide to suit portrait pages
+fn grid(per_page: usize) -> (usize, usize) {
+    let rows = (per_page as f64).sqrt().ceil() as usize;
+    (rows, per_page.div_ceil(rows))
+}
+
+/// Drawing operators of one page
+fn page_content(
+    title: &str,
+    panels: &[Panel],
+    number: usize,
+    per_page: usize,
+    paper: PaperSize,
+) -> String {
+    let (width, height) = paper.dimensions();
+    let mut ops = String::new();
+    text(
+        &mut ops,
+        "F2",
+        16.0,
+        PAGE_MARGIN,
+        height - PAGE_MARGIN - 16.0,
+        title,
+    );
+    text(
+        &mut ops,
+        "F1",
+        9.0,
+        PAGE_MARGIN,
+        PAGE_MARGIN,
+        &format!("Page {}", number),
+    );
+
+    let (rows, columns) = grid(per_page);
+    let top = height - PAGE_MARGIN - HEADER;
+    let slot_width = (width - 2.0 * PAGE_MARGIN - GUTTER * (columns - 1) as f64) / columns as f64;
+    let slot_height = (top - PAGE_MARGIN - FOOTER - GUTTER * (rows - 1) as f64) / rows as f64;
+    for (i, panel) in panels.iter().enumerate() {
+        let left = PAGE_MARGIN + (i % columns) as f64 * (slot_width + GUTTER);
+        let slot_top = top - (i / columns) as f64 * (slot_height + GUTTER);
+        text(&mut ops, "F2", 11.0, left, slot_top - 11.0, &panel.caption);
+        draw_panel(
+            &mut ops,
+            &panel.drawing,
+            (left, slot_top - CAPTION),
+            slot_width,
+            slot_height - CAPTION,
+        );
+    }
+    ops
+}
+
+/// Draw `drawing` as large as fits the box whose top left corner is `top_left`, centered in it
+fn draw_panel(ops: &mut String, drawing: &Drawing, top_left: Point, width: f64, height: f64) {
+    let scale = (width / drawing.width).min(height / drawing.height);
+    let left = top_left.0 + (width - drawing.width * scale) / 2.0;
+    let top = top_left.1 - (height - drawing.height * scale) / 2.0;
+    // PDF's y axis points up, the drawing's down
+    let at = |(x, y): Point| {
+        (
+            left + (x - drawing.origin.0) * scale,
+            top - (y - drawing.origin.1) * scale,
+        )
+    };
+
+    let _ = writeln!(ops, "0.6 G {:.2} w", 0.75 * scale);
+    for hex in &drawing.hexes {
+        path(ops, hex.corners.iter().map(|&p| at(p)));
+        ops.push_str(if hex.inside { "0.85 g b\n" } else { "s\n" });
+    }
+    let _ = writeln!(ops, "0 G {:.2} w 1 J 1 j", 3.0 * scale);
+    for cycle in &drawing.loops {
+        path(ops, cycle.iter().map(|&p| at(p)));
+        ops.push_str("s\n");
+    }
+
+    let size = 0.8 * HEX_SIZE * scale;
+    for hex in &drawing.hexes {
+        let (x, y) = at(hex.center);
+        match hex.label {
+            // Helvetica has no emoji, so markers are drawn: an open circle for a sheep and a
+            // filled one for a wolf
+            Some(Label::Marker(marker)) => {
+                let _ = writeln!(ops, "0 G 0 g {:.2} w", 0.1 * size);
+                circle(ops, (x, y), 0.35 * size);
+                ops.push_str(if marker == Marker::Wolf { "f\n" } else { "s\n" });
+            }
+            Some(label) => {
+                let label = label.text();
+                let left = x - text_width(&label) * size / 2.0;
+                // Digits and capitals are about 0.7 em tall, so this centers them vertically
+                ops.push_str("0 g\n");
+                text(ops, "F2", size, left, y - 0.35 * size, &label);
+            }
+            None => {}
+        }
+    }
+}
+
+/// Start a path through `points` in page coordinates, to be closed and painted by the caller
+fn path(ops: &mut String, points: impl IntoIterator<Item = Point>) {
+    for (i, (x, y)) in points.into_iter().enumerate() {
+        let _ = writeln!(ops, "{:.2} {:.2} {}", x, y, if i == 0 { "m" } else { "l" });
+    }
+}
+
+/// A circle as four Bézier curves
+fn circle(ops: &mut String, (x, y): Point, r: f64) {
+    let k = 0.5523 * r;
+    let _ = writeln!(ops, "{:.2} {:.2} m", x + r, y);
+    let _ = writeln!(
+        ops,
+        "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c",
+        x + r,
+        y + k,
+        x + k,
+        y + r,
+        x,
+        y + r
+    );
+    let _ = writeln!(
+        ops,
+        "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c",
+        x - k,
+        y + r,
+        x - r,
+        y + k,
+        x - r,
+        y
+    );
+    let _ = writeln!(
+        ops,
+        "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c",
+        x - r,
+        y - k,
+        x - k,
+        y - r,
+        x,
+        y - r
+    );
+    let _ = writeln!(
+        ops,
+        "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c",
+        x + k,
+        y - r,
+        x + r,
+        y - k,
+        x + r,
+        y
+    );
+}
+
+/// Show `s` with its baseline starting at (`x`, `y`)
+fn text(ops: &mut String, font: &str, size: f64, x: f64, y: f64, s: &str) {
+    let _ = writeln!(
+        ops,
+        "BT /{} {:.2} Tf {:.2} {:.2} Td ({}) Tj ET",
+        font,
+        size,
+        x,
+        y,
+        escape(s)
+    );
+}
+
+/// `s` as the body of a PDF string. The standard fonts only cover Latin text, so anything
+/// outside ASCII prints as `?`.
+fn escape(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '(' | ')' | '\\' => format!("\\{}", c),
+            ' '..='~' => c.to_string(),
+            _ => "?".to_string(),
+        })
+        .collect()
+}
+
+/// Width of a clue in Helvetica Bold, in ems
+fn text_width(s: &str) -> f64 {
+    s.chars()
+        .map(|c| match c {
+            'E' => 0.667,
+            'O' => 0.778,
+            _ => 0.556,
+        })
+        .sum()
+}
+
+/// A PDF file of pages with the given drawing operators, sharing the two fonts
+fn document(contents: &[String], paper: PaperSize) -> Vec<u8> {
+    let (width, height) = paper.dimensions();
+    // Objects 1 to 4 are the catalog, page tree and fonts; each page then takes two, itself
+    // and its contents
+    let page_ids: Vec<usize> = (0..contents.len()).map(|i| 5 + 2 * i).collect();
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            contents.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold >>".to_string(),
+    ];
+    for (content, id) in contents.iter().zip(&page_ids) {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+             /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            width,
+            height,
+            id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        let _ = writeln!(pdf, "{} 0 obj\n{}\nendobj", i + 1, object);
+    }
+    let xref = pdf.len();
+    let _ = writeln!(pdf, "xref\n0 {}\n0000000000 65535 f ", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(pdf, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    );
+    pdf.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Coord, MapBuilder, Region};
+    use googletest::prelude::*;
+
+    fn puzzle() -> Map {
+        MapBuilder::new(1)
+            .fill(Region::Outside)
+            .inside([Coord::new(0, 0)])
+            .marker(Coord::new(1, 0))
+            .build()
+            .unwrap()
+    }
+
+    fn layout(per_page: usize) -> PrintLayout {
+        PrintLayout {
+            paper: PaperSize::Letter,
+            per_page,
+            title: "Weekly (hex)".to_string(),
+        }
+    }
+
+    #[googletest::test]
+    fn sheets_hold_the_puzzles_then_their_answers() {
+        let mut maps = vec![puzzle(); 5];
+        maps[1].level = Some(3);
+        maps[4] = maps[4].without_answer();
+        let pdf = String::from_utf8(print_sheets(&maps, &layout(4)).unwrap()).unwrap();
+
+        // Two pages of puzzles, then one of the four answers
+        expect_that!(pdf, contains_substring("/Count 3"));
+        expect_that!(pdf.matches("/Type /Page ").count(), eq(3));
+        expect_that!(pdf, contains_substring("(Puzzle 2 - level 3) Tj"));
+        expect_that!(pdf, contains_substring("(Puzzle 5) Tj"));
+        expect_that!(pdf, contains_substring("(Answer 4) Tj"));
+        expect_that!(pdf, not(contains_substring("(Answer 5)")));
+        expect_that!(pdf, contains_substring("(Weekly \\(hex\\) - Answers) Tj"));
+        expect_that!(pdf, contains_substring("/MediaBox [0 0 612.00 792.00]"));
+    }
+
+    #[googletest::test]
+    fn cross_reference_table_finds_every_object() {
+        let pdf = print_sheets(&[puzzle()], &layout(1)).unwrap();
+        let pdf = String::from_utf8(pdf).unwrap();
+
+        let xref: usize = pdf
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|tail| tail.lines().next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        expect_that!(pdf[xref..].starts_with("xref\n0 9\n"), eq(true));
+        let offsets: Vec<usize> = pdf[xref..]
+            .lines()
+            .skip(3)
+            .take(8)
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        for (i, offset) in offsets.iter().enumerate() {
+            expect_that!(
+                pdf[*offset..].starts_with(&format!("{} 0 obj\n", i + 1)),
+                eq(true)
+            );
+        }
+    }
+
+    #[googletest::test]
+    fn grids_fill_portrait_pages() {
+        expect_that!(grid(1), eq((1, 1)));
+        expect_that!(grid(2), eq((2, 1)));
+        expect_that!(grid(4), eq((2, 2)));
+        expect_that!(grid(6), eq((3, 2)));
+    }
+}
@@ -0,0 +1,583 @@
+//! QR codes of share codes, for printing next to puzzles so readers can open them on a phone.
+//! Byte mode at error correction level M (about 15% of the symbol can be damaged), in the
+//! smallest version that fits, following ISO/IEC 18004.
+
+use std::fmt::Write as _;
+
+use crate::error::SlitherError;
+
+/// Error correction codewords per block at level M, by version
+const ECC_PER_BLOCK: [usize; 41] = [
+    0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28,
+    28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+];
+/// Error correction blocks at level M, by version
+const BLOCKS: [usize; 41] = [
+    0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23,
+    25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49,
+];
+/// Level M in the format information
+const FORMAT_LEVEL_M: u32 = 0b00;
+/// Light modules around the symbol, which scanners need to find it
+const QUIET_ZONE: usize = 4;
+
+/// A QR code symbol: `size` by `size` modules, dark where true
+#[derive(Clone, Debug, PartialEq)]
+pub struct QrCode {
+    pub version: usize,
+    pub size: usize,
+    modules: Vec<bool>,
+    /// Finder, timing, alignment, format and version modules, which masks leave alone
+    function: Vec<bool>,
+}
+
+impl QrCode {
+    /// The smallest symbol holding `data`. Fails if it exceeds the 2331 bytes of version 40.
+    pub fn encode(data: &[u8]) -> Result<QrCode, SlitherError> {
+        let version = (1..=40)
+            .find(|&v| data_capacity(v) * 8 >= 4 + count_bits(v) + data.len() * 8)
+            .ok_or_else(|| {
+                SlitherError::InvalidData(format!(
+                    "{} bytes is too long for a QR code, which holds at most {}",
+                    data.len(),
+                    data_capacity(40) - 3
+                ))
+            })?;
+
+        // Byte mode, the length, the data, then a terminator and padding to fill the version
+        let mut bits = Bits::default();
+        bits.push(0b0100, 4);
+        bits.push(data.len() as u32, count_bits(version));
+        for &byte in data {
+            bits.push(byte as u32, 8);
+        }
+        let capacity = data_capacity(version) * 8;
+        bits.push(0, (capacity - bits.len).min(4));
+        bits.push(0, (8 - bits.len % 8) % 8);
+        let mut codewords = bits.bytes;
+        for pad in [0xec, 0x11].into_iter().cycle() {
+            if codewords.len() == data_capacity(version) {
+                break;
+            }
+            codewords.push(pad);
+        }
+
+        let size = 4 * version + 17;
+        let mut qr = QrCode {
+            version,
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+        };
+        qr.draw_function_patterns();
+        qr.draw_codewords(&add_error_correction(&codewords, version));
+
+        // Keep the mask that leaves the fewest patterns confusing to scanners
+        let mask = (0..8)
+            .min_by_key(|&mask| {
+                let mut candidate = qr.clone();
+                candidate.apply_mask(mask);
+                candidate.draw_format_bits(mask);
+                candidate.penalty()
+            })
+            .expect("there are eight masks");
+        qr.apply_mask(mask);
+        qr.draw_format_bits(mask);
+        Ok(qr)
+    }
+
+    /// Whether the module at column `x` and row `y` is dark
+    pub fn module(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        for (x, y) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            self.draw_finder(x, y);
+        }
+        let positions = alignment_positions(self.version);
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                // The corners with finder patterns have no alignment pattern
+                if !matches!((i, j), (0, 0)) && (i, j) != (0, last) && (i, j) != (last, 0) {
+                    self.draw_alignment(x, y);
+                }
+            }
+        }
+        // Reserve the format areas, drawn once the mask is known
+        self.draw_format_bits(0);
+        self.draw_version();
+    }
+
+    /// A finder pattern centered on (`x`, `y`), with its light separator
+    fn draw_finder(&mut self, x: usize, y: usize) {
+        for dy in -4i32..=4 {
+            for dx in -4i32..=4 {
+                let (xx, yy) = (x as i32 + dx, y as i32 + dy);
+                if (0..self.size as i32).contains(&xx) && (0..self.size as i32).contains(&yy) {
+                    let distance = dx.abs().max(dy.abs());
+                    self.set_function(xx as usize, yy as usize, distance != 2 && distance != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, x: usize, y: usize) {
+        for dy in -2i32..=2 {
+            for dx in -2i32..=2 {
+                let dark = dx.abs().max(dy.abs()) != 1;
+                self.set_function((x as i32 + dx) as usize, (y as i32 + dy) as usize, dark);
+            }
+        }
+    }
+
+    /// Both copies of the level and mask, with their BCH check bits
+    fn draw_format_bits(&mut self, mask: u32) {
+        let bits = format_bits(mask);
+        let bit = |i: usize| bits >> i & 1 != 0;
+        let size = self.size;
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    /// Both copies of the version, from version 7 on
+    fn draw_version(&mut self) {
+        if self.version < 7 {
+            return;
+        }
+        let bits = version_bits(self.version);
+        for i in 0..18 {
+            let dark = bits >> i & 1 != 0;
+            let (a, b) = (self.size - 11 + i % 3, i / 3);
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// Place the codewords in two-module columns zigzagging up and down from the bottom right
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let mut i = 0;
+        let mut right = self.size - 1;
+        while right >= 1 {
+            // The vertical timing pattern takes a whole column
+            if right == 6 {
+                right = 5;
+            }
+            for vertical in 0..self.size {
+                for j in 0..2 {
+                    let x = right - j;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward {
+                        self.size - 1 - vertical
+                    } else {
+                        vertical
+                    };
+                    if !self.function[y * self.size + x] && i < codewords.len() * 8 {
+                        self.modules[y * self.size + x] = codewords[i / 8] >> (7 - i % 8) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if !self.function[y * self.size + x] && masked(mask, x, y) {
+                    self.modules[y * self.size + x] ^= true;
+                }
+            }
+        }
+    }
+
+    /// The four penalty scores of the standard: long runs of one color, 2x2 blocks, patterns
+    /// that look like finders, and a balance of dark and light away from half
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let mut penalty = 0;
+        let lines = (0..size).flat_map(|i| {
+            [
+                (0..size).map(|j| self.module(j, i)).collect::<Vec<_>>(),
+                (0..size).map(|j| self.module(i, j)).collect::<Vec<_>>(),
+            ]
+        });
+        let finder = [true, false, true, true, true, false, true];
+        for line in lines {
+            let mut run = 1;
+            for j in 1..=size {
+                if j < size && line[j] == line[j - 1] {
+                    run += 1;
+                } else {
+                    if run >= 5 {
+                        penalty += run - 2;
+                    }
+                    run = 1;
+                }
+            }
+            for j in 0..=size - 7 {
+                if line[j..j + 7] != finder {
+                    continue;
+                }
+                let light = |range: std::ops::Range<usize>| range.clone().all(|k| !line[k]);
+                if (j >= 4 && light(j - 4..j)) || (j + 11 <= size && light(j + 7..j + 11)) {
+                    penalty += 40;
+                }
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = self.module(x, y);
+                if color == self.module(x + 1, y)
+                    && color == self.module(x, y + 1)
+                    && color == self.module(x + 1, y + 1)
+                {
+                    penalty += 3;
+                }
+            }
+        }
+        let dark = self.modules.iter().filter(|&&m| m).count();
+        let total = size * size;
+        let deviation = (dark * 20).abs_diff(total * 10);
+        penalty + deviation.div_ceil(total).saturating_sub(1) * 10
+    }
+
+    /// The symbol as an SVG document, one unit per module, with its quiet zone
+    pub fn to_svg(&self) -> String {
+        let side = self.size + 2 * QUIET_ZONE;
+        let mut path = String::new();
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if self.module(x, y) {
+                    let _ = write!(path, "M{},{}h1v1h-1z", x + QUIET_ZONE, y + QUIET_ZONE);
+                }
+            }
+        }
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {side} {side}\" \
+             shape-rendering=\"crispEdges\">\n<rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>\n\
+             <path d=\"{path}\" fill=\"#000\"/>\n</svg>\n"
+        )
+    }
+}
+
+/// Bits appended most significant first
+#[derive(Default)]
+struct Bits {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl Bits {
+    fn push(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            if self.len.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            let bit = (value >> i & 1) as u8;
+            *self.bytes.last_mut().expect("a byte was pushed") |= bit << (7 - self.len % 8);
+            self.len += 1;
+        }
+    }
+}
+
+/// Bits of the length field in byte mode
+fn count_bits(version: usize) -> usize {
+    if version < 10 { 8 } else { 16 }
+}
+
+/// Modules left for codewords once the function patterns are drawn
+fn raw_modules(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignments = version / 7 + 2;
+        modules -= (25 * alignments - 10) * alignments - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules
+}
+
+/// Data codewords of `version` at level M
+fn data_capacity(version: usize) -> usize {
+    raw_modules(version) / 8 - ECC_PER_BLOCK[version] * BLOCKS[version]
+}
+
+/// Centers of the alignment patterns along either axis
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let size = 4 * version + 17;
+    let step = if version == 32 {
+        26
+    } else {
+        (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2
+    };
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| size - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// Level M and `mask`, with their BCH check bits, XORed with the standard's mask
+fn format_bits(mask: u32) -> u32 {
+    let data = FORMAT_LEVEL_M << 3 | mask;
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    (data << 10 | rem) ^ 0x5412
+}
+
+/// `version` with its BCH check bits
+fn version_bits(version: usize) -> u32 {
+    let mut rem = version as u32;
+    for _ in 0..12 {
+        rem = (rem << 1) ^ ((rem >> 11) * 0x1f25);
+    }
+    (version as u32) << 12 | rem
+}
+
+/// Whether mask pattern `mask` flips the module at column `x` and row `y`
+fn masked(mask: u32, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+    }
+}
+
+/// Split the data codewords into blocks, append each block's Reed-Solomon codewords, and
+/// interleave the blocks
+fn add_error_correction(data: &[u8], version: usize) -> Vec<u8> {
+    let blocks = BLOCKS[version];
+    let ecc_len = ECC_PER_BLOCK[version];
+    let raw = raw_modules(version) / 8;
+    let short_blocks = blocks - raw % blocks;
+    let short_len = raw / blocks;
+    let divisor = rs_divisor(ecc_len);
+
+    let mut split = Vec::new();
+    let mut rest = data;
+    for i in 0..blocks {
+        let len = short_len - ecc_len + usize::from(i >= short_blocks);
+        let (block, tail) = rest.split_at(len);
+        rest = tail;
+        split.push((block.to_vec(), rs_remainder(block, &divisor)));
+    }
+
+    let mut result = Vec::with_capacity(raw);
+    for i in 0..short_len - ecc_len + 1 {
+        for (block, _) in &split {
+            if let Some(&byte) = block.get(i) {
+                result.push(byte);
+            }
+        }
+    }
+    for i in 0..ecc_len {
+        for (_, ecc) in &split {
+            result.push(ecc[i]);
+        }
+    }
+    result
+}
+
+/// Product in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u16 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11d);
+        z ^= ((y >> i) & 1) as u16 * x as u16;
+    }
+    z as u8
+}
+
+/// Coefficients of the Reed-Solomon generator polynomial of `degree`, highest first, leaving
+/// out the leading 1
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0; degree];
+    result[degree - 1] = 1;
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_multiply(d, factor);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::prelude::*;
+
+    /// Read the codewords back out of a symbol, undoing its mask
+    fn read_codewords(qr: &QrCode) -> Vec<u8> {
+        let bits = (0..15).fold(0, |bits, i| {
+            let x = qr.size - 1 - i.min(7);
+            let (x, y) = if i < 8 { (x, 8) } else { (8, qr.size - 15 + i) };
+            bits | (qr.module(x, y) as u32) << i
+        });
+        let mask = (0..8).find(|&m| format_bits(m) == bits).unwrap();
+        let mut plain = qr.clone();
+        plain.apply_mask(mask);
+
+        let mut codewords = Vec::new();
+        let mut i = 0;
+        let mut right = qr.size - 1;
+        loop {
+            if right == 6 {
+                right = 5;
+            }
+            for vertical in 0..qr.size {
+                for j in 0..2 {
+                    let x = right - j;
+                    let y = if (right + 1) & 2 == 0 {
+                        qr.size - 1 - vertical
+                    } else {
+                        vertical
+                    };
+                    if !qr.function[y * qr.size + x] {
+                        if i % 8 == 0 {
+                            codewords.push(0);
+                        }
+                        *codewords.last_mut().unwrap() |= (plain.module(x, y) as u8) << (7 - i % 8);
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+        codewords.truncate(raw_modules(qr.version) / 8);
+        codewords
+    }
+
+    #[googletest::test]
+    fn error_correction_matches_the_reference_example() {
+        // "HELLO WORLD" in alphanumeric mode at 1-M, from the Thonky QR code tutorial
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        expect_that!(
+            rs_remainder(&data, &rs_divisor(10)),
+            eq(&vec![196, 35, 39, 119, 235, 215, 231, 226, 93, 23])
+        );
+    }
+
+    #[googletest::test]
+    fn format_and_version_bits_match_the_standard() {
+        expect_that!(format_bits(0), eq(0b101010000010010));
+        expect_that!(format_bits(5), eq(0b100000011001110));
+        expect_that!(format_bits(7), eq(0b100101010100000));
+        expect_that!(version_bits(7), eq(0b000111110010010100));
+        expect_that!(alignment_positions(7), eq(&vec![6, 22, 38]));
+        expect_that!(alignment_positions(32), eq(&vec![6, 34, 60, 86, 112, 138]));
+    }
+
+    #[googletest::test]
+    fn symbols_hold_their_data_in_the_smallest_version() {
+        expect_that!(QrCode::encode(&[b'x'; 14]).unwrap().version, eq(1));
+        expect_that!(QrCode::encode(&[b'x'; 15]).unwrap().version, eq(2));
+        expect_that!(QrCode::encode(&[0; 2331]).unwrap().version, eq(40));
+        expect_that!(QrCode::encode(&[0; 2332]), err(anything()));
+
+        for (len, version) in [(60, 4), (200, 10), (400, 15)] {
+            let data: Vec<u8> = (0..len).map(|i| (i * 7 % 251) as u8).collect();
+            let qr = QrCode::encode(&data).unwrap();
+            expect_that!(qr.version, eq(version));
+            expect_that!(qr.size, eq(4 * version + 17));
+
+            let codewords = read_codewords(&qr);
+            expect_that!(
+                codewords,
+                eq(&add_error_correction(
+                    &codewords_of(&data, version),
+                    version
+                ))
+            );
+        }
+    }
+
+    /// Data codewords of `data` in byte mode for `version`, before error correction
+    fn codewords_of(data: &[u8], version: usize) -> Vec<u8> {
+        let mut bits = Bits::default();
+        bits.push(0b0100, 4);
+        bits.push(data.len() as u32, count_bits(version));
+        for &byte in data {
+            bits.push(byte as u32, 8);
+        }
+        bits.push(0, (data_capacity(version) * 8 - bits.len).min(4));
+        bits.push(0, (8 - bits.len % 8) % 8);
+        let mut codewords = bits.bytes;
+        let mut pads = [0xec, 0x11].into_iter().cycle();
+        codewords.resize_with(data_capacity(version), || pads.next().unwrap());
+        codewords
+    }
+
+    #[googletest::test]
+    fn svg_draws_dark_modules_inside_a_quiet_zone() {
+        let qr = QrCode::encode(b"U0xUSAM").unwrap();
+        let svg = qr.to_svg();
+        let dark = qr.modules.iter().filter(|&&m| m).count();
+        expect_that!(svg.matches("h1v1h-1z").count(), eq(dark));
+        expect_that!(svg, contains_substring("viewBox=\"0 0 29 29\""));
+        // The top left finder's corner sits just inside the quiet zone
+        expect_that!(svg, contains_substring("M4,4h1v1h-1z"));
+    }
+}
@@ -0,0 +1,577 @@
+//! Solving puzzles from their visible clues alone.
+//!
+//! The hex grid is seen as an edge graph: its vertices are the corners of the cells and its
+//! edges are their sides. A solution is the set of edges on the loop (or loops), every corner
+//! touching either none of them or exactly two.
+//!
+//! The backtracking search does not branch on edges one at a time. It is the search the
+//! generator uses for uniqueness checks, which relates cells as being in the same region or
+//! different ones: a side is on the loop exactly when the cells on either side of it differ,
+//! so every decided relation decides an edge state, and a single branch on a cell's region
+//! decides all six of its sides at once.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+pub use crate::generator::logic::{
+    Deductions, LogicConfig, Rule, RuleStep, deduce, deduce_with, next_hint,
+};
+#[cfg(feature = "sat")]
+use crate::generator::sat;
+use crate::generator::uniqueness::{self, find_solutions};
+use crate::generator::{SolverBackend, board_from_inside};
+use crate::model::{Coord, Edge, KnownRegion, Map, PuzzleView, Region, Vertex};
+pub use crate::model::{EdgeMark, PlayerState};
+
+/// The corners and sides of a hexagonal board, numbered in a fixed order for the radius
+pub struct EdgeGraph {
+    radius: u8,
+    edges: Vec<Edge>,
+    /// The corners at either end of every edge
+    corners: Vec<[usize; 2]>,
+    corner_count: usize,
+    /// Edge index of every side of every cell, by direction as in `neighbors`
+    sides: HashMap<Coord, [usize; 6]>,
+}
+
+impl EdgeGraph {
+    pub fn new(radius: u8) -> Self {
+        let coords: Vec<Coord> = Map::new(radius).iter_coords().collect();
+        let mut corners: HashMap<Vertex, usize> = HashMap::new();
+        let mut corner = |cell: Coord, direction: usize| {
+            let next = corners.len();
+            *corners.entry(cell.vertices()[direction]).or_insert(next)
+        };
+
+        let mut edges = Vec::new();
+        let mut edge_corners = Vec::new();
+        let mut sides = HashMap::new();
+        for &cell in &coords {
+            let mut cell_sides = [0; 6];
+            for (direction, &n) in cell.neighbors().iter().enumerate() {
+                let shared = sides
+                    .get(&n)
+                    .map(|n_sides: &[usize; 6]| n_sides[(direction + 3) % 6]);
+                cell_sides[direction] = shared.unwrap_or_else(|| {
+                    edges.push(Edge::new(cell, n));
+                    edge_corners.push([corner(cell, (direction + 5) % 6), corner(cell, direction)]);
+                    edges.len() - 1
+                });
+            }
+            sides.insert(cell, cell_sides);
+        }
+
+        Self {
+            radius,
+            edges,
+            corners: edge_corners,
+            corner_count: corners.len(),
+            sides,
+        }
+    }
+
+    pub fn radius(&self) -> u8 {
+        self.radius
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    pub fn corner_count(&self) -> usize {
+        self.corner_count
+    }
+
+    /// The corners at either end of edge `edge`
+    pub fn corners(&self, edge: usize) -> [usize; 2] {
+        self.corners[edge]
+    }
+
+    /// Index of the side of `cell` facing its neighbor in `direction`
+    pub fn edge(&self, cell: Coord, direction: usize) -> Option<usize> {
+        self.sides.get(&cell).map(|sides| sides[direction])
+    }
+
+    /// Indices of the edges on the loop(s) of an answer: the sides between cells in different
+    /// regions, counting positions off the board as Outside
+    pub fn loop_edges(&self, answer: &Map) -> Vec<usize> {
+        let region = |c: Coord| {
+            answer
+                .cells
+                .get(&c)
+                .map_or(KnownRegion::Outside, |cell| cell.region)
+        };
+        (0..self.edges.len())
+            .filter(|&e| {
+                let [a, b] = self.edges[e].cells();
+                region(a) != region(b)
+            })
+            .collect()
+    }
+
+    /// Split loop edges into the separate loops they form, each listed in order around its loop.
+    /// Fails if some corner doesn't touch exactly zero or two of the edges.
+    pub fn loops(&self, edges: &[usize]) -> Result<Vec<Vec<usize>>, String> {
+        let mut at_corner: Vec<Vec<usize>> = vec![Vec::new(); self.corner_count];
+        for &e in edges {
+            for c in self.corners[e] {
+                at_corner[c].push(e);
+            }
+        }
+        if let Some(c) = at_corner
+            .iter()
+            .position(|es| !es.is_empty() && es.len() != 2)
+        {
+            return Err(format!(
+                "corner {} touches {} loop edges",
+                c,
+                at_corner[c].len()
+            ));
+        }
+
+        let mut visited = vec![false; self.edges.len()];
+        let mut loops = Vec::new();
+        for &start in edges {
+            if visited[start] {
+                continue;
+            }
+            let mut path = Vec::new();
+            let (mut edge, mut corner) = (start, self.corners[start][1]);
+            while !visited[edge] {
+                visited[edge] = true;
+                path.push(edge);
+                let next = at_corner[corner]
+                    .iter()
+                    .copied()
+                    .find(|&e| e != edge)
+                    .expect("every loop corner touches two edges");
+                let [a, b] = self.corners[next];
+                corner = if a == corner { b } else { a };
+                edge = next;
+            }
+            loops.push(path);
+        }
+        Ok(loops)
+    }
+}
+
+/// An edge the player can work out next, and why
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Hint {
+    /// Index in `EdgeGraph::new(radius)`
+    pub edge: usize,
+    pub mark: EdgeMark,
+    /// The rule that decides the edge. `None` when it already follows from the markers and the
+    /// player's marks, like the last side at a corner whose other two sides are marked.
+    pub rule: Option<Rule>,
+    /// Cells the rule was applied to
+    pub at: Vec<Coord>,
+}
+
+/// Something wrong with a player's marks, however the rest of the board is filled in
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Conflict {
+    /// A corner with more than two lines, or with one line and every other side crossed out
+    DeadEnd { corner: usize },
+    /// A visible clue or marker the marks around its cell can no longer satisfy
+    Clue { cell: Coord },
+    /// A closed loop while lines are left over elsewhere, or one loop more than the puzzle has.
+    /// Lists the edges of the first closed loop.
+    ClosedLoop { edges: Vec<usize> },
+}
+
+/// The first conflict in a player's marks, checking corners, then clues, then closed loops, each
+/// in board order. `None` if the marks can still be completed without breaking a rule, though
+/// not necessarily into the answer.
+pub fn find_conflict(view: &PuzzleView, player: &PlayerState) -> Option<Conflict> {
+    let graph = EdgeGraph::new(view.radius);
+    let mark = |edge: usize| player.mark(edge);
+    let count = |edges: &[usize], of: Option<EdgeMark>| {
+        edges.iter().filter(|&&edge| mark(edge) == of).count()
+    };
+
+    let mut at_corner: Vec<Vec<usize>> = vec![Vec::new(); graph.corner_count()];
+    for e in 0..graph.edges().len() {
+        for c in graph.corners(e) {
+            at_corner[c].push(e);
+        }
+    }
+    for (corner, edges) in at_corner.iter().enumerate() {
+        let lines = count(edges, Some(EdgeMark::Line));
+        if lines > 2 || (lines == 1 && count(edges, None) == 0) {
+            return Some(Conflict::DeadEnd { corner });
+        }
+    }
+
+    let region = |c: Coord| match view.cells.get(&c) {
+        Some(cell) => cell.marker.map(|marker| marker.region()),
+        None => Some(Region::Outside),
+    };
+    for coord in view.iter_coords() {
+        let cell = &view.cells[&coord];
+        let sides = graph.sides[&coord];
+        if let Some(clue) = cell.clue {
+            // Sides facing off the board, holes included, only count if the rim does
+            let counted: Vec<usize> = coord
+                .neighbors()
+                .iter()
+                .zip(sides)
+                .filter(|(n, _)| {
+                    view.rim_edges.is_counted()
+                        || (n.in_bounds(view.radius) && !view.holes.contains(n))
+                })
+                .map(|(_, side)| side)
+                .collect();
+            let lines = count(&counted, Some(EdgeMark::Line));
+            let open = count(&counted, None);
+            if !(lines..=lines + open).any(|n| clue.allowed_counts() & (1 << n) != 0) {
+                return Some(Conflict::Clue { cell: coord });
+            }
+        }
+        let Some(inside) = region(coord) else {
+            continue;
+        };
+        for (direction, n) in coord.neighbors().into_iter().enumerate() {
+            let expected = match region(n) {
+                Some(n_region) if n_region != inside => EdgeMark::Line,
+                Some(_) => EdgeMark::Cross,
+                None => continue,
+            };
+            if mark(sides[direction]).is_some_and(|m| m != expected) {
+                return Some(Conflict::Clue { cell: coord });
+            }
+        }
+    }
+
+    // No corner has more than two lines now, so lines form paths and closed loops
+    let mut closed = Vec::new();
+    let mut loose = 0;
+    let mut seen = vec![false; graph.edges().len()];
+    for start in (0..graph.edges().len()).filter(|&edge| mark(edge) == Some(EdgeMark::Line)) {
+        if seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let (mut component, mut stack, mut ends) = (Vec::new(), vec![start], false);
+        while let Some(edge) = stack.pop() {
+            component.push(edge);
+            for c in graph.corners(edge) {
+                let lines = at_corner[c]
+                    .iter()
+                    .filter(|&&e| mark(e) == Some(EdgeMark::Line));
+                ends |= lines.clone().count() < 2;
+                for &next in lines {
+                    if !seen[next] {
+                        seen[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        if ends {
+            loose += component.len();
+        } else {
+            component.sort();
+            closed.push(component);
+        }
+    }
+    let loops = view.loop_count as usize;
+    if closed.len() > loops || (closed.len() == loops && loose > 0) {
+        return Some(Conflict::ClosedLoop {
+            edges: closed.swap_remove(0),
+        });
+    }
+    None
+}
+
+/// A solution of a puzzle
+pub struct Solution {
+    /// The solved map, with every clue visible
+    pub answer: Map,
+    /// The loops, as edge indices of `EdgeGraph::new(radius)` in order around each loop
+    pub loops: Vec<Vec<usize>>,
+}
+
+/// Find up to `limit` solutions of the puzzle formed by the visible clues and markers of `view`
+pub fn solve(view: &PuzzleView, limit: usize) -> Vec<Solution> {
+    let graph = EdgeGraph::new(view.radius);
+    find_solutions(view, limit)
+        .into_iter()
+        .map(|inside| {
+            let board = Map::with_holes(view.radius, view.holes.clone());
+            let mut answer = board_from_inside(board, &inside);
+            answer.loop_count = view.loop_count;
+            let loops = graph
+                .loops(&graph.loop_edges(&answer))
+                .expect("solutions are bounded by loops");
+            Solution { answer, loops }
+        })
+        .collect()
+}
+
+/// Number of solutions of the puzzle formed by the visible clues and markers of `view`, counting
+/// no further than `limit`: 2 is enough to tell whether a puzzle is unique, 1 whether it can be
+/// solved at all. The search stops as soon as `limit` solutions are found, and prunes every
+/// branch the clues or connectivity rule out, so a low limit stays fast on large boards.
+pub fn count_solutions(view: &PuzzleView, limit: usize) -> usize {
+    uniqueness::count_solutions(view, limit)
+}
+
+/// Solutions found by a count and the work it took
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchStats {
+    pub solutions: usize,
+    /// Nodes of the backtracking search, or decisions of the SAT solver
+    pub nodes: usize,
+}
+
+/// Like [`count_solutions`], but with the given solver backend, reporting how much searching
+/// it took
+pub fn count_solutions_with(
+    view: &PuzzleView,
+    limit: usize,
+    backend: SolverBackend,
+) -> SearchStats {
+    let (solutions, nodes) = match backend {
+        SolverBackend::Backtracking => uniqueness::count_solutions_and_nodes(view, limit),
+        #[cfg(feature = "sat")]
+        SolverBackend::Sat => sat::count_solutions_and_nodes(view, limit),
+    };
+    SearchStats { solutions, nodes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Difficulty;
+    use crate::generator::{
+        Generator, LoopGenerator, MultiLoopGenerator, loop_length, map_from_inside,
+    };
+    use crate::model::Clue;
+    use googletest::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::collections::HashSet;
+
+    #[googletest::test]
+    fn edge_graph_counts_corners_and_sides() {
+        // A single hexagon, then the seven hexagons of radius 1
+        expect_that!(EdgeGraph::new(0).edges().len(), eq(6));
+        expect_that!(EdgeGraph::new(0).corner_count(), eq(6));
+        expect_that!(EdgeGraph::new(1).edges().len(), eq(30));
+        expect_that!(EdgeGraph::new(1).corner_count(), eq(24));
+
+        let graph = EdgeGraph::new(2);
+        let (cell, n) = (Coord::new(0, 0), Coord::new(1, 0));
+        expect_that!(graph.edge(cell, 0), eq(graph.edge(n, 3)));
+        expect_that!(graph.edge(Coord::new(3, 0), 0), none());
+    }
+
+    #[googletest::test]
+    fn finds_the_unique_solution() {
+        let map = LoopGenerator.generate(3, Difficulty::Hard, &mut StdRng::seed_from_u64(7));
+        let solutions = solve(&map.view(), 2);
+        expect_that!(solutions.len(), eq(1));
+        let solution = &solutions[0];
+        for coord in map.iter_coords() {
+            expect_that!(
+                solution.answer.cells[&coord].region,
+                eq(map.cells[&coord].region)
+            );
+        }
+        expect_that!(solution.loops.len(), eq(1));
+        expect_that!(solution.loops[0].len() as u32, eq(loop_length(&map)));
+    }
+
+    #[googletest::test]
+    fn ignores_the_answer_under_hidden_clues() {
+        let map = LoopGenerator.generate(3, Difficulty::Hard, &mut StdRng::seed_from_u64(7));
+        let mut scrambled = map.clone();
+        for cell in scrambled.cells.values_mut().filter(|c| !c.clue_visible()) {
+            cell.region = Region::Inside.into();
+            cell.full_neighbor_count = 0;
+        }
+        let solutions = solve(&scrambled.view(), 2);
+        expect_that!(solutions.len(), eq(1));
+        for coord in map.iter_coords() {
+            expect_that!(
+                solutions[0].answer.cells[&coord].region,
+                eq(map.cells[&coord].region)
+            );
+        }
+    }
+
+    #[googletest::test]
+    fn finds_every_loop_of_a_multi_loop_answer() {
+        let map = MultiLoopGenerator { loops: 2 }.generate(
+            3,
+            Difficulty::Easy,
+            &mut StdRng::seed_from_u64(2),
+        );
+        let solutions = solve(&map.view(), 1);
+        expect_that!(solutions.len(), eq(1));
+        expect_that!(solutions[0].loops.len(), eq(2));
+    }
+
+    #[googletest::test]
+    fn stops_at_the_limit() {
+        let mut map = LoopGenerator.generate_solution(2, &mut StdRng::seed_from_u64(1));
+        for cell in map.cells.values_mut() {
+            cell.clue = Clue::Hidden;
+        }
+        expect_that!(solve(&map.view(), 3).len(), eq(3));
+        expect_that!(solve(&map.view(), 0).len(), eq(0));
+    }
+
+    #[googletest::test]
+    fn counts_solutions_up_to_the_limit() {
+        let puzzle = LoopGenerator.generate(4, Difficulty::Hard, &mut StdRng::seed_from_u64(3));
+        expect_that!(count_solutions(&puzzle.view(), 2), eq(1));
+
+        let mut blank = puzzle;
+        for cell in blank.cells.values_mut() {
+            cell.clue = Clue::Hidden;
+        }
+        for limit in [0, 1, 2, 5] {
+            expect_that!(count_solutions(&blank.view(), limit), eq(limit));
+        }
+    }
+
+    #[googletest::test]
+    fn contradictory_clues_have_no_solutions() {
+        let mut map = LoopGenerator.generate_solution(2, &mut StdRng::seed_from_u64(4));
+        let cell = map.cells.get_mut(&Coord::new(0, 0)).unwrap();
+        cell.full_neighbor_count = (cell.full_neighbor_count + 1) % 7;
+        expect_that!(count_solutions(&map.view(), 1), eq(0));
+    }
+
+    #[googletest::test]
+    fn loops_reject_dangling_edges() {
+        let graph = EdgeGraph::new(1);
+        expect_that!(graph.loops(&[0]), err(contains_substring("touches 1")));
+    }
+
+    /// The marks of the answer's loop edges, and crosses on every other edge
+    fn answer_marks(map: &Map) -> PlayerState {
+        let graph = EdgeGraph::new(map.radius);
+        let on_loop = graph.loop_edges(map);
+        PlayerState {
+            marks: (0..graph.edges().len())
+                .map(|e| {
+                    let mark = if on_loop.contains(&e) {
+                        EdgeMark::Line
+                    } else {
+                        EdgeMark::Cross
+                    };
+                    (e, mark)
+                })
+                .collect(),
+            ..PlayerState::default()
+        }
+    }
+
+    #[googletest::test]
+    fn the_answer_has_no_conflicts() {
+        let map = LoopGenerator.generate(3, Difficulty::Hard, &mut StdRng::seed_from_u64(2));
+        let mut player = answer_marks(&map);
+        expect_that!(find_conflict(&map.view(), &player), none());
+        player.marks.retain(|&e, _| e % 3 == 0);
+        expect_that!(find_conflict(&map.view(), &player), none());
+    }
+
+    #[googletest::test]
+    fn dead_ends_are_reported() {
+        let map = LoopGenerator.generate(2, Difficulty::Hard, &mut StdRng::seed_from_u64(5));
+        let graph = EdgeGraph::new(2);
+        let center = Coord::new(0, 0);
+        // The two sides of the center meeting at one of its corners, and the third side there
+        let (a, b) = (
+            graph.edge(center, 0).unwrap(),
+            graph.edge(center, 1).unwrap(),
+        );
+        let c = graph.edge(Coord::new(1, 0), 2).unwrap();
+        let corner = graph.corners(a)[1];
+
+        let mut player = PlayerState::default();
+        player.marks.insert(a, EdgeMark::Line);
+        player.marks.insert(b, EdgeMark::Cross);
+        player.marks.insert(c, EdgeMark::Cross);
+        expect_that!(
+            find_conflict(&map.view(), &player),
+            some(eq(&Conflict::DeadEnd { corner }))
+        );
+        player.marks.insert(b, EdgeMark::Line);
+        player.marks.insert(c, EdgeMark::Line);
+        expect_that!(
+            find_conflict(&map.view(), &player),
+            some(eq(&Conflict::DeadEnd { corner }))
+        );
+    }
+
+    #[googletest::test]
+    fn broken_clues_are_reported() {
+        let map = LoopGenerator.generate_solution(2, &mut StdRng::seed_from_u64(1));
+        let graph = EdgeGraph::new(2);
+        let center = Coord::new(0, 0);
+        let count = map.cells[&center].full_neighbor_count as usize;
+        // Cross out one more side of the center than its clue leaves room for
+        let mut player = PlayerState::default();
+        for direction in 0..7 - count {
+            player
+                .marks
+                .insert(graph.edge(center, direction).unwrap(), EdgeMark::Cross);
+        }
+        expect_that!(
+            find_conflict(&map.view(), &player),
+            some(eq(&Conflict::Clue { cell: center }))
+        );
+    }
+
+    #[googletest::test]
+    fn markers_are_checked_against_the_board_edge() {
+        let mut map = map_from_inside(1, &HashSet::from([Coord::new(1, 0)]));
+        for cell in map.cells.values_mut() {
+            cell.clue = Clue::Hidden;
+        }
+        map.cells.get_mut(&Coord::new(1, 0)).unwrap().clue = Clue::Marker;
+        let graph = EdgeGraph::new(1);
+
+        let mut player = PlayerState::default();
+        player
+            .marks
+            .insert(graph.edge(Coord::new(1, 0), 0).unwrap(), EdgeMark::Cross);
+        expect_that!(
+            find_conflict(&map.view(), &player),
+            some(eq(&Conflict::Clue {
+                cell: Coord::new(1, 0)
+            }))
+        );
+    }
+
+    #[googletest::test]
+    fn closed_loops_are_premature_while_lines_are_left_over() {
+        let mut map = map_from_inside(2, &HashSet::from([Coord::new(0, 0)]));
+        for cell in map.cells.values_mut() {
+            cell.clue = Clue::Hidden;
+        }
+        let graph = EdgeGraph::new(2);
+        let mut hexagon: Vec<usize> = (0..6)
+            .map(|d| graph.edge(Coord::new(0, 0), d).unwrap())
+            .collect();
+        let mut player = PlayerState::default();
+        for &edge in &hexagon {
+            player.marks.insert(edge, EdgeMark::Line);
+        }
+        expect_that!(find_conflict(&map.view(), &player), none());
+
+        let stray = graph.edge(Coord::new(2, 0), 0).unwrap();
+        player.marks.insert(stray, EdgeMark::Line);
+        hexagon.sort();
+        expect_that!(
+            find_conflict(&map.view(), &player),
+            some(eq(&Conflict::ClosedLoop { edges: hexagon }))
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Vector rendering of puzzles for print: the hexagons with their clues and markers, and
+//! optionally the answer, shaded, with its loop drawn over it.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::error::SlitherError;
+use crate::model::{Coord, Loop, Map, Marker, Region, Vertex, VisibleClue};
+
+/// Distance from the center of a hexagon to its corners, in drawing units
+pub(crate) const HEX_SIZE: f64 = 20.0;
+/// Blank space around the board
+const MARGIN: f64 = 4.0;
+
+const STYLE: &str = "\
+.hex { fill: #fff; stroke: #999; stroke-width: 1; }
+.inside { fill: #fde68a; }
+.clue { font: bold 16px sans-serif; text-anchor: middle; dominant-baseline: central; }
+.loop { fill: none; stroke: #c2410c; stroke-width: 3; stroke-linejoin: round; }";
+
+/// A point of a drawing. The y axis points down, as in SVG.
+pub(crate) type Point = (f64, f64);
+
+/// A cell of the board as drawn
+pub(crate) struct Hex {
+    pub center: Point,
+    pub corners: [Point; 6],
+    /// Shaded as Inside; only set when drawing the answer
+    pub inside: bool,
+    pub label: Option<Label>,
+}
+
+/// What a hexagon shows in its middle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Label {
+    Count(u8),
+    Parity(u8),
+    Marker(Marker),
+}
+
+impl Label {
+    /// The label as text, with markers as their emoji
+    pub fn text(self) -> String {
+        match self {
+            Label::Count(count) => count.to_string(),
+            Label::Parity(parity) => (if parity == 0 { "E" } else { "O" }).to_string(),
+            Label::Marker(Marker::Sheep) => "🐑".to_string(),
+            Label::Marker(Marker::Wolf) => "🐺".to_string(),
+        }
+    }
+}
+
+/// A map laid out for rendering, in drawing units
+pub(crate) struct Drawing {
+    pub hexes: Vec<Hex>,
+    /// Each loop of the answer as the corners it passes through, in order
+    pub loops: Vec<Vec<Point>>,
+    /// Top left corner of the area covered, margin included
+    pub origin: Point,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Center of the hexagon at `coord`. Hexagons are flat-topped, as in the web viewer.
+fn center(coord: Coord) -> Point {
+    let (q, r) = (coord.q as f64, coord.r as f64);
+    (HEX_SIZE * 1.5 * q, HEX_SIZE * 3f64.sqrt() * (q / 2.0 + r))
+}
+
+/// A corner is where three hexagons meet, at the average of their centers
+fn corner(vertex: Vertex) -> Point {
+    let centers = vertex.cells().map(center);
+    (
+        centers.iter().map(|c| c.0).sum::<f64>() / 3.0,
+        centers.iter().map(|c| c.1).sum::<f64>() / 3.0,
+    )
+}
+
+/// Lay out `map`: the puzzle as the player sees it, and with `solution` also its answer.
+/// Fails if the answer is asked for but isn't a valid set of loops.
+pub(crate) fn draw(map: &Map, solution: bool) -> Result<Drawing, SlitherError> {
+    let loops = if solution {
+        Loop::of(map)
+            .cycles()?
+            .iter()
+            .map(|path| path.vertices.iter().map(|&v| corner(v)).collect())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let hexes: Vec<Hex> = map
+        .iter_coords()
+        .map(|coord| {
+            let cell = &map.cells[&coord];
+            let (x, y) = center(coord);
+            let label = match (cell.visible_clue(), cell.marker()) {
+                (Some(VisibleClue::Count(count)), _) => Some(Label::Count(count)),
+                (Some(VisibleClue::Parity(parity)), _) => Some(Label::Parity(parity)),
+                (None, marker) => marker.map(Label::Marker),
+            };
+            Hex {
+                center: (x, y),
+                corners: std::array::from_fn(|i| {
+                    let angle = (60.0 * i as f64).to_radians();
+                    (x + HEX_SIZE * angle.cos(), y + HEX_SIZE * angle.sin())
+                }),
+                inside: solution && cell.region.region() == Some(Region::Inside),
+                label,
+            }
+        })
+        .collect();
+
+    let half_height = HEX_SIZE * 3f64.sqrt() / 2.0;
+    let xs = hexes.iter().map(|hex| hex.center.0);
+    let ys = hexes.iter().map(|hex| hex.center.1);
+    let min_x = xs.clone().fold(f64::MAX, f64::min) - HEX_SIZE - MARGIN;
+    let max_x = xs.fold(f64::MIN, f64::max) + HEX_SIZE + MARGIN;
+    let min_y = ys.clone().fold(f64::MAX, f64::min) - half_height - MARGIN;
+    let max_y = ys.fold(f64::MIN, f64::max) + half_height + MARGIN;
+    Ok(Drawing {
+        hexes,
+        loops,
+        origin: (min_x, min_y),
+        width: max_x - min_x,
+        height: max_y - min_y,
+    })
+}
+
+/// Render `map` as an SVG document: the puzzle as the player sees it, and with `solution`
+/// also its answer. Fails if the answer is asked for but isn't a valid set of loops.
+pub fn render(map: &Map, solution: bool) -> Result<String, SlitherError> {
+    let drawing = draw(map, solution)?;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{:.1} {:.1} {:.1} {:.1}" width="{:.0}" height="{:.0}">"#,
+        drawing.origin.0,
+        drawing.origin.1,
+        drawing.width,
+        drawing.height,
+        drawing.width,
+        drawing.height
+    );
+    let _ = writeln!(svg, "<style>\n{}\n</style>", STYLE);
+
+    for hex in &drawing.hexes {
+        let points: Vec<String> = hex
+            .corners
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect();
+        let class = if hex.inside { "hex inside" } else { "hex" };
+        let _ = writeln!(
+            svg,
+            r#"<polygon class="{}" points="{}"/>"#,
+            class,
+            points.join(" ")
+        );
+        if let Some(label) = hex.label {
+            let _ = writeln!(
+                svg,
+                r#"<text class="clue" x="{:.1}" y="{:.1}">{}</text>"#,
+                hex.center.0,
+                hex.center.1,
+                label.text()
+            );
+        }
+    }
+
+    for path in &drawing.loops {
+        let points: Vec<String> = path
+            .iter()
+            .map(|(x, y)| format!("{:.1} {:.1}", x, y))
+            .collect();
+        let _ = writeln!(
+            svg,
+            r#"<path class="loop" d="M {} Z"/>"#,
+            points.join(" L ")
+        );
+    }
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+/// Save the rendering of `map` to an SVG file
+pub fn save_svg<P: AsRef<Path>>(map: &Map, path: P, solution: bool) -> Result<(), SlitherError> {
+    Ok(std::fs::write(path, render(map, solution)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::MapBuilder;
+    use googletest::prelude::*;
+
+    fn one_inside() -> MapBuilder {
+        MapBuilder::new(1)
+            .fill(Region::Outside)
+            .inside([Coord::new(0, 0)])
+    }
+
+    #[googletest::test]
+    fn puzzles_render_their_hexagons_and_visible_clues() {
+        let map = one_inside()
+            .parity(Coord::new(1, 0))
+            .visible(Coord::new(0, 1), false)
+            .marker(Coord::new(-1, 0))
+            .build()
+            .unwrap();
+        let svg = render(&map, false).unwrap();
+
+        expect_that!(svg.matches("<polygon").count(), eq(7));
+        expect_that!(svg.matches(r#"<text class="clue""#).count(), eq(6));
+        expect_that!(svg, contains_substring(">6</text>"));
+        expect_that!(svg, contains_substring(">O</text>"));
+        expect_that!(svg, contains_substring(">🐺</text>"));
+        expect_that!(svg, not(contains_substring("<path")));
+        expect_that!(svg, not(contains_substring("hex inside")));
+    }
+
+    #[googletest::test]
+    fn solutions_draw_the_loop_around_the_inside() {
+        let map = one_inside().build().unwrap();
+        let svg = render(&map, true).unwrap();
+
+        expect_that!(svg.matches(r#"class="hex inside""#).count(), eq(1));
+        expect_that!(svg.matches("<path").count(), eq(1));
+        // The loop runs around the middle hexagon, through its six corners
+        expect_that!(svg.matches(" L ").count(), eq(5));
+    }
+}
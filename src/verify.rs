@@ -0,0 +1,172 @@
+//! Checking saved puzzles before they ship: every map under a folder must load, its clues
+//! must agree with the answer stored with it, the answer must be a valid loop (or loops), and
+//! the visible clues must have that answer as their only solution.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::model::Map;
+use crate::{io as map_io, solver};
+
+/// Outcome of checking one saved puzzle
+#[derive(Debug)]
+pub struct FileReport {
+    pub path: PathBuf,
+    /// Why the puzzle failed, if it did
+    pub result: Result<(), String>,
+}
+
+/// Every `.bin`, `.map.json`, `.map.txt` or `.slpack` file under `dir`, in sorted order. Other
+/// JSON and text files, like traces, metadata and seed lists, are not maps.
+pub fn find_maps(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "bin")
+                || path.to_string_lossy().ends_with(".map.json")
+                || path.to_string_lossy().ends_with(".map.txt")
+                || map_io::is_pack(&path)
+            {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Check every map file under `dir`
+pub fn verify_dir(dir: &Path) -> io::Result<Vec<FileReport>> {
+    Ok(find_maps(dir)?
+        .into_iter()
+        .map(|path| FileReport {
+            result: verify_file(&path),
+            path,
+        })
+        .collect())
+}
+
+/// Load a saved puzzle, or every puzzle of a pack, and check it
+pub fn verify_file(path: &Path) -> Result<(), String> {
+    let maps = map_io::load_puzzles(path).map_err(|e| format!("unreadable: {}", e))?;
+    if !map_io::is_pack(path) {
+        return maps.iter().try_for_each(verify_map);
+    }
+    maps.iter()
+        .enumerate()
+        .try_for_each(|(i, map)| verify_map(map).map_err(|e| format!("puzzle {}: {}", i, e)))
+}
+
+/// Check a puzzle: its answer is a valid loop, every clue matches the answer, and
+/// the visible clues and markers have exactly one solution
+pub fn verify_map(map: &Map) -> Result<(), String> {
+    map.validate_topology()
+        .map_err(|e| format!("invalid loop: {}", e))?;
+
+    let mut expected = map.clone();
+    expected.recompute_clues();
+    for coord in map.iter_coords() {
+        let cell = &map.cells[&coord];
+        let count = expected.cells[&coord].full_neighbor_count;
+        if cell.full_neighbor_count != count {
+            return Err(format!(
+                "clue at ({}, {}) is {} but the answer gives {}",
+                coord.q, coord.r, cell.full_neighbor_count, count
+            ));
+        }
+    }
+
+    match solver::count_solutions(&map.view(), 2) {
+        1 => Ok(()),
+        0 => Err("the visible clues have no solution".to_string()),
+        _ => Err("more than one answer fits the visible clues".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Difficulty;
+    use crate::generator::{Generator, LoopGenerator};
+    use crate::model::{Clue, Coord};
+    use googletest::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use tempfile::tempdir;
+
+    fn puzzle(seed: u64) -> Map {
+        LoopGenerator.generate(3, Difficulty::Hard, &mut StdRng::seed_from_u64(seed))
+    }
+
+    #[googletest::test]
+    fn generated_puzzles_pass() {
+        for seed in 0..3 {
+            expect_that!(verify_map(&puzzle(seed)), ok(anything()));
+        }
+    }
+
+    #[googletest::test]
+    fn wrong_clues_fail() {
+        let mut map = puzzle(1);
+        let cell = map.cells.get_mut(&Coord::new(0, 0)).unwrap();
+        cell.full_neighbor_count = (cell.full_neighbor_count + 1) % 7;
+        expect_that!(verify_map(&map), err(contains_substring("clue at (0, 0)")));
+    }
+
+    #[googletest::test]
+    fn ambiguous_puzzles_fail() {
+        let mut map = puzzle(2);
+        for cell in map.cells.values_mut() {
+            cell.clue = Clue::Hidden;
+        }
+        expect_that!(verify_map(&map), err(contains_substring("more than one")));
+    }
+
+    #[googletest::test]
+    fn reports_every_file_in_nested_folders() -> Result<()> {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("small")).unwrap();
+        map_io::save_map(
+            &puzzle(0),
+            dir.path().join("small/0.bin"),
+            map_io::MapEncoding::Standard,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("small/1.bin"), [0, 3, 0]).unwrap();
+        map_io::save_map(
+            &puzzle(1),
+            dir.path().join("small/2.map.json"),
+            map_io::MapEncoding::Standard,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("seeds.txt"), "0.bin 0").unwrap();
+        std::fs::write(dir.path().join("metadata.json"), "[]").unwrap();
+        let mut ambiguous = puzzle(2);
+        for cell in ambiguous.cells.values_mut() {
+            cell.clue = Clue::Hidden;
+        }
+        map_io::save_pack(&[puzzle(0), ambiguous], dir.path().join("small/3.slpack")).unwrap();
+        map_io::save_map(
+            &puzzle(1),
+            dir.path().join("small/4.map.txt"),
+            map_io::MapEncoding::Standard,
+        )
+        .unwrap();
+
+        let reports = verify_dir(dir.path()).unwrap();
+        expect_that!(reports, len(eq(5)));
+        expect_that!(reports[0].result, ok(anything()));
+        expect_that!(reports[1].result, err(contains_substring("unreadable")));
+        expect_that!(reports[2].result, ok(anything()));
+        expect_that!(
+            reports[3].result,
+            err(eq("puzzle 1: more than one answer fits the visible clues"))
+        );
+        expect_that!(reports[4].result, ok(anything()));
+        Ok(())
+    }
+}
@@ -1,17 +1,25 @@
-use crate::model::{Cell, Coord, Map};
+use crate::io::SAVE_VERSION;
+use crate::model::{Cell, Coord, Map, PlayerState};
+use crate::solver::EdgeGraph;
 use serde::Serialize;
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
 /// Custom serialization structure for the Map.
-/// We convert the HashMap<Coord, Cell> to a flat list of cells to ensure
-/// consistent JSON serialization and easier consumption in JavaScript,
-/// avoiding potential issues with non-string keys in JSON objects.
+/// We convert the cell grid to a flat list of cells in board order, so the same map always
+/// gives the same JSON, and to avoid non-string keys in JSON objects.
 #[derive(Serialize)]
 struct WebMap<'a> {
     radius: u8,
     cells: Vec<WebCell<'a>>,
+    /// Every side in `EdgeGraph` order, so marks keep their indices: the cell it belongs to,
+    /// the cell across it, and the cells beside those at either end, which place it on screen
+    edges: Vec<[Coord; 4]>,
+    /// The map as saved, for the viewer to put in save files
+    puzzle: &'a Map,
+    /// Marks to start from, when resuming
+    player: Option<&'a PlayerState>,
 }
 
 #[derive(Serialize)]
@@ -23,27 +31,93 @@ struct WebCell<'a> {
 impl<'a> From<&'a Map> for WebMap<'a> {
     fn from(map: &'a Map) -> Self {
         let cells = map
-            .cells
-            .iter()
-            .map(|(&coords, cell)| WebCell { coords, cell })
+            .iter_coords()
+            .map(|coords| WebCell {
+                coords,
+                cell: &map.cells[&coords],
+            })
             .collect();
         WebMap {
             radius: map.radius,
             cells,
+            edges: edge_cells(map.radius),
+            puzzle: map,
+            player: None,
+        }
+    }
+}
+
+fn edge_cells(radius: u8) -> Vec<[Coord; 4]> {
+    let graph = EdgeGraph::new(radius);
+    let mut edges = vec![None; graph.edges().len()];
+    for coord in Map::new(radius).iter_coords() {
+        let around = coord.neighbors();
+        for direction in 0..6 {
+            let edge = graph
+                .edge(coord, direction)
+                .expect("board cells have sides");
+            edges[edge].get_or_insert([
+                coord,
+                around[direction],
+                around[(direction + 5) % 6],
+                around[(direction + 1) % 6],
+            ]);
         }
     }
+    edges.into_iter().flatten().collect()
+}
+
+/// The maps as the JSON the viewer template reads
+fn maps_json(maps: &[Map]) -> String {
+    let web_maps: Vec<WebMap> = maps.iter().map(WebMap::from).collect();
+    web_maps_json(&web_maps)
+}
+
+fn web_maps_json(web_maps: &[WebMap]) -> String {
+    serde_json::to_string(web_maps).expect("Failed to serialize maps")
 }
 
 pub fn show_map(map: &Map) {
-    let web_map = WebMap::from(map);
-    let json_data = serde_json::to_string(&web_map).expect("Failed to serialize map");
+    show_maps(std::slice::from_ref(map));
+}
+
+/// Show a puzzle to play, with the player's marks so far and the answer hidden
+pub fn show_progress(map: &Map, player: &PlayerState) {
+    let web_map = WebMap {
+        player: Some(player),
+        ..WebMap::from(map)
+    };
+    open_page(&page(&web_maps_json(&[web_map]), false));
+}
+
+/// A standalone viewer page showing the maps side by side, with their data embedded so it
+/// opens without a server. With `show_answer` unset, the answer starts hidden.
+pub fn viewer_html(maps: &[Map], show_answer: bool) -> String {
+    page(&maps_json(maps), show_answer)
+}
 
+fn page(json_data: &str, show_answer: bool) -> String {
     // Read template (embedded at compile time)
     let template = include_str!("web_viewer/template.html");
 
     // Inject data
-    let html_content = template.replace("/* DATA_PLACEHOLDER */ null", &json_data);
+    let html = template
+        .replace("/* DATA_PLACEHOLDER */ null", json_data)
+        .replace("/* SAVE_VERSION */ 1", &SAVE_VERSION.to_string());
+    if show_answer {
+        html
+    } else {
+        html.replace(r#"id="show-solution" checked"#, r#"id="show-solution""#)
+    }
+}
+
+/// Show several maps side by side, such as the different solutions of an ambiguous puzzle
+pub fn show_maps(maps: &[Map]) {
+    open_page(&viewer_html(maps, true));
+}
 
+/// Write a viewer page to the current directory and open it in the browser
+fn open_page(html_content: &str) {
     // Write to a temporary HTML file in the current directory.
     let output_path = Path::new("slithergen_view.html");
 
@@ -58,3 +132,69 @@ pub fn show_map(map: &Map) {
         eprintln!("Failed to open browser: {}", e);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Edge, MapBuilder, Region};
+    use googletest::prelude::*;
+
+    #[googletest::test]
+    fn cells_are_listed_in_board_order_whatever_order_they_were_filled_in() {
+        let map = MapBuilder::new(2)
+            .fill(Region::Outside)
+            .inside([Coord::new(0, 0), Coord::new(1, 0)])
+            .build()
+            .unwrap();
+        let mut reversed = Map::new(map.radius);
+        for coord in map.iter_coords().collect::<Vec<_>>().into_iter().rev() {
+            reversed.cells.insert(coord, map.cells[&coord].clone());
+        }
+
+        let json = maps_json(std::slice::from_ref(&map));
+        expect_that!(maps_json(&[reversed]), eq(&json));
+
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let coords: Vec<Coord> = parsed[0]["cells"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|cell| serde_json::from_value(cell["coords"].clone()).unwrap())
+            .collect();
+        expect_that!(coords, eq(&map.iter_coords().collect::<Vec<_>>()));
+    }
+
+    #[googletest::test]
+    fn edges_keep_their_edge_graph_indices_and_corners() {
+        let graph = EdgeGraph::new(2);
+        let edges = edge_cells(2);
+        expect_that!(edges.len(), eq(graph.edges().len()));
+        for (edge, [cell, across, before, after]) in graph.edges().iter().zip(&edges) {
+            expect_that!(*edge, eq(Edge::new(*cell, *across)));
+            for third in [before, after] {
+                expect_that!(cell.neighbors().contains(third), eq(true));
+                expect_that!(across.neighbors().contains(third), eq(true));
+            }
+        }
+    }
+
+    #[googletest::test]
+    fn exported_pages_embed_the_maps_and_start_with_the_answer_hidden() {
+        let map = MapBuilder::new(2)
+            .fill(Region::Outside)
+            .inside([Coord::new(0, 0)])
+            .build()
+            .unwrap();
+        let maps = [map.clone(), map];
+
+        let html = viewer_html(&maps, false);
+        expect_that!(html, contains_substring(maps_json(&maps)));
+        expect_that!(html, not(contains_substring("DATA_PLACEHOLDER")));
+        expect_that!(html, contains_substring(r#"id="show-solution" />"#));
+
+        expect_that!(
+            viewer_html(&maps, true),
+            contains_substring(r#"id="show-solution" checked"#)
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use googletest::prelude::*;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+
+/// Generate six radius 1 puzzles from seed 11 into `output`
+fn generate(output: &Path, extra: &[&str]) -> ExitStatus {
+    Command::new(env!("CARGO_BIN_EXE_slithergen"))
+        .args(["--radius", "1", "--difficulty", "easy", "--count", "6"])
+        .args(["--seed", "11", "--output"])
+        .arg(output)
+        .args(extra)
+        .output()
+        .unwrap()
+        .status
+}
+
+#[googletest::test]
+fn rerun_with_skip_regenerates_the_missing_puzzles() {
+    let dir = tempfile::tempdir().unwrap();
+    assert_that!(generate(dir.path(), &[]).success(), eq(true));
+    let folder = dir.path().join("radius_1").join("easy");
+    let read = |n: usize| std::fs::read(folder.join(format!("{}.bin", n))).unwrap();
+    let first: Vec<Vec<u8>> = (0..6).map(read).collect();
+
+    for n in 3..6 {
+        std::fs::remove_file(folder.join(format!("{}.bin", n))).unwrap();
+    }
+    assert_that!(
+        generate(dir.path(), &["--on-conflict", "skip"]).success(),
+        eq(true)
+    );
+    let rerun: Vec<Vec<u8>> = (0..6).map(read).collect();
+    expect_that!(rerun, eq(&first));
+}
@@ -0,0 +1,53 @@
+use googletest::prelude::*;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+use std::time::Duration;
+
+/// Generate three radius 1 puzzles from seed 11 into `output`
+fn generate(output: &Path, extra: &[&str]) -> ExitStatus {
+    Command::new(env!("CARGO_BIN_EXE_slithergen"))
+        .args(["--radius", "1", "--difficulty", "easy", "--count", "3"])
+        .args(["--seed", "11", "--output"])
+        .arg(output)
+        .args(extra)
+        .output()
+        .unwrap()
+        .status
+}
+
+/// Every file under `dir` with its contents, by path relative to `dir`
+fn read_tree(dir: &Path) -> BTreeMap<String, Vec<u8>> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(folder) = pending.pop() {
+        for entry in std::fs::read_dir(folder).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let name = path.strip_prefix(dir).unwrap().display().to_string();
+                files.insert(name, std::fs::read(path).unwrap());
+            }
+        }
+    }
+    files
+}
+
+#[googletest::test]
+fn seeded_runs_save_identical_files() {
+    let runs = tempfile::tempdir().unwrap();
+    for extra in [&[][..], &["--format", "json"][..]] {
+        let (first, second) = (runs.path().join("first"), runs.path().join("second"));
+        assert_that!(generate(&first, extra).success(), eq(true));
+        // Let the clock move on, so a timestamp in the files would differ
+        std::thread::sleep(Duration::from_millis(1100));
+        assert_that!(generate(&second, extra).success(), eq(true));
+
+        let files = read_tree(&first);
+        expect_that!(files.len(), gt(3));
+        expect_that!(read_tree(&second), eq(&files));
+        std::fs::remove_dir_all(&first).unwrap();
+        std::fs::remove_dir_all(&second).unwrap();
+    }
+}